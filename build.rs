@@ -0,0 +1,62 @@
+use std::{env, fs, process::Command};
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let dirty = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .ok()
+        .is_some_and(|out| out.status.success() && !out.stdout.is_empty());
+
+    let features: Vec<String> = env::vars()
+        .filter_map(|(var, _)| Some(var.strip_prefix("CARGO_FEATURE_")?.to_lowercase()))
+        .collect();
+
+    // resolved versions, the requirements in Cargo.toml are too vague
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+
+    println!(
+        "cargo:rustc-env=BUILD_GIT_HASH={git_hash}{}",
+        if dirty { "-dirty" } else { "" }
+    );
+    println!(
+        "cargo:rustc-env=BUILD_PROFILE={}",
+        env::var("PROFILE").unwrap()
+    );
+    println!(
+        "cargo:rustc-env=BUILD_TARGET={}",
+        env::var("TARGET").unwrap()
+    );
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+    println!(
+        "cargo:rustc-env=BUILD_WGPU={}",
+        locked_version(&lock, "wgpu")
+    );
+    println!(
+        "cargo:rustc-env=BUILD_WINIT={}",
+        locked_version(&lock, "winit")
+    );
+
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+}
+
+fn locked_version(lock: &str, package: &str) -> String {
+    let name = format!("name = \"{package}\"");
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == name {
+            if let Some(version) = lines.next().and_then(|l| l.strip_prefix("version = ")) {
+                return version.trim_matches('"').to_string();
+            }
+        }
+    }
+    "unknown".to_string()
+}
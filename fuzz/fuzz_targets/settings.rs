@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use toml_edit::Document;
+use wgpu_template::settings::{merge_document, GlobalSettings, DEFAULT_SETTINGS};
+
+// arbitrary settings files: loading may fail, but never panic,
+// and merging the defaults in always gives a valid file
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(mut document) = text.parse::<Document>() else {
+        return;
+    };
+
+    _ = GlobalSettings::from_document(document.clone());
+
+    let defaults: Document = DEFAULT_SETTINGS.parse().unwrap();
+    merge_document(document.as_table_mut(), defaults.as_table());
+
+    let merged = document.to_string();
+    let reparsed: Document = merged.parse().expect("merged settings are invalid");
+
+    // merging again changes nothing
+    let mut again = reparsed;
+    merge_document(again.as_table_mut(), defaults.as_table());
+    assert_eq!(again.to_string(), merged);
+});
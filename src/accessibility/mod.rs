@@ -0,0 +1,90 @@
+use std::process::Command;
+
+use crate::settings::{AccessibilitySettings, SystemToggle};
+
+pub use screen_reader::ScreenReader;
+
+//
+
+pub mod screen_reader;
+
+//
+
+/// resolved accessibility preferences
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Accessibility {
+    /// no idle rotation or other purely decorative animation
+    pub reduced_motion: bool,
+    /// an opaque black background and a white scene
+    pub high_contrast: bool,
+}
+
+//
+
+impl Accessibility {
+    /// settings file choices, falling back to the OS preferences
+    pub fn resolve(settings: &AccessibilitySettings) -> Self {
+        let result = Self {
+            reduced_motion: settings.reduced_motion.resolve(system_reduced_motion),
+            high_contrast: settings.high_contrast.resolve(system_high_contrast),
+        };
+
+        tracing::debug!("{result:?}");
+        result
+    }
+}
+
+impl SystemToggle {
+    pub fn resolve(self, system: impl FnOnce() -> Option<bool>) -> bool {
+        match self {
+            SystemToggle::System => system().unwrap_or(false),
+            SystemToggle::On => true,
+            SystemToggle::Off => false,
+        }
+    }
+}
+
+/// `None` if the platform doesn't say
+fn system_reduced_motion() -> Option<bool> {
+    if cfg!(target_os = "macos") {
+        defaults_read("com.apple.universalaccess", "reduceMotion")
+    } else if cfg!(all(unix, not(target_os = "macos"))) {
+        gsettings_get("org.gnome.desktop.interface", "enable-animations").map(|anim| !anim)
+    } else {
+        None
+    }
+}
+
+fn system_high_contrast() -> Option<bool> {
+    if cfg!(target_os = "macos") {
+        defaults_read("com.apple.universalaccess", "increaseContrast")
+    } else if cfg!(all(unix, not(target_os = "macos"))) {
+        gsettings_get("org.gnome.desktop.a11y.interface", "high-contrast")
+    } else {
+        None
+    }
+}
+
+fn gsettings_get(schema: &str, key: &str) -> Option<bool> {
+    let out = Command::new("gsettings")
+        .args(["get", schema, key])
+        .output()
+        .ok()?;
+    match String::from_utf8_lossy(&out.stdout).trim() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn defaults_read(domain: &str, key: &str) -> Option<bool> {
+    let out = Command::new("defaults")
+        .args(["read", domain, key])
+        .output()
+        .ok()?;
+    match String::from_utf8_lossy(&out.stdout).trim() {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ => None,
+    }
+}
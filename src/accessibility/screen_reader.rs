@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use accesskit::{
+    Action as Request, Checked, DefaultActionVerb, Live, NodeBuilder, NodeClassSet, NodeId, Role,
+    Tree, TreeUpdate,
+};
+use accesskit_winit::{ActionRequestEvent, Adapter};
+use winit::{event::WindowEvent, event_loop::EventLoopProxy, window::Window};
+
+use crate::{input::Action, RuntimeSettings, UserEvent};
+
+//
+
+/// exposes the window contents to platform screen readers through AccessKit
+///
+/// every bound action is a button, a live status line reads out state changes
+pub struct ScreenReader {
+    adapter: Adapter,
+    tree: AccessTree,
+}
+
+#[derive(Clone)]
+struct AccessTree {
+    title: Arc<str>,
+    /// actions and their key bindings, in node order
+    actions: Vec<(Action, String)>,
+    settings: RuntimeSettings,
+}
+
+//
+
+impl ScreenReader {
+    const WINDOW: NodeId = NodeId(0);
+    const STATUS: NodeId = NodeId(1);
+    const FIRST_ACTION: u64 = 2;
+
+    /// must be created before the window is made visible
+    pub fn new(
+        window: &Window,
+        title: Arc<str>,
+        actions: Vec<(Action, String)>,
+        settings: &RuntimeSettings,
+        proxy: EventLoopProxy<UserEvent>,
+    ) -> Self {
+        let tree = AccessTree {
+            title,
+            actions,
+            settings: settings.clone(),
+        };
+
+        let adapter = Adapter::new(
+            window,
+            {
+                let tree = tree.clone();
+                move || tree.build()
+            },
+            proxy,
+        );
+
+        Self { adapter, tree }
+    }
+
+    /// returns true if the event was consumed by AccessKit
+    pub fn window_event(&self, window: &Window, event: &WindowEvent) -> bool {
+        self.adapter.on_event(window, event)
+    }
+
+    /// send the new state to the screen reader, if one is listening
+    pub fn update(&mut self, settings: &RuntimeSettings) {
+        self.tree.settings = settings.clone();
+        self.adapter.update_if_active(|| self.tree.build());
+    }
+
+    /// the action a screen reader asked for
+    pub fn action_requested(&self, event: &ActionRequestEvent) -> Option<Action> {
+        if event.request.action != Request::Default {
+            return None;
+        }
+
+        let index = event.request.target.0.checked_sub(Self::FIRST_ACTION)?;
+        self.tree
+            .actions
+            .get(index as usize)
+            .map(|(action, _)| *action)
+    }
+}
+
+impl AccessTree {
+    fn build(&self) -> TreeUpdate {
+        let mut classes = NodeClassSet::lock_global();
+        let mut nodes = Vec::new();
+
+        let mut window = NodeBuilder::new(Role::Window);
+        window.set_name(self.title.to_string());
+        window.push_child(ScreenReader::STATUS);
+
+        let mut status = NodeBuilder::new(Role::Status);
+        status.set_live(Live::Polite);
+        status.set_name(self.status());
+        nodes.push((ScreenReader::STATUS, status.build(&mut classes)));
+
+        for (i, (action, binding)) in self.actions.iter().enumerate() {
+            let id = NodeId(ScreenReader::FIRST_ACTION + i as u64);
+            window.push_child(id);
+
+            let toggled = match action {
+                Action::ToggleUv => Some(self.settings.enable_uv),
+                Action::ToggleInterpolation => Some(self.settings.interpolate),
+                _ => None,
+            };
+
+            let mut button = NodeBuilder::new(if toggled.is_some() {
+                Role::ToggleButton
+            } else {
+                Role::Button
+            });
+            button.set_name(action.description());
+            button.set_description(format!("shortcut: {binding}"));
+            button.add_action(Request::Default);
+            button.set_default_action_verb(DefaultActionVerb::Click);
+            if let Some(toggled) = toggled {
+                button.set_checked(if toggled {
+                    Checked::True
+                } else {
+                    Checked::False
+                });
+            }
+            nodes.push((id, button.build(&mut classes)));
+        }
+
+        nodes.push((ScreenReader::WINDOW, window.build(&mut classes)));
+
+        TreeUpdate {
+            nodes,
+            tree: Some(Tree::new(ScreenReader::WINDOW)),
+            focus: ScreenReader::WINDOW,
+        }
+    }
+
+    fn status(&self) -> String {
+        let on_off = |b: bool| if b { "on" } else { "off" };
+        format!(
+            "UV colors {}, interpolation {}, color vision {:?}",
+            on_off(self.settings.enable_uv),
+            on_off(self.settings.interpolate),
+            self.settings.color_vision,
+        )
+    }
+}
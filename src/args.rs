@@ -0,0 +1,171 @@
+use std::{env, ops::Range, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::build_info::BUILD;
+
+//
+
+/// command line arguments
+///
+/// these override the settings file for a single run
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    /// `--seed <u64>`
+    pub seed: Option<u64>,
+    /// `--capture-frame <N>`
+    pub capture_frame: Option<u64>,
+    /// `--capture-tick <N>`, repeatable
+    pub capture_ticks: Vec<u64>,
+    /// `--render-frames <A..B>`, offline rendering
+    pub render_frames: Option<Range<u64>>,
+    /// `--fps <f64>`
+    pub fps: Option<f64>,
+    /// `--out <dir>`
+    pub out: Option<PathBuf>,
+    /// `--size <W>x<H>`
+    pub size: Option<(u32, u32)>,
+    /// `--gamma-audit`
+    pub gamma_audit: bool,
+    /// `--bench-workgroups`
+    pub bench_workgroups: bool,
+    /// `--tour`
+    pub tour: bool,
+    /// `--project <dir>`
+    pub project: Option<PathBuf>,
+}
+
+//
+
+impl Args {
+    /// parse the process arguments
+    ///
+    /// exits the process on invalid arguments
+    pub fn parse() -> Self {
+        match Self::try_parse(env::args().skip(1)) {
+            Ok(v) => v,
+            Err(err) => {
+                eprintln!("{err}\n\n{}", Self::USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    pub fn try_parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut result = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    result.seed = Some(Self::value(&arg, args.next())?);
+                }
+                "--capture-frame" => {
+                    result.capture_frame = Some(Self::value(&arg, args.next())?);
+                }
+                "--capture-tick" => {
+                    result.capture_ticks.push(Self::value(&arg, args.next())?);
+                }
+                "--render-frames" => {
+                    let value: String = Self::value(&arg, args.next())?;
+                    result.render_frames = Some(Self::range(&arg, &value)?);
+                }
+                "--fps" => {
+                    result.fps = Some(Self::value(&arg, args.next())?);
+                }
+                "--out" => {
+                    result.out = Some(Self::value(&arg, args.next())?);
+                }
+                "--size" => {
+                    let value: String = Self::value(&arg, args.next())?;
+                    result.size = Some(Self::size(&arg, &value)?);
+                }
+                "--gamma-audit" => {
+                    result.gamma_audit = true;
+                }
+                "--bench-workgroups" => {
+                    result.bench_workgroups = true;
+                }
+                "--tour" => {
+                    result.tour = true;
+                }
+                "--project" => {
+                    result.project = Some(Self::value(&arg, args.next())?);
+                }
+                "-V" | "--version" => {
+                    println!("{BUILD}");
+                    std::process::exit(0);
+                }
+                "-h" | "--help" => {
+                    println!("{}", Self::USAGE);
+                    std::process::exit(0);
+                }
+                other => return Err(anyhow!("unexpected argument `{other}`")),
+            }
+        }
+
+        Ok(result)
+    }
+
+    const USAGE: &'static str = concat!(
+        "usage: ",
+        env!("CARGO_PKG_NAME"),
+        " [options]\n",
+        "\n",
+        "options:\n",
+        "  --seed <u64>           override the RNG seed from the settings file\n",
+        "  --capture-frame <N>    dump frame N with its render targets and settings\n",
+        "                         into capture-frame-N.zip for bug reports\n",
+        "  --capture-tick <N>     render the state of simulation tick N into capture-tick-N.png,\n",
+        "                         at --size, the simulation waits for it (repeatable)\n",
+        "  --render-frames <A..B>  render frames A to B (exclusive) offline and exit\n",
+        "  --fps <f64>            simulated frames per second for --render-frames and --tour (60)\n",
+        "  --out <dir>            output directory for --render-frames and --tour (frames)\n",
+        "  --size <W>x<H>         resolution for --render-frames and --tour (the window resolution)\n",
+        "  --gamma-audit          render a gamma reference chart into gamma-audit.png,\n",
+        "                         report blending and sRGB encoding mistakes and exit\n",
+        "  --bench-workgroups     time the compute utilities with every workgroup size and exit\n",
+        "  --tour                 fly through the demo switching every effect and exit,\n",
+        "                         with --out the frames are rendered offline instead\n",
+        "  --project <dir>        open the project in <dir>\n",
+        "  -V, --version          print the build info\n",
+        "  -h, --help             print this help",
+    );
+
+    fn range(arg: &str, value: &str) -> Result<Range<u64>> {
+        let (start, end) = value
+            .split_once("..")
+            .ok_or_else(|| anyhow!("`{arg}` expects a range like `0..600`"))?;
+        let range =
+            Self::value(arg, Some(start.to_string()))?..Self::value(arg, Some(end.to_string()))?;
+        if range.is_empty() {
+            return Err(anyhow!("`{arg}` range `{value}` is empty"));
+        }
+        Ok(range)
+    }
+
+    fn size(arg: &str, value: &str) -> Result<(u32, u32)> {
+        let (w, h) = value
+            .split_once('x')
+            .ok_or_else(|| anyhow!("`{arg}` expects a size like `1920x1080`"))?;
+        let size = (
+            Self::value(arg, Some(w.to_string()))?,
+            Self::value(arg, Some(h.to_string()))?,
+        );
+        if size.0 == 0 || size.1 == 0 {
+            return Err(anyhow!("`{arg}` size `{value}` is empty"));
+        }
+        Ok(size)
+    }
+
+    fn value<T>(arg: &str, value: Option<String>) -> Result<T>
+    where
+        T: std::str::FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let value = value.ok_or_else(|| anyhow!("`{arg}` requires a value"))?;
+        value
+            .parse()
+            .with_context(|| format!("invalid value `{value}` for `{arg}`"))
+    }
+}
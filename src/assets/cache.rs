@@ -0,0 +1,174 @@
+use std::{
+    fmt, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+use crate::dirs::APP_DIRS;
+
+//
+
+/// content-hash-keyed store of processed assets
+///
+/// lives in `<cache dir>/assets` by default, entries are never invalidated,
+/// a changed source or processor simply hashes to a new key
+#[derive(Debug, Clone)]
+pub struct AssetCache {
+    dir: PathBuf,
+}
+
+/// sha256 of the processor identity and the source bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey([u8; 32]);
+
+//
+
+impl AssetCache {
+    pub fn open() -> Result<Self> {
+        let dirs = APP_DIRS
+            .as_ref()
+            .ok_or_else(|| anyhow!("Could not get project dirs"))?;
+
+        Self::open_at(dirs.cache_dir().join("assets"))
+    }
+
+    pub fn open_at(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn get(&self, key: CacheKey) -> Option<Vec<u8>> {
+        match fs::read(self.path(key)) {
+            Ok(data) => Some(data),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => {
+                tracing::warn!("Failed to read cached asset {key}: {err}");
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: CacheKey, data: &[u8]) -> Result<()> {
+        // write + rename, so a crash never leaves a truncated entry behind
+        let path = self.path(key);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// the cached output of `process` for `input`, or run `process` and cache the result
+    ///
+    /// bump `version` whenever the processor output changes
+    pub fn get_or_process(
+        &self,
+        processor: &str,
+        version: u32,
+        input: &[u8],
+        process: impl FnOnce(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let key = CacheKey::new(processor, version, input);
+        if let Some(data) = self.get(key) {
+            tracing::trace!("asset cache hit: {processor} {key}");
+            return Ok(data);
+        }
+
+        tracing::debug!("asset cache miss: {processor} {key}");
+        let data = process(input)?;
+        if let Err(err) = self.put(key, &data) {
+            tracing::warn!("Failed to cache asset {key}: {err}");
+        }
+        Ok(data)
+    }
+
+    fn path(&self, key: CacheKey) -> PathBuf {
+        self.dir.join(key.to_string())
+    }
+}
+
+impl CacheKey {
+    pub fn new(processor: &str, version: u32, input: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update((processor.len() as u64).to_le_bytes());
+        hasher.update(processor.as_bytes());
+        hasher.update(version.to_le_bytes());
+        hasher.update(input);
+        Self(hasher.finalize().into())
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn hits_misses_and_invalidation() {
+        let dir = std::env::temp_dir().join(format!("asset-cache-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        let cache = AssetCache::open_at(&dir).unwrap();
+
+        let runs = Cell::new(0);
+        let reverse = |input: &[u8]| {
+            runs.set(runs.get() + 1);
+            Ok(input.iter().rev().copied().collect())
+        };
+
+        // miss, then hit
+        assert_eq!(
+            cache.get_or_process("reverse", 1, b"abc", reverse).unwrap(),
+            b"cba"
+        );
+        assert_eq!(
+            cache.get_or_process("reverse", 1, b"abc", reverse).unwrap(),
+            b"cba"
+        );
+        assert_eq!(runs.get(), 1);
+
+        // a new input, processor version or processor is a new key
+        cache.get_or_process("reverse", 1, b"abd", reverse).unwrap();
+        cache.get_or_process("reverse", 2, b"abc", reverse).unwrap();
+        cache
+            .get_or_process("reverse2", 1, b"abc", reverse)
+            .unwrap();
+        assert_eq!(runs.get(), 4);
+
+        // failures aren't cached
+        let failing = |_: &[u8]| Err(anyhow!("broken"));
+        assert!(cache.get_or_process("fails", 1, b"abc", failing).is_err());
+        assert_eq!(cache.get(CacheKey::new("fails", 1, b"abc")), None);
+
+        // the processor name can't run into the input
+        assert_ne!(CacheKey::new("ab", 1, b"c"), CacheKey::new("a", 1, b"bc"));
+        // a fresh cache over the same directory sees the entries
+        let reopened = AssetCache::open_at(&dir).unwrap();
+        assert_eq!(
+            reopened.get(CacheKey::new("reverse", 1, b"abc")).unwrap(),
+            b"cba"
+        );
+        assert!(fs::read_dir(&dir).unwrap().all(|entry| entry
+            .unwrap()
+            .path()
+            .extension()
+            .is_none()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::Arc,
+};
+
+//
+
+/// an asset path relative to the asset root, always with `/` separators
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AssetId(Arc<str>);
+
+/// dependencies between assets (material -> textures -> shader)
+///
+/// used to figure out what has to be rebuilt, and in which order,
+/// when a single file changes
+#[derive(Debug, Default, Clone)]
+pub struct AssetGraph {
+    /// asset -> assets it depends on
+    deps: BTreeMap<AssetId, BTreeSet<AssetId>>,
+    /// asset -> assets that depend on it
+    dependents: BTreeMap<AssetId, BTreeSet<AssetId>>,
+}
+
+/// a dependency cycle, the first and the last asset are the same
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle(pub Vec<AssetId>);
+
+//
+
+impl AssetId {
+    pub fn new(path: &str) -> Self {
+        Self(path.replace('\\', "/").trim_start_matches("./").into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AssetGraph {
+    /// replace the dependencies of `asset`
+    ///
+    /// the graph is left untouched if the new dependencies would form a cycle
+    pub fn set_dependencies(
+        &mut self,
+        asset: AssetId,
+        deps: impl IntoIterator<Item = AssetId>,
+    ) -> Result<(), Cycle> {
+        let deps: BTreeSet<AssetId> = deps.into_iter().collect();
+
+        for dep in &deps {
+            if let Some(mut path) = self.path(dep, &asset) {
+                path.insert(0, asset.clone());
+                return Err(Cycle(path));
+            }
+        }
+
+        self.unlink(&asset);
+        for dep in &deps {
+            self.dependents
+                .entry(dep.clone())
+                .or_default()
+                .insert(asset.clone());
+        }
+        self.deps.insert(asset, deps);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, asset: &AssetId) {
+        self.unlink(asset);
+        self.deps.remove(asset);
+    }
+
+    pub fn dependencies(&self, asset: &AssetId) -> impl Iterator<Item = &AssetId> {
+        self.deps.get(asset).into_iter().flatten()
+    }
+
+    pub fn dependents(&self, asset: &AssetId) -> impl Iterator<Item = &AssetId> {
+        self.dependents.get(asset).into_iter().flatten()
+    }
+
+    /// everything that has to be rebuilt after `changed` changed,
+    /// `changed` itself included
+    ///
+    /// the list is ordered so that every asset comes after its own dependencies
+    pub fn invalidate(&self, changed: &AssetId) -> Vec<AssetId> {
+        self.invalidate_all([changed])
+    }
+
+    /// [`Self::invalidate`] for multiple changes at once,
+    /// shared dependents are rebuilt only once
+    pub fn invalidate_all<'a>(
+        &self,
+        changed: impl IntoIterator<Item = &'a AssetId>,
+    ) -> Vec<AssetId> {
+        let mut dirty = BTreeSet::new();
+        let mut stack: Vec<&AssetId> = changed.into_iter().collect();
+        while let Some(asset) = stack.pop() {
+            if dirty.insert(asset.clone()) {
+                stack.extend(self.dependents(asset));
+            }
+        }
+
+        // Kahn's algorithm limited to the dirty set
+        let mut pending: BTreeMap<&AssetId, usize> = dirty
+            .iter()
+            .map(|asset| {
+                let deps = self.dependencies(asset).filter(|d| dirty.contains(*d));
+                (asset, deps.count())
+            })
+            .collect();
+
+        let mut order = Vec::with_capacity(dirty.len());
+        let mut ready: BTreeSet<&AssetId> = pending
+            .iter()
+            .filter(|(_, &n)| n == 0)
+            .map(|(&asset, _)| asset)
+            .collect();
+
+        while let Some(asset) = ready.pop_first() {
+            pending.remove(asset);
+            order.push(asset.clone());
+
+            for dependent in self.dependents(asset) {
+                if let Some(n) = pending.get_mut(dependent) {
+                    *n -= 1;
+                    if *n == 0 {
+                        ready.insert(dependent);
+                    }
+                }
+            }
+        }
+
+        debug_assert!(pending.is_empty(), "cycles are rejected on insert");
+        order
+    }
+
+    /// dependency path `from -> .. -> to`
+    fn path(&self, from: &AssetId, to: &AssetId) -> Option<Vec<AssetId>> {
+        if from == to {
+            return Some(vec![from.clone()]);
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![vec![from.clone()]];
+        while let Some(path) = stack.pop() {
+            let last = path.last().unwrap();
+            for dep in self.dependencies(last) {
+                let mut next = path.clone();
+                next.push(dep.clone());
+                if dep == to {
+                    return Some(next);
+                }
+                if visited.insert(dep.clone()) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn unlink(&mut self, asset: &AssetId) {
+        for dep in self.deps.get(asset).into_iter().flatten() {
+            if let Some(dependents) = self.dependents.get_mut(dep) {
+                dependents.remove(asset);
+            }
+        }
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for AssetId {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("asset dependency cycle: ")?;
+        for (i, asset) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" -> ")?;
+            }
+            write!(f, "{asset}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Cycle {}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(paths: &[&str]) -> Vec<AssetId> {
+        paths.iter().map(|&path| path.into()).collect()
+    }
+
+    /// material -> two textures -> one shader
+    fn diamond() -> AssetGraph {
+        let mut graph = AssetGraph::default();
+        graph
+            .set_dependencies("material".into(), ids(&["albedo.png", "normal.png"]))
+            .unwrap();
+        graph
+            .set_dependencies("albedo.png".into(), ids(&["shader.wgsl"]))
+            .unwrap();
+        graph
+            .set_dependencies("normal.png".into(), ids(&["shader.wgsl"]))
+            .unwrap();
+        graph
+    }
+
+    #[test]
+    fn diamond_rebuilds_the_shared_dependent_once_and_last() {
+        let graph = diamond();
+        assert_eq!(
+            graph.invalidate(&"shader.wgsl".into()),
+            ids(&["shader.wgsl", "albedo.png", "normal.png", "material"])
+        );
+        assert_eq!(
+            graph.invalidate(&"normal.png".into()),
+            ids(&["normal.png", "material"])
+        );
+        assert_eq!(graph.invalidate(&"material".into()), ids(&["material"]));
+        // not in the graph at all
+        assert_eq!(graph.invalidate(&"other".into()), ids(&["other"]));
+    }
+
+    #[test]
+    fn invalidation_orders_dependencies_first() {
+        let mut graph = diamond();
+        graph
+            .set_dependencies("scene".into(), ids(&["material", "shader.wgsl"]))
+            .unwrap();
+
+        let order = graph.invalidate_all(&ids(&["scene", "albedo.png", "shader.wgsl"]));
+        assert_eq!(
+            order,
+            ids(&[
+                "shader.wgsl",
+                "albedo.png",
+                "normal.png",
+                "material",
+                "scene"
+            ])
+        );
+        for (i, asset) in order.iter().enumerate() {
+            for dep in graph.dependencies(asset) {
+                let at = order.iter().position(|a| a == dep).unwrap();
+                assert!(at < i, "{dep} after {asset}");
+            }
+        }
+    }
+
+    #[test]
+    fn cycles_are_rejected_and_leave_the_graph_alone() {
+        let mut graph = diamond();
+        let cycle = graph
+            .set_dependencies("shader.wgsl".into(), ids(&["material"]))
+            .unwrap_err();
+        assert_eq!(
+            cycle,
+            Cycle(ids(&[
+                "shader.wgsl",
+                "material",
+                "normal.png",
+                "shader.wgsl"
+            ]))
+        );
+        assert_eq!(
+            cycle.to_string(),
+            "asset dependency cycle: shader.wgsl -> material -> normal.png -> shader.wgsl"
+        );
+        assert_eq!(graph.dependencies(&"shader.wgsl".into()).count(), 0);
+
+        let itself = graph.set_dependencies("a".into(), ids(&["a"])).unwrap_err();
+        assert_eq!(itself, Cycle(ids(&["a", "a"])));
+
+        // replacing the dependencies unlinks the old ones, which allows the edge
+        graph
+            .set_dependencies("material".into(), ids(&["normal.png"]))
+            .unwrap();
+        graph.remove(&"normal.png".into());
+        graph
+            .set_dependencies("shader.wgsl".into(), ids(&["material"]))
+            .unwrap();
+        assert_eq!(graph.invalidate(&"albedo.png".into()), ids(&["albedo.png"]));
+    }
+}
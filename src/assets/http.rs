@@ -0,0 +1,197 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+use crate::settings::RemoteAsset;
+
+use super::{AssetId, AssetSource};
+
+//
+
+/// assets downloaded over HTTP on first use
+///
+/// downloads are kept in a local directory and validated with their sha256,
+/// [`AssetSource::read`] never blocks: a missing asset starts a download on the tokio runtime
+/// and reports `None`, the id is sent to `arrived` once it is available
+pub struct HttpSource {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    dir: PathBuf,
+    client: reqwest::Client,
+    entries: HashMap<AssetId, RemoteAsset>,
+    in_flight: Mutex<HashSet<AssetId>>,
+    arrived: Sender<AssetId>,
+}
+
+//
+
+impl HttpSource {
+    pub fn new(dir: PathBuf, entries: &[RemoteAsset], arrived: Sender<AssetId>) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let entries = entries
+            .iter()
+            .map(|entry| (AssetId::new(&entry.id), entry.clone()))
+            .collect();
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                dir,
+                client: reqwest::Client::new(),
+                entries,
+                in_flight: <_>::default(),
+                arrived,
+            }),
+        })
+    }
+
+    /// start downloading every asset that isn't available locally and intact yet
+    pub fn prefetch(&self) {
+        for id in self.inner.entries.keys() {
+            if self.inner.local(id).is_none() {
+                self.inner.clone().spawn_download(id.clone());
+            }
+        }
+    }
+}
+
+impl AssetSource for HttpSource {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    fn read(&self, id: &AssetId) -> Result<Option<Vec<u8>>> {
+        if !self.inner.entries.contains_key(id) {
+            return Ok(None);
+        }
+
+        if let Some(data) = self.inner.local(id) {
+            return Ok(Some(data));
+        }
+
+        self.inner.clone().spawn_download(id.clone());
+        Ok(None)
+    }
+}
+
+impl Inner {
+    fn path(&self, entry: &RemoteAsset) -> PathBuf {
+        self.dir.join(entry.sha256.to_ascii_lowercase())
+    }
+
+    /// the downloaded file, if it exists and is intact,
+    /// a corrupted one is deleted so it gets downloaded again
+    fn local(&self, id: &AssetId) -> Option<Vec<u8>> {
+        let entry = self.entries.get(id)?;
+        let path = self.path(entry);
+        let data = fs::read(&path).ok()?;
+        if verify(&data, &entry.sha256) {
+            return Some(data);
+        }
+
+        tracing::warn!("Downloaded {id} is corrupted, downloading again");
+        if let Err(err) = fs::remove_file(&path) {
+            tracing::error!("Failed to delete the corrupted {}: {err}", path.display());
+        }
+        None
+    }
+
+    fn spawn_download(self: Arc<Self>, id: AssetId) {
+        if !self.in_flight.lock().unwrap().insert(id.clone()) {
+            return;
+        }
+
+        tokio::spawn(async move {
+            match self.download(&id).await {
+                Ok(()) => {
+                    tracing::info!("downloaded {id}");
+                    _ = self.arrived.send(id.clone());
+                }
+                Err(err) => tracing::error!("Failed to download {id}: {err}"),
+            }
+            self.in_flight.lock().unwrap().remove(&id);
+        });
+    }
+
+    async fn download(&self, id: &AssetId) -> Result<()> {
+        let entry = &self.entries[id];
+        tracing::info!("downloading {id} from {}", entry.url);
+
+        let data = self
+            .client
+            .get(entry.url.as_ref())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        if !verify(&data, &entry.sha256) {
+            return Err(anyhow!("checksum mismatch"));
+        }
+
+        let path = self.path(entry);
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, &data).await?;
+        tokio::fs::rename(tmp, path).await?;
+
+        Ok(())
+    }
+}
+
+fn verify(data: &[u8], sha256: &str) -> bool {
+    let hash = Sha256::digest(data);
+    let hex: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+    hex.eq_ignore_ascii_case(sha256)
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn corrupted_downloads_are_deleted() {
+        let dir = std::env::temp_dir().join(format!("http-source-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        let sha256: String = Sha256::digest(b"model")
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        let entry = RemoteAsset {
+            id: "models/a.glb".into(),
+            url: "http://localhost/a.glb".into(),
+            sha256: sha256.into(),
+        };
+        let source =
+            HttpSource::new(dir.clone(), std::slice::from_ref(&entry), mpsc::channel().0).unwrap();
+        let (inner, id) = (&source.inner, AssetId::new("models/a.glb"));
+        let path = inner.path(&entry);
+
+        assert!(verify(b"model", &entry.sha256));
+        assert!(!verify(b"modem", &entry.sha256));
+        assert_eq!(inner.local(&id), None);
+
+        fs::write(&path, b"modem").unwrap();
+        assert_eq!(inner.local(&id), None);
+        assert!(!path.exists());
+
+        fs::write(&path, b"model").unwrap();
+        assert_eq!(inner.local(&id).as_deref(), Some(&b"model"[..]));
+        // other ids aren't this source's
+        assert_eq!(source.read(&"models/b.glb".into()).unwrap(), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
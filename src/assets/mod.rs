@@ -0,0 +1,294 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use anyhow::{anyhow, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{dirs::APP_DIRS, settings::AssetSettings};
+
+pub use self::{
+    cache::{AssetCache, CacheKey},
+    graph::{AssetGraph, AssetId, Cycle},
+    http::HttpSource,
+    source::{AssetSource, DirSource, PakSource, ZipSource},
+};
+
+//
+
+pub mod cache;
+pub mod graph;
+pub mod http;
+pub mod simplify;
+pub mod source;
+
+//
+
+/// asset sources, dependency tracking and hot reload
+pub struct Assets {
+    root: PathBuf,
+    /// highest priority first
+    sources: Vec<Box<dyn AssetSource>>,
+    pub graph: AssetGraph,
+    pub cache: Option<AssetCache>,
+
+    watcher: Option<(RecommendedWatcher, Receiver<PathBuf>)>,
+    /// assets that became available in the background (downloads)
+    arrived: (Sender<AssetId>, Receiver<AssetId>),
+}
+
+//
+
+impl Assets {
+    pub fn new(settings: &AssetSettings) -> Self {
+        let cache = AssetCache::open()
+            .map_err(|err| tracing::warn!("Asset cache disabled: {err}"))
+            .ok();
+
+        // loose files beat archives, later archives beat earlier ones
+        let mut sources: Vec<Box<dyn AssetSource>> = vec![Box::new(DirSource::new(&settings.root))];
+        for archive in settings.archives.iter().rev() {
+            match source::open_archive(archive) {
+                Ok(source) => sources.push(source),
+                Err(err) => tracing::error!("Failed to mount {}: {err}", archive.display()),
+            }
+        }
+
+        let mut assets = Self {
+            root: settings.root.clone(),
+            sources,
+            graph: AssetGraph::default(),
+            cache,
+            watcher: None,
+            arrived: mpsc::channel(),
+        };
+
+        if !settings.remote.is_empty() {
+            if let Err(err) = assets.mount_remote(settings) {
+                tracing::error!("Failed to mount remote assets: {err}");
+            }
+        }
+
+        if settings.hot_reload {
+            if let Err(err) = assets.watch() {
+                tracing::warn!("Asset hot reload disabled: {err}");
+            }
+        }
+
+        assets
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// mount another source with the lowest priority
+    pub fn mount(&mut self, source: Box<dyn AssetSource>) {
+        tracing::debug!("mounted asset source {}", source.name());
+        self.sources.push(source);
+    }
+
+    /// read `id` from the highest priority source that has it
+    pub fn read(&self, id: &AssetId) -> Result<Vec<u8>> {
+        for source in &self.sources {
+            if let Some(data) = source.read(id)? {
+                tracing::trace!("loaded {id} from {}", source.name());
+                return Ok(data);
+            }
+        }
+
+        Err(anyhow!("asset not found: {id}"))
+    }
+
+    /// `id` after `process`, through the processed asset cache when there is one,
+    /// see [`AssetCache::get_or_process`]
+    pub fn read_processed(
+        &self,
+        id: &AssetId,
+        processor: &str,
+        version: u32,
+        process: impl FnOnce(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let source = self.read(id)?;
+        match self.cache.as_ref() {
+            Some(cache) => cache.get_or_process(processor, version, &source, process),
+            None => process(&source),
+        }
+    }
+
+    /// assets that have to be reloaded since the last call, changed files and
+    /// finished downloads, ordered so that dependencies are reloaded before their dependents
+    pub fn poll_changes(&mut self) -> Vec<AssetId> {
+        let mut changed: Vec<AssetId> = self
+            .watcher
+            .iter()
+            .flat_map(|(_, rx)| rx.try_iter())
+            .filter_map(|path| self.id_of(&path))
+            .collect();
+        changed.extend(self.arrived.1.try_iter());
+        if changed.is_empty() {
+            return changed;
+        }
+
+        let reload = self.graph.invalidate_all(&changed);
+        tracing::debug!("assets changed: {changed:?}, reloading: {reload:?}");
+        reload
+    }
+
+    fn mount_remote(&mut self, settings: &AssetSettings) -> Result<()> {
+        let dirs = APP_DIRS
+            .as_ref()
+            .ok_or_else(|| anyhow!("Could not get project dirs"))?;
+
+        let source = HttpSource::new(
+            dirs.cache_dir().join("downloads"),
+            &settings.remote,
+            self.arrived.0.clone(),
+        )?;
+        source.prefetch();
+        self.mount(Box::new(source));
+
+        Ok(())
+    }
+
+    fn watch(&mut self) -> Result<()> {
+        if !self.root.is_dir() {
+            tracing::debug!("asset root {} not found, not watching", self.root.display());
+            return Ok(());
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+                Ok(event)
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) =>
+                {
+                    for path in event.paths {
+                        _ = tx.send(path);
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!("Asset watcher error: {err}"),
+            })?;
+
+        watcher.watch(&self.root, RecursiveMode::Recursive)?;
+        self.watcher = Some((watcher, rx));
+
+        Ok(())
+    }
+
+    fn id_of(&self, path: &Path) -> Option<AssetId> {
+        // the watcher may report absolute paths
+        let root = self.root.canonicalize().ok();
+        let rel = path
+            .strip_prefix(&self.root)
+            .ok()
+            .or_else(|| path.strip_prefix(root.as_ref()?).ok())?;
+
+        Some(AssetId::new(rel.to_str()?))
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loose_files_beat_archives_and_later_archives_earlier_ones() {
+        let dir = std::env::temp_dir().join(format!("asset-override-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("root")).unwrap();
+        std::fs::write(dir.join("root/a"), "loose").unwrap();
+
+        let pak = |name: &str, entries: &[(&str, &str)]| {
+            let path = dir.join(name);
+            let ids: Vec<AssetId> = entries.iter().map(|&(id, _)| id.into()).collect();
+            let data = entries.iter().map(|(_, data)| data.as_bytes());
+            PakSource::write(std::fs::File::create(&path).unwrap(), ids.iter().zip(data)).unwrap();
+            path
+        };
+        let first = pak(
+            "first.pak",
+            &[("a", "first"), ("b", "first"), ("c", "first")],
+        );
+        let second = pak("second.pak", &[("a", "second"), ("b", "second")]);
+
+        let assets = Assets::new(&AssetSettings {
+            root: dir.join("root"),
+            archives: vec![first, second],
+            hot_reload: false,
+            ..AssetSettings::default()
+        });
+        let read = |id: &str| String::from_utf8(assets.read(&id.into()).unwrap()).unwrap();
+        assert_eq!(read("a"), "loose");
+        assert_eq!(read("b"), "second");
+        assert_eq!(read("c"), "first");
+        assert!(assets.read(&"d".into()).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn processed_assets_follow_their_source() {
+        let dir = std::env::temp_dir().join(format!("processed-assets-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("root")).unwrap();
+        let mut assets = Assets::new(&AssetSettings {
+            root: dir.join("root"),
+            hot_reload: false,
+            ..AssetSettings::default()
+        });
+        assets.cache = Some(AssetCache::open_at(dir.join("cache")).unwrap());
+
+        let runs = std::cell::Cell::new(0);
+        let upper = |data: &[u8]| {
+            runs.set(runs.get() + 1);
+            Ok(data.to_ascii_uppercase())
+        };
+        let id = AssetId::new("shaders/note.txt");
+        std::fs::create_dir_all(dir.join("root/shaders")).unwrap();
+        std::fs::write(dir.join("root/shaders/note.txt"), "first").unwrap();
+
+        assert_eq!(
+            assets.read_processed(&id, "upper", 1, upper).unwrap(),
+            b"FIRST"
+        );
+        assert_eq!(
+            assets.read_processed(&id, "upper", 1, upper).unwrap(),
+            b"FIRST"
+        );
+        assert_eq!(runs.get(), 1);
+
+        // an edited source is a new key
+        std::fs::write(dir.join("root/shaders/note.txt"), "second").unwrap();
+        assert_eq!(
+            assets.read_processed(&id, "upper", 1, upper).unwrap(),
+            b"SECOND"
+        );
+        assert_eq!(runs.get(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn finished_downloads_are_reloaded_without_a_watcher() {
+        let mut assets = Assets::new(&AssetSettings {
+            root: PathBuf::from("does/not/exist"),
+            hot_reload: false,
+            ..AssetSettings::default()
+        });
+        assert!(assets.watcher.is_none());
+        assert!(assets.poll_changes().is_empty());
+
+        let model = AssetId::new("models/sponza.glb");
+        assets.arrived.0.send(model.clone()).unwrap();
+        assert_eq!(assets.poll_changes(), [model]);
+        assert!(assets.poll_changes().is_empty());
+    }
+}
@@ -0,0 +1,459 @@
+use std::{cmp::Ordering, collections::BTreeMap, collections::BinaryHeap};
+
+use anyhow::{bail, Result};
+use glam::{DVec3, Vec3};
+
+use super::AssetCache;
+
+//
+
+/// one level of detail, indices into the original vertices
+///
+/// vertices are never moved or created, collapses keep one end of the edge,
+/// so every level shares the full detail vertex buffer and its attributes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lod {
+    pub indices: Vec<u32>,
+    /// roughly how far the surface moved, in mesh units, for picking a level by screen size
+    pub error: f32,
+}
+
+/// plane distance squared, summed, with the total weight for the average
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric {
+    /// xx xy xz xw yy yz yw zz zw ww
+    q: [f64; 10],
+    weight: f64,
+}
+
+#[derive(Debug, PartialEq)]
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    versions: (u32, u32),
+}
+
+//
+
+/// bump when the output of [`simplify`] or [`lod_chain`] changes
+pub const VERSION: u32 = 1;
+
+/// boundaries and UV seams only move if nothing else can
+const BOUNDARY_WEIGHT: f64 = 10.0;
+
+/// quadric error edge collapse down to about `target_triangles`
+///
+/// stops early when no collapse is left that wouldn't flip a triangle
+pub fn simplify(positions: &[Vec3], indices: &[u32], target_triangles: usize) -> Lod {
+    assert_eq!(indices.len() % 3, 0);
+    let positions: Vec<DVec3> = positions.iter().map(|p| p.as_dvec3()).collect();
+    let mut triangles: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]])
+        .filter(|[a, b, c]| a != b && b != c && c != a)
+        .collect();
+    let mut alive = vec![true; triangles.len()];
+    let mut live = triangles.len();
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    // ordered, so the quadrics sum up the same way and ties break the same way every run
+    let mut edges: BTreeMap<(u32, u32), (u32, usize)> = BTreeMap::new();
+    for (i, t) in triangles.iter().enumerate() {
+        let [a, b, c] = t.map(|v| positions[v as usize]);
+        let normal = (b - a).cross(c - a);
+        let area = normal.length() * 0.5;
+        if area > 0.0 {
+            let plane = Quadric::plane(normal.normalize(), a, area);
+            for &v in t {
+                quadrics[v as usize].add(&plane);
+            }
+        }
+        for e in 0..3 {
+            let (a, b) = (t[e], t[(e + 1) % 3]);
+            edges.entry((a.min(b), a.max(b))).or_insert((0, i)).0 += 1;
+        }
+    }
+
+    // a plane through each boundary edge, perpendicular to its triangle
+    for (&(a, b), &(uses, triangle)) in &edges {
+        if uses != 1 {
+            continue;
+        }
+        let t = triangles[triangle].map(|v| positions[v as usize]);
+        let normal = (t[1] - t[0]).cross(t[2] - t[0]);
+        let edge = positions[b as usize] - positions[a as usize];
+        let perpendicular = edge.cross(normal).normalize_or_zero();
+        if perpendicular != DVec3::ZERO {
+            let plane = Quadric::plane(
+                perpendicular,
+                positions[a as usize],
+                BOUNDARY_WEIGHT * edge.length_squared(),
+            );
+            quadrics[a as usize].add(&plane);
+            quadrics[b as usize].add(&plane);
+        }
+    }
+
+    let mut vertex_triangles = vec![Vec::new(); positions.len()];
+    for (i, t) in triangles.iter().enumerate() {
+        for &v in t {
+            vertex_triangles[v as usize].push(i as u32);
+        }
+    }
+
+    let mut versions = vec![0u32; positions.len()];
+    let mut heap = BinaryHeap::new();
+    let push = |heap: &mut BinaryHeap<Collapse>,
+                quadrics: &[Quadric],
+                versions: &[u32],
+                a: u32,
+                b: u32| {
+        let q = quadrics[a as usize].sum(&quadrics[b as usize]);
+        let to_a = q.error(positions[a as usize]);
+        let to_b = q.error(positions[b as usize]);
+        let (from, to, cost) = if to_b <= to_a {
+            (a, b, to_b)
+        } else {
+            (b, a, to_a)
+        };
+        heap.push(Collapse {
+            cost,
+            from,
+            to,
+            versions: (versions[from as usize], versions[to as usize]),
+        });
+    };
+    for &(a, b) in edges.keys() {
+        push(&mut heap, &quadrics, &versions, a, b);
+    }
+
+    let mut error = 0.0f64;
+    while live > target_triangles {
+        let Some(collapse) = heap.pop() else {
+            break;
+        };
+        let (from, to) = (collapse.from as usize, collapse.to as usize);
+        if collapse.versions != (versions[from], versions[to]) {
+            continue;
+        }
+
+        // moving `from` onto `to` must not turn any remaining triangle over
+        // (the ones around the edge disappear instead)
+        let flips = vertex_triangles[from].iter().any(|&i| {
+            let t = triangles[i as usize];
+            if !alive[i as usize] || t.contains(&(to as u32)) {
+                return false;
+            }
+            let before = t.map(|v| positions[v as usize]);
+            let after = t.map(|v| positions[if v as usize == from { to } else { v as usize }]);
+            let n0 = (before[1] - before[0]).cross(before[2] - before[0]);
+            let n1 = (after[1] - after[0]).cross(after[2] - after[0]);
+            n0.dot(n1) <= 0.0
+        });
+        if flips {
+            continue;
+        }
+
+        for t in std::mem::take(&mut vertex_triangles[from]) {
+            if !alive[t as usize] {
+                continue;
+            }
+            let triangle = &mut triangles[t as usize];
+            if triangle.contains(&(to as u32)) {
+                alive[t as usize] = false;
+                live -= 1;
+            } else {
+                for v in triangle.iter_mut().filter(|v| **v as usize == from) {
+                    *v = to as u32;
+                }
+                vertex_triangles[to].push(t);
+            }
+        }
+        let merged = quadrics[to].sum(&quadrics[from]);
+        quadrics[to] = merged;
+        // stale entries for either end are skipped from now on
+        versions[from] += 1;
+        versions[to] += 1;
+        error = error.max(collapse.cost);
+
+        vertex_triangles[to].retain(|&t| alive[t as usize]);
+        let mut neighbours: Vec<u32> = vertex_triangles[to]
+            .iter()
+            .flat_map(|&t| triangles[t as usize])
+            .filter(|&v| v as usize != to)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for v in neighbours {
+            push(&mut heap, &quadrics, &versions, to as u32, v);
+        }
+    }
+
+    Lod {
+        indices: triangles
+            .iter()
+            .zip(&alive)
+            .filter(|(_, alive)| **alive)
+            .flat_map(|(t, _)| *t)
+            .collect(),
+        error: error.sqrt() as f32,
+    }
+}
+
+/// the full detail mesh and up to `levels` more, each about half the triangles of the last
+///
+/// each level is simplified from the one before, so errors only grow
+pub fn lod_chain(positions: &[Vec3], indices: &[u32], levels: usize) -> Vec<Lod> {
+    let mut chain = vec![Lod {
+        indices: indices.to_vec(),
+        error: 0.0,
+    }];
+    for _ in 0..levels {
+        let last = chain.last().unwrap();
+        let triangles = last.indices.len() / 3;
+        let mut lod = simplify(positions, &last.indices, triangles / 2);
+        // barely simpler isn't worth a level
+        if lod.indices.len() / 3 * 10 > triangles * 9 || lod.indices.is_empty() {
+            break;
+        }
+        lod.error = lod.error.max(last.error);
+        chain.push(lod);
+    }
+    chain
+}
+
+/// [`lod_chain`] through the processed asset cache
+pub fn cached_lod_chain(
+    cache: Option<&AssetCache>,
+    positions: &[Vec3],
+    indices: &[u32],
+    levels: usize,
+) -> Result<Vec<Lod>> {
+    let Some(cache) = cache else {
+        return Ok(lod_chain(positions, indices, levels));
+    };
+
+    let input = [
+        &(levels as u32).to_le_bytes()[..],
+        &(positions.len() as u32).to_le_bytes(),
+        bytemuck::cast_slice(positions),
+        bytemuck::cast_slice(indices),
+    ]
+    .concat();
+    let data = cache.get_or_process("lod chain", VERSION, &input, |_| {
+        Ok(encode(&lod_chain(positions, indices, levels)))
+    })?;
+    decode(&data)
+}
+
+/// error, index count and indices per level, little endian
+fn encode(chain: &[Lod]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for lod in chain {
+        data.extend(lod.error.to_le_bytes());
+        data.extend((lod.indices.len() as u32).to_le_bytes());
+        data.extend(lod.indices.iter().flat_map(|i| i.to_le_bytes()));
+    }
+    data
+}
+
+fn decode(mut data: &[u8]) -> Result<Vec<Lod>> {
+    let next = |data: &mut &[u8]| -> Result<[u8; 4]> {
+        let Some((word, rest)) = data.split_first_chunk::<4>() else {
+            bail!("truncated LOD chain");
+        };
+        *data = rest;
+        Ok(*word)
+    };
+
+    let mut chain = Vec::new();
+    while !data.is_empty() {
+        let error = f32::from_le_bytes(next(&mut data)?);
+        let len = u32::from_le_bytes(next(&mut data)?);
+        let indices = (0..len)
+            .map(|_| next(&mut data).map(u32::from_le_bytes))
+            .collect::<Result<_>>()?;
+        chain.push(Lod { indices, error });
+    }
+    Ok(chain)
+}
+
+impl Quadric {
+    /// `weight` × squared distance to the plane through `point`
+    fn plane(normal: DVec3, point: DVec3, weight: f64) -> Self {
+        let [a, b, c] = normal.to_array();
+        let d = -normal.dot(point);
+        Self {
+            q: [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * weight),
+            weight,
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        for (q, o) in self.q.iter_mut().zip(other.q) {
+            *q += o;
+        }
+        self.weight += other.weight;
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        let mut sum = *self;
+        sum.add(other);
+        sum
+    }
+
+    /// the weighted mean squared distance
+    fn error(&self, p: DVec3) -> f64 {
+        let q = &self.q;
+        let DVec3 { x, y, z } = p;
+        let e = q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9];
+        e.max(0.0) / self.weight.max(f64::MIN_POSITIVE)
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// cheapest first out of the max heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` × `n` quads on the unit square
+    fn grid(n: u32) -> (Vec<Vec3>, Vec<u32>) {
+        let positions = (0..=n)
+            .flat_map(|y| (0..=n).map(move |x| Vec3::new(x as f32, y as f32, 0.0) / n as f32))
+            .collect();
+        let indices = (0..n)
+            .flat_map(|y| {
+                (0..n).flat_map(move |x| {
+                    let i = y * (n + 1) + x;
+                    [i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]
+                })
+            })
+            .collect();
+        (positions, indices)
+    }
+
+    fn sphere(rings: u32, segments: u32) -> (Vec<Vec3>, Vec<u32>) {
+        let mut positions = vec![Vec3::Y, -Vec3::Y];
+        for ring in 1..rings {
+            let theta = ring as f32 / rings as f32 * std::f32::consts::PI;
+            for segment in 0..segments {
+                let phi = segment as f32 / segments as f32 * std::f32::consts::TAU;
+                positions.push(Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ));
+            }
+        }
+        let at = |ring: u32, segment: u32| 2 + (ring - 1) * segments + segment % segments;
+        let mut indices = Vec::new();
+        for s in 0..segments {
+            indices.extend([0, at(1, s + 1), at(1, s)]);
+            indices.extend([1, at(rings - 1, s), at(rings - 1, s + 1)]);
+            for ring in 1..rings - 1 {
+                let (a, b) = (at(ring, s), at(ring, s + 1));
+                let (c, d) = (at(ring + 1, s), at(ring + 1, s + 1));
+                indices.extend([a, b, d, a, d, c]);
+            }
+        }
+        (positions, indices)
+    }
+
+    fn signed_area(positions: &[Vec3], indices: &[u32]) -> f32 {
+        indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| positions[i as usize]);
+                (b - a).cross(c - a).z * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn flat_grid_collapses_without_error() {
+        let (positions, indices) = grid(10);
+        let lod = simplify(&positions, &indices, 2);
+
+        assert!(
+            lod.indices.len() / 3 <= 8,
+            "{} triangles",
+            lod.indices.len() / 3
+        );
+        assert!(lod.error < 1e-4, "{}", lod.error);
+        // the outline stays, nothing folds over
+        assert!((signed_area(&positions, &lod.indices) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sphere_chain_gets_coarser() {
+        let (positions, indices) = sphere(24, 48);
+        let chain = lod_chain(&positions, &indices, 6);
+        assert!(chain.len() >= 5, "{} levels", chain.len());
+
+        for pair in chain.windows(2) {
+            assert!(pair[1].indices.len() < pair[0].indices.len());
+            assert!(pair[1].error >= pair[0].error);
+        }
+        for lod in &chain {
+            assert!(lod
+                .indices
+                .chunks_exact(3)
+                .all(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0]));
+        }
+        // a handful of triangles is still roughly the unit sphere
+        let last = chain.last().unwrap();
+        assert!(last.error < 0.5, "{}", last.error);
+    }
+
+    #[test]
+    fn cached_chain_round_trips() {
+        let dir = std::env::temp_dir().join(format!("lod-cache-{}", std::process::id()));
+        let cache = AssetCache::open_at(&dir).unwrap();
+        let (positions, indices) = sphere(8, 16);
+
+        let fresh = cached_lod_chain(Some(&cache), &positions, &indices, 3).unwrap();
+        let cached = cached_lod_chain(Some(&cache), &positions, &indices, 3).unwrap();
+        assert_eq!(fresh, cached);
+        assert_eq!(fresh, lod_chain(&positions, &indices, 3));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Result};
+use zip::{result::ZipError, ZipArchive};
+
+use super::AssetId;
+
+//
+
+/// somewhere assets can be read from
+///
+/// sources are read-only, [`super::Assets`] asks each mounted source in order
+pub trait AssetSource: Send + Sync {
+    /// for logs
+    fn name(&self) -> &str;
+
+    /// `Ok(None)` if this source doesn't have `id`
+    fn read(&self, id: &AssetId) -> Result<Option<Vec<u8>>>;
+}
+
+/// loose files in a directory
+pub struct DirSource {
+    name: String,
+    root: PathBuf,
+}
+
+/// a zip archive
+pub struct ZipSource {
+    name: String,
+    archive: Mutex<ZipArchive<File>>,
+}
+
+/// a `.pak` file: a tiny uncompressed archive with an index up front
+///
+/// ```text
+/// "WPAK" u32:version u32:count
+/// count * (u16:name_len name u64:offset u64:len)
+/// data
+/// ```
+///
+/// all integers are little endian, offsets are from the start of the file
+pub struct PakSource {
+    name: String,
+    file: Mutex<File>,
+    index: HashMap<AssetId, (u64, u64)>,
+}
+
+//
+
+impl DirSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            name: root.display().to_string(),
+            root,
+        }
+    }
+}
+
+impl AssetSource for DirSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&self, id: &AssetId) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.root.join(id.as_str())) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl ZipSource {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            name: path.display().to_string(),
+            archive: Mutex::new(ZipArchive::new(File::open(path)?)?),
+        })
+    }
+}
+
+impl AssetSource for ZipSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&self, id: &AssetId) -> Result<Option<Vec<u8>>> {
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = match archive.by_name(id.as_str()) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut buf = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut buf)?;
+        Ok(Some(buf))
+    }
+}
+
+impl PakSource {
+    const MAGIC: &'static [u8; 4] = b"WPAK";
+    const VERSION: u32 = 1;
+
+    /// the bytes an index entry takes besides its name
+    const ENTRY_LEN: u64 = 2 + 8 + 8;
+
+    /// the index is checked against the file size, a corrupt or truncated pak
+    /// is an error instead of a huge allocation
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != Self::MAGIC {
+            return Err(anyhow!("{} is not a pak file", path.display()));
+        }
+        let version = read_u32(&mut file)?;
+        if version != Self::VERSION {
+            return Err(anyhow!("unsupported pak version {version}"));
+        }
+
+        let count = read_u32(&mut file)?;
+        if count as u64 * Self::ENTRY_LEN > file_len.saturating_sub(12) {
+            return Err(anyhow!(
+                "corrupt pak {}: {count} entries don't fit in {file_len} bytes",
+                path.display()
+            ));
+        }
+        let mut index = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut len = [0u8; 2];
+            file.read_exact(&mut len)?;
+            let mut name = vec![0u8; u16::from_le_bytes(len) as usize];
+            file.read_exact(&mut name)?;
+
+            let offset = read_u64(&mut file)?;
+            let len = read_u64(&mut file)?;
+            if offset.checked_add(len).is_none_or(|end| end > file_len) {
+                return Err(anyhow!(
+                    "corrupt pak {}: {} ends past the end of the file",
+                    path.display(),
+                    String::from_utf8_lossy(&name)
+                ));
+            }
+            index.insert(AssetId::new(std::str::from_utf8(&name)?), (offset, len));
+        }
+
+        Ok(Self {
+            name: path.display().to_string(),
+            file: Mutex::new(file),
+            index,
+        })
+    }
+
+    /// write a pak file containing `entries`
+    pub fn write<'a>(
+        mut to: impl Write,
+        entries: impl IntoIterator<Item = (&'a AssetId, &'a [u8])>,
+    ) -> Result<()> {
+        let entries: Vec<_> = entries.into_iter().collect();
+
+        let header_len = 12
+            + entries
+                .iter()
+                .map(|(id, _)| 2 + id.as_str().len() as u64 + 16)
+                .sum::<u64>();
+
+        to.write_all(Self::MAGIC)?;
+        to.write_all(&Self::VERSION.to_le_bytes())?;
+        to.write_all(&u32::try_from(entries.len())?.to_le_bytes())?;
+
+        let mut offset = header_len;
+        for (id, data) in &entries {
+            to.write_all(&u16::try_from(id.as_str().len())?.to_le_bytes())?;
+            to.write_all(id.as_str().as_bytes())?;
+            to.write_all(&offset.to_le_bytes())?;
+            to.write_all(&(data.len() as u64).to_le_bytes())?;
+            offset += data.len() as u64;
+        }
+
+        for (_, data) in &entries {
+            to.write_all(data)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl AssetSource for PakSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&self, id: &AssetId) -> Result<Option<Vec<u8>>> {
+        let Some(&(offset, len)) = self.index.get(id) else {
+            return Ok(None);
+        };
+
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; len as usize];
+        file.read_exact(&mut buf)?;
+        Ok(Some(buf))
+    }
+}
+
+/// open a `.zip` or `.pak` archive based on its extension
+pub fn open_archive(path: &Path) -> Result<Box<dyn AssetSource>> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("zip") => Ok(Box::new(ZipSource::open(path)?)),
+        Some("pak") => Ok(Box::new(PakSource::open(path)?)),
+        _ => Err(anyhow!("unknown archive type: {}", path.display())),
+    }
+}
+
+fn read_u32(from: &mut impl Read) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    from.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(from: &mut impl Read) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    from.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries() -> Vec<(AssetId, Vec<u8>)> {
+        vec![
+            ("shaders/a.wgsl".into(), b"fn a() {}".to_vec()),
+            ("empty".into(), Vec::new()),
+            ("textures/b.png".into(), (0..=255).collect()),
+        ]
+    }
+
+    #[test]
+    fn pak_round_trips() {
+        let dir = temp_dir("pak-round-trip");
+        let path = dir.join("assets.pak");
+        let entries = entries();
+        PakSource::write(
+            File::create(&path).unwrap(),
+            entries.iter().map(|(id, data)| (id, &data[..])),
+        )
+        .unwrap();
+
+        let pak = open_archive(&path).unwrap();
+        for (id, data) in &entries {
+            assert_eq!(pak.read(id).unwrap().as_ref(), Some(data));
+        }
+        assert_eq!(pak.read(&"missing".into()).unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn zip_round_trips() {
+        let dir = temp_dir("zip-round-trip");
+        let path = dir.join("assets.zip");
+        let entries = entries();
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (id, data) in &entries {
+            zip.start_file(id.as_str(), FileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let archive = open_archive(&path).unwrap();
+        for (id, data) in &entries {
+            assert_eq!(archive.read(id).unwrap().as_ref(), Some(data));
+        }
+        assert_eq!(archive.read(&"missing".into()).unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_paks_are_rejected() {
+        let dir = temp_dir("pak-corrupt");
+        let mut pak = Vec::new();
+        let entries = entries();
+        PakSource::write(&mut pak, entries.iter().map(|(id, data)| (id, &data[..]))).unwrap();
+
+        let open = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            PakSource::open(&path).map(|_| ())
+        };
+        assert!(open("ok.pak", &pak).is_ok());
+
+        // billions of entries in a tiny file
+        let mut huge_count = pak.clone();
+        huge_count[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(open("count.pak", &huge_count).is_err());
+
+        // an entry claiming exabytes
+        let mut huge_len = pak.clone();
+        let len_at = 12 + 2 + "shaders/a.wgsl".len() + 8;
+        huge_len[len_at..len_at + 8].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        assert!(open("len.pak", &huge_len).is_err());
+
+        // cut off in the data
+        assert!(open("truncated.pak", &pak[..pak.len() - 1]).is_err());
+        // cut off in the index
+        assert!(open("index.pak", &pak[..20]).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
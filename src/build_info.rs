@@ -0,0 +1,53 @@
+use std::fmt;
+
+use serde::Serialize;
+
+//
+
+/// what exactly is running, for bug reports
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// short commit hash, `-dirty` if there were uncommitted changes
+    pub git_hash: &'static str,
+    pub profile: &'static str,
+    pub target: &'static str,
+    /// comma separated cargo features
+    pub features: &'static str,
+    pub wgpu: &'static str,
+    pub winit: &'static str,
+}
+
+pub const BUILD: BuildInfo = BuildInfo {
+    name: env!("CARGO_PKG_NAME"),
+    version: env!("CARGO_PKG_VERSION"),
+    git_hash: env!("BUILD_GIT_HASH"),
+    profile: env!("BUILD_PROFILE"),
+    target: env!("BUILD_TARGET"),
+    features: env!("BUILD_FEATURES"),
+    wgpu: env!("BUILD_WGPU"),
+    winit: env!("BUILD_WINIT"),
+};
+
+//
+
+impl fmt::Display for BuildInfo {
+    /// the about panel
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {} ({})", self.name, self.version, self.git_hash)?;
+        writeln!(f, "profile:  {}", self.profile)?;
+        writeln!(f, "target:   {}", self.target)?;
+        writeln!(
+            f,
+            "features: {}",
+            if self.features.is_empty() {
+                "none"
+            } else {
+                self.features
+            }
+        )?;
+        writeln!(f, "wgpu:     {}", self.wgpu)?;
+        write!(f, "winit:    {}", self.winit)
+    }
+}
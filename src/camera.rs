@@ -0,0 +1,433 @@
+use glam::{DVec2, DVec3, Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use wgpu::CompareFunction;
+
+use crate::{
+    coords::CoordinateSystem,
+    spline::{ArcLength, Spline},
+};
+
+//
+
+/// 2D camera looking at the XY plane
+///
+/// one world unit is half of the viewport height at zoom 1
+///
+/// world positions are f64 and rendering is camera relative (floating origin):
+/// [`Self::view_proj`] has no translation, [`Self::relative`] rebases
+/// positions around the camera in f64 before they become f32 for the GPU,
+/// so nothing jitters far away from the origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2d {
+    pub position: DVec2,
+    pub zoom: f32,
+}
+
+/// 3D camera, for scenes that need depth
+///
+/// camera relative like [`Camera2d`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera3d {
+    pub position: DVec3,
+    pub rotation: Quat,
+    pub projection: Projection,
+    /// off-center lens shift in NDC units, `(1, 0)` moves the image half a screen right
+    pub shift: Vec2,
+    /// view space plane `(normal, distance)` used as the near plane,
+    /// for clipping mirror and portal views to their surface
+    pub oblique_near: Option<Vec4>,
+    /// the convention `position` and `rotation` are in,
+    /// an unrotated camera looks forward (-Z for Y-up, +Y for Z-up) with up up
+    pub coordinates: CoordinateSystem,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// `height` world units fit vertically
+    Orthographic { height: f32, near: f32, far: f32 },
+    /// vertical field of view in radians, `far: None` is infinitely far
+    Perspective {
+        fov_y: f32,
+        near: f32,
+        far: Option<f32>,
+    },
+}
+
+/// moves a [`Camera3d`] along a spline at a constant speed,
+/// for demos and cinematic captures
+///
+/// the camera looks along the path, or at `look_at`, keeping its up axis up
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraRail {
+    pub path: Spline,
+    arc: ArcLength,
+    /// world units per second, negative runs backwards
+    pub speed: f32,
+    /// how far along the path the camera is
+    pub distance: f32,
+    /// wrap around at the ends, closed paths always wrap
+    pub looping: bool,
+    pub look_at: Option<DVec3>,
+}
+
+/// which end of the depth range is near
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepthMode {
+    /// near is 0, far is 1
+    #[default]
+    Standard,
+    /// near is 1, far is 0, floats have the most precision near 0,
+    /// which cancels out the perspective divide's precision loss far away
+    Reversed,
+}
+
+/// logarithmic depth for planet or terrain sized scenes, where even reversed-Z
+/// runs out of precision; meant for perspective projections
+///
+/// the vertex shader replaces the depth with `log2(1 + w) / log2(1 + far)`,
+/// anything reading depth back (fog, post passes) goes through [`Self::decode`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LogDepth {
+    /// view distance that maps to the far end of the depth range
+    pub far: f32,
+}
+
+//
+
+impl Camera2d {
+    pub const MIN_ZOOM: f32 = 0.05;
+    pub const MAX_ZOOM: f32 = 50.0;
+
+    /// camera relative, see [`Self::relative`]
+    pub fn view_proj(&self, aspect: f32, depth: DepthMode) -> Mat4 {
+        let (near, far) = match depth {
+            DepthMode::Standard => (-1.0, 1.0),
+            DepthMode::Reversed => (1.0, -1.0),
+        };
+        Mat4::orthographic_rh(-aspect, aspect, 1.0, -1.0, near, far)
+            * Mat4::from_scale(Vec3::new(self.zoom, self.zoom, 1.0))
+    }
+
+    /// a world position relative to the camera, precise enough for f32
+    pub fn relative(&self, world: DVec2) -> Vec2 {
+        (world - self.position).as_vec2()
+    }
+
+    fn world_per_pixel(&self, viewport_height: f32) -> f64 {
+        2.0 / (viewport_height as f64 * self.zoom as f64)
+    }
+
+    /// move the camera so that the world follows a pointer moved by `delta` pixels
+    pub fn pan_pixels(&mut self, delta: Vec2, viewport_height: f32) {
+        self.position -= delta.as_dvec2() * self.world_per_pixel(viewport_height);
+    }
+
+    /// the camera moved to the closest whole pixel of a `viewport_height` pixels high view,
+    /// and the part that was rounded off, in pixels
+    pub fn snapped(&self, viewport_height: f32) -> (Self, Vec2) {
+        let world_per_pixel = self.world_per_pixel(viewport_height);
+        let pixels = self.position / world_per_pixel;
+        let snapped = Self {
+            position: pixels.round() * world_per_pixel,
+            ..*self
+        };
+        (snapped, (pixels - pixels.round()).as_vec2())
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+    }
+
+    /// zoom while keeping the world point under `pixel` in place
+    pub fn zoom_at(&mut self, factor: f32, pixel: Vec2, viewport: Vec2) {
+        let from_center = (pixel - viewport * 0.5).as_dvec2();
+        let before = self.position + from_center * self.world_per_pixel(viewport.y);
+        self.zoom_by(factor);
+        self.position = before - from_center * self.world_per_pixel(viewport.y);
+    }
+}
+
+impl Camera3d {
+    /// the default camera in the `coordinates` convention, usually
+    /// `settings.coordinates`: 5 units behind the origin, looking at it with up up
+    pub fn new(coordinates: CoordinateSystem) -> Self {
+        let forward = coordinates.to_internal().inverse() * Vec3::NEG_Z;
+        Self {
+            position: -5.0 * forward.as_dvec3(),
+            rotation: Quat::IDENTITY,
+            projection: Projection::Perspective {
+                fov_y: 60f32.to_radians(),
+                near: 0.1,
+                far: None,
+            },
+            shift: Vec2::ZERO,
+            oblique_near: None,
+            coordinates,
+        }
+    }
+
+    /// camera relative, only the rotation
+    pub fn view(&self) -> Mat4 {
+        self.coordinates.to_internal4() * Mat4::from_quat(self.rotation.inverse())
+    }
+
+    /// a world position relative to the camera, precise enough for f32
+    pub fn relative(&self, world: DVec3) -> Vec3 {
+        (world - self.position).as_vec3()
+    }
+
+    pub fn view_proj(&self, aspect: f32, depth: DepthMode) -> Mat4 {
+        let mut proj = self.projection.matrix(aspect, depth);
+        if let Some(plane) = self.oblique_near {
+            proj = oblique(proj, plane, depth);
+        }
+        Mat4::from_translation(self.shift.extend(0.0)) * proj * self.view()
+    }
+}
+
+impl CameraRail {
+    pub fn new(path: Spline, speed: f32) -> Self {
+        Self {
+            arc: path.arc_length(32),
+            path,
+            speed,
+            distance: 0.0,
+            looping: false,
+            look_at: None,
+        }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.arc.length()
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        let length = self.length();
+        self.distance += self.speed * dt;
+        self.distance = if self.looping || self.path.closed() {
+            self.distance.rem_euclid(length.max(f32::EPSILON))
+        } else {
+            self.distance.clamp(0.0, length)
+        };
+    }
+
+    /// an open, non looping rail reached its end
+    pub fn finished(&self) -> bool {
+        !(self.looping || self.path.closed())
+            && if self.speed < 0.0 {
+                self.distance <= 0.0
+            } else {
+                self.distance >= self.length()
+            }
+    }
+
+    /// move and turn `camera`, leaving its projection alone
+    pub fn apply(&self, camera: &mut Camera3d) {
+        let t = self.arc.parameter(self.distance);
+        let position = self.path.point(t).as_dvec3();
+        let forward = match self.look_at {
+            Some(target) => (target - position).as_vec3(),
+            None => self.path.tangent(t) * self.speed.signum(),
+        };
+        camera.position = position;
+        if let Some(rotation) = look_rotation(forward, camera.coordinates) {
+            camera.rotation = rotation;
+        }
+    }
+}
+
+/// the camera rotation looking along `forward` with the up axis up,
+/// `None` if there is no such direction
+pub fn look_rotation(forward: Vec3, coordinates: CoordinateSystem) -> Option<Quat> {
+    let up = coordinates.up();
+    let forward = forward.try_normalize()?;
+    let up = (up - forward * forward.dot(up)).try_normalize()?;
+
+    // where an unrotated camera looks, in this convention
+    let internal = coordinates.to_internal().inverse();
+    let (rest_forward, rest_up) = ((internal * Vec3::NEG_Z).normalize(), coordinates.up());
+
+    let to = Mat3::from_cols(forward, up, forward.cross(up));
+    let from = Mat3::from_cols(rest_forward, rest_up, rest_forward.cross(rest_up));
+    Some(Quat::from_mat3(&(to * from.transpose())).normalize())
+}
+
+impl Projection {
+    /// right handed, looking down -Z, depth in `0..=1`
+    pub fn matrix(&self, aspect: f32, depth: DepthMode) -> Mat4 {
+        let reversed = depth == DepthMode::Reversed;
+        match *self {
+            Projection::Orthographic { height, near, far } => {
+                let (h, w) = (height * 0.5, height * 0.5 * aspect);
+                let (near, far) = if reversed { (far, near) } else { (near, far) };
+                Mat4::orthographic_rh(-w, w, -h, h, near, far)
+            }
+            Projection::Perspective {
+                fov_y,
+                near,
+                far: Some(far),
+            } => {
+                let (near, far) = if reversed { (far, near) } else { (near, far) };
+                Mat4::perspective_rh(fov_y, aspect, near, far)
+            }
+            Projection::Perspective {
+                fov_y,
+                near,
+                far: None,
+            } => {
+                if reversed {
+                    Mat4::perspective_infinite_reverse_rh(fov_y, aspect, near)
+                } else {
+                    Mat4::perspective_infinite_rh(fov_y, aspect, near)
+                }
+            }
+        }
+    }
+}
+
+impl DepthMode {
+    pub fn compare(self) -> CompareFunction {
+        match self {
+            DepthMode::Standard => CompareFunction::LessEqual,
+            DepthMode::Reversed => CompareFunction::GreaterEqual,
+        }
+    }
+
+    /// the far plane, what depth buffers are cleared to
+    pub fn clear(self) -> f32 {
+        match self {
+            DepthMode::Standard => 1.0,
+            DepthMode::Reversed => 0.0,
+        }
+    }
+}
+
+impl LogDepth {
+    /// the shader constant
+    pub fn coef(&self) -> f32 {
+        1.0 / (self.far + 1.0).log2()
+    }
+
+    /// view distance -> depth, in `Standard` depth mode
+    pub fn encode(&self, distance: f32) -> f32 {
+        (1.0 + distance.max(0.0)).log2() * self.coef()
+    }
+
+    /// depth -> view distance, in `Standard` depth mode
+    pub fn decode(&self, depth: f32) -> f32 {
+        (depth / self.coef()).exp2() - 1.0
+    }
+}
+
+/// replace the near plane of `proj` with the view space `plane`
+///
+/// Lengyel, "Oblique View Frustum Depth Projection and Clipping" (2005),
+/// adapted to a `0..=1` depth range
+fn oblique(proj: Mat4, plane: Vec4, depth: DepthMode) -> Mat4 {
+    // the far corner of the frustum opposite to the plane
+    let far = depth.clear();
+    let corner = proj.inverse() * Vec4::new(plane.x.signum(), plane.y.signum(), far, 1.0);
+    let scaled = plane / plane.dot(corner);
+
+    let mut rows = proj.transpose();
+    rows.z_axis = match depth {
+        DepthMode::Standard => scaled,
+        // z' = w - z
+        DepthMode::Reversed => rows.w_axis - scaled,
+    };
+    rows.transpose()
+}
+
+/// in the internal convention, not the configured one
+impl Default for Camera3d {
+    fn default() -> Self {
+        Self::new(CoordinateSystem::INTERNAL)
+    }
+}
+
+impl Default for Camera2d {
+    fn default() -> Self {
+        Self {
+            position: DVec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::GlobalSettings;
+
+    /// where `camera` looks, in its own convention
+    fn forward(camera: &Camera3d) -> Vec3 {
+        (camera.view().inverse() * Vec4::NEG_Z).truncate()
+    }
+
+    #[test]
+    fn rail_follows_the_path() {
+        for coordinates in [CoordinateSystem::INTERNAL, CoordinateSystem::Z_UP_RIGHT] {
+            let up = coordinates.up();
+            let side = if up == Vec3::Y { Vec3::Z } else { Vec3::Y };
+            let path = Spline::catmull_rom(&[Vec3::ZERO, Vec3::X, Vec3::X + side], false);
+            let mut rail = CameraRail::new(path, 1.0);
+            let mut camera = Camera3d {
+                coordinates,
+                ..<_>::default()
+            };
+
+            rail.apply(&mut camera);
+            assert_eq!(camera.position, DVec3::ZERO);
+            let start = forward(&camera);
+            assert!(start.dot(Vec3::X) > 0.9, "{start}");
+            // level, the camera's up is the world's up
+            assert!(((camera.rotation * up).dot(up) - 1.0).abs() < 1e-5);
+
+            rail.advance(rail.length() / 2.0);
+            rail.apply(&mut camera);
+            assert!(camera.position.as_vec3().abs_diff_eq(Vec3::X, 0.05));
+
+            rail.advance(100.0);
+            assert!(rail.finished());
+            rail.look_at = Some(DVec3::ZERO);
+            rail.apply(&mut camera);
+            let back = forward(&camera);
+            assert!(
+                back.abs_diff_eq(-(Vec3::X + side).normalize(), 1e-4),
+                "{back}"
+            );
+        }
+    }
+
+    #[test]
+    fn z_up_setting_changes_the_view() {
+        let settings =
+            GlobalSettings::from_document("[coordinates]\nup = \"Z\"\n".parse().unwrap()).unwrap();
+        let camera = Camera3d::new(settings.coordinates);
+        let y_up = Camera3d::default();
+        assert_eq!(camera.position, DVec3::new(0.0, -5.0, 0.0));
+        assert_ne!(camera.view(), y_up.view());
+
+        // both look at the origin, with their own up axis pointing up on screen
+        for (camera, up) in [(camera, Vec3::Z), (y_up, Vec3::Y)] {
+            let origin = camera.relative(DVec3::ZERO);
+            let clip = camera.view_proj(1.0, DepthMode::Standard) * origin.extend(1.0);
+            assert!(clip.w > 0.0);
+            assert!(clip.truncate().truncate().abs_diff_eq(Vec2::ZERO, 1e-5));
+            let above = camera.view_proj(1.0, DepthMode::Standard) * (origin + up).extend(1.0);
+            assert!(above.y / above.w > 0.0);
+        }
+    }
+
+    #[test]
+    fn looping_rail_wraps() {
+        let path = Spline::catmull_rom(&[Vec3::ZERO, Vec3::X], false);
+        let mut rail = CameraRail::new(path, -1.0);
+        rail.looping = true;
+        rail.advance(0.25);
+        assert!((rail.distance - 0.75).abs() < 1e-4);
+        assert!(!rail.finished());
+    }
+}
@@ -0,0 +1,290 @@
+use glam::{Mat3, Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+//
+
+/// color vision deficiency to simulate in the final pass
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorVision {
+    #[default]
+    Normal,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+/// the color space a window surface is shown in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// SDR monitors, and whatever the OS color manages
+    #[default]
+    Srgb,
+    /// wide gamut displays that show the surface without color management,
+    /// sRGB colors come out oversaturated on them otherwise
+    DisplayP3,
+    /// linear sRGB primaries in a float surface (scRGB), values past 0..1
+    /// reach colors and brightness outside of sRGB where the backend supports it
+    ExtendedSrgb,
+}
+
+/// how bright the extended color space output gets, in nits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrLevels {
+    /// a scene value of 1
+    pub paper_white: f32,
+    /// the display peak, brighter values are rolled off towards it,
+    /// `None` leaves them alone
+    pub max: Option<f32>,
+}
+
+//
+
+impl ColorVision {
+    pub const ALL: [Self; 4] = [
+        Self::Normal,
+        Self::Protanopia,
+        Self::Deuteranopia,
+        Self::Tritanopia,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|v| *v == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// simulation matrix for linear RGB
+    ///
+    /// full severity matrices from Machado, Oliveira & Fernandes (2009)
+    pub fn matrix(self) -> Mat3 {
+        let rows = match self {
+            Self::Normal => return Mat3::IDENTITY,
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        };
+
+        Mat3::from_cols_array_2d(&rows).transpose()
+    }
+
+    /// [`Self::matrix`] for RGBA, alpha is left alone
+    pub fn matrix4(self) -> Mat4 {
+        Mat4::from_mat3(self.matrix())
+    }
+
+    /// what a linear RGB color looks like with this color vision
+    pub fn simulate(self, linear_rgb: Vec3) -> Vec3 {
+        (self.matrix() * linear_rgb).clamp(Vec3::ZERO, Vec3::ONE)
+    }
+}
+
+impl ColorSpace {
+    /// from linear sRGB to the linear RGB of this color space
+    pub fn gamut(self) -> Mat3 {
+        match self {
+            Self::Srgb | Self::ExtendedSrgb => Mat3::IDENTITY,
+            Self::DisplayP3 => Mat3::from_cols_array_2d(&[
+                [0.822462, 0.177538, 0.0],
+                [0.033194, 0.966806, 0.0],
+                [0.017083, 0.072397, 0.910520],
+            ])
+            .transpose(),
+        }
+    }
+
+    /// needs a float surface, the output is linear and isn't clamped
+    pub fn is_extended(self) -> bool {
+        self == Self::ExtendedSrgb
+    }
+}
+
+impl HdrLevels {
+    /// scRGB 1.0
+    pub const REFERENCE_WHITE: f32 = 80.0;
+
+    /// scales linear scene colors to scRGB
+    pub fn scale(&self) -> f32 {
+        self.paper_white / Self::REFERENCE_WHITE
+    }
+
+    /// the peak in scRGB, 0 without one
+    pub fn peak(&self) -> f32 {
+        self.max.map_or(0.0, |max| max / Self::REFERENCE_WHITE)
+    }
+}
+
+/// what the final pass does above 80% of an scRGB `peak`:
+/// rolls the brightest channel off towards it, keeping the hue
+pub fn hdr_rolloff(rgb: Vec3, peak: f32) -> Vec3 {
+    let m = rgb.max_element();
+    let knee = peak * 0.8;
+    if peak <= 0.0 || m <= knee {
+        return rgb;
+    }
+    let range = peak - knee;
+    let rolled = knee + range * (1.0 - (-(m - knee) / range).exp());
+    rgb * (rolled / m)
+}
+
+impl Default for HdrLevels {
+    fn default() -> Self {
+        Self {
+            paper_white: Self::REFERENCE_WHITE,
+            max: None,
+        }
+    }
+}
+
+/// the Okabe-Ito palette, distinguishable with all common color vision deficiencies
+///
+/// sRGB: black, orange, sky blue, bluish green, yellow, blue, vermillion, reddish purple
+pub const OKABE_ITO: [[u8; 3]; 8] = [
+    [0, 0, 0],
+    [230, 159, 0],
+    [86, 180, 233],
+    [0, 158, 115],
+    [240, 228, 66],
+    [0, 114, 178],
+    [213, 94, 0],
+    [204, 121, 167],
+];
+
+/// a named list of sRGB colors for the indexed color mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub name: &'static str,
+    /// from dark to bright, at most 256
+    pub colors: &'static [[u8; 3]],
+}
+
+impl Palette {
+    pub const BUILTIN: [Self; 4] = [
+        Self {
+            name: "GameBoy",
+            colors: &[[15, 56, 15], [48, 98, 48], [139, 172, 15], [155, 188, 15]],
+        },
+        Self {
+            name: "Grayscale",
+            colors: &[[0, 0, 0], [85, 85, 85], [170, 170, 170], [255, 255, 255]],
+        },
+        Self {
+            name: "Sepia",
+            colors: &[
+                [30, 20, 12],
+                [74, 52, 33],
+                [124, 92, 60],
+                [176, 140, 98],
+                [222, 196, 152],
+                [250, 236, 208],
+            ],
+        },
+        Self {
+            name: "Night",
+            colors: &[
+                [8, 12, 32],
+                [24, 40, 88],
+                [56, 88, 160],
+                [120, 160, 220],
+                [220, 236, 255],
+            ],
+        },
+    ];
+
+    /// a builtin palette by name
+    pub fn find(name: &str) -> Option<usize> {
+        Self::BUILTIN
+            .iter()
+            .position(|palette| palette.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// sample the viridis colormap at `t` in `0..=1`, in sRGB
+///
+/// perceptually uniform and color vision deficiency safe, good for heatmaps
+pub fn viridis(t: f32) -> Vec3 {
+    const STOPS: [[f32; 3]; 9] = [
+        [0.267, 0.005, 0.329],
+        [0.283, 0.141, 0.458],
+        [0.254, 0.265, 0.530],
+        [0.207, 0.372, 0.553],
+        [0.164, 0.471, 0.558],
+        [0.128, 0.567, 0.551],
+        [0.135, 0.659, 0.518],
+        [0.267, 0.749, 0.441],
+        [0.478, 0.820, 0.3176],
+    ];
+    const END: [f32; 3] = [0.993, 0.906, 0.144];
+
+    let t = t.clamp(0.0, 1.0) * STOPS.len() as f32;
+    let i = (t as usize).min(STOPS.len() - 1);
+    let next = STOPS.get(i + 1).copied().unwrap_or(END);
+    Vec3::from(STOPS[i]).lerp(Vec3::from(next), t - i as f32)
+}
+
+/// sRGB u8 -> linear
+pub fn srgb_to_linear(srgb: [u8; 3]) -> Vec3 {
+    Vec3::from(srgb.map(|c| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }))
+}
+
+/// linear -> sRGB u8
+pub fn linear_to_srgb(linear: Vec3) -> [u8; 3] {
+    linear.to_array().map(|c| {
+        let c = c.clamp(0.0, 1.0);
+        let c = if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c * 255.0).round() as u8
+    })
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_fits_in_display_p3() {
+        let gamut = ColorSpace::DisplayP3.gamut();
+        assert!((gamut * Vec3::ONE).abs_diff_eq(Vec3::ONE, 1e-5));
+        for primary in [Vec3::X, Vec3::Y, Vec3::Z] {
+            let p3 = gamut * primary;
+            // less saturated, but still inside
+            assert!(p3.min_element() >= 0.0 && p3.max_element() < 1.0, "{p3}");
+        }
+        assert_eq!(ColorSpace::ExtendedSrgb.gamut(), Mat3::IDENTITY);
+    }
+
+    #[test]
+    fn hdr_rolls_off_below_the_peak() {
+        let peak = 10.0;
+        assert_eq!(
+            hdr_rolloff(Vec3::new(8.0, 1.0, 0.0), peak),
+            Vec3::new(8.0, 1.0, 0.0)
+        );
+        let rolled = hdr_rolloff(Vec3::new(100.0, 50.0, 0.0), peak);
+        assert!(rolled.x > 9.9 && rolled.x <= peak, "{rolled}");
+        assert!((rolled.y / rolled.x - 0.5).abs() < 1e-5);
+        assert_eq!(hdr_rolloff(Vec3::splat(100.0), 0.0), Vec3::splat(100.0));
+    }
+}
@@ -0,0 +1,93 @@
+use glam::{DVec3, Mat3, Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+//
+
+/// the axis convention world positions, cameras and imported models use
+///
+/// rendering works in Y-up right handed space internally,
+/// [`Self::to_internal`] is applied on the way in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoordinateSystem {
+    pub up: UpAxis,
+    pub handedness: Handedness,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpAxis {
+    /// glTF, Maya, Unity
+    #[default]
+    Y,
+    /// Blender, most CAD tools, Unreal
+    Z,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Handedness {
+    #[default]
+    Right,
+    Left,
+}
+
+//
+
+impl CoordinateSystem {
+    /// the internal convention
+    pub const INTERNAL: Self = Self {
+        up: UpAxis::Y,
+        handedness: Handedness::Right,
+    };
+
+    /// Blender and most CAD tools
+    pub const Z_UP_RIGHT: Self = Self {
+        up: UpAxis::Z,
+        handedness: Handedness::Right,
+    };
+
+    /// this convention -> Y-up right handed
+    pub fn to_internal(self) -> Mat3 {
+        // left handed: mirror the axis pointing away from the viewer
+        let mirror = match (self.handedness, self.up) {
+            (Handedness::Right, _) => Mat3::IDENTITY,
+            (Handedness::Left, UpAxis::Y) => Mat3::from_diagonal(Vec3::new(1.0, 1.0, -1.0)),
+            (Handedness::Left, UpAxis::Z) => Mat3::from_diagonal(Vec3::new(1.0, -1.0, 1.0)),
+        };
+
+        let up = match self.up {
+            UpAxis::Y => Mat3::IDENTITY,
+            // (x, y, z) -> (x, z, -y)
+            UpAxis::Z => Mat3::from_cols(Vec3::X, Vec3::NEG_Z, Vec3::Y),
+        };
+
+        up * mirror
+    }
+
+    pub fn to_internal4(self) -> Mat4 {
+        Mat4::from_mat3(self.to_internal())
+    }
+
+    /// matrix converting positions and directions from `source` to this convention,
+    /// for importers of models made in other tools
+    pub fn convert_from(self, source: Self) -> Mat3 {
+        self.to_internal().inverse() * source.to_internal()
+    }
+
+    pub fn convert_point_from(self, source: Self, point: DVec3) -> DVec3 {
+        let m = self.convert_from(source).as_dmat3();
+        m * point
+    }
+
+    /// converting from `source` mirrors the geometry,
+    /// importers have to reverse the triangle winding
+    pub fn flips_winding_from(self, source: Self) -> bool {
+        self.convert_from(source).determinant() < 0.0
+    }
+
+    pub fn up(self) -> Vec3 {
+        match self.up {
+            UpAxis::Y => Vec3::Y,
+            UpAxis::Z => Vec3::Z,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+
+//
+
+/// where settings, caches and data are stored
+pub static APP_DIRS: Lazy<Option<AppDirs>> = Lazy::new(AppDirs::detect);
+
+//
+
+/// the OS specific `ProjectDirs`,
+/// or folders next to the executable in portable mode
+#[derive(Debug, Clone)]
+pub struct AppDirs {
+    config: PathBuf,
+    data: PathBuf,
+    data_local: PathBuf,
+    cache: PathBuf,
+    portable: bool,
+}
+
+//
+
+impl AppDirs {
+    /// a file with this name next to the executable enables portable mode
+    pub const PORTABLE_MARKER: &'static str = "portable.txt";
+
+    /// the `ProjectDirs` identity, see `migrate::PREVIOUS_IDENTITIES` when changing it
+    pub fn project() -> Option<ProjectDirs> {
+        ProjectDirs::from("org", "xorbits", env!("CARGO_PKG_NAME"))
+    }
+
+    pub fn detect() -> Option<Self> {
+        if let Some(root) = Self::portable_root() {
+            tracing::info!("portable mode, storing everything in {}", root.display());
+            return Some(Self::portable(&root));
+        }
+
+        let dirs = Self::project()?;
+        Some(Self {
+            config: dirs.config_dir().to_path_buf(),
+            data: dirs.data_dir().to_path_buf(),
+            data_local: dirs.data_local_dir().to_path_buf(),
+            cache: dirs.cache_dir().to_path_buf(),
+            portable: false,
+        })
+    }
+
+    pub fn portable(root: &Path) -> Self {
+        Self {
+            config: root.join("config"),
+            data: root.join("data"),
+            data_local: root.join("data"),
+            cache: root.join("cache"),
+            portable: true,
+        }
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data
+    }
+
+    pub fn data_local_dir(&self) -> &Path {
+        &self.data_local
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache
+    }
+
+    pub fn is_portable(&self) -> bool {
+        self.portable
+    }
+
+    /// the executable's directory, if it contains the portable marker
+    fn portable_root() -> Option<PathBuf> {
+        let exe = env::current_exe().ok()?;
+        let root = exe.parent()?;
+        root.join(Self::PORTABLE_MARKER)
+            .is_file()
+            .then(|| root.to_path_buf())
+    }
+}
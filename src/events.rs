@@ -0,0 +1,62 @@
+use std::time::Instant;
+
+use winit::{
+    event::{ElementState, Event, KeyboardInput, WindowEvent},
+    window::WindowId,
+};
+
+use crate::{
+    idle::{self, IdleDetector},
+    input::{Action, InputMap},
+    redraw::Redraw,
+};
+
+//
+
+/// the first thing the main loop does with every event, before the rest of the app
+/// sees it: anything sent to a window or the loop invalidates the output, input ends
+/// idling, and the modifiers and key presses of `window` go through the key bindings
+///
+/// returns the action a key press completed, running it is up to the caller
+pub fn dispatch<T>(
+    event: &Event<T>,
+    window: WindowId,
+    now: Instant,
+    input: &mut InputMap,
+    idle: &mut IdleDetector,
+    redraw: &mut Redraw,
+) -> Option<Action> {
+    if matches!(
+        event,
+        Event::WindowEvent { .. } | Event::UserEvent(_) | Event::RedrawRequested(_)
+    ) {
+        redraw.invalidate();
+    }
+    if idle::is_input(event) && idle.input(now) {
+        redraw.invalidate();
+    }
+
+    // the mirror has no bindings
+    let Event::WindowEvent { window_id, event } = event else {
+        return None;
+    };
+    if *window_id != window {
+        return None;
+    }
+    match event {
+        WindowEvent::ModifiersChanged(modifiers) => {
+            input.modifiers_changed(*modifiers);
+            None
+        }
+        WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } => input.key_pressed(*key),
+        _ => None,
+    }
+}
@@ -0,0 +1,551 @@
+use std::{borrow::Cow, mem::size_of, sync::mpsc};
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+use glam::{Vec2, Vec3, Vec4};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    *,
+};
+
+use super::capture::CapturedImage;
+
+//
+
+/// a mesh for [`bake`], the high poly mesh doesn't need UVs
+#[derive(Debug, Clone, Copy)]
+pub struct BakeMesh<'a> {
+    pub positions: &'a [Vec3],
+    pub normals: &'a [Vec3],
+    pub uvs: &'a [Vec2],
+    pub indices: &'a [u32],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BakeSettings {
+    /// width and height of the maps
+    pub size: u32,
+    /// how far the high poly surface may be from the low poly one, in mesh units
+    pub distance: f32,
+    /// occluders further away than this don't darken the AO
+    pub ao_distance: f32,
+    pub ao_samples: u32,
+    /// texels to grow the baked islands by, so filtering doesn't pick up the background
+    pub dilate: u32,
+}
+
+/// object space normals and AO in the low poly mesh's UV space
+#[derive(Debug, Clone, PartialEq)]
+pub struct BakedMaps {
+    pub size: u32,
+    /// normal in xyz, AO in w (1 is open), rows top to bottom,
+    /// `None` where no low poly triangle covers the texel
+    pub texels: Vec<Option<Vec4>>,
+}
+
+/// the shared part of the bakers: a mesh drawn at its UVs into offscreen
+/// position and normal targets, a compute pass per covered texel
+/// with the ray cast geometry in group 1, and the results read back
+pub(super) struct TexelBake<'a> {
+    device: &'a Device,
+    size: u32,
+    positions: TextureView,
+    normals: TextureView,
+    encoder: CommandEncoder,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Params {
+    size: [u32; 2],
+    ao_samples: u32,
+    distance: f32,
+    ao_distance: f32,
+    _pad: [u32; 3],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct SurfaceVertex {
+    position: Vec3,
+    normal: Vec3,
+    uv: Vec2,
+}
+
+//
+
+impl Default for BakeSettings {
+    fn default() -> Self {
+        Self {
+            size: 1024,
+            distance: 0.1,
+            ao_distance: 1.0,
+            ao_samples: 64,
+            dilate: 4,
+        }
+    }
+}
+
+/// bake the detail of `high` into maps for `low`
+///
+/// every covered texel casts rays against every high poly triangle, there is no
+/// acceleration structure, so it's for tools and asset processing, not for frames;
+/// blocks until the GPU is done
+pub fn bake(
+    device: &Device,
+    queue: &Queue,
+    low: &BakeMesh,
+    high: &BakeMesh,
+    settings: &BakeSettings,
+) -> Result<BakedMaps> {
+    if high.normals.len() != high.positions.len() {
+        return Err(anyhow!("the high poly mesh needs a normal per vertex"));
+    }
+    let bake = TexelBake::new(device, low, settings.size)?;
+
+    let high_normals: Vec<Vec4> = high.normals.iter().map(|n| n.extend(0.0)).collect();
+    let high_normals = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("bake high poly normals"),
+        contents: bytemuck::cast_slice(&high_normals),
+        usage: BufferUsages::STORAGE,
+    });
+    let params = Params {
+        size: [settings.size; 2],
+        ao_samples: settings.ao_samples,
+        distance: settings.distance,
+        ao_distance: settings.ao_distance,
+        _pad: [0; 3],
+    };
+    let texels = bake.run(
+        queue,
+        "bake",
+        include_str!("./bake.wgsl"),
+        bytemuck::bytes_of(&params),
+        &[high_normals.as_entire_binding()],
+        high,
+    )?;
+
+    let mut maps = BakedMaps {
+        size: settings.size,
+        texels,
+    };
+    dilate(&mut maps.texels, maps.size, settings.dilate, true);
+    Ok(maps)
+}
+
+impl<'a> TexelBake<'a> {
+    /// record drawing `mesh` at its UVs
+    pub(super) fn new(device: &'a Device, mesh: &BakeMesh, size: u32) -> Result<Self> {
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return Err(anyhow!("baking needs compute shaders"));
+        }
+        if mesh.uvs.len() != mesh.positions.len() || mesh.normals.len() != mesh.positions.len() {
+            return Err(anyhow!("the baked mesh needs a normal and a UV per vertex"));
+        }
+        if mesh.indices.is_empty() {
+            return Err(anyhow!("nothing to bake"));
+        }
+
+        let target = |label| {
+            device
+                .create_texture(&TextureDescriptor {
+                    label: Some(label),
+                    size: Extent3d {
+                        width: size,
+                        height: size,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::Rgba32Float,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&<_>::default())
+        };
+        let positions = target("bake positions");
+        let normals = target("bake normals");
+
+        let vertices: Vec<SurfaceVertex> = (0..mesh.positions.len())
+            .map(|i| SurfaceVertex {
+                position: mesh.positions[i],
+                normal: mesh.normals[i],
+                uv: mesh.uvs[i],
+            })
+            .collect();
+        let vertices = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("bake surface"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let indices = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("bake surface"),
+            contents: bytemuck::cast_slice(mesh.indices),
+            usage: BufferUsages::INDEX,
+        });
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("bake surface"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./uv_surface.wgsl"))),
+        });
+        let targets = [
+            Some(ColorTargetState::from(TextureFormat::Rgba32Float)),
+            Some(ColorTargetState::from(TextureFormat::Rgba32Float)),
+        ];
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("bake surface"),
+            layout: None,
+            vertex: VertexState {
+                module: &module,
+                entry_point: "vs_surface",
+                buffers: &[VertexBufferLayout {
+                    array_stride: size_of::<SurfaceVertex>() as _,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: "fs_surface",
+                targets: &targets,
+            }),
+            // UV islands can be mirrored
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("bake"),
+        });
+        {
+            let attachment = |view| {
+                Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: true,
+                    },
+                })
+            };
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("bake surface"),
+                color_attachments: &[attachment(&positions), attachment(&normals)],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_vertex_buffer(0, vertices.slice(..));
+            pass.set_index_buffer(indices.slice(..), IndexFormat::Uint32);
+            pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
+        }
+
+        Ok(Self {
+            device,
+            size,
+            positions,
+            normals,
+            encoder,
+        })
+    }
+
+    /// run the `bake` entry point of `shader` over every texel and read back its output
+    ///
+    /// group 0 is `params` (a uniform), the position and normal textures,
+    /// the `vec4<f32>` per texel output and then `extra` read only storage buffers,
+    /// `ray.wgsl` is prepended with `occluders` in group 1
+    pub(super) fn run(
+        mut self,
+        queue: &Queue,
+        label: &str,
+        shader: &str,
+        params: &[u8],
+        extra: &[BindingResource],
+        occluders: &BakeMesh,
+    ) -> Result<Vec<Option<Vec4>>> {
+        let device = self.device;
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some(label),
+            source: ShaderSource::Wgsl(Cow::Owned(format!(
+                "{}\n{shader}",
+                include_str!("./ray.wgsl")
+            ))),
+        });
+
+        let entry = |binding, ty| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty,
+            count: None,
+        };
+        let buffer = |ty| BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        let read_only = buffer(BufferBindingType::Storage { read_only: true });
+        // explicit, float targets aren't filterable everywhere
+        let texture = BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: false },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        };
+        let mut entries = vec![
+            entry(0, buffer(BufferBindingType::Uniform)),
+            entry(1, texture),
+            entry(2, texture),
+            entry(3, buffer(BufferBindingType::Storage { read_only: false })),
+        ];
+        entries.extend((0..extra.len() as u32).map(|i| entry(4 + i, read_only)));
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &entries,
+        });
+        let ray_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("ray cast geometry"),
+            entries: &[entry(0, read_only), entry(1, read_only)],
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[&layout, &ray_layout],
+                push_constant_ranges: &[],
+            })),
+            module: &module,
+            entry_point: "bake",
+        });
+
+        let init = |label, contents: &[u8], usage| {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage,
+            })
+        };
+        let params = init("bake params", params, BufferUsages::UNIFORM);
+        let output_size = self.size as u64 * self.size as u64 * size_of::<Vec4>() as u64;
+        let output = device.create_buffer(&BufferDescriptor {
+            label: Some("bake output"),
+            size: output_size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let occluder_positions: Vec<Vec4> =
+            occluders.positions.iter().map(|p| p.extend(1.0)).collect();
+        let occluder_positions = init(
+            "ray cast positions",
+            bytemuck::cast_slice(&occluder_positions),
+            BufferUsages::STORAGE,
+        );
+        let occluder_indices = init(
+            "ray cast indices",
+            bytemuck::cast_slice(occluders.indices),
+            BufferUsages::STORAGE,
+        );
+
+        let mut resources = vec![
+            params.as_entire_binding(),
+            BindingResource::TextureView(&self.positions),
+            BindingResource::TextureView(&self.normals),
+            output.as_entire_binding(),
+        ];
+        resources.extend(extra.iter().cloned());
+        let bind_group = |layout, resources: Vec<BindingResource>| {
+            let entries: Vec<BindGroupEntry> = resources
+                .into_iter()
+                .enumerate()
+                .map(|(binding, resource)| BindGroupEntry {
+                    binding: binding as u32,
+                    resource,
+                })
+                .collect();
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &entries,
+            })
+        };
+        let bind_group_0 = bind_group(&layout, resources);
+        let bind_group_1 = bind_group(
+            &ray_layout,
+            vec![
+                occluder_positions.as_entire_binding(),
+                occluder_indices.as_entire_binding(),
+            ],
+        );
+
+        {
+            let mut pass = self
+                .encoder
+                .begin_compute_pass(&ComputePassDescriptor { label: Some(label) });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group_0, &[]);
+            pass.set_bind_group(1, &bind_group_1, &[]);
+            pass.dispatch_workgroups(self.size.div_ceil(8), self.size.div_ceil(8), 1);
+        }
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: Some("bake readback"),
+            size: output_size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.encoder
+            .copy_buffer_to_buffer(&output, 0, &readback, 0, output_size);
+        queue.submit([self.encoder.finish()]);
+
+        let (tx, rx) = mpsc::channel();
+        let slice = readback.slice(..);
+        slice.map_async(MapMode::Read, move |res| _ = tx.send(res));
+        device.poll(Maintain::Wait);
+        rx.recv()??;
+        // a negative w marks texels the mesh doesn't cover
+        let texels = bytemuck::cast_slice::<_, Vec4>(&slice.get_mapped_range())
+            .iter()
+            .map(|&texel| (texel.w >= 0.0).then_some(texel))
+            .collect();
+        readback.unmap();
+        Ok(texels)
+    }
+}
+
+impl BakedMaps {
+    pub fn get(&self, x: u32, y: u32) -> Option<Vec4> {
+        self.texels[(y * self.size + x) as usize]
+    }
+
+    /// `0.5 + 0.5 * n` in RGB, the background is black
+    pub fn normal_image(&self) -> CapturedImage {
+        self.image(|texel| {
+            let n = (texel.truncate() * 0.5 + 0.5) * 255.0;
+            [n.x as u8, n.y as u8, n.z as u8, 255]
+        })
+    }
+
+    pub fn ao_image(&self) -> CapturedImage {
+        self.image(|texel| {
+            let ao = (texel.w * 255.0) as u8;
+            [ao, ao, ao, 255]
+        })
+    }
+
+    fn image(&self, pixel: impl Fn(Vec4) -> [u8; 4]) -> CapturedImage {
+        CapturedImage {
+            width: self.size,
+            height: self.size,
+            format: TextureFormat::Rgba8Unorm,
+            rgba: self
+                .texels
+                .iter()
+                .flat_map(|texel| texel.map_or([0, 0, 0, 0], &pixel))
+                .collect(),
+        }
+    }
+}
+
+/// fill uncovered texels from their covered neighbours, `steps` texels out,
+/// renormalizing xyz for normal maps
+pub(super) fn dilate(texels: &mut [Option<Vec4>], size: u32, steps: u32, normalize: bool) {
+    let size = size as i32;
+    for _ in 0..steps {
+        let last = texels.to_vec();
+        for y in 0..size {
+            for x in 0..size {
+                if last[(y * size + x) as usize].is_some() {
+                    continue;
+                }
+                let (sum, n) = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .into_iter()
+                    .map(|(dx, dy)| (x + dx, y + dy))
+                    .filter(|&(x, y)| x >= 0 && y >= 0 && x < size && y < size)
+                    .filter_map(|(x, y)| last[(y * size + x) as usize])
+                    .fold((Vec4::ZERO, 0), |(sum, n), texel| (sum + texel, n + 1));
+                if n > 0 {
+                    let mut average = sum / n as f32;
+                    if normalize {
+                        average = average.truncate().normalize_or_zero().extend(average.w);
+                    }
+                    texels[(y * size + x) as usize] = Some(average);
+                }
+            }
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::test_util::{plane, test_device};
+
+    const SETTINGS: BakeSettings = BakeSettings {
+        size: 16,
+        distance: 0.05,
+        ao_distance: 0.5,
+        ao_samples: 32,
+        dilate: 0,
+    };
+
+    #[test]
+    fn bakes_high_poly_normals() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let low = plane(1, 0.0, 0.0, Vec3::Z);
+        let tilted = Vec3::new(0.6, 0.0, 0.8);
+        let high = plane(8, 0.02, 0.0, tilted);
+        let maps = bake(&device, &queue, &low.mesh(), &high.mesh(), &SETTINGS).unwrap();
+
+        for texel in &maps.texels {
+            let texel = texel.expect("the quad covers the whole map");
+            assert!(texel.truncate().abs_diff_eq(tilted, 1e-3), "{texel}");
+            // nothing above an open plane
+            assert_eq!(texel.w, 1.0);
+        }
+        assert_eq!(maps.normal_image().rgba.len(), 16 * 16 * 4);
+    }
+
+    #[test]
+    fn bakes_occlusion_under_a_roof() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let low = plane(1, 0.0, 0.0, Vec3::Z);
+        let ground = plane(4, 0.02, 0.0, Vec3::Z);
+        let roof = plane(1, 0.1, 10.0, -Vec3::Z);
+        let high = ground.merge(&roof);
+        let maps = bake(&device, &queue, &low.mesh(), &high.mesh(), &SETTINGS).unwrap();
+
+        for texel in &maps.texels {
+            let texel = texel.unwrap();
+            // the roof is above the search distance, the ground normal wins
+            assert!(texel.truncate().abs_diff_eq(Vec3::Z, 1e-3), "{texel}");
+            assert!(texel.w < 0.1, "{texel}");
+        }
+    }
+
+    #[test]
+    fn dilation_fills_the_background() {
+        let mut maps = BakedMaps {
+            size: 4,
+            texels: vec![None; 16],
+        };
+        maps.texels[5] = Some(Vec4::new(0.0, 0.0, 1.0, 0.5));
+        dilate(&mut maps.texels, 4, 1, true);
+        assert_eq!(maps.texels.iter().flatten().count(), 5);
+        assert_eq!(maps.get(1, 0), Some(Vec4::new(0.0, 0.0, 1.0, 0.5)));
+        dilate(&mut maps.texels, 4, 4, true);
+        assert!(maps.texels.iter().all(Option::is_some));
+    }
+}
@@ -0,0 +1,59 @@
+// normal and AO baking: every covered texel of the low poly surface casts a ray
+// along its normal to find the high poly surface, and AO rays from there
+
+struct Params {
+    size: vec2<u32>,
+    ao_samples: u32,
+    // how far the high poly surface may be from the low poly one
+    distance: f32,
+    ao_distance: f32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var positions: texture_2d<f32>;
+@group(0) @binding(2) var normals: texture_2d<f32>;
+// object space normal in xyz, AO in w, w < 0 where nothing was baked
+@group(0) @binding(3) var<storage, read_write> output: array<vec4<f32>>;
+@group(0) @binding(4) var<storage, read> high_normals: array<vec4<f32>>;
+
+@compute @workgroup_size(8, 8)
+fn bake(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size.x || id.y >= params.size.y {
+        return;
+    }
+    let texel = id.y * params.size.x + id.x;
+    let position = textureLoad(positions, vec2<i32>(id.xy), 0);
+    if position.w == 0.0 {
+        output[texel] = vec4<f32>(0.0, 0.0, 0.0, -1.0);
+        return;
+    }
+    let low_normal = normalize(textureLoad(normals, vec2<i32>(id.xy), 0).xyz);
+
+    // from outside the low poly surface inwards, the hit closest to the surface wins
+    let origin = position.xyz + low_normal * params.distance;
+    let hit = trace_nearest(origin, -low_normal, 0.0, 2.0 * params.distance, params.distance);
+    if hit.t == NO_HIT {
+        output[texel] = vec4<f32>(low_normal, 1.0);
+        return;
+    }
+
+    let i = hit.triangle * 3u;
+    let w = vec3<f32>(1.0 - hit.barycentric.x - hit.barycentric.y, hit.barycentric);
+    let normal = normalize(
+        high_normals[ray_indices[i]].xyz * w.x
+        + high_normals[ray_indices[i + 1u]].xyz * w.y
+        + high_normals[ray_indices[i + 2u]].xyz * w.z
+    );
+
+    // around the face normal, a smoothed normal would send rays into the surface itself
+    let face = face_normal(hit.triangle, low_normal);
+    let surface = origin - low_normal * hit.t + face * 1e-4;
+    var open = 0u;
+    for (var s = 0u; s < params.ao_samples; s++) {
+        if !occluded(surface, cosine_hemisphere(face, s, params.ao_samples), params.ao_distance) {
+            open += 1u;
+        }
+    }
+
+    output[texel] = vec4<f32>(normal, f32(open) / f32(max(params.ao_samples, 1u)));
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wgpu::{
+    BlendComponent, BlendFactor, BlendOperation, BlendState, ColorTargetState, ColorWrites,
+    RenderPipeline, TextureFormat,
+};
+
+//
+
+/// named blend presets for the color target of a pipeline
+///
+/// colors are straight (not premultiplied) alpha unless the preset says otherwise
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum BlendMode {
+    /// no blending, alpha is ignored
+    Opaque,
+    #[default]
+    Alpha,
+    /// the color is already multiplied by its alpha
+    Premultiplied,
+    /// adds the color times its alpha, for glows and particles
+    Additive,
+    /// multiplies what's behind by the color, for shadows and tints
+    Multiply,
+}
+
+/// one pipeline per [`BlendMode`] of the same shader
+pub struct BlendPermutations {
+    pipelines: BTreeMap<BlendMode, RenderPipeline>,
+}
+
+//
+
+impl BlendMode {
+    pub const ALL: [Self; 5] = [
+        Self::Opaque,
+        Self::Alpha,
+        Self::Premultiplied,
+        Self::Additive,
+        Self::Multiply,
+    ];
+
+    pub fn to_blend_state(self) -> Option<BlendState> {
+        match self {
+            Self::Opaque => None,
+            Self::Alpha => Some(BlendState::ALPHA_BLENDING),
+            Self::Premultiplied => Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            Self::Additive => Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            }),
+            Self::Multiply => Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::Zero,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            }),
+        }
+    }
+
+    pub fn color_target(self, format: TextureFormat) -> ColorTargetState {
+        ColorTargetState {
+            format,
+            blend: self.to_blend_state(),
+            write_mask: ColorWrites::ALL,
+        }
+    }
+}
+
+impl BlendPermutations {
+    /// `create` builds the pipeline for one mode, targets that can't be blended
+    /// (palette indices) only get [`BlendMode::Opaque`]
+    pub fn new(blendable: bool, mut create: impl FnMut(BlendMode) -> RenderPipeline) -> Self {
+        let modes: &[BlendMode] = if blendable {
+            &BlendMode::ALL
+        } else {
+            &[BlendMode::Opaque]
+        };
+        Self {
+            pipelines: modes.iter().map(|&mode| (mode, create(mode))).collect(),
+        }
+    }
+
+    /// falls back to [`BlendMode::Opaque`] when `mode` wasn't built
+    pub fn get(&self, mode: BlendMode) -> &RenderPipeline {
+        self.pipelines
+            .get(&mode)
+            .unwrap_or_else(|| &self.pipelines[&BlendMode::Opaque])
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        assert_eq!(BlendMode::Opaque.to_blend_state(), None);
+        assert_eq!(
+            BlendMode::default().to_blend_state(),
+            Some(BlendState::ALPHA_BLENDING)
+        );
+        for mode in BlendMode::ALL {
+            let name = serde_json::to_string(&mode).unwrap();
+            assert_eq!(serde_json::from_str::<BlendMode>(&name).unwrap(), mode);
+        }
+        assert_eq!(
+            serde_json::from_str::<BlendMode>("\"Premultiplied\"").unwrap(),
+            BlendMode::Premultiplied
+        );
+    }
+}
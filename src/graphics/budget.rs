@@ -0,0 +1,247 @@
+use std::{fmt, time::Duration};
+
+//
+
+/// frames between two shedding decisions, gives the change time to show up in the timings
+const COOLDOWN: u32 = 30;
+
+/// per system GPU budgets, and the policy that degrades optional systems
+/// while the whole frame goes over the target frame time
+///
+/// the system furthest over its own budget sheds first, ties go to the
+/// lowest priority; once the frame is well under the target again,
+/// the highest priority shed system gets a level back
+#[derive(Debug)]
+pub struct FrameBudget {
+    target: Duration,
+    systems: Vec<SystemBudget>,
+    /// exponential moving averages in seconds, the frame and then every system
+    smoothed: Option<(f64, Vec<f64>)>,
+    /// degradation level per system, 0 is full quality
+    levels: Vec<u8>,
+    cooldown: u32,
+}
+
+/// one measured system, in the order of the GPU timer spans
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SystemBudget {
+    pub name: &'static str,
+    pub budget: Duration,
+    /// higher is more important, sheds last and comes back first
+    pub priority: u8,
+    /// how many steps it can be degraded, 0 for systems that can't be shed
+    pub levels: u8,
+}
+
+/// a decision of [`FrameBudget::update`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shedding {
+    Shed {
+        system: &'static str,
+        level: u8,
+        /// smoothed
+        time: Duration,
+        budget: Duration,
+        frame: Duration,
+    },
+    Restored {
+        system: &'static str,
+        level: u8,
+    },
+}
+
+//
+
+impl FrameBudget {
+    pub fn new(target: Duration, systems: Vec<SystemBudget>) -> Self {
+        tracing::debug!("frame budget {target:?}: {systems:?}");
+        Self {
+            target,
+            levels: vec![0; systems.len()],
+            systems,
+            smoothed: None,
+            cooldown: 0,
+        }
+    }
+
+    pub fn target(&self) -> Duration {
+        self.target
+    }
+
+    /// the current degradation level of `system`, 0 is full quality
+    pub fn level(&self, system: &str) -> u8 {
+        self.systems
+            .iter()
+            .position(|s| s.name == system)
+            .map_or(0, |i| self.levels[i])
+    }
+
+    /// feed a GPU frame time and its spans, one per system
+    pub fn update(&mut self, frame: Duration, spans: &[Duration]) -> Option<Shedding> {
+        let spans =
+            || (0..self.systems.len()).map(|i| spans.get(i).map_or(0.0, Duration::as_secs_f64));
+        let (smoothed_frame, smoothed) = match self.smoothed.take() {
+            Some((f, s)) => (
+                f + (frame.as_secs_f64() - f) * 0.1,
+                s.iter()
+                    .zip(spans())
+                    .map(|(s, t)| s + (t - s) * 0.1)
+                    .collect(),
+            ),
+            None => (frame.as_secs_f64(), spans().collect()),
+        };
+        let smoothed = &*self.smoothed.insert((smoothed_frame, smoothed)).1;
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return None;
+        }
+
+        let target = self.target.as_secs_f64();
+        let decision = if smoothed_frame > target {
+            let over = |i: usize| smoothed[i] - self.systems[i].budget.as_secs_f64();
+            let i = (0..self.systems.len())
+                .filter(|&i| self.levels[i] < self.systems[i].levels)
+                .max_by(|&a, &b| {
+                    let key = |i: usize| (over(i) > 0.0, u8::MAX - self.systems[i].priority);
+                    key(a).cmp(&key(b)).then(over(a).total_cmp(&over(b)))
+                })?;
+            self.levels[i] += 1;
+            Shedding::Shed {
+                system: self.systems[i].name,
+                level: self.levels[i],
+                time: Duration::from_secs_f64(smoothed[i]),
+                budget: self.systems[i].budget,
+                frame: Duration::from_secs_f64(smoothed_frame),
+            }
+        } else if smoothed_frame < target * 0.75 {
+            let i = (0..self.systems.len())
+                .filter(|&i| self.levels[i] > 0)
+                .max_by_key(|&i| self.systems[i].priority)?;
+            self.levels[i] -= 1;
+            Shedding::Restored {
+                system: self.systems[i].name,
+                level: self.levels[i],
+            }
+        } else {
+            return None;
+        };
+
+        self.cooldown = COOLDOWN;
+        Some(decision)
+    }
+}
+
+impl fmt::Display for Shedding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shed {
+                system,
+                level,
+                time,
+                budget,
+                frame,
+            } => write!(
+                f,
+                "GPU frame {frame:?} over budget, shedding `{system}` to level {level} \
+                 (it takes {time:?}, its budget is {budget:?})"
+            ),
+            Self::Restored { system, level } => {
+                write!(
+                    f,
+                    "GPU frame under budget, `{system}` back to level {level}"
+                )
+            }
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn budget() -> FrameBudget {
+        FrameBudget::new(
+            16 * MS,
+            vec![
+                SystemBudget {
+                    name: "scene",
+                    budget: 10 * MS,
+                    priority: 255,
+                    levels: 0,
+                },
+                SystemBudget {
+                    name: "post",
+                    budget: 2 * MS,
+                    priority: 1,
+                    levels: 1,
+                },
+                SystemBudget {
+                    name: "particles",
+                    budget: 4 * MS,
+                    priority: 0,
+                    levels: 2,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn sheds_the_system_over_its_budget() {
+        let mut budget = budget();
+        // post is over its budget, particles aren't
+        let shed = budget.update(20 * MS, &[12 * MS, 5 * MS, 3 * MS]).unwrap();
+        assert!(matches!(
+            shed,
+            Shedding::Shed {
+                system: "post",
+                level: 1,
+                ..
+            }
+        ));
+        assert_eq!(budget.level("post"), 1);
+
+        // cooling down
+        for _ in 0..COOLDOWN {
+            assert_eq!(budget.update(20 * MS, &[12 * MS, 2 * MS, 3 * MS]), None);
+        }
+        // post can't go further, the scene can't be shed at all
+        let shed = budget.update(20 * MS, &[12 * MS, 2 * MS, 3 * MS]).unwrap();
+        assert!(matches!(
+            shed,
+            Shedding::Shed {
+                system: "particles",
+                level: 1,
+                ..
+            }
+        ));
+        assert!(shed.to_string().contains("shedding `particles` to level 1"));
+    }
+
+    #[test]
+    fn restores_by_priority_once_well_under() {
+        // within the dead band
+        let mut frame = budget();
+        frame.levels = vec![0, 1, 2];
+        assert_eq!(frame.update(14 * MS, &[]), None);
+
+        let mut frame = budget();
+        frame.levels = vec![0, 1, 2];
+        assert_eq!(
+            frame.update(8 * MS, &[]),
+            Some(Shedding::Restored {
+                system: "post",
+                level: 0
+            })
+        );
+        for _ in 0..=COOLDOWN {
+            frame.update(8 * MS, &[]);
+        }
+        assert_eq!(frame.level("particles"), 1);
+        assert_eq!(frame.level("unknown"), 0);
+    }
+}
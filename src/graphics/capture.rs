@@ -0,0 +1,245 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use wgpu::{
+    BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d, ImageCopyBuffer,
+    ImageDataLayout, Maintain, MapMode, Queue, Texture, TextureFormat,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use zip::{write::FileOptions, ZipWriter};
+
+use super::{pixel_layout::PixelLayout, Graphics};
+use crate::{
+    build_info::BuildInfo,
+    settings::SettingsInner,
+    sim::{SimState, Simulation},
+    RuntimeSettings,
+};
+
+//
+
+/// `--capture-frame N` state
+///
+/// holds a copy of the settings so the dump can describe the whole run
+pub struct FrameCapture {
+    pub frame: u64,
+    pub settings: SettingsInner,
+}
+
+/// `--capture-tick N` state: screenshots of exact simulation ticks
+///
+/// holds the simulation at the next tick, so it's rendered from the tick's own
+/// state instead of whatever frame the wall clock happens to land on
+#[derive(Debug, Default)]
+pub struct TickCapture {
+    /// the ticks left, in descending order
+    ticks: Vec<u64>,
+}
+
+/// a render target read back to the CPU, tightly packed RGBA8
+pub struct CapturedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub rgba: Vec<u8>,
+}
+
+/// the `capture.json` inside of the dump
+#[derive(Debug, Serialize)]
+pub struct CaptureDesc<'a> {
+    pub frame: u64,
+    pub build: BuildInfo,
+    pub adapter: String,
+    pub backend: String,
+    pub passes: Vec<PassDesc>,
+    pub settings: &'a SettingsInner,
+    pub runtime: &'a RuntimeSettings,
+    pub sim: &'a SimState,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PassDesc {
+    pub name: &'static str,
+    /// file name of the dumped render target
+    pub target: String,
+    pub format: String,
+    pub size: (u32, u32),
+    pub clear: Option<[f64; 4]>,
+    pub draws: u32,
+}
+
+/// zip file builder for capture dumps
+pub struct CaptureWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+}
+
+//
+
+impl FrameCapture {
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(format!("capture-frame-{}.zip", self.frame))
+    }
+}
+
+impl TickCapture {
+    pub fn new(ticks: impl IntoIterator<Item = u64>) -> Self {
+        let mut ticks: Vec<u64> = ticks.into_iter().collect();
+        ticks.sort_unstable_by(|a, b| b.cmp(a));
+        ticks.dedup();
+        Self { ticks }
+    }
+
+    pub fn path(tick: u64) -> PathBuf {
+        PathBuf::from(format!("capture-tick-{tick}.png"))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    /// hold `sim` at the next tick, call before its first update
+    pub fn start(&self, sim: &mut Simulation) {
+        sim.hold_at(self.ticks.last().copied());
+    }
+
+    /// call after every simulation update, renders the held tick at `size`,
+    /// then lets the simulation run on to the next one
+    ///
+    /// returns the tick that was written
+    pub fn update(
+        &mut self,
+        graphics: &mut Graphics,
+        sim: &mut Simulation,
+        settings: &RuntimeSettings,
+        size: (u32, u32),
+    ) -> Result<Option<u64>> {
+        // ticks from before the capture started are long gone
+        while self.ticks.last().is_some_and(|&tick| tick < sim.tick()) {
+            let tick = self.ticks.pop().unwrap();
+            tracing::warn!("tick {tick} passed before it could be captured");
+        }
+        self.start(sim);
+        if !sim.is_held() {
+            return Ok(None);
+        }
+
+        let tick = self.ticks.pop().unwrap();
+        self.start(sim);
+        // the tick itself, interpolation would mix in the previous one
+        let image = graphics.render_offscreen(settings, &sim.current(), size)?;
+        image.write_png(BufWriter::new(File::create(Self::path(tick))?))?;
+        Ok(Some(tick))
+    }
+}
+
+impl CapturedImage {
+    /// copy `texture` to the CPU
+    ///
+    /// blocks until the GPU is done, only meant for one-off captures
+    pub fn read(device: &Device, queue: &Queue, texture: &Texture) -> Result<Self> {
+        let format = texture.format();
+        // palette indices are dumped as grayscale
+        let layout = PixelLayout::of(format)
+            .ok_or_else(|| anyhow!("cannot capture a {format:?} render target"))?;
+
+        let (width, height) = (texture.width(), texture.height());
+        let row = width * layout.bytes_per_pixel();
+        let padded_row = row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("capture readback"),
+            size: padded_row as u64 * height as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("capture readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([encoder.finish()]);
+
+        let (tx, rx) = mpsc::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(MapMode::Read, move |res| _ = tx.send(res));
+        device.poll(Maintain::Wait);
+        rx.recv()??;
+
+        let mut pixels = Vec::with_capacity((row * height) as usize);
+        for padded in slice.get_mapped_range().chunks(padded_row as usize) {
+            pixels.extend_from_slice(&padded[..row as usize]);
+        }
+        buffer.unmap();
+
+        Ok(Self {
+            width,
+            height,
+            format,
+            rgba: layout.to_rgba8(pixels),
+        })
+    }
+
+    pub fn write_png(&self, to: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(to, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        if self.format.is_srgb() {
+            encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.rgba)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+impl CaptureWriter<File> {
+    pub fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            zip: ZipWriter::new(File::create(path)?),
+        })
+    }
+}
+
+impl<W: Write + Seek> CaptureWriter<W> {
+    pub fn add_image(&mut self, name: &str, image: &CapturedImage) -> Result<()> {
+        // PNGs are compressed already
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        self.zip.start_file(name, options)?;
+        image.write_png(&mut self.zip)
+    }
+
+    pub fn add_desc(&mut self, desc: &CaptureDesc) -> Result<()> {
+        self.zip
+            .start_file("capture.json", FileOptions::default())?;
+        serde_json::to_writer_pretty(&mut self.zip, desc)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.zip.finish()?;
+        Ok(())
+    }
+}
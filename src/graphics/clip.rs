@@ -0,0 +1,174 @@
+use wgpu::RenderPass;
+
+//
+
+/// a rect in logical pixels (winit's logical coordinates), origin at the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// nested clip rects of UI panels and scroll areas, as scissor rects
+///
+/// every pushed rect is intersected with the ones under it, so children never
+/// draw outside of their parents; the rects are in logical pixels and get scaled
+/// by the DPI scale and the render scale into the viewport the UI is drawn to
+#[derive(Debug, Clone)]
+pub struct ClipStack {
+    /// `[x, y, width, height]` in target pixels
+    viewport: [f32; 4],
+    /// logical to target pixels
+    scale: f32,
+    stack: Vec<ClipRect>,
+}
+
+//
+
+impl ClipRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width: width.max(0.0),
+            height: height.max(0.0),
+        }
+    }
+
+    pub fn intersect(self, other: Self) -> Self {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        Self::new(
+            x,
+            y,
+            (self.x + self.width).min(other.x + other.width) - x,
+            (self.y + self.height).min(other.y + other.height) - y,
+        )
+    }
+
+    /// the smallest rect around both
+    pub fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self::new(
+            x,
+            y,
+            (self.x + self.width).max(other.x + other.width) - x,
+            (self.y + self.height).max(other.y + other.height) - y,
+        )
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+}
+
+impl ClipStack {
+    /// `viewport` is where the UI is drawn in the target, like from
+    /// [`super::post::integer_viewport`], `scale_factor` is the window's DPI scale
+    pub fn new(viewport: [f32; 4], scale_factor: f64, render_scale: f32) -> Self {
+        Self {
+            viewport,
+            scale: scale_factor as f32 * render_scale,
+            stack: Vec::new(),
+        }
+    }
+
+    /// clip to `rect` inside of the current clip, until the matching [`Self::pop`]
+    pub fn push(&mut self, rect: ClipRect) {
+        let rect = match self.current() {
+            Some(current) => current.intersect(rect),
+            None => rect,
+        };
+        self.stack.push(rect);
+    }
+
+    pub fn pop(&mut self) {
+        let popped = self.stack.pop();
+        debug_assert!(popped.is_some(), "unbalanced ClipStack::pop");
+    }
+
+    /// the innermost clip, `None` draws to the whole viewport
+    pub fn current(&self) -> Option<ClipRect> {
+        self.stack.last().copied()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// `[x, y, width, height]` in target pixels, inside of the viewport,
+    /// edges are rounded to the nearest pixel so neighbouring panels don't overlap
+    pub fn scissor(&self) -> [u32; 4] {
+        let [vx, vy, vw, vh] = self.viewport;
+        let (x0, y0, x1, y1) = match self.current() {
+            Some(rect) => (
+                vx + rect.x * self.scale,
+                vy + rect.y * self.scale,
+                vx + (rect.x + rect.width) * self.scale,
+                vy + (rect.y + rect.height) * self.scale,
+            ),
+            None => (vx, vy, vx + vw, vy + vh),
+        };
+        let clamp_x = |x: f32| x.clamp(vx, vx + vw).round().max(0.0) as u32;
+        let clamp_y = |y: f32| y.clamp(vy, vy + vh).round().max(0.0) as u32;
+        let (x0, y0) = (clamp_x(x0), clamp_y(y0));
+        let (x1, y1) = (clamp_x(x1).max(x0), clamp_y(y1).max(y0));
+        [x0, y0, x1 - x0, y1 - y0]
+    }
+
+    /// nothing would be drawn, skip the draws
+    pub fn is_clipped(&self) -> bool {
+        let [_, _, w, h] = self.scissor();
+        w == 0 || h == 0
+    }
+
+    /// set the scissor rect of `pass` to the current clip
+    pub fn apply(&self, pass: &mut RenderPass) {
+        let [x, y, w, h] = self.scissor();
+        pass.set_scissor_rect(x, y, w, h);
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_clips_intersect() {
+        let mut clips = ClipStack::new([0.0, 0.0, 800.0, 600.0], 1.0, 1.0);
+        assert_eq!(clips.scissor(), [0, 0, 800, 600]);
+
+        clips.push(ClipRect::new(100.0, 100.0, 300.0, 200.0));
+        // a scrolled child sticking out of its parent
+        clips.push(ClipRect::new(50.0, 250.0, 200.0, 200.0));
+        assert_eq!(clips.scissor(), [100, 250, 150, 50]);
+        assert_eq!(clips.depth(), 2);
+
+        clips.pop();
+        assert_eq!(clips.scissor(), [100, 100, 300, 200]);
+
+        clips.push(ClipRect::new(500.0, 0.0, 10.0, 10.0));
+        assert!(clips.is_clipped());
+        assert_eq!(clips.scissor()[2..], [0, 0]);
+        clips.pop();
+        clips.pop();
+        assert_eq!(clips.current(), None);
+    }
+
+    #[test]
+    fn scales_into_the_viewport() {
+        // a letterboxed viewport on a 2x display at half the render resolution
+        let mut clips = ClipStack::new([10.0, 20.0, 400.0, 300.0], 2.0, 0.5);
+        clips.push(ClipRect::new(5.5, 5.0, 100.0, 1000.0));
+        assert_eq!(clips.scissor(), [16, 25, 100, 295]);
+
+        let mut clips = ClipStack::new([0.0, 0.0, 100.0, 100.0], 1.5, 1.0);
+        clips.push(ClipRect::new(-10.0, 10.0, 20.0, 20.0));
+        assert_eq!(clips.scissor(), [0, 15, 15, 30]);
+    }
+}
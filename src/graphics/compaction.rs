@@ -0,0 +1,307 @@
+use std::{borrow::Cow, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::*;
+
+use super::prefix_sum::{PrefixSum, ScanBuffers};
+
+//
+
+/// filters a buffer of elements on the GPU into a tight buffer of the ones to keep,
+/// in their original order, with the number kept in its own buffer,
+/// for culling and killing particles without a readback
+///
+/// the predicate is WGSL, `fn keep(i: u32) -> bool`,
+/// reading word `w` of element `i` with `element(i, w)`
+pub struct Compaction {
+    prefix_sum: PrefixSum,
+    stride: u32,
+    layout: BindGroupLayout,
+    flag: ComputePipeline,
+    scatter: ComputePipeline,
+}
+
+/// the offsets for compacting one input into one output
+pub struct CompactBuffers {
+    capacity: u32,
+    params: Buffer,
+    offsets: Buffer,
+    scan: ScanBuffers,
+    bind_group: BindGroup,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Params {
+    count: u32,
+    stride: u32,
+    _pad: [u32; 2],
+}
+
+//
+
+impl Compaction {
+    const WORKGROUP: u32 = 256;
+
+    /// elements are `stride` `u32`s each
+    pub fn new(device: &Device, stride: u32, predicate: &str) -> Self {
+        assert!(stride > 0);
+        let source = format!("{}\n{predicate}", include_str!("./compaction.wgsl"));
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("compaction"),
+            source: ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+
+        let storage = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("compaction"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, false),
+                storage(3, false),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("compaction"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("compaction"),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point,
+            })
+        };
+
+        Self {
+            prefix_sum: PrefixSum::new(device),
+            stride,
+            flag: pipeline("flag"),
+            scatter: pipeline("scatter"),
+            layout,
+        }
+    }
+
+    /// offsets for compacting up to `capacity` elements of `input` into `output`,
+    /// both need `STORAGE` usage and room for `capacity` elements
+    pub fn buffers(
+        &self,
+        device: &Device,
+        input: &Buffer,
+        output: &Buffer,
+        capacity: u32,
+    ) -> CompactBuffers {
+        let capacity = capacity.max(1);
+        let size = capacity as u64 * self.stride as u64 * 4;
+        assert!(input.size() >= size && output.size() >= size);
+
+        let params = device.create_buffer(&BufferDescriptor {
+            label: Some("compaction params"),
+            size: size_of::<Params>() as _,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let offsets = device.create_buffer(&BufferDescriptor {
+            label: Some("compaction offsets"),
+            size: capacity as u64 * 4,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let scan = self.prefix_sum.buffers(device, &offsets, capacity);
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("compaction"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: input.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: offsets.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        CompactBuffers {
+            capacity,
+            params,
+            offsets,
+            scan,
+            bind_group,
+        }
+    }
+
+    /// keep from the first `count` elements of the input,
+    /// the output past [`CompactBuffers::count`] is left as it was
+    ///
+    /// the params are written through the queue,
+    /// so one compaction per [`CompactBuffers`] per submission
+    pub fn compact(
+        &self,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        buffers: &CompactBuffers,
+        count: u32,
+    ) {
+        assert!(count <= buffers.capacity);
+        let params = Params {
+            count,
+            stride: self.stride,
+            _pad: [0; 2],
+        };
+        queue.write_buffer(&buffers.params, 0, bytemuck::bytes_of(&params));
+
+        let workgroups = count.div_ceil(Self::WORKGROUP);
+        if workgroups > 0 {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("compaction flags"),
+            });
+            pass.set_pipeline(&self.flag);
+            pass.set_bind_group(0, &buffers.bind_group, &[]);
+            pass.dispatch_workgroups(workgroups, 1, 1);
+        }
+
+        self.prefix_sum
+            .scan(queue, encoder, &buffers.scan, count, false);
+
+        if workgroups > 0 {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("compaction scatter"),
+            });
+            pass.set_pipeline(&self.scatter);
+            pass.set_bind_group(0, &buffers.bind_group, &[]);
+            pass.dispatch_workgroups(workgroups, 1, 1);
+        }
+    }
+}
+
+impl CompactBuffers {
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// a single `u32`, how many elements were kept,
+    /// copy it into indirect draw or dispatch arguments
+    pub fn count(&self) -> &Buffer {
+        self.scan.total()
+    }
+
+    /// per input element, where it went if it was kept
+    pub fn offsets(&self) -> &Buffer {
+        &self.offsets
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+    use super::*;
+    use crate::graphics::test_util::{read_buffer, test_device};
+
+    /// elements of (value, index), keeping the odd values
+    fn check(len: u32) {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let elements: Vec<[u32; 2]> = (0..len)
+            .map(|i| [i.wrapping_mul(2654435761) >> 7, i])
+            .collect();
+        let init = |contents: &[u8], usage| {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents,
+                usage,
+            })
+        };
+        let size = len.max(1) as usize * 8;
+        let input = init(
+            &[
+                bytemuck::cast_slice(&elements),
+                &vec![0; size - len as usize * 8],
+            ]
+            .concat(),
+            BufferUsages::STORAGE,
+        );
+        let output = init(
+            &vec![0xff; size],
+            BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+        );
+        let readback = |size| {
+            device.create_buffer(&BufferDescriptor {
+                label: None,
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let (output_readback, count_readback) = (readback(size as u64), readback(4));
+
+        let compaction = Compaction::new(
+            &device,
+            2,
+            "fn keep(i: u32) -> bool { return (element(i, 0u) & 1u) == 1u; }",
+        );
+        let buffers = compaction.buffers(&device, &input, &output, len);
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        compaction.compact(&queue, &mut encoder, &buffers, len);
+        encoder.copy_buffer_to_buffer(&output, 0, &output_readback, 0, size as u64);
+        encoder.copy_buffer_to_buffer(buffers.count(), 0, &count_readback, 0, 4);
+        queue.submit([encoder.finish()]);
+
+        let expected: Vec<[u32; 2]> = elements.into_iter().filter(|e| e[0] & 1 == 1).collect();
+        let count = read_buffer::<u32>(&device, &count_readback)[0];
+        assert_eq!(count as usize, expected.len());
+        let compacted: Vec<[u32; 2]> = read_buffer(&device, &output_readback);
+        assert_eq!(&compacted[..expected.len()], &expected[..]);
+        // untouched past the count
+        assert!(compacted[expected.len()..]
+            .iter()
+            .all(|e| *e == [u32::MAX; 2]));
+    }
+
+    #[test]
+    fn keeps_matching_elements_in_order() {
+        for len in [0, 1, 100, 256, 1000, 70_000] {
+            check(len);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+// keeps the elements `keep` is true for, in order: flag, exclusive scan, scatter
+//
+// prepended with the caller's `fn keep(i: u32) -> bool`,
+// which reads the elements through `element`
+
+struct Params {
+    count: u32,
+    // u32s per element
+    stride: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> input: array<u32>;
+@group(0) @binding(2) var<storage, read_write> offsets: array<u32>;
+@group(0) @binding(3) var<storage, read_write> output: array<u32>;
+
+fn element(i: u32, word: u32) -> u32 {
+    return input[i * params.stride + word];
+}
+
+@compute @workgroup_size(256)
+fn flag(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i < params.count {
+        offsets[i] = select(0u, 1u, keep(i));
+    }
+}
+
+// after the scan, the offsets are where the kept elements go
+@compute @workgroup_size(256)
+fn scatter(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.count || !keep(i) {
+        return;
+    }
+    let dst = offsets[i] * params.stride;
+    let src = i * params.stride;
+    for (var word = 0u; word < params.stride; word++) {
+        output[dst + word] = input[src + word];
+    }
+}
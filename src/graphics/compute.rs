@@ -0,0 +1,110 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use wgpu::*;
+
+//
+
+/// submission stream for compute work that doesn't depend on this frame's rendering
+/// (particles, mipmap generation, culling)
+///
+/// the work is submitted before the frame's render commands,
+/// which is the synchronization point: render passes in the same frame see the results
+///
+/// wgpu exposes a single queue per device, so on every backend the stream shares
+/// the graphics queue and only gets its own submission. The separation still lets the
+/// driver overlap it with the previous frame's rendering, and the timings below
+/// show how long the compute work takes from submission to completion.
+pub struct ComputeStream {
+    pending: Vec<CommandBuffer>,
+    /// downlevel devices without compute shaders drop the work
+    supported: bool,
+    async_compute: bool,
+    stats: Arc<Mutex<ComputeStats>>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ComputeStats {
+    pub submissions: u64,
+    /// total submission to completion time
+    pub busy: Duration,
+}
+
+//
+
+impl ComputeStream {
+    pub fn new(info: &AdapterInfo, supported: bool) -> Self {
+        // Vulkan, DX12 and Metal have dedicated compute queues,
+        // but wgpu 0.17 doesn't let us create more than one queue
+        let async_compute = false;
+        tracing::debug!(
+            "async compute on {:?}: {}",
+            info.backend,
+            if async_compute {
+                "separate queue"
+            } else {
+                "shared with the graphics queue"
+            }
+        );
+
+        Self {
+            pending: Vec::new(),
+            supported,
+            async_compute,
+            stats: <_>::default(),
+        }
+    }
+
+    /// users check this before recording compute work and use a CPU path otherwise
+    pub fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.async_compute
+    }
+
+    pub fn encoder(&self, device: &Device, label: &str) -> CommandEncoder {
+        device.create_command_encoder(&CommandEncoderDescriptor { label: Some(label) })
+    }
+
+    /// queue finished compute work for the next [`Self::submit`]
+    pub fn push(&mut self, commands: CommandBuffer) {
+        if !self.supported {
+            tracing::warn!("compute work submitted without compute shader support, dropped");
+            return;
+        }
+        self.pending.push(commands);
+    }
+
+    /// submit everything queued so far, call before the frame's render commands
+    pub fn submit(&mut self, queue: &Queue) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        queue.submit(self.pending.drain(..));
+
+        let submitted = Instant::now();
+        let stats = self.stats.clone();
+        queue.on_submitted_work_done(move || {
+            let mut stats = stats.lock().unwrap();
+            stats.submissions += 1;
+            stats.busy += submitted.elapsed();
+        });
+    }
+
+    pub fn stats(&self) -> ComputeStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+impl ComputeStats {
+    pub fn average(&self) -> Duration {
+        self.busy
+            .checked_div(self.submissions as u32)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{Arc, Mutex, TryLockError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use wgpu::{AdapterInfo, CommandEncoder, Device};
+
+use crate::build_info::{BuildInfo, BUILD};
+
+//
+
+/// how many of the latest debug markers end up in the dump
+const MARKERS: usize = 64;
+
+/// the log of the device, for panics
+static INSTALLED: Mutex<Option<(CrashLog, AdapterInfo)>> = Mutex::new(None);
+
+/// what the GPU was last asked to do, dumped to `gpu-crash-<time>.json`
+/// when wgpu reports a validation error or runs out of memory
+///
+/// wgpu errors only name the resource labels, the dump shows which pass of which
+/// frame recorded the commands around them
+#[derive(Clone, Default)]
+pub struct CrashLog {
+    state: Arc<Mutex<CrashState>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct CrashState {
+    frame: u64,
+    /// the passes recorded in the current frame, in order
+    passes: Vec<&'static str>,
+    /// labels of the long lived pipelines, bind groups and buffers
+    resources: Vec<Resource>,
+    markers: VecDeque<Marker>,
+    /// only the first error is dumped, the ones after it are usually caused by it
+    #[serde(skip)]
+    dumped: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Resource {
+    kind: &'static str,
+    label: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct Marker {
+    frame: u64,
+    label: &'static str,
+}
+
+#[derive(Serialize)]
+struct CrashDump<'a> {
+    error: String,
+    build: BuildInfo,
+    adapter: &'a str,
+    backend: String,
+    driver: &'a str,
+    #[serde(flatten)]
+    state: &'a CrashState,
+}
+
+//
+
+impl CrashLog {
+    /// replace the default error handler (a panic) with one that dumps the log first,
+    /// [`dump_panic`] dumps this log too
+    pub fn install(&self, device: &Device, info: &AdapterInfo) {
+        *INSTALLED.lock().unwrap() = Some((self.clone(), info.clone()));
+
+        let log = self.clone();
+        let info = info.clone();
+        device.on_uncaptured_error(Box::new(move |error| {
+            log.dump_logged(&error.to_string(), &info, true);
+            panic!("wgpu error: {error}");
+        }));
+    }
+
+    pub fn begin_frame(&self, frame: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.frame = frame;
+        state.passes.clear();
+    }
+
+    pub fn resource(&self, kind: &'static str, label: &'static str) {
+        let mut state = self.state.lock().unwrap();
+        state.resources.push(Resource { kind, label });
+    }
+
+    /// record `label` as the next pass of this frame, also as a debug group in `encoder`
+    /// for graphics debuggers, pop it with [`CommandEncoder::pop_debug_group`]
+    pub fn pass(&self, encoder: &mut CommandEncoder, label: &'static str) {
+        encoder.push_debug_group(label);
+        self.state.lock().unwrap().passes.push(label);
+        self.marker(label);
+    }
+
+    /// a debug marker on the CPU side only, for work that isn't a pass
+    pub fn marker(&self, label: &'static str) {
+        let mut state = self.state.lock().unwrap();
+        if state.markers.len() == MARKERS {
+            state.markers.pop_front();
+        }
+        let frame = state.frame;
+        state.markers.push_back(Marker { frame, label });
+    }
+
+    fn dump_logged(&self, error: &str, info: &AdapterInfo, wait: bool) {
+        match self.dump(error, info, wait) {
+            Ok(Some(path)) => tracing::error!("GPU state dumped to {}", path.display()),
+            Ok(None) => {}
+            Err(err) => tracing::error!("Failed to dump the GPU state: {err}"),
+        }
+    }
+
+    /// `wait` for the lock, panics can happen while this thread holds it
+    fn dump(&self, error: &str, info: &AdapterInfo, wait: bool) -> Result<Option<PathBuf>> {
+        let mut state = match self.state.try_lock() {
+            Ok(state) => state,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) if wait => self.state.lock().unwrap(),
+            Err(TryLockError::WouldBlock) => return Err(anyhow!("the crash log is in use")),
+        };
+        if state.dumped {
+            return Ok(None);
+        }
+        state.dumped = true;
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = PathBuf::from(format!("gpu-crash-{time}.json"));
+
+        let dump = CrashDump {
+            error: error.to_string(),
+            build: BUILD,
+            adapter: &info.name,
+            backend: format!("{:?}", info.backend),
+            driver: &info.driver_info,
+            state: &state,
+        };
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&path)?), &dump)?;
+        Ok(Some(path))
+    }
+}
+
+/// dump the installed [`CrashLog`] for a panic on any thread, from the panic hook
+pub fn dump_panic(message: &str) {
+    // a panic while the lock is held can't dump anything
+    let Ok(installed) = INSTALLED.try_lock() else {
+        return;
+    };
+    if let Some((log, info)) = installed.as_ref() {
+        log.dump_logged(message, info, false);
+    }
+}
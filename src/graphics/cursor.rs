@@ -0,0 +1,453 @@
+use std::{borrow::Cow, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{DVec2, Vec2, Vec4};
+use wgpu::{util::DeviceExt, *};
+
+//
+
+/// a cursor drawn at the end of the final pass, while the OS cursor is hidden
+///
+/// it shows up in captures and recordings, looks the same in exclusive
+/// fullscreen, and can be any image
+pub struct SoftwareCursor {
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    uniforms: Buffer,
+    bind_group: BindGroup,
+    /// of the image, in pixels
+    size: (u32, u32),
+    hotspot: (u32, u32),
+    scale: f32,
+    srgb_output: bool,
+    pub pointer: CursorPosition,
+}
+
+/// where the software cursor is drawn, from window events and raw input
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CursorPosition {
+    /// in window pixels, `None` while the cursor is outside
+    position: Option<DVec2>,
+    /// raw mouse motion since the last frame
+    raw: DVec2,
+    /// a `CursorMoved` came since the last frame
+    moved: bool,
+}
+
+/// `Uniforms` in `cursor.wgsl`
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Uniforms {
+    rect: Vec4,
+    srgb_output: u32,
+    _pad: [u32; 3],
+}
+
+//
+
+impl SoftwareCursor {
+    /// the builtin arrow
+    pub const ARROW_SIZE: u32 = 32;
+
+    /// with the builtin arrow, `format` is the output format
+    pub fn new(device: &Device, queue: &Queue, format: TextureFormat, scale: f32) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("cursor"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./cursor.wgsl"))),
+        });
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("cursor"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("cursor"),
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("cursor"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                ..<_>::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
+        // crisp at whole number scales
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("cursor"),
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
+            ..<_>::default()
+        });
+        let uniforms = device.create_buffer(&BufferDescriptor {
+            label: Some("cursor"),
+            size: size_of::<Uniforms>() as _,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let size = Self::ARROW_SIZE;
+        let bind_group = Self::bind_group(
+            device,
+            queue,
+            &layout,
+            &sampler,
+            &uniforms,
+            &arrow(size),
+            (size, size),
+        );
+        Self {
+            pipeline,
+            layout,
+            sampler,
+            uniforms,
+            bind_group,
+            size: (size, size),
+            hotspot: (0, 0),
+            scale: scale.clamp(0.25, 8.0),
+            srgb_output: format.is_srgb(),
+            pointer: CursorPosition::default(),
+        }
+    }
+
+    /// replace the image, `rgba` is sRGB with straight alpha, row by row,
+    /// `hotspot` is the pixel at the pointer position
+    pub fn set_image(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        rgba: &[u8],
+        size: (u32, u32),
+        hotspot: (u32, u32),
+    ) {
+        assert_eq!(rgba.len(), (size.0 * size.1 * 4) as usize);
+        self.bind_group = Self::bind_group(
+            device,
+            queue,
+            &self.layout,
+            &self.sampler,
+            &self.uniforms,
+            rgba,
+            size,
+        );
+        self.size = size;
+        self.hotspot = (hotspot.0.min(size.0), hotspot.1.min(size.1));
+    }
+
+    /// latch the position and draw over `view`, `size` is the window size in pixels
+    pub fn draw(
+        &mut self,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        size: (u32, u32),
+    ) {
+        let window = Vec2::new(size.0.max(1) as f32, size.1.max(1) as f32);
+        let Some(position) = self.pointer.latch(window) else {
+            return;
+        };
+
+        let hotspot = Vec2::new(self.hotspot.0 as f32, self.hotspot.1 as f32) * self.scale;
+        let extent = Vec2::new(self.size.0 as f32, self.size.1 as f32) * self.scale;
+        // whole pixels, so the image isn't resampled
+        let corner = (position - hotspot).round();
+        let to_clip = |pixels: Vec2| pixels / window * Vec2::new(2.0, -2.0);
+        let (corner, extent) = (to_clip(corner) + Vec2::new(-1.0, 1.0), to_clip(extent));
+        let uniforms = Uniforms {
+            rect: Vec4::new(corner.x, corner.y, extent.x, extent.y),
+            srgb_output: self.srgb_output as u32,
+            _pad: [0; 3],
+        };
+        queue.write_buffer(&self.uniforms, 0, bytemuck::bytes_of(&uniforms));
+
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("cursor"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..4, 0..1);
+    }
+
+    fn bind_group(
+        device: &Device,
+        queue: &Queue,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        uniforms: &Buffer,
+        rgba: &[u8],
+        (width, height): (u32, u32),
+    ) -> BindGroup {
+        let texture = device.create_texture_with_data(
+            queue,
+            &TextureDescriptor {
+                label: Some("cursor"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            rgba,
+        );
+        let view = texture.create_view(&<_>::default());
+
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("cursor"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: uniforms.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+impl CursorPosition {
+    /// `CursorMoved`, in window pixels
+    pub fn moved(&mut self, position: (f64, f64)) {
+        self.position = Some(position.into());
+        self.moved = true;
+    }
+
+    /// `CursorLeft`, hides the cursor
+    pub fn left(&mut self) {
+        self.position = None;
+    }
+
+    /// raw mouse motion, `DeviceEvent::MouseMotion`
+    ///
+    /// it usually arrives before the OS has moved its cursor, when no
+    /// `CursorMoved` came in a frame the raw motion moves the cursor instead
+    pub fn raw_motion(&mut self, delta: (f64, f64)) {
+        self.raw += DVec2::from(delta);
+    }
+
+    /// the position to draw at this frame, takes the raw motion,
+    /// kept inside a `window` sized rect
+    pub fn latch(&mut self, window: Vec2) -> Option<Vec2> {
+        if !std::mem::take(&mut self.moved) {
+            self.position = self.position.map(|position| position + self.raw);
+        }
+        self.raw = DVec2::ZERO;
+        self.position = self
+            .position
+            .map(|position| position.clamp(DVec2::ZERO, window.as_dvec2()));
+        self.position.map(|position| position.as_vec2())
+    }
+}
+
+/// the builtin white arrow with a black outline, the hotspot is the top left pixel
+pub fn arrow(size: u32) -> Vec<u8> {
+    // in 32nds of the image
+    const OUTLINE: [Vec2; 7] = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(0.0, 25.0),
+        Vec2::new(6.0, 19.5),
+        Vec2::new(10.0, 28.0),
+        Vec2::new(14.0, 26.0),
+        Vec2::new(10.0, 18.0),
+        Vec2::new(19.0, 18.0),
+    ];
+    let inside = |p: Vec2| {
+        let p = p * 32.0 / size as f32;
+        let mut inside = false;
+        for (i, &a) in OUTLINE.iter().enumerate() {
+            let b = OUTLINE[(i + 1) % OUTLINE.len()];
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                inside = !inside;
+            }
+        }
+        inside
+    };
+
+    let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let texel = if !inside(center) {
+                [0, 0, 0, 0]
+            } else if [(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)]
+                .iter()
+                .any(|&(dx, dy)| !inside(center + Vec2::new(dx, dy)))
+            {
+                [0, 0, 0, 255]
+            } else {
+                [255, 255, 255, 255]
+            };
+            rgba.extend(texel);
+        }
+    }
+    rgba
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::test_util::{read_buffer, test_device};
+
+    #[test]
+    fn builtin_arrow() {
+        let size = SoftwareCursor::ARROW_SIZE;
+        let image = arrow(size);
+        let texel = |x: u32, y: u32| &image[((y * size + x) * 4) as usize..][..4];
+
+        // the tip is at the hotspot
+        assert_eq!(texel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(texel(3, 10), [255, 255, 255, 255]);
+        assert_eq!(texel(size - 1, 0)[3], 0);
+        assert_eq!(texel(size - 1, size - 1)[3], 0);
+    }
+
+    #[test]
+    fn draws_at_the_hotspot() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let format = TextureFormat::Rgba8UnormSrgb;
+        let target = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 64,
+                height: 64,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&<_>::default());
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: 64 * 64 * 4,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut cursor = SoftwareCursor::new(&device, &queue, format, 1.0);
+        cursor.pointer.moved((20.0, 10.0));
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        cursor.draw(&queue, &mut encoder, &view, (64, 64));
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &readback,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(64 * 4),
+                    rows_per_image: None,
+                },
+            },
+            target.size(),
+        );
+        queue.submit([encoder.finish()]);
+
+        let texels: Vec<[u8; 4]> = read_buffer(&device, &readback);
+        let texel = |x: usize, y: usize| texels[y * 64 + x];
+        assert_eq!(texel(20, 10), [0, 0, 0, 255]);
+        assert_eq!(texel(23, 20), [255, 255, 255, 255]);
+        assert_eq!(texel(19, 10)[3], 0);
+        assert_eq!(texel(20, 9)[3], 0);
+    }
+
+    #[test]
+    fn raw_motion_leads_until_the_os_cursor_moves() {
+        let window = Vec2::new(100.0, 100.0);
+        let mut cursor = CursorPosition::default();
+        assert_eq!(cursor.latch(window), None);
+
+        cursor.moved((10.0, 10.0));
+        cursor.raw_motion((5.0, 0.0));
+        // the OS position for this frame already includes the motion
+        assert_eq!(cursor.latch(window), Some(Vec2::new(10.0, 10.0)));
+
+        cursor.raw_motion((5.0, 0.0));
+        cursor.raw_motion((0.0, -2.0));
+        assert_eq!(cursor.latch(window), Some(Vec2::new(15.0, 8.0)));
+        cursor.moved((16.0, 8.0));
+        assert_eq!(cursor.latch(window), Some(Vec2::new(16.0, 8.0)));
+
+        cursor.raw_motion((500.0, 0.0));
+        assert_eq!(cursor.latch(window), Some(Vec2::new(100.0, 8.0)));
+
+        cursor.left();
+        cursor.raw_motion((5.0, 0.0));
+        assert_eq!(cursor.latch(window), None);
+    }
+}
@@ -0,0 +1,41 @@
+// the software cursor, a textured quad over the final image
+
+struct FragmentInput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+struct Uniforms {
+    // top left corner and size in clip space
+    rect: vec4<f32>,
+    // the output format encodes to sRGB itself
+    srgb_output: u32,
+};
+
+@group(0) @binding(0) var image: texture_2d<f32>;
+@group(0) @binding(1) var image_sampler: sampler;
+@group(0) @binding(2) var<uniform> uniforms: Uniforms;
+
+// a triangle strip quad
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> FragmentInput {
+    let uv = vec2<f32>(f32(i & 1u), f32(i >> 1u));
+
+    var fin: FragmentInput;
+    fin.pos = vec4<f32>(uniforms.rect.xy + uv * uniforms.rect.zw, 0.0, 1.0);
+    fin.uv = uv;
+    return fin;
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(fin: FragmentInput) -> @location(0) vec4<f32> {
+    let col = textureSample(image, image_sampler, fin.uv);
+    if uniforms.srgb_output != 0u {
+        return col;
+    }
+    return vec4<f32>(linear_to_srgb(col.rgb), col.a);
+}
@@ -0,0 +1,267 @@
+use std::{borrow::Cow, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::*;
+
+use super::stencil::{depth_stencil_format, DepthStencil};
+use crate::{camera::DepthMode, spline::Spline};
+
+//
+
+/// lines collected on the CPU for one frame of [`DebugDraw`],
+/// positions are camera relative like the rest of the scene
+#[derive(Debug, Default, Clone)]
+pub struct DebugLines {
+    vertices: Vec<LineVertex>,
+}
+
+/// draws [`DebugLines`] on top of the scene, depth tested but not written
+pub struct DebugDraw {
+    pipeline: RenderPipeline,
+    params: Buffer,
+    bind_group: BindGroup,
+    vertices: Buffer,
+    count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct LineVertex {
+    pub position: Vec3,
+    /// not a `Vec4`, which would add padding
+    pub color: [f32; 4],
+}
+
+//
+
+impl DebugLines {
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: Vec4) {
+        let color = color.to_array();
+        self.vertices.extend([
+            LineVertex {
+                position: from,
+                color,
+            },
+            LineVertex {
+                position: to,
+                color,
+            },
+        ]);
+    }
+
+    pub fn polyline(&mut self, points: &[Vec3], color: Vec4) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+    }
+
+    /// three axis aligned lines through `at`
+    pub fn cross(&mut self, at: Vec3, size: f32, color: Vec4) {
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.line(at - axis * size * 0.5, at + axis * size * 0.5, color);
+        }
+    }
+
+    /// the curve, its control polygon at half alpha and a cross on every segment end
+    pub fn spline(&mut self, spline: &Spline, color: Vec4) {
+        self.polyline(&spline.polyline(16), color);
+        let faded = color * Vec4::new(1.0, 1.0, 1.0, 0.5);
+        for i in 0..spline.segments() {
+            let [p0, c0, c1, p1] = spline.segment(i);
+            self.polyline(&[p0, c0, c1, p1], faded);
+            self.cross(p0, 0.1, color);
+        }
+        if !spline.closed() {
+            self.cross(spline.point(spline.segments() as f32), 0.1, color);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+}
+
+impl DebugDraw {
+    pub fn new(device: &Device, format: TextureFormat, depth: Option<DepthMode>) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("debug lines"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./debug_lines.wgsl"))),
+        });
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("debug lines"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let params = device.create_buffer(&BufferDescriptor {
+            label: Some("debug lines params"),
+            size: size_of::<Mat4>() as _,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("debug lines"),
+            layout: &layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            }],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("debug lines"),
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("debug lines"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: size_of::<LineVertex>() as _,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &vertex_attr_array![0 => Float32x3, 1 => Float32x4],
+                }],
+            },
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineList,
+                ..<_>::default()
+            },
+            depth_stencil: depth.map(|depth| {
+                DepthStencil::new(depth_stencil_format(device.features()), Some(depth))
+                    .read_only()
+                    .to_state()
+            }),
+            multisample: <_>::default(),
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            params,
+            bind_group,
+            vertices: Self::create_buffer(device, 1024),
+            count: 0,
+        }
+    }
+
+    /// write `lines` for the next [`Self::draw`], the vertex buffer grows if needed
+    pub fn upload(&mut self, device: &Device, queue: &Queue, view_proj: Mat4, lines: &DebugLines) {
+        let size = (lines.vertices.len() * size_of::<LineVertex>()) as u64;
+        if size > self.vertices.size() {
+            self.vertices = Self::create_buffer(device, lines.vertices.len().next_power_of_two());
+        }
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&view_proj));
+        queue.write_buffer(&self.vertices, 0, bytemuck::cast_slice(&lines.vertices));
+        self.count = lines.vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+        if self.count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertices.slice(..));
+        pass.draw(0..self.count, 0..1);
+    }
+
+    fn create_buffer(device: &Device, capacity: usize) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("debug lines"),
+            size: (capacity.max(1) * size_of::<LineVertex>()) as _,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{capture::CapturedImage, test_util::test_device};
+
+    #[test]
+    fn draws_a_spline() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+
+        let format = TextureFormat::Rgba8Unorm;
+        let target = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 32,
+                height: 32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        // a horizontal line through the middle of the screen
+        let spline = Spline::catmull_rom(
+            &[Vec3::new(-2.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)],
+            false,
+        );
+        let mut lines = DebugLines::default();
+        lines.spline(&spline, Vec4::new(1.0, 0.0, 0.0, 1.0));
+
+        let mut debug = DebugDraw::new(&device, format, None);
+        debug.upload(&device, &queue, Mat4::IDENTITY, &lines);
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        {
+            let view = target.create_view(&<_>::default());
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            debug.draw(&mut pass);
+        }
+        queue.submit([encoder.finish()]);
+
+        let image = CapturedImage::read(&device, &queue, &target).unwrap();
+        let red = |x: usize, y: usize| image.rgba[(y * 32 + x) * 4] > 128;
+        for x in [2, 8, 24, 29] {
+            assert!(red(x, 15) || red(x, 16), "{x}");
+        }
+        assert!(!red(8, 4) && !red(8, 28));
+    }
+}
@@ -0,0 +1,139 @@
+use std::env;
+
+use wgpu::Backends;
+
+use crate::settings::GpuDebugSettings;
+
+//
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// the validation the settings ask for
+///
+/// wgpu 0.17 enables the backend validation layers in debug builds only and doesn't
+/// take instance flags, so the Vulkan layers are switched with the loader's environment
+/// variables before the instance is created; there is no such switch for the DX12 debug layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugLayers {
+    pub validation: bool,
+    pub gpu_based_validation: bool,
+}
+
+//
+
+impl DebugLayers {
+    pub fn new(settings: &GpuDebugSettings) -> Self {
+        let validation = settings.validation.unwrap_or(cfg!(debug_assertions));
+        Self {
+            validation,
+            gpu_based_validation: validation && settings.gpu_based_validation,
+        }
+    }
+
+    /// call before creating the [`wgpu::Instance`]
+    pub fn configure(self, backends: Backends) {
+        tracing::debug!("{self:?}");
+
+        let vars = self.vulkan_env(cfg!(debug_assertions), |var| env::var(var).ok());
+        if backends.contains(Backends::VULKAN) {
+            for (var, value) in vars {
+                tracing::debug!("{var}={value}");
+                env::set_var(var, value);
+            }
+        }
+
+        if backends.contains(Backends::DX12) && self.validation != cfg!(debug_assertions) {
+            tracing::warn!(
+                "the DX12 debug layer follows the build profile, `validation` only affects Vulkan"
+            );
+        }
+    }
+
+    /// Vulkan loader variables, appended to what's already set
+    fn vulkan_env(
+        self,
+        debug_build: bool,
+        get: impl Fn(&str) -> Option<String>,
+    ) -> Vec<(&'static str, String)> {
+        let append = |var: &'static str, value: &str| {
+            // the layer list is like PATH, the others are comma separated
+            let separator = match var {
+                "VK_INSTANCE_LAYERS" if cfg!(windows) => ';',
+                "VK_INSTANCE_LAYERS" => ':',
+                _ => ',',
+            };
+            let value = match get(var).filter(|old| !old.is_empty()) {
+                Some(old) if old.split(separator).any(|v| v == value) => old,
+                Some(old) => format!("{old}{separator}{value}"),
+                None => value.to_string(),
+            };
+            (var, value)
+        };
+
+        let mut vars = Vec::new();
+        match (self.validation, debug_build) {
+            (true, false) => vars.push(append("VK_INSTANCE_LAYERS", VALIDATION_LAYER)),
+            // wgpu enables it when it's there, the loader hides it
+            (false, true) => vars.push(append("VK_LOADER_LAYERS_DISABLE", VALIDATION_LAYER)),
+            _ => {}
+        }
+        if self.gpu_based_validation {
+            vars.push(append(
+                "VK_LAYER_ENABLES",
+                "VK_VALIDATION_FEATURE_ENABLE_GPU_ASSISTED_EXT",
+            ));
+        }
+        vars
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn vulkan_loader_variables() {
+        let layers = |validation, gpu_based_validation| {
+            DebugLayers::new(&GpuDebugSettings {
+                validation,
+                gpu_based_validation,
+            })
+        };
+        let none = |_: &str| None;
+
+        // the defaults leave everything to wgpu
+        assert!(layers(Some(true), false).vulkan_env(true, none).is_empty());
+        assert!(layers(Some(false), false)
+            .vulkan_env(false, none)
+            .is_empty());
+        // gpu based validation needs validation
+        assert_eq!(layers(Some(false), true), layers(Some(false), false));
+
+        assert_eq!(
+            layers(Some(true), true).vulkan_env(false, |var| {
+                (var == "VK_INSTANCE_LAYERS").then(|| "VK_LAYER_MESA_overlay".into())
+            }),
+            [
+                (
+                    "VK_INSTANCE_LAYERS",
+                    "VK_LAYER_MESA_overlay:VK_LAYER_KHRONOS_validation".to_string()
+                ),
+                (
+                    "VK_LAYER_ENABLES",
+                    "VK_VALIDATION_FEATURE_ENABLE_GPU_ASSISTED_EXT".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            layers(Some(false), false)
+                .vulkan_env(true, |_| Some("VK_LAYER_KHRONOS_validation".into())),
+            [(
+                "VK_LOADER_LAYERS_DISABLE",
+                "VK_LAYER_KHRONOS_validation".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use crate::{platform::power::ThermalState, settings::DynamicResolutionSettings};
+
+//
+
+/// frames between two scale changes, gives the new scale time to show up in the timings
+const COOLDOWN: u32 = 30;
+
+/// adjusts the render scale so the GPU frame time stays under the budget
+///
+/// the smoothed GPU time has to leave a dead band around the budget before
+/// the scale changes: above it the scale drops right away, below it the scale
+/// only grows after a few calm frames, so it doesn't oscillate
+#[derive(Debug)]
+pub struct DynamicResolution {
+    settings: DynamicResolutionSettings,
+    budget: Duration,
+    /// exponential moving average of the GPU time in seconds
+    smoothed: Option<f64>,
+    cooldown: u32,
+    /// lowers the max scale and relaxes the budget to the throttled frame rate
+    thermal: ThermalState,
+}
+
+//
+
+impl DynamicResolution {
+    /// `refresh_period` is the budget if the settings don't give one
+    pub fn new(settings: DynamicResolutionSettings, refresh_period: Option<Duration>) -> Self {
+        let budget = if settings.budget_ms > 0.0 {
+            Duration::from_secs_f32(settings.budget_ms / 1000.0)
+        } else {
+            refresh_period.unwrap_or(Duration::from_micros(16_667))
+        };
+        tracing::debug!("dynamic resolution budget: {budget:?}");
+
+        Self {
+            settings,
+            budget,
+            smoothed: None,
+            cooldown: 0,
+            thermal: ThermalState::Nominal,
+        }
+    }
+
+    /// feed a GPU frame time, returns the new scale if it should change
+    pub fn update(&mut self, gpu_time: Duration, scale: f32) -> Option<f32> {
+        let time = gpu_time.as_secs_f64();
+        let smoothed = match self.smoothed {
+            Some(smoothed) => smoothed + (time - smoothed) * 0.1,
+            None => time,
+        };
+        self.smoothed = Some(smoothed);
+
+        let min = self.settings.min_scale;
+        let max = self
+            .settings
+            .max_scale
+            .min(self.thermal.max_render_scale())
+            .max(min);
+        // a hot device can't wait for the cooldown
+        if scale > max {
+            self.cooldown = COOLDOWN;
+            return Some(max);
+        }
+
+        if self.cooldown != 0 {
+            self.cooldown -= 1;
+            return None;
+        }
+
+        let budget = self.budget().as_secs_f64();
+        let load = smoothed / budget;
+
+        // the cost scales with the pixel count, the square of the scale
+        let target = if load > 0.95 {
+            scale * (0.9 / load).sqrt() as f32
+        } else if load < 0.75 {
+            // grow carefully, too far means an immediate drop
+            scale * 1.05
+        } else {
+            return None;
+        };
+
+        let target = target.clamp(min, max);
+        // skip tiny changes, every change reallocates the scene target
+        if (target - scale).abs() < 0.02 {
+            return None;
+        }
+
+        self.cooldown = COOLDOWN;
+        Some(target)
+    }
+
+    /// the frame time of the throttled frame rate when that's longer
+    pub fn budget(&self) -> Duration {
+        let throttled = self
+            .thermal
+            .max_fps()
+            .map(|fps| Duration::from_secs_f32(1.0 / fps));
+        self.budget.max(throttled.unwrap_or_default())
+    }
+
+    pub fn set_thermal(&mut self, thermal: ThermalState) {
+        self.thermal = thermal;
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// a 10 ms budget, scales 0.5 to 1
+    fn controller() -> DynamicResolution {
+        DynamicResolution::new(
+            DynamicResolutionSettings {
+                enabled: true,
+                budget_ms: 10.0,
+                min_scale: 0.5,
+                max_scale: 1.0,
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn over_budget_drops_in_proportion() {
+        // twice the budget: the pixels for 90% of it
+        let scale = controller().update(20 * MS, 1.0).unwrap();
+        assert!((scale - 0.45f32.sqrt()).abs() < 1e-5, "{scale}");
+    }
+
+    #[test]
+    fn under_budget_grows_slowly() {
+        let scale = controller().update(5 * MS, 0.6).unwrap();
+        assert!((scale - 0.63).abs() < 1e-5, "{scale}");
+    }
+
+    #[test]
+    fn stays_within_min_and_max() {
+        assert_eq!(controller().update(100 * MS, 0.6), Some(0.5));
+        assert_eq!(controller().update(100 * MS, 0.5), None);
+        assert_eq!(controller().update(MS, 0.97), Some(1.0));
+        // 0.99 to 1.0 isn't worth reallocating the scene target
+        assert_eq!(controller().update(MS, 0.99), None);
+        assert_eq!(controller().update(MS, 1.0), None);
+    }
+
+    #[test]
+    fn hysteresis() {
+        // inside the dead band of 75-95% of the budget
+        let mut dynamic = controller();
+        for _ in 0..100 {
+            assert_eq!(dynamic.update(Duration::from_micros(8500), 0.8), None);
+        }
+
+        // a single spike barely moves the average
+        assert_eq!(dynamic.update(15 * MS, 0.8), None);
+
+        // a change is followed by a cooldown, even if still over budget
+        let mut dynamic = controller();
+        let dropped = dynamic.update(20 * MS, 1.0).unwrap();
+        for _ in 0..COOLDOWN {
+            assert_eq!(dynamic.update(20 * MS, dropped), None);
+        }
+        assert!(dynamic
+            .update(20 * MS, dropped)
+            .is_some_and(|s| s < dropped));
+    }
+
+    #[test]
+    fn thermal_throttling() {
+        let settings = DynamicResolutionSettings {
+            enabled: true,
+            budget_ms: 10.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+        };
+        let mut dynamic = DynamicResolution::new(settings, None);
+        let ms = Duration::from_millis;
+
+        // calm, stays at the top
+        assert_eq!(dynamic.update(ms(8), 1.0), None);
+
+        // hot, drops below the thermal limit right away
+        dynamic.set_thermal(ThermalState::Serious);
+        assert_eq!(dynamic.budget(), Duration::from_secs_f32(1.0 / 45.0));
+        assert_eq!(dynamic.update(ms(8), 1.0), Some(0.75));
+        // and doesn't grow past it
+        for _ in 0..100 {
+            assert_eq!(dynamic.update(ms(1), 0.75), None);
+        }
+
+        // cooled down, grows again after the cooldown
+        dynamic.set_thermal(ThermalState::Nominal);
+        assert_eq!(dynamic.budget(), ms(10));
+        let grown = (0..100).find_map(|_| dynamic.update(ms(1), 0.75));
+        assert!(grown.is_some_and(|scale| scale > 0.75));
+    }
+}
@@ -0,0 +1,292 @@
+use std::{borrow::Cow, fmt, mem::size_of};
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, *};
+
+use super::{
+    capture::CapturedImage,
+    post::{PostProcess, PostUniforms},
+};
+
+//
+
+/// `--gamma-audit`: renders a reference chart through the scene target, the final pass
+/// and the output format, then checks the bytes that come out
+///
+/// the chart is a black to white gradient over three patches:
+/// white at 50% alpha blended over black, an opaque 50% gray and an opaque 20% gray,
+/// all in linear light; with a correct pipeline the blended patch matches the gray one
+/// and 20% encodes to sRGB 124
+pub struct GammaAudit {
+    pipeline: RenderPipeline,
+    vertices: Buffer,
+    count: u32,
+}
+
+/// the chart as read back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GammaReport {
+    pub output: TextureFormat,
+    /// the 50% alpha blend
+    pub blended: u8,
+    /// the opaque 50% gray
+    pub half: u8,
+    /// the opaque 20% gray
+    pub fifth: u8,
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct ChartVertex {
+    position: [f32; 2],
+    /// linear
+    color: [f32; 4],
+}
+
+//
+
+impl GammaAudit {
+    pub const SIZE: (u32, u32) = (256, 64);
+
+    /// `scene_format` is what the chart is drawn and blended in, like the scene
+    pub fn new(device: &Device, scene_format: TextureFormat) -> Result<Self> {
+        if scene_format == PostProcess::INDEX_FORMAT {
+            return Err(anyhow!(
+                "the gamma audit doesn't work in indexed color mode"
+            ));
+        }
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("gamma chart"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./gamma_chart.wgsl"))),
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("gamma chart"),
+            layout: None,
+            vertex: VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: size_of::<ChartVertex>() as _,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+                }],
+            },
+            primitive: <_>::default(),
+            depth_stencil: None,
+            multisample: <_>::default(),
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: scene_format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let vertices = chart();
+        Ok(Self {
+            pipeline,
+            vertices: device.create_buffer_init(&util::BufferInitDescriptor {
+                label: Some("gamma chart"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: BufferUsages::VERTEX,
+            }),
+            count: vertices.len() as u32,
+        })
+    }
+
+    /// draw the chart into `post`'s scene target and post-process it into `output`,
+    /// `post` gets a chart sized scene target
+    pub fn run(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        post: &mut PostProcess,
+        output: TextureFormat,
+    ) -> Result<(GammaReport, CapturedImage)> {
+        let (width, height) = Self::SIZE;
+        let uniforms = PostUniforms {
+            srgb_output: output.is_srgb() as u32,
+            ..PostUniforms::default()
+        };
+        post.prepare(device, queue, Self::SIZE, &uniforms);
+
+        let target = device.create_texture(&TextureDescriptor {
+            label: Some("gamma chart"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: output,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("gamma chart"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("gamma chart"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &post.scene().unwrap().view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_vertex_buffer(0, self.vertices.slice(..));
+            pass.draw(0..self.count, 0..1);
+        }
+        post.blit(
+            &mut encoder,
+            &target.create_view(&TextureViewDescriptor::default()),
+            None,
+        );
+        queue.submit([encoder.finish()]);
+
+        let image = CapturedImage::read(device, queue, &target)?;
+        // the green channel in the middle of each patch
+        let patch = |i: u32| {
+            let (x, y) = (width * (2 * i + 1) / 6, height * 3 / 4);
+            image.rgba[((y * width + x) * 4 + 1) as usize]
+        };
+        let report = GammaReport {
+            output,
+            blended: patch(0),
+            half: patch(1),
+            fifth: patch(2),
+        };
+        Ok((report, image))
+    }
+}
+
+impl GammaReport {
+    /// 20% linear gray in sRGB
+    pub const FIFTH: u8 = 124;
+    const TOLERANCE: u8 = 3;
+
+    /// what's wrong, empty if nothing is
+    pub fn problems(&self) -> Vec<String> {
+        let near = |a: u8, b: u8| a.abs_diff(b) <= Self::TOLERANCE;
+        let mut problems = Vec::new();
+
+        if !near(self.fifth, Self::FIFTH) {
+            problems.push(match self.fifth {
+                // 0.2 * 255
+                v if near(v, 51) => format!(
+                    "linear colors reach the {:?} output unencoded, everything is too dark",
+                    self.output
+                ),
+                // 124 encoded again
+                v if near(v, 186) => format!(
+                    "colors are sRGB encoded twice for the {:?} output, everything is washed out",
+                    self.output
+                ),
+                v => format!("20% gray came out as {v}, expected {}", Self::FIFTH),
+            });
+        }
+        if !near(self.blended, self.half) {
+            problems.push(format!(
+                "a 50% alpha blend came out as {}, 50% gray as {}: \
+                 blending happens on sRGB encoded values, blends look too dark",
+                self.blended, self.half
+            ));
+        }
+        problems
+    }
+}
+
+impl fmt::Display for GammaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} output: 50% blend {}, 50% gray {}, 20% gray {} (expected {})",
+            self.output,
+            self.blended,
+            self.half,
+            self.fifth,
+            Self::FIFTH
+        )
+    }
+}
+
+/// the gradient in the top half and the patches in the bottom half, in clip space
+fn chart() -> Vec<ChartVertex> {
+    let rect = |x: [f32; 2], y: [f32; 2], left: [f32; 4], right: [f32; 4]| {
+        let v = |position, color| ChartVertex { position, color };
+        [
+            v([x[0], y[0]], left),
+            v([x[1], y[0]], right),
+            v([x[1], y[1]], right),
+            v([x[0], y[0]], left),
+            v([x[1], y[1]], right),
+            v([x[0], y[1]], left),
+        ]
+    };
+    let gray = |v: f32, a: f32| [v, v, v, a];
+    let third = 1.0 / 3.0;
+
+    [
+        rect([-1.0, 1.0], [0.0, 1.0], gray(0.0, 1.0), gray(1.0, 1.0)),
+        rect([-1.0, -third], [-1.0, 0.0], gray(1.0, 0.5), gray(1.0, 0.5)),
+        rect([-third, third], [-1.0, 0.0], gray(0.5, 1.0), gray(0.5, 1.0)),
+        rect([third, 1.0], [-1.0, 0.0], gray(0.2, 1.0), gray(0.2, 1.0)),
+    ]
+    .concat()
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::test_util::test_device;
+
+    #[test]
+    fn correct_pipeline_passes() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+
+        for output in [
+            TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Bgra8UnormSrgb,
+            TextureFormat::Rgba8Unorm,
+        ] {
+            let mut post = PostProcess::new(&device, output, false, FilterMode::Linear);
+            let audit = GammaAudit::new(&device, post.scene_format()).unwrap();
+            let (report, image) = audit.run(&device, &queue, &mut post, output).unwrap();
+            assert_eq!(report.problems(), Vec::<String>::new(), "{report}");
+            assert_eq!(image.rgba.len(), 256 * 64 * 4);
+        }
+    }
+
+    #[test]
+    fn flags_mistakes() {
+        let report = |blended, fifth| GammaReport {
+            output: TextureFormat::Bgra8Unorm,
+            blended,
+            half: 188,
+            fifth,
+        };
+        assert!(report(188, 124).problems().is_empty());
+        assert!(report(187, 51).problems()[0].contains("too dark"));
+        assert!(report(188, 186).problems()[0].contains("twice"));
+        assert!(report(128, 124).problems()[0].contains("blending"));
+        assert_eq!(report(128, 51).problems().len(), 2);
+    }
+}
@@ -0,0 +1,23 @@
+struct VertexInput {
+    @location(0) pos: vec2<f32>,
+    // linear
+    @location(1) col: vec4<f32>,
+};
+
+struct FragmentInput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) col: vec4<f32>,
+};
+
+@vertex
+fn vs_main(vin: VertexInput) -> FragmentInput {
+    var fin: FragmentInput;
+    fin.pos = vec4<f32>(vin.pos, 0.0, 1.0);
+    fin.col = vin.col;
+    return fin;
+}
+
+@fragment
+fn fs_main(fin: FragmentInput) -> @location(0) vec4<f32> {
+    return fin.col;
+}
@@ -0,0 +1,145 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{DAffine3, DMat4, DQuat, DVec3, Mat4};
+
+use crate::sim::SimState;
+
+//
+
+/// the half of the scene globals that only depends on a simulation state,
+/// the same every time the state is replayed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimGlobals {
+    /// world space transform of the triangle
+    pub model: DAffine3,
+}
+
+/// the half of the scene globals latched right before the frame is recorded:
+/// the camera and the display settings
+///
+/// input that moved the camera late never feeds back into [`SimGlobals`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PresentGlobals {
+    /// camera position, the model is made camera relative with it
+    pub camera: DVec3,
+    pub view_proj: Mat4,
+    pub palette_len: u32,
+    pub log_depth_coef: f32,
+    pub reversed_z: bool,
+    pub high_contrast: bool,
+}
+
+/// `Push` in `shader.wgsl`
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub(super) struct PushConstant {
+    pub mvp: Mat4,
+    /// indexed color only
+    pub palette_len: u32,
+    pub log_depth_coef: f32,
+    pub reversed_z: u32,
+    pub high_contrast: u32,
+}
+
+//
+
+impl SimGlobals {
+    pub fn new(state: &SimState) -> Self {
+        Self {
+            model: DAffine3::from_quat(DQuat::from_rotation_z(state.rotation as f64)),
+        }
+    }
+}
+
+impl PushConstant {
+    /// combine both halves, the model goes camera relative in `f64` first
+    pub fn latch(sim: &SimGlobals, present: &PresentGlobals) -> Self {
+        let mut model = sim.model;
+        model.translation -= present.camera;
+        Self {
+            mvp: present.view_proj * DMat4::from(model).as_mat4(),
+            palette_len: present.palette_len,
+            log_depth_coef: present.log_depth_coef,
+            reversed_z: present.reversed_z as u32,
+            high_contrast: present.high_contrast as u32,
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use glam::{DVec2, Vec2, Vec3};
+
+    use super::*;
+    use crate::{
+        camera::{Camera2d, DepthMode},
+        settings::SimulationSettings,
+        sim::Simulation,
+    };
+
+    fn present(camera: &Camera2d) -> PresentGlobals {
+        PresentGlobals {
+            camera: camera.position.extend(0.0),
+            view_proj: camera.view_proj(16.0 / 9.0, DepthMode::Standard),
+            palette_len: 4,
+            log_depth_coef: 0.0,
+            reversed_z: false,
+            high_contrast: false,
+        }
+    }
+
+    #[test]
+    fn latching_is_camera_relative() {
+        let sim = SimGlobals {
+            model: DAffine3::from_translation(DVec3::new(1e9 + 1.0, 2.0, 0.0)),
+        };
+        let camera = Camera2d {
+            position: DVec2::new(1e9, 0.0),
+            ..Camera2d::default()
+        };
+        let push = PushConstant::latch(&sim, &present(&camera));
+
+        let expected = camera.view_proj(16.0 / 9.0, DepthMode::Standard)
+            * Mat4::from_translation(Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(push.mvp, expected);
+        assert_eq!(push.palette_len, 4);
+    }
+
+    #[test]
+    fn replays_ignore_late_latched_cameras() {
+        let settings = SimulationSettings::default();
+        let (mut live, mut replay) = (Simulation::new(&settings), Simulation::new(&settings));
+        // the live run pans and the replay zooms, both late, right before the latch
+        let (mut live_camera, mut replay_camera) = (Camera2d::default(), Camera2d::default());
+
+        for tick in 0..120 {
+            live.step();
+            live_camera.pan_pixels(Vec2::new(3.0, -1.0), 720.0);
+            let live_sim = SimGlobals::new(&live.current());
+            let live_push = PushConstant::latch(&live_sim, &present(&live_camera));
+
+            replay.step();
+            replay_camera.zoom_by(1.01);
+            let replay_sim = SimGlobals::new(&replay.current());
+            let replay_push = PushConstant::latch(&replay_sim, &present(&replay_camera));
+
+            assert_eq!(
+                live.current().rotation.to_bits(),
+                replay.current().rotation.to_bits(),
+                "tick {tick}"
+            );
+            assert_eq!(
+                live_sim.model.to_cols_array().map(f64::to_bits),
+                replay_sim.model.to_cols_array().map(f64::to_bits),
+                "tick {tick}"
+            );
+            assert_ne!(
+                bytemuck::bytes_of(&live_push),
+                bytemuck::bytes_of(&replay_push),
+                "tick {tick}"
+            );
+        }
+        assert_eq!(live.tick(), replay.tick());
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use wgpu::*;
+
+use super::readback::Readback;
+
+//
+
+/// how long the GPU takes for a frame's render commands
+///
+/// timestamp queries where the adapter has them,
+/// otherwise the time from submission until the queue reports the work done,
+/// which also counts the time the work waited behind earlier frames
+///
+/// with timestamps the frame can also be split into spans, one per system
+pub struct GpuTimer {
+    timestamps: Option<Timestamps>,
+    /// the submission timing
+    last: Arc<Mutex<Option<Duration>>>,
+    /// of the latest measurement, empty without timestamps
+    spans: Vec<Duration>,
+}
+
+struct Timestamps {
+    queries: QuerySet,
+    /// `spans + 1`
+    count: u32,
+    /// the next query [`GpuTimer::split`] writes
+    next: u32,
+    resolve: Buffer,
+    readback: Readback<u64>,
+    /// nanoseconds per tick
+    period: f32,
+}
+
+//
+
+impl GpuTimer {
+    /// `spans` is one more than the [`Self::split`]s per frame
+    pub fn new(device: &Device, queue: &Queue, spans: u32) -> Self {
+        let count = spans.max(1) + 1;
+        let timestamps = device
+            .features()
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| Timestamps {
+                queries: device.create_query_set(&QuerySetDescriptor {
+                    label: Some("frame timer"),
+                    ty: QueryType::Timestamp,
+                    count,
+                }),
+                count,
+                next: 0,
+                resolve: device.create_buffer(&BufferDescriptor {
+                    label: Some("frame timer"),
+                    size: count as u64 * 8,
+                    usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback: Readback::new(device, count as usize, "frame timer"),
+                period: queue.get_timestamp_period(),
+            });
+        if timestamps.is_none() {
+            tracing::debug!("no timestamp queries, timing the GPU from submission to completion");
+        }
+
+        Self {
+            timestamps,
+            last: <_>::default(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// record at the start of the frame's encoder
+    pub fn begin(&mut self, encoder: &mut CommandEncoder) {
+        if let Some(t) = self.timestamps.as_mut() {
+            encoder.write_timestamp(&t.queries, 0);
+            t.next = 1;
+        }
+    }
+
+    /// end the current span and start the next one, between passes
+    pub fn split(&mut self, encoder: &mut CommandEncoder) {
+        if let Some(t) = self.timestamps.as_mut() {
+            if t.next + 1 < t.count {
+                encoder.write_timestamp(&t.queries, t.next);
+                t.next += 1;
+            }
+        }
+    }
+
+    /// record at the end of the frame's encoder
+    pub fn end(&mut self, encoder: &mut CommandEncoder) {
+        if let Some(t) = self.timestamps.as_mut() {
+            // spans that weren't split off end here too
+            for query in t.next..t.count {
+                encoder.write_timestamp(&t.queries, query);
+            }
+            encoder.resolve_query_set(&t.queries, 0..t.count, &t.resolve, 0);
+            t.readback.copy_from(encoder, &t.resolve, 0);
+        }
+    }
+
+    /// call after submitting the encoder
+    pub fn submitted(&mut self, queue: &Queue) {
+        if let Some(t) = self.timestamps.as_mut() {
+            t.readback.submitted();
+            return;
+        }
+
+        let submitted = Instant::now();
+        let last = self.last.clone();
+        queue.on_submitted_work_done(move || {
+            *last.lock().unwrap() = Some(submitted.elapsed());
+        });
+    }
+
+    /// the latest measurement, a frame or two old
+    pub fn poll(&mut self) -> Option<Duration> {
+        let Some(t) = self.timestamps.as_mut() else {
+            return self.last.lock().unwrap().take();
+        };
+
+        let ticks = t.readback.poll()?;
+        let duration = |from: u64, to: u64| {
+            let ticks = to.checked_sub(from)?;
+            Some(Duration::from_nanos(
+                (ticks as f64 * t.period as f64) as u64,
+            ))
+        };
+        self.spans = ticks
+            .windows(2)
+            .map(|pair| duration(pair[0], pair[1]).unwrap_or_default())
+            .collect();
+        duration(ticks[0], *ticks.last()?)
+    }
+
+    /// the spans of the latest [`Self::poll`]ed frame
+    pub fn spans(&self) -> &[Duration] {
+        &self.spans
+    }
+}
@@ -0,0 +1,302 @@
+use std::{borrow::Cow, fmt, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{UVec2, Vec3, Vec4};
+use wgpu::*;
+
+use super::{
+    post::{PostUniforms, SceneTarget},
+    readback::Readback,
+};
+use crate::color::{hdr_rolloff, linear_to_srgb};
+
+//
+
+/// reads back the scene target pixel under the cursor: the linear color the
+/// scene rendered, what the final pass makes of it and the depth
+///
+/// a compute pass copies the pixel into a small buffer, the results
+/// arrive through a [`Readback`] a frame or two later
+pub struct PixelInspector {
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+    pixel: Buffer,
+    probe: Buffer,
+    readback: Readback<Probe>,
+    /// the final pass settings of the latest probe
+    uniforms: PostUniforms,
+}
+
+/// one inspected pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelSample {
+    /// in the scene target
+    pub pixel: UVec2,
+    /// as rendered, before the final pass, or the palette index / 255 in indexed mode
+    pub linear: Vec4,
+    /// written to the output by the final pass, sRGB encoded unless it's extended
+    pub output: Vec4,
+    pub depth: f32,
+}
+
+/// `Probe` in `inspector.wgsl`
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Probe {
+    color: Vec4,
+    pixel: UVec2,
+    depth: f32,
+    _pad: f32,
+}
+
+//
+
+impl PixelInspector {
+    pub fn new(device: &Device) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("pixel inspector"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./inspector.wgsl"))),
+        });
+
+        let entry = |binding, ty| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty,
+            count: None,
+        };
+        let texture = |sample_type| BindingType::Texture {
+            sample_type,
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("pixel inspector"),
+            entries: &[
+                entry(
+                    0,
+                    BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                ),
+                entry(1, texture(TextureSampleType::Float { filterable: false })),
+                entry(2, texture(TextureSampleType::Float { filterable: false })),
+                entry(
+                    3,
+                    BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                ),
+            ],
+        });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("pixel inspector"),
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("pixel inspector"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            module: &module,
+            entry_point: "inspect",
+        });
+
+        let buffer = |label, size, usage| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            layout,
+            pipeline,
+            pixel: buffer(
+                "inspected pixel",
+                16,
+                BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            ),
+            probe: buffer(
+                "pixel probe",
+                size_of::<Probe>() as u64,
+                BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            ),
+            readback: Readback::new(device, 1, "pixel probe"),
+            uniforms: PostUniforms::default(),
+        }
+    }
+
+    /// record reading `pixel` of `scene`,
+    /// `uniforms` are the final pass settings it's shown with
+    pub fn probe(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        scene: &SceneTarget,
+        pixel: UVec2,
+        uniforms: &PostUniforms,
+    ) {
+        let Some(target) = self.readback.target() else {
+            return;
+        };
+        self.uniforms = *uniforms;
+        queue.write_buffer(
+            &self.pixel,
+            0,
+            bytemuck::bytes_of(&pixel.extend(0).extend(0)),
+        );
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("pixel inspector"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: self.pixel.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&scene.view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&scene.depth_only),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: self.probe.as_entire_binding(),
+                },
+            ],
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("pixel inspector"),
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(1, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.probe, 0, target, 0, size_of::<Probe>() as u64);
+    }
+
+    /// call after submitting the encoder of [`Self::probe`]
+    pub fn submitted(&mut self) {
+        self.readback.submitted();
+    }
+
+    /// the newest finished probe
+    pub fn poll(&mut self) -> Option<PixelSample> {
+        let probe = *self.readback.poll()?.first()?;
+        Some(PixelSample {
+            pixel: probe.pixel,
+            linear: probe.color,
+            output: final_pass(probe.color, &self.uniforms),
+            depth: probe.depth,
+        })
+    }
+}
+
+/// what `output` in `post.wgsl` does to a color, without the dithering
+fn final_pass(linear: Vec4, uniforms: &PostUniforms) -> Vec4 {
+    let color = uniforms.color_matrix * linear;
+    if uniforms.extended_output != 0 {
+        let rgb = hdr_rolloff(color.truncate().max(Vec3::ZERO), uniforms.hdr_peak);
+        return rgb.extend(color.w);
+    }
+    let [r, g, b] = linear_to_srgb(color.truncate()).map(|c| c as f32 / 255.0);
+    Vec4::new(r, g, b, color.w.clamp(0.0, 1.0))
+}
+
+impl fmt::Display for PixelSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            pixel,
+            linear,
+            output,
+            depth,
+        } = self;
+        write!(
+            f,
+            "pixel {} {}: linear [{:.4}, {:.4}, {:.4}, {:.4}], output [{:.3}, {:.3}, {:.3}, {:.3}], depth {depth:.6}",
+            pixel.x, pixel.y, linear.x, linear.y, linear.z, linear.w, output.x, output.y, output.z, output.w
+        )
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{post::PostProcess, test_util::test_device};
+
+    #[test]
+    fn reads_color_and_depth() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+
+        let mut post = PostProcess::new(
+            &device,
+            TextureFormat::Rgba16Float,
+            false,
+            FilterMode::Nearest,
+        );
+        post.prepare(&device, &queue, (4, 4), &PostUniforms::default());
+        let scene = post.scene().unwrap();
+
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &scene.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color {
+                        r: 2.5,
+                        g: 0.25,
+                        b: 0.0,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &scene.depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(0.5),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        let mut inspector = PixelInspector::new(&device);
+        let uniforms = PostUniforms::default();
+        inspector.probe(
+            &device,
+            &queue,
+            &mut encoder,
+            scene,
+            UVec2::new(3, 1),
+            &uniforms,
+        );
+        queue.submit([encoder.finish()]);
+        inspector.submitted();
+        device.poll(Maintain::Wait);
+
+        let sample = inspector.poll().unwrap();
+        assert_eq!(sample.pixel, UVec2::new(3, 1));
+        assert_eq!(sample.linear, Vec4::new(2.5, 0.25, 0.0, 1.0));
+        assert!((sample.depth - 0.5).abs() < 1e-6, "{}", sample.depth);
+        // clamped and sRGB encoded, 0.25 is 137 / 255
+        assert_eq!(sample.output.x, 1.0);
+        assert_eq!((sample.output.y * 255.0).round(), 137.0);
+    }
+}
@@ -0,0 +1,22 @@
+// reads one pixel of the scene target and its depth for the pixel inspector
+
+struct Probe {
+    color: vec4<f32>,
+    pixel: vec2<u32>,
+    depth: f32,
+    _pad: f32,
+};
+
+@group(0) @binding(0) var<uniform> pixel: vec4<u32>;
+@group(0) @binding(1) var scene: texture_2d<f32>;
+// not a `texture_depth_2d`, the GL backend can't `textureLoad` those
+@group(0) @binding(2) var depth: texture_2d<f32>;
+@group(0) @binding(3) var<storage, read_write> probe: Probe;
+
+@compute @workgroup_size(1)
+fn inspect() {
+    let p = vec2<i32>(pixel.xy);
+    probe.color = textureLoad(scene, p, 0);
+    probe.pixel = pixel.xy;
+    probe.depth = textureLoad(depth, p, 0).r;
+}
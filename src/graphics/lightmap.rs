@@ -0,0 +1,599 @@
+use std::{borrow::Cow, mem::size_of};
+
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    *,
+};
+
+use super::{
+    bake::{dilate, BakeMesh, TexelBake},
+    blend::BlendMode,
+    stencil::{depth_stencil_format, DepthStencil},
+};
+use crate::camera::DepthMode;
+
+//
+
+/// a light for [`bake_lightmap`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BakeLight {
+    /// `direction` is the way the light travels
+    Directional { direction: Vec3, color: Vec3 },
+    /// falls off to nothing at `range`
+    Point {
+        position: Vec3,
+        color: Vec3,
+        range: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightmapSettings {
+    /// width and height of the lightmap
+    pub size: u32,
+    /// uniform sky radiance, black for interiors lit only by their lights
+    pub sky: Vec3,
+    pub sky_samples: u32,
+    /// rays that get this far see the sky
+    pub sky_distance: f32,
+    /// rays start this far off the surface, against self shadowing
+    pub bias: f32,
+    /// texels to grow the baked islands by, so filtering doesn't pick up the background
+    pub dilate: u32,
+}
+
+/// irradiance per texel of the second UV set, direct light and sky light, no bounces
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lightmap {
+    pub size: u32,
+    /// rows top to bottom, `None` where no triangle covers the texel
+    pub texels: Vec<Option<Vec3>>,
+}
+
+/// the lightmap material path: unlit albedo times the baked irradiance
+pub struct LightmapMaterial {
+    layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+    sampler: Sampler,
+}
+
+/// per draw uniform of [`LightmapMaterial`]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct MaterialUniform {
+    pub mvp: Mat4,
+    pub albedo: Vec4,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct LightmapVertex {
+    pub position: Vec3,
+    pub lightmap_uv: Vec2,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Params {
+    size: [u32; 2],
+    light_count: u32,
+    sky_samples: u32,
+    sky: Vec4,
+    bias: f32,
+    _pad: [u32; 3],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct GpuLight {
+    vector: Vec4,
+    color: Vec4,
+}
+
+//
+
+/// lightmaps are RGBM in `Rgba8Unorm`, irradiance up to this much survives
+pub const RGBM_RANGE: f32 = 8.0;
+
+impl Default for LightmapSettings {
+    fn default() -> Self {
+        Self {
+            size: 512,
+            sky: Vec3::splat(0.3),
+            sky_samples: 64,
+            sky_distance: 100.0,
+            bias: 1e-3,
+            dilate: 4,
+        }
+    }
+}
+
+/// bake `lights` and the sky into a lightmap for static geometry,
+/// `scene.uvs` is the second UV set, which must not overlap
+///
+/// the scene is also the occluder, every ray tests every triangle,
+/// so it's for tools and asset processing; blocks until the GPU is done
+pub fn bake_lightmap(
+    device: &Device,
+    queue: &Queue,
+    scene: &BakeMesh,
+    lights: &[BakeLight],
+    settings: &LightmapSettings,
+) -> Result<Lightmap> {
+    let bake = TexelBake::new(device, scene, settings.size)?;
+
+    let mut gpu_lights: Vec<GpuLight> = lights.iter().map(GpuLight::from).collect();
+    // storage buffers can't be empty
+    if gpu_lights.is_empty() {
+        gpu_lights.push(GpuLight::zeroed());
+    }
+    let gpu_lights = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("lightmap lights"),
+        contents: bytemuck::cast_slice(&gpu_lights),
+        usage: BufferUsages::STORAGE,
+    });
+    let params = Params {
+        size: [settings.size; 2],
+        light_count: lights.len() as u32,
+        sky_samples: settings.sky_samples,
+        sky: settings.sky.extend(settings.sky_distance),
+        bias: settings.bias,
+        _pad: [0; 3],
+    };
+
+    let mut texels = bake.run(
+        queue,
+        "lightmap",
+        include_str!("./lightmap.wgsl"),
+        bytemuck::bytes_of(&params),
+        &[gpu_lights.as_entire_binding()],
+        scene,
+    )?;
+    dilate(&mut texels, settings.size, settings.dilate, false);
+
+    Ok(Lightmap {
+        size: settings.size,
+        texels: texels
+            .into_iter()
+            .map(|texel| texel.map(Vec4::truncate))
+            .collect(),
+    })
+}
+
+impl Lightmap {
+    pub fn get(&self, x: u32, y: u32) -> Option<Vec3> {
+        self.texels[(y * self.size + x) as usize]
+    }
+
+    /// RGBM bytes, the background is black
+    pub fn rgbm(&self) -> Vec<u8> {
+        self.texels
+            .iter()
+            .flat_map(|texel| encode_rgbm(texel.unwrap_or(Vec3::ZERO)))
+            .collect()
+    }
+
+    /// an `Rgba8Unorm` texture for [`LightmapMaterial`]
+    pub fn upload(&self, device: &Device, queue: &Queue) -> Texture {
+        device.create_texture_with_data(
+            queue,
+            &TextureDescriptor {
+                label: Some("lightmap"),
+                size: Extent3d {
+                    width: self.size,
+                    height: self.size,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8Unorm,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            &self.rgbm(),
+        )
+    }
+}
+
+/// the multiplier rounded up so the color never clips
+pub fn encode_rgbm(color: Vec3) -> [u8; 4] {
+    let m = (color.max_element() / RGBM_RANGE).clamp(1.0 / 255.0, 1.0);
+    let m = (m * 255.0).ceil() / 255.0;
+    let rgb = (color / (m * RGBM_RANGE)).clamp(Vec3::ZERO, Vec3::ONE) * 255.0;
+    [
+        rgb.x.round() as u8,
+        rgb.y.round() as u8,
+        rgb.z.round() as u8,
+        (m * 255.0).round() as u8,
+    ]
+}
+
+pub fn decode_rgbm(rgbm: [u8; 4]) -> Vec3 {
+    let [r, g, b, m] = rgbm.map(|v| v as f32 / 255.0);
+    Vec3::new(r, g, b) * m * RGBM_RANGE
+}
+
+impl LightmapMaterial {
+    pub fn new(
+        device: &Device,
+        format: TextureFormat,
+        depth: Option<DepthMode>,
+        blend: BlendMode,
+    ) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("lightmap material"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./lightmap_material.wgsl"))),
+        });
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("lightmap material"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("lightmap material"),
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("lightmap material"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[LightmapVertex::layout()],
+            },
+            primitive: PrimitiveState {
+                cull_mode: Some(Face::Back),
+                ..<_>::default()
+            },
+            depth_stencil: depth.map(|depth| {
+                DepthStencil::new(depth_stencil_format(device.features()), Some(depth)).to_state()
+            }),
+            multisample: <_>::default(),
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(blend.color_target(format))],
+            }),
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("lightmap"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..<_>::default()
+        });
+
+        Self {
+            layout,
+            pipeline,
+            sampler,
+        }
+    }
+
+    /// `uniform` holds a [`MaterialUniform`]
+    pub fn bind_group(
+        &self,
+        device: &Device,
+        uniform: &Buffer,
+        lightmap: &TextureView,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("lightmap material"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(lightmap),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    pub fn pipeline(&self) -> &RenderPipeline {
+        &self.pipeline
+    }
+}
+
+impl LightmapVertex {
+    pub const ATTRIBUTES: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+
+    pub fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as _,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+impl From<&BakeLight> for GpuLight {
+    fn from(light: &BakeLight) -> Self {
+        match *light {
+            BakeLight::Directional { direction, color } => Self {
+                vector: direction.extend(0.0),
+                color: color.extend(0.0),
+            },
+            BakeLight::Point {
+                position,
+                color,
+                range,
+            } => Self {
+                vector: position.extend(1.0),
+                color: color.extend(range),
+            },
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{
+        capture::CapturedImage,
+        test_util::{plane, test_device, TestMesh},
+    };
+
+    const SETTINGS: LightmapSettings = LightmapSettings {
+        size: 16,
+        sky: Vec3::ZERO,
+        sky_samples: 0,
+        sky_distance: 10.0,
+        bias: 1e-3,
+        dilate: 0,
+    };
+
+    const SUN: BakeLight = BakeLight::Directional {
+        direction: Vec3::NEG_Z,
+        color: Vec3::ONE,
+    };
+
+    /// the ground in the left half of the lightmap, a roof over it in the right half
+    fn covered_ground() -> TestMesh {
+        let mut ground = plane(2, 0.0, 0.0, Vec3::Z);
+        let mut roof = plane(1, 0.5, 2.0, Vec3::NEG_Z);
+        for uv in &mut ground.uvs {
+            uv.x *= 0.5;
+        }
+        for uv in &mut roof.uvs {
+            uv.x = 0.5 + uv.x * 0.5;
+        }
+        // facing down
+        for t in roof.indices.chunks_exact_mut(3) {
+            t.swap(1, 2);
+        }
+        ground.merge(&roof)
+    }
+
+    fn left_half(lightmap: &Lightmap) -> impl Iterator<Item = Vec3> + '_ {
+        (0..lightmap.size)
+            .flat_map(move |y| (0..lightmap.size / 2).map(move |x| lightmap.get(x, y).unwrap()))
+    }
+
+    #[test]
+    fn direct_light_and_shadow() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let open = plane(2, 0.0, 0.0, Vec3::Z);
+        let lit = bake_lightmap(&device, &queue, &open.mesh(), &[SUN], &SETTINGS).unwrap();
+        for texel in &lit.texels {
+            assert!(texel.unwrap().abs_diff_eq(Vec3::ONE, 1e-4), "{texel:?}");
+        }
+
+        let covered = covered_ground();
+        let shadowed = bake_lightmap(&device, &queue, &covered.mesh(), &[SUN], &SETTINGS).unwrap();
+        for texel in left_half(&shadowed) {
+            assert_eq!(texel, Vec3::ZERO);
+        }
+    }
+
+    #[test]
+    fn point_light_falls_off() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let ground = plane(2, 0.0, 0.0, Vec3::Z);
+        let light = BakeLight::Point {
+            position: Vec3::new(0.5, 0.5, 0.5),
+            color: Vec3::ONE,
+            range: 2.0,
+        };
+        let lightmap = bake_lightmap(&device, &queue, &ground.mesh(), &[light], &SETTINGS).unwrap();
+
+        // straight below, a quarter of the range away
+        let center = lightmap.get(8, 8).unwrap().x;
+        let expected = (1.0 - 0.5f32.hypot(1.0 / 32.0f32.hypot(1.0 / 32.0)) / 2.0).powi(2);
+        assert!((center - expected).abs() < 0.02, "{center} {expected}");
+        assert!(lightmap.get(0, 0).unwrap().x < center);
+    }
+
+    #[test]
+    fn sky_light_is_occluded() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let settings = LightmapSettings {
+            sky: Vec3::ONE,
+            sky_samples: 64,
+            ..SETTINGS
+        };
+        let open = plane(2, 0.0, 0.0, Vec3::Z);
+        let lightmap = bake_lightmap(&device, &queue, &open.mesh(), &[], &settings).unwrap();
+        for texel in &lightmap.texels {
+            assert!(texel.unwrap().abs_diff_eq(Vec3::ONE, 1e-4), "{texel:?}");
+        }
+
+        let covered = covered_ground();
+        let lightmap = bake_lightmap(&device, &queue, &covered.mesh(), &[], &settings).unwrap();
+        for texel in left_half(&lightmap) {
+            assert!(texel.x < 0.2, "{texel}");
+        }
+    }
+
+    #[test]
+    fn rgbm_round_trips() {
+        for color in [
+            Vec3::ZERO,
+            Vec3::new(0.5, 0.25, 0.125),
+            Vec3::ONE,
+            Vec3::new(7.0, 1.0, 0.0),
+        ] {
+            let decoded = decode_rgbm(encode_rgbm(color));
+            assert!(
+                decoded.abs_diff_eq(color, color.max_element() / 64.0 + 1e-3),
+                "{color} {decoded}"
+            );
+        }
+    }
+
+    #[test]
+    fn rgbm_keeps_the_rounded_up_multiplier() {
+        for k in 1..=255u8 {
+            // the brightest channel needs exactly the multiplier k / 255
+            let color = Vec3::new(k as f32 / 255.0 * RGBM_RANGE, 0.0, 0.0);
+            let rgbm = encode_rgbm(color);
+            assert_eq!(rgbm[3], k);
+            assert_eq!(rgbm[0], 255, "k {k}");
+            assert!((decode_rgbm(rgbm).x - color.x).abs() < 1e-5, "k {k}");
+        }
+    }
+
+    #[test]
+    fn material_multiplies_albedo() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+
+        let lightmap = Lightmap {
+            size: 4,
+            texels: vec![Some(Vec3::splat(0.5)); 16],
+        }
+        .upload(&device, &queue);
+        let format = TextureFormat::Rgba8Unorm;
+        let material = LightmapMaterial::new(&device, format, None, BlendMode::Opaque);
+        let uniform = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&MaterialUniform {
+                mvp: Mat4::IDENTITY,
+                albedo: Vec4::new(1.0, 0.5, 0.25, 1.0),
+            }),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group =
+            material.bind_group(&device, &uniform, &lightmap.create_view(&<_>::default()));
+        // a full screen quad
+        let vertices = [
+            (Vec2::new(-1.0, -1.0), Vec2::new(0.0, 1.0)),
+            (Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0)),
+            (Vec2::new(1.0, 1.0), Vec2::new(1.0, 0.0)),
+            (Vec2::new(-1.0, 1.0), Vec2::new(0.0, 0.0)),
+        ]
+        .map(|(p, uv)| LightmapVertex {
+            position: p.extend(0.5),
+            lightmap_uv: uv,
+        });
+        let vertices = [0, 1, 2, 0, 2, 3].map(|i| vertices[i]);
+        let vbo = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let target = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 8,
+                height: 8,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        {
+            let view = target.create_view(&<_>::default());
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(material.pipeline());
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_vertex_buffer(0, vbo.slice(..));
+            pass.draw(0..6, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+
+        let image = CapturedImage::read(&device, &queue, &target).unwrap();
+        let pixel = &image.rgba[(4 * 8 + 4) * 4..][..4];
+        for (got, expected) in pixel.iter().zip([128, 64, 32, 255]) {
+            assert!(got.abs_diff(expected) <= 2, "{pixel:?}");
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// lightmap baking: direct light with shadow rays plus sky light
+// from cosine weighted hemisphere rays, per covered texel of the second UV set
+
+struct Params {
+    size: vec2<u32>,
+    light_count: u32,
+    sky_samples: u32,
+    // rgb, a is how far a ray has to get to count as seeing the sky
+    sky: vec4<f32>,
+    // start rays this far off the surface
+    bias: f32,
+};
+
+struct Light {
+    // w = 0: direction the light travels in, w = 1: position
+    vector: vec4<f32>,
+    // rgb, a is the range of point lights
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var positions: texture_2d<f32>;
+@group(0) @binding(2) var normals: texture_2d<f32>;
+// irradiance in rgb, w < 0 where nothing was baked
+@group(0) @binding(3) var<storage, read_write> output: array<vec4<f32>>;
+@group(0) @binding(4) var<storage, read> lights: array<Light>;
+
+@compute @workgroup_size(8, 8)
+fn bake(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size.x || id.y >= params.size.y {
+        return;
+    }
+    let texel = id.y * params.size.x + id.x;
+    let position = textureLoad(positions, vec2<i32>(id.xy), 0);
+    if position.w == 0.0 {
+        output[texel] = vec4<f32>(0.0, 0.0, 0.0, -1.0);
+        return;
+    }
+    let normal = normalize(textureLoad(normals, vec2<i32>(id.xy), 0).xyz);
+    let origin = position.xyz + normal * params.bias;
+
+    var irradiance = vec3<f32>(0.0);
+    for (var i = 0u; i < params.light_count; i++) {
+        let light = lights[i];
+        var to_light: vec3<f32>;
+        var distance = NO_HIT;
+        var attenuation = 1.0;
+        if light.vector.w == 0.0 {
+            to_light = -normalize(light.vector.xyz);
+        } else {
+            let offset = light.vector.xyz - position.xyz;
+            distance = length(offset);
+            to_light = offset / distance;
+            let falloff = saturate(1.0 - distance / light.color.a);
+            attenuation = falloff * falloff;
+        }
+
+        let n_dot_l = dot(normal, to_light);
+        if n_dot_l > 0.0 && attenuation > 0.0 && !occluded(origin, to_light, distance) {
+            irradiance += light.color.rgb * n_dot_l * attenuation;
+        }
+    }
+
+    // a uniform sky, cosine weighted samples make the visible fraction the irradiance
+    var open = 0u;
+    for (var s = 0u; s < params.sky_samples; s++) {
+        if !occluded(origin, cosine_hemisphere(normal, s, params.sky_samples), params.sky.a) {
+            open += 1u;
+        }
+    }
+    irradiance += params.sky.rgb * f32(open) / f32(max(params.sky_samples, 1u));
+
+    output[texel] = vec4<f32>(irradiance, 1.0);
+}
@@ -0,0 +1,38 @@
+// static geometry lit by its baked lightmap
+
+struct Material {
+    mvp: mat4x4<f32>,
+    albedo: vec4<f32>,
+};
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) lightmap_uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) lightmap_uv: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> material: Material;
+@group(0) @binding(1) var lightmap: texture_2d<f32>;
+@group(0) @binding(2) var lightmap_sampler: sampler;
+
+// matches `RGBM_RANGE`
+const RGBM_RANGE: f32 = 8.0;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip = material.mvp * vec4<f32>(in.position, 1.0);
+    out.lightmap_uv = in.lightmap_uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let rgbm = textureSample(lightmap, lightmap_sampler, in.lightmap_uv);
+    let irradiance = rgbm.rgb * rgbm.a * RGBM_RANGE;
+    return vec4<f32>(material.albedo.rgb * irradiance, material.albedo.a);
+}
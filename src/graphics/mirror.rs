@@ -0,0 +1,136 @@
+use wgpu::{CommandEncoder, SurfaceTexture};
+use winit::window::WindowId;
+
+use super::{
+    post::{PostOutput, PostProcess},
+    surface::{Surface, ViewFlavor},
+};
+use crate::color::ColorSpace;
+
+//
+
+/// a second window that shows every frame of the main one,
+/// a projector or a small preview for presentations and installations
+///
+/// the final pass runs once more into its surface, letterboxed to keep the aspect ratio;
+/// the surface doesn't wait for vsync, so a slower monitor doesn't hold back the main one
+pub struct Mirror {
+    surface: Surface,
+    /// latest size from `Resized`
+    pending_resize: Option<(u32, u32)>,
+}
+
+/// an acquired mirror frame, presented after the main frame is submitted
+pub struct MirrorFrame {
+    texture: SurfaceTexture,
+}
+
+//
+
+impl Mirror {
+    /// `None` if the surface format doesn't match the main one
+    pub fn new(surface: Surface, main_format: wgpu::TextureFormat) -> Option<Self> {
+        let format = surface.view_format(ViewFlavor::Linear);
+        if format != main_format {
+            tracing::warn!(
+                "mirror surface format {format:?} differs from the main one {main_format:?}, no mirror"
+            );
+            return None;
+        }
+        Some(Self {
+            surface,
+            pending_resize: None,
+        })
+    }
+
+    /// the final pass uses its own uniforms for it, [`PostOutput::Mirror`]
+    pub fn color_space(&self) -> ColorSpace {
+        self.surface.color_space()
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.surface.window.id()
+    }
+
+    pub fn resized(&mut self, size: (u32, u32)) {
+        self.pending_resize = Some(size);
+    }
+
+    /// blit the scene target of `post` into the mirror, `content` is the size of the
+    /// main output the image should look like
+    ///
+    /// skipped while the mirror has no frame available
+    pub fn blit(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        post: &PostProcess,
+        content: (u32, u32),
+    ) -> Option<MirrorFrame> {
+        if let Some(size) = self.pending_resize.take() {
+            // minimized windows can't have a swapchain
+            if size.0 != 0 && size.1 != 0 && size != self.surface.size() {
+                self.surface.configure(Some(size));
+            }
+        }
+        if self.surface.stale() {
+            self.surface.configure(None);
+        }
+
+        let texture = match self.surface.try_acquire() {
+            Ok(texture) => texture?,
+            Err(err) => {
+                tracing::warn!("Failed to acquire a mirror frame: {err}");
+                return None;
+            }
+        };
+        let view = self.surface.view(&texture, ViewFlavor::Linear);
+        post.blit_to(
+            encoder,
+            &view,
+            Some(fit_viewport(content, self.surface.size())),
+            PostOutput::Mirror,
+        );
+        Some(MirrorFrame { texture })
+    }
+}
+
+impl MirrorFrame {
+    pub fn present(self) {
+        self.texture.present();
+    }
+}
+
+/// the largest `content` aspect rect centered in `output`
+pub fn fit_viewport(content: (u32, u32), output: (u32, u32)) -> [f32; 4] {
+    let (cw, ch) = (content.0.max(1) as f32, content.1.max(1) as f32);
+    let (ow, oh) = (output.0 as f32, output.1 as f32);
+    let fit = (ow / cw).min(oh / ch);
+    let (w, h) = ((cw * fit).round(), (ch * fit).round());
+    [((ow - w) * 0.5).floor(), ((oh - h) * 0.5).floor(), w, h]
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterboxes() {
+        // a 16:9 frame on a 4:3 projector
+        assert_eq!(
+            fit_viewport((1920, 1080), (1024, 768)),
+            [0.0, 96.0, 1024.0, 576.0]
+        );
+        // a downscaled preview with the same aspect
+        assert_eq!(
+            fit_viewport((1280, 720), (640, 360)),
+            [0.0, 0.0, 640.0, 360.0]
+        );
+        // pillarboxed
+        assert_eq!(
+            fit_viewport((100, 100), (300, 200)),
+            [50.0, 0.0, 200.0, 200.0]
+        );
+    }
+}
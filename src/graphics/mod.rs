@@ -1,44 +1,211 @@
-use std::{borrow::Cow, mem::size_of, sync::Arc, thread, time::Instant};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    future::Future,
+    mem::size_of,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::{anyhow, Result};
-use glam::{Mat2, Mat4, Vec2, Vec4};
+use anyhow::{anyhow, bail, Result};
+use glam::{DAffine3, Mat2, Mat4, Vec2, Vec3, Vec4};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
     *,
 };
-use winit::window::Window;
+use winit::{
+    dpi::PhysicalSize,
+    window::{Window, WindowId},
+};
 
-use crate::{settings::GlobalSettings, RuntimeSettings};
+use crate::{
+    build_info::BUILD,
+    camera::{Camera2d, DepthMode, LogDepth},
+    color::{ColorSpace, HdrLevels, Palette},
+    idle::IdleEffect,
+    latency::LatencyTester,
+    platform::{power::ThermalState, safe_area::SafeArea},
+    rng::RngService,
+    settings::{GlobalSettings, PacingSettings, SettingsInner},
+    sim::SimState,
+    RuntimeSettings,
+};
 
-use self::surface::{Surface, SurfaceBuilder};
+use self::{
+    blend::{BlendMode, BlendPermutations},
+    budget::{FrameBudget, SystemBudget},
+    capture::{CaptureDesc, CaptureWriter, CapturedImage, FrameCapture, PassDesc},
+    compute::ComputeStream,
+    crash::CrashLog,
+    cursor::SoftwareCursor,
+    debug_layers::DebugLayers,
+    dynamic_resolution::DynamicResolution,
+    gamma_audit::{GammaAudit, GammaReport},
+    globals::{PresentGlobals, PushConstant, SimGlobals},
+    gpu_timer::GpuTimer,
+    inspector::{PixelInspector, PixelSample},
+    mirror::Mirror,
+    objects::{ObjectId, ObjectTransforms},
+    pacing::FramePacing,
+    poll::PollThread,
+    post::{integer_viewport, Dither, PostOutput, PostProcess, PostUniforms, SceneTarget},
+    resize::ResizeDebounce,
+    share::{FrameShare, FrameSink},
+    stencil::{stencil_clear_ops, DepthStencil},
+    support::Support,
+    surface::{Surface, SurfaceBuilder, ViewFlavor},
+    workgroup::{WorkgroupSizes, WorkgroupTiming},
+};
 
 use bytemuck::{Pod, Zeroable};
 
 //
 
+pub mod bake;
+pub mod blend;
+pub mod budget;
+pub mod capture;
+pub mod clip;
+pub mod compaction;
+pub mod compute;
+pub mod crash;
+pub mod cursor;
+pub mod debug_draw;
+pub mod debug_layers;
+pub mod dynamic_resolution;
+pub mod gamma_audit;
+pub mod globals;
+pub mod gpu_timer;
+pub mod inspector;
+pub mod lightmap;
+pub mod mirror;
+pub mod objects;
+pub mod pacing;
+pub mod pixel_layout;
+pub mod poll;
+pub mod post;
+pub mod prefix_sum;
+pub mod procedural;
+pub mod radix_sort;
+pub mod readback;
+pub mod resize;
+pub mod shader_debug;
+pub mod share;
+pub mod skinning;
+pub mod stencil;
+pub mod support;
 pub mod surface;
+#[cfg(test)]
+mod test_util;
+pub mod workgroup;
 
 //
 
 pub struct Graphics {
+    pub camera: Camera2d,
+    /// logarithmic depth for the current scene, starts with `graphics.log_depth`
+    pub log_depth: Option<LogDepth>,
+    /// `window.cursor.software`, drawn over the final image
+    pub cursor: Option<SoftwareCursor>,
+    /// the latency tester's white square in the top left corner
+    pub latency_flash: bool,
+    /// anti burn-in dimming or orbiting, applied in the final pass
+    pub idle: IdleEffect,
+    /// the levels of the main window's display, with an extended color space
+    pub hdr: HdrLevels,
+    /// the edges of the main window hidden behind a notch or cutout,
+    /// overlays like the latency tester flash stay inside
+    pub safe_area: SafeArea,
+    /// the window pixel to read back with the pixel inspector, in physical pixels
+    pub inspect: Option<Vec2>,
+    /// created the first time a pixel is inspected
+    inspector: Option<PixelInspector>,
+
+    /// first, so it's stopped before anything else is dropped
+    poll: Option<PollThread>,
     device: Arc<Device>,
     queue: Queue,
-    surface: Surface,
+    /// `None` when headless
+    surface: Option<Surface>,
+    /// the [`ViewFlavor::Linear`] format of the surface, or of the offscreen
+    /// targets when headless, the scene and the final pass render in it
+    format: TextureFormat,
+    /// the [`ViewFlavor::Encoded`] one, for UI passes like the software cursor
+    ui_format: TextureFormat,
+    /// `window.mirror`
+    mirror: Option<Mirror>,
+    share: FrameShare,
 
-    boot: Instant,
+    state: SimState,
     value: f32,
+    frame_index: u64,
+    /// latest size from `Resized` and when it arrived
+    resize: ResizeDebounce,
+    /// fixed scene resolution in pixel art mode
+    pixel_art: Option<(u32, u32)>,
+    /// the scene renders palette indices
+    indexed: bool,
+    dither: Dither,
+    depth: DepthMode,
+    render_scale: f32,
+    /// the power saving cap over `render_scale`
+    max_render_scale: Option<f32>,
+    thermal: ThermalState,
+    pacing: FramePacing,
+    pacing_settings: PacingSettings,
+    /// submitted frames the GPU may still be working on
+    in_flight: VecDeque<SubmissionIndex>,
+    /// 0 for no limit
+    max_frames_in_flight: usize,
+    /// acquire in [`Self::begin_frame`], before the input is handled
+    low_latency: bool,
+    /// the swapchain image of the next frame, acquired early
+    acquired: Option<SurfaceTexture>,
+    dynamic_resolution: Option<DynamicResolution>,
+    /// only with dynamic resolution, the frame budget or while profiling
+    gpu_timer: Option<GpuTimer>,
+    /// [`Self::set_profiling`]
+    profiling: bool,
+    /// the latest measurement of `gpu_timer`
+    gpu_time: Option<Duration>,
+    budget: Option<FrameBudget>,
+    capture: Option<FrameCapture>,
+    crash: CrashLog,
+    pub compute: ComputeStream,
+    /// for compute pipelines made with this device
+    pub workgroups: WorkgroupSizes,
 
+    gpu: Adapter,
+    info: AdapterInfo,
     #[allow(unused)]
     limits: Limits,
+    #[allow(unused)]
+    rng: RngService,
 
+    objects: ObjectTransforms,
+    triangle: ObjectId,
     vbo: Buffer,
-    pipeline: RenderPipeline,
+    pipelines: BlendPermutations,
+    /// the preset the scene draws with
+    blend: BlendMode,
+    push: PushBinding,
+    post: PostProcess,
 }
 
-#[derive(Clone, Copy, Pod, Zeroable)]
-#[repr(C)]
-struct PushConstant {
-    mvp: Mat4,
+/// how [`PushConstant`] reaches the scene shader
+enum PushBinding {
+    Constants,
+    /// downlevel devices without push constants
+    Uniform {
+        buffer: Buffer,
+        bind_group: BindGroup,
+    },
+}
+
+/// the views of the texture a frame is rendered into, one per [`ViewFlavor`]
+struct OutputViews {
+    linear: TextureView,
+    encoded: TextureView,
 }
 
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -52,29 +219,88 @@ struct Vertex {
 //
 
 impl Graphics {
-    pub async fn init(settings: &GlobalSettings, window: Arc<Window>) -> Result<Self> {
+    pub async fn init(
+        settings: &GlobalSettings,
+        rng: &RngService,
+        window: Arc<Window>,
+        mirror: Option<Arc<Window>>,
+    ) -> Result<Self> {
+        Self::start(settings, rng, window, mirror)?.await
+    }
+
+    /// creates the surfaces right away, on the calling thread like some platforms need,
+    /// the returned future picks the GPU, presents a cleared frame and compiles the
+    /// pipelines, and can be spawned while the rest of the app starts up
+    pub fn start(
+        settings: &GlobalSettings,
+        rng: &RngService,
+        window: Arc<Window>,
+        mirror: Option<Arc<Window>>,
+    ) -> Result<impl Future<Output = Result<Self>>> {
+        Self::prepare(settings, rng, Some(window), mirror)
+    }
+
+    /// without a window, for tests and tools that only render offscreen
+    /// with [`Self::render_offscreen`], [`Self::frame`] does nothing
+    pub async fn headless(settings: &GlobalSettings, rng: &RngService) -> Result<Self> {
+        Self::prepare(settings, rng, None, None)?.await
+    }
+
+    fn prepare(
+        settings: &GlobalSettings,
+        rng: &RngService,
+        window: Option<Arc<Window>>,
+        mirror: Option<Arc<Window>>,
+    ) -> Result<impl Future<Output = Result<Self>>> {
         let s = &settings.graphics;
 
+        let backends = s.allowed_backends.to_backends();
+        DebugLayers::new(&s.debug).configure(backends);
         let instance = Arc::new(wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: s.allowed_backends.to_backends(),
-            ..<_>::default()
+            backends,
+            dx12_shader_compiler: s.dx12.to_dx12_compiler(),
         }));
 
-        #[cfg(not(target_family = "wasm"))]
-        {
-            let inst = instance.clone();
-            thread::spawn(move || {
-                inst.poll_all(true);
-            });
-        }
+        let refresh = window
+            .as_ref()
+            .and_then(|window| window.current_monitor())
+            .and_then(|monitor| monitor.refresh_rate_millihertz());
+        let surface_builder = window
+            .map(|window| SurfaceBuilder::new(instance.clone(), backends, window))
+            .transpose()?;
+        let mirror_builder = mirror
+            .map(|window| SurfaceBuilder::new(instance.clone(), backends, window))
+            .transpose()?;
+
+        let (settings, rng) = (settings.clone(), *rng);
+        Ok(async move {
+            Self::create(
+                &settings,
+                &rng,
+                &instance,
+                refresh,
+                surface_builder,
+                mirror_builder,
+            )
+            .await
+        })
+    }
 
-        let surface_builder = SurfaceBuilder::new(instance.clone(), window)?;
+    async fn create(
+        settings: &GlobalSettings,
+        rng: &RngService,
+        instance: &wgpu::Instance,
+        refresh: Option<u32>,
+        surface_builder: Option<SurfaceBuilder>,
+        mirror_builder: Option<SurfaceBuilder>,
+    ) -> Result<Self> {
+        let s = &settings.graphics;
 
         let gpu = instance
             .request_adapter(&RequestAdapterOptions {
                 power_preference: s.gpu_preference.to_power_preference(),
                 force_fallback_adapter: s.force_software_rendering,
-                compatible_surface: Some(&surface_builder.surface),
+                compatible_surface: surface_builder.as_ref().map(|builder| &builder.surface),
             })
             .await
             .ok_or_else(|| anyhow!("Could not find a suitable GPU"))?;
@@ -87,6 +313,15 @@ impl Graphics {
         }; */
         let features = gpu.features();
         let limits = gpu.limits();
+        let mut support = Support::new(features, &limits, &gpu.get_downlevel_capabilities())?;
+        // wgpu-hal 0.17 emulates push constants on GL with plain uniforms,
+        // and panics on the `u32` members
+        if gpu.get_info().backend == wgpu::Backend::Gl {
+            support.push_constants = false;
+        }
+        for fallback in support.fallbacks() {
+            tracing::info!("downlevel GPU: {fallback}");
+        }
 
         let (device, queue) = gpu
             .request_device(
@@ -99,73 +334,330 @@ impl Graphics {
             )
             .await?;
         let device = Arc::new(device);
+        #[cfg(not(target_family = "wasm"))]
+        let poll = Some(PollThread::spawn(device.clone())?);
+        #[cfg(target_family = "wasm")]
+        let poll = None;
+        let info = gpu.get_info();
+        if info.backend == wgpu::Backend::Dx12 {
+            s.dx12.log_compiler();
+        }
+        let workgroups = WorkgroupSizes::for_adapter(&info, &limits);
+        tracing::debug!("compute workgroups: {workgroups}");
+        let low_latency = s.advanced.low_latency
+            && matches!(info.backend, wgpu::Backend::Vulkan | wgpu::Backend::Metal);
+        if s.advanced.low_latency && !low_latency {
+            tracing::warn!("low latency presenting needs Vulkan or Metal");
+        }
 
-        let surface = surface_builder.build(s, &gpu, device.clone());
+        let crash = CrashLog::default();
+        crash.install(&device, &info);
 
-        let module = device.create_shader_module(ShaderModuleDescriptor {
-            label: None,
-            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./shader.wgsl"))),
+        let mut surface = surface_builder
+            .map(|builder| builder.build(s, &gpu, device.clone(), settings.window.color_space));
+        // the offscreen targets can be read back as they are
+        let view_format = |flavor| {
+            surface
+                .as_ref()
+                .map_or(TextureFormat::Rgba8UnormSrgb, |surface| {
+                    surface.view_format(flavor)
+                })
+        };
+        let (format, ui_format) = (
+            view_format(ViewFlavor::Linear),
+            view_format(ViewFlavor::Encoded),
+        );
+        let mirror_color_space = settings.window.mirror.color_space;
+        let mirror = mirror_builder.and_then(|builder| {
+            let mut settings = s.clone();
+            settings.vsync = false;
+            settings.advanced.present_mode = None;
+            let surface = builder.build(&settings, &gpu, device.clone(), mirror_color_space);
+            Mirror::new(surface, format)
         });
 
-        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[],
-            push_constant_ranges: &[PushConstantRange {
-                stages: ShaderStages::VERTEX,
-                range: 0..size_of::<PushConstant>() as u32,
-            }],
+        // get something on the screen as soon as possible,
+        // while the pipelines compile on another thread
+        let (indexed, depth) = (s.indexed.enabled, s.depth);
+        let filter = if s.pixel_art.enabled {
+            FilterMode::Nearest
+        } else {
+            FilterMode::Linear
+        };
+        let pipeline = tokio::task::spawn_blocking({
+            let device = device.clone();
+            move || {
+                let post = PostProcess::new(&device, format, indexed, filter);
+                let pipeline =
+                    Self::create_pipeline(&device, post.scene_format(), indexed, depth, support);
+                (pipeline, post)
+            }
         });
 
-        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&layout),
-            vertex: VertexState {
-                module: &module,
-                entry_point: "vs_main",
-                buffers: &[VertexBufferLayout {
-                    array_stride: size_of::<Vertex>() as _,
-                    step_mode: VertexStepMode::Vertex,
-                    attributes: &[
-                        VertexAttribute {
-                            format: VertexFormat::Float32x4,
-                            offset: 0,
-                            shader_location: 0,
-                        },
-                        VertexAttribute {
-                            format: VertexFormat::Float32x2,
-                            offset: size_of::<Vec4>() as _,
-                            shader_location: 1,
-                        },
-                    ],
-                }],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleStrip,
-                strip_index_format: None,
-                front_face: FrontFace::Ccw,
-                cull_mode: None,
-                unclipped_depth: false,
-                polygon_mode: PolygonMode::Fill,
-                conservative: false,
+        if let Some(surface) = surface.as_mut() {
+            Self::present_clear(&device, &queue, surface)?;
+        }
+
+        let vbo = Self::create_vbo(&device);
+        let mut objects = ObjectTransforms::new(&device, 64);
+        let triangle = objects.insert(DAffine3::IDENTITY);
+        let ((pipelines, push), post) = pipeline.await?;
+
+        for (kind, label) in [
+            ("render pipeline", "scene"),
+            ("vertex buffer", "triangle"),
+            ("render pipeline", "post"),
+            ("bind group", "post"),
+            ("texture", "scene"),
+            ("texture", "scene depth"),
+            ("texture", "palette"),
+        ] {
+            crash.resource(kind, label);
+        }
+
+        let pacing = FramePacing::new(refresh, surface.as_ref().is_some_and(Surface::vsync));
+        let pacing_period = pacing.period();
+        let profiling = settings.features.profiler;
+        let gpu_timer = (s.dynamic_resolution.enabled || s.budget.enabled || profiling)
+            .then(|| GpuTimer::new(&device, &queue, 2));
+        let budget = s.budget.enabled.then(|| {
+            let ms = |ms: f32| Duration::from_secs_f32(ms.max(0.0) / 1000.0);
+            let target = if s.budget.target_ms > 0.0 {
+                ms(s.budget.target_ms)
+            } else {
+                pacing_period.unwrap_or(Duration::from_micros(16_667))
+            };
+            // in the order of the GPU timer spans
+            FrameBudget::new(
+                target,
+                vec![
+                    SystemBudget {
+                        name: "scene",
+                        budget: ms(s.budget.scene_ms),
+                        priority: u8::MAX,
+                        levels: 0,
+                    },
+                    SystemBudget {
+                        name: "post",
+                        budget: ms(s.budget.post_ms),
+                        priority: 0,
+                        levels: 1,
+                    },
+                ],
+            )
+        });
+
+        let cursor = (settings.window.cursor.software && surface.is_some())
+            .then(|| SoftwareCursor::new(&device, &queue, ui_format, settings.window.cursor.scale));
+
+        Ok(Self {
+            camera: Camera2d::default(),
+            log_depth: s.log_depth,
+            cursor,
+            latency_flash: false,
+            idle: IdleEffect::NONE,
+            hdr: HdrLevels::default(),
+            safe_area: SafeArea::NONE,
+            inspect: None,
+            inspector: None,
+
+            poll,
+            device,
+            queue,
+            surface,
+            format,
+            ui_format,
+            mirror,
+            share: FrameShare::default(),
+
+            state: SimState::default(),
+            value: 0.0,
+            frame_index: 0,
+            resize: ResizeDebounce::new(Duration::from_millis(s.resize_debounce_ms as _)),
+            pixel_art: s
+                .pixel_art
+                .enabled
+                .then_some(s.pixel_art.resolution)
+                .filter(|&(w, h)| w != 0 && h != 0)
+                .filter(|&(w, h)| {
+                    // + the border
+                    let fits = w.max(h) + 2 <= support.max_texture_size;
+                    if !fits {
+                        tracing::warn!("pixel art resolution {w}x{h} is too large for the GPU");
+                    }
+                    fits
+                }),
+            indexed,
+            dither: s.dither,
+            depth: s.depth,
+            render_scale: if s.dynamic_resolution.enabled {
+                s.dynamic_resolution.max_scale
+            } else {
+                s.render_scale
+            }
+            .clamp(0.25, 2.0),
+            max_render_scale: None,
+            thermal: ThermalState::Nominal,
+            pacing,
+            pacing_settings: s.pacing,
+            in_flight: VecDeque::new(),
+            max_frames_in_flight: if low_latency {
+                1
+            } else {
+                s.advanced.max_frames_in_flight as usize
             },
-            depth_stencil: None,
-            multisample: <_>::default(),
-            fragment: Some(FragmentState {
-                module: &module,
-                entry_point: "fs_main",
-                targets: &[Some(ColorTargetState {
-                    format: surface.format(),
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
+            low_latency,
+            acquired: None,
+            dynamic_resolution: s
+                .dynamic_resolution
+                .enabled
+                .then(|| DynamicResolution::new(s.dynamic_resolution, pacing_period)),
+            gpu_timer,
+            profiling,
+            gpu_time: None,
+            budget,
+            capture: None,
+            crash,
+            compute: ComputeStream::new(&info, support.compute),
+            workgroups,
+
+            gpu,
+            info,
+            limits,
+            rng: *rng,
+
+            objects,
+            triangle,
+            vbo,
+            pipelines,
+            blend: s.blend,
+            push,
+            post,
+        })
+    }
+
+    fn create_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        indexed: bool,
+        depth: DepthMode,
+        support: Support,
+    ) -> (BlendPermutations, PushBinding) {
+        const SHADER: &str = include_str!("./shader.wgsl");
+        let source = if support.push_constants {
+            Cow::Borrowed(SHADER)
+        } else {
+            Cow::Owned(SHADER.replace(
+                "var<push_constant> push: Push;",
+                "@group(0) @binding(0) var<uniform> push: Push;",
+            ))
+        };
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("scene"),
+            source: ShaderSource::Wgsl(source),
+        });
+
+        let (layout, push) = if support.push_constants {
+            let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("scene"),
+                bind_group_layouts: &[],
+                push_constant_ranges: &[PushConstantRange {
+                    stages: ShaderStages::VERTEX_FRAGMENT,
+                    range: 0..size_of::<PushConstant>() as u32,
+                }],
+            });
+            (layout, PushBinding::Constants)
+        } else {
+            let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("scene"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("scene push constants"),
+                size: size_of::<PushConstant>() as _,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("scene"),
+                layout: &bind_group_layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("scene"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            (layout, PushBinding::Uniform { buffer, bind_group })
+        };
+
+        // indices can't be blended
+        let pipelines = BlendPermutations::new(!indexed, |blend| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("scene"),
+                layout: Some(&layout),
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: "vs_main",
+                    buffers: &[VertexBufferLayout {
+                        array_stride: size_of::<Vertex>() as _,
+                        step_mode: VertexStepMode::Vertex,
+                        attributes: &[
+                            VertexAttribute {
+                                format: VertexFormat::Float32x4,
+                                offset: 0,
+                                shader_location: 0,
+                            },
+                            VertexAttribute {
+                                format: VertexFormat::Float32x2,
+                                offset: size_of::<Vec4>() as _,
+                                shader_location: 1,
+                            },
+                        ],
+                    }],
+                },
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleStrip,
+                    strip_index_format: None,
+                    front_face: FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(
+                    DepthStencil::new(support.depth_format(), Some(depth)).to_state(),
+                ),
+                multisample: <_>::default(),
+                fragment: Some(FragmentState {
+                    module: &module,
+                    entry_point: if indexed { "fs_index" } else { "fs_main" },
+                    targets: &[Some(blend.color_target(format))],
+                }),
+                multiview: None,
+            })
         });
 
+        (pipelines, push)
+    }
+
+    fn create_vbo(device: &Device) -> Buffer {
         const SCALE: f32 = 0.8;
         let rot_mat = Mat2::from_angle(2.0 * std::f32::consts::FRAC_PI_3);
-        let vbo = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("triangle"),
             contents: bytemuck::cast_slice(&[
                 Vertex {
                     col: Vec4::new(1.0, 0.0, 0.0, 1.0),
@@ -184,89 +676,814 @@ impl Graphics {
                 },
             ]),
             usage: BufferUsages::VERTEX,
-        });
+        })
+    }
 
-        Ok(Self {
-            device,
-            queue,
-            surface,
+    /// clear the window and show it
+    fn present_clear(device: &Device, queue: &Queue, surface: &mut Surface) -> Result<()> {
+        let texture = surface.acquire()?;
+        let view = texture
+            .texture
+            .create_view(&TextureViewDescriptor { ..<_>::default() });
 
-            boot: Instant::now(),
-            value: 0.0,
+        let mut encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor { ..<_>::default() });
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            ..<_>::default()
+        });
+        queue.submit([encoder.finish()]);
 
-            limits,
+        texture.present();
+        surface.window.set_visible(true);
 
-            vbo,
-            pipeline,
-        })
+        Ok(())
     }
 
     pub fn scrolled(&mut self, delta: (f32, f32)) {
-        self.value += delta.0 + delta.1;
+        self.value = (self.value + delta.0 + delta.1).clamp(0.0, 10.0);
         tracing::debug!("value: {}", self.value);
     }
 
+    /// the swapchain is reconfigured at most once per frame,
+    /// and only after the size has settled for `resize_debounce_ms`
+    ///
+    /// meanwhile frames are still rendered at the previous size, Windows and macOS
+    /// scale them to the window, X11 and Wayland show them as they are in a corner
     pub fn resized(&mut self, size: (u32, u32)) {
-        self.surface.configure(Some(size));
+        self.resize.resized(size, Instant::now());
     }
 
-    pub fn frame(&mut self, _settings: &RuntimeSettings) {
-        let texture = self
+    /// resize and render synchronously, from inside of the resize event
+    ///
+    /// Windows and macOS block the event loop during live resizes,
+    /// so this is the only way for the contents to track the window edges
+    pub fn resized_redraw(
+        &mut self,
+        size: (u32, u32),
+        settings: &RuntimeSettings,
+        state: &SimState,
+    ) {
+        self.resized(size);
+        // there is nothing to render to
+        let minimized = self
             .surface
-            .acquire()
-            .expect("Failed to acquire the next frame");
+            .as_ref()
+            .and_then(|surface| surface.window.is_minimized())
+            .unwrap_or(false);
+        if !resize::can_render(size) || minimized {
+            return;
+        }
+        self.apply_resize(true);
+        self.frame(settings, state);
+    }
 
-        let texture_view = texture
-            .texture
-            .create_view(&TextureViewDescriptor { ..<_>::default() });
+    /// render to `window` from now on, like after it was recreated to change
+    /// its transparency, the device, pipelines and scene resources stay
+    pub fn replace_window(&mut self, window: Arc<Window>) -> Result<()> {
+        let Some(surface) = self.surface.as_mut() else {
+            bail!("headless graphics can't render to a window");
+        };
+        self.acquired = None;
+        surface.replace_window(&self.gpu, window)?;
+        self.resize.clear();
+        self.pacing.reset_interval();
+        Ok(())
+    }
+
+    /// a debounced resize is waiting for the next frame
+    pub fn resize_pending(&self) -> bool {
+        self.resize.is_pending()
+    }
+
+    fn apply_resize(&mut self, force: bool) {
+        let Some(surface) = self.surface.as_mut() else {
+            self.resize.clear();
+            return;
+        };
+        let size = self
+            .resize
+            .take(Instant::now(), force, surface.size(), surface.stale());
+        if let Some(size) = size {
+            // can't be presented after the swapchain is rebuilt
+            self.acquired = None;
+            surface.configure(Some(size));
+            self.pacing.reset_interval();
+        }
+    }
+
+    /// a suboptimal or outdated swapchain is debounced like a resize,
+    /// instead of being reconfigured on every frame the platform reports it
+    fn resize_if_stale(&mut self) {
+        let Some(surface) = self.surface.as_ref() else {
+            return;
+        };
+        if surface.stale() {
+            let PhysicalSize { width, height } = surface.window.inner_size();
+            self.resize.stale((width, height), Instant::now());
+        }
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.surface.as_ref().is_some_and(Surface::vsync)
+    }
+
+    /// switch vsync at runtime, frame pacing follows
+    pub fn set_vsync(&mut self, vsync: bool) {
+        let Some(surface) = self.surface.as_mut() else {
+            return;
+        };
+        if vsync == surface.vsync() {
+            return;
+        }
+        self.acquired = None;
+        surface.set_vsync(vsync);
+        let refresh = surface
+            .window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz());
+        self.pacing = FramePacing::new(refresh, vsync);
+        tracing::info!("vsync: {vsync}");
+    }
+
+    /// switch the blend preset of the scene, every preset is already compiled
+    pub fn set_blend(&mut self, blend: BlendMode) {
+        self.blend = blend;
+    }
+
+    /// limit the render scale, dynamic resolution and missed vsync reductions
+    /// keep working below it
+    pub fn set_max_render_scale(&mut self, max: Option<f32>) {
+        self.max_render_scale = max.map(|max| max.clamp(0.25, 2.0));
+    }
+
+    /// throttle for a hot device, the render scale drops right away,
+    /// dynamic resolution also relaxes its budget to the throttled frame rate
+    pub fn set_thermal(&mut self, thermal: ThermalState) {
+        if thermal != self.thermal {
+            tracing::info!("thermal state: {thermal:?}");
+        }
+        self.thermal = thermal;
+        if let Some(dynamic) = self.dynamic_resolution.as_mut() {
+            dynamic.set_thermal(thermal);
+        }
+    }
+
+    /// the window of the [`Mirror`], its events go to [`Self::mirror_resized`]
+    /// and [`Self::close_mirror`]
+    pub fn mirror_window_id(&self) -> Option<WindowId> {
+        self.mirror.as_ref().map(Mirror::window_id)
+    }
+
+    pub fn mirror_resized(&mut self, size: (u32, u32)) {
+        if let Some(mirror) = self.mirror.as_mut() {
+            mirror.resized(size);
+        }
+    }
+
+    /// publish every final frame to `sink` from now on
+    pub fn add_frame_sink(&mut self, sink: Box<dyn FrameSink>) {
+        self.share.add(sink);
+    }
+
+    /// drop the mirror surface and its window
+    pub fn close_mirror(&mut self) {
+        self.mirror = None;
+    }
+
+    /// run the [`GammaAudit`] chart through the final pass into the surface format
+    pub fn gamma_audit(&mut self) -> Result<(GammaReport, CapturedImage)> {
+        GammaAudit::new(&self.device, self.post.scene_format())?.run(
+            &self.device,
+            &self.queue,
+            &mut self.post,
+            self.format,
+        )
+    }
+
+    /// `--bench-workgroups`
+    pub fn bench_workgroups(&self) -> Vec<WorkgroupTiming> {
+        workgroup::benchmark(&self.device, &self.queue)
+    }
+
+    /// dump frame number `frame`, counted from 0, into a zip file when it's presented
+    pub fn capture_at(&mut self, frame: u64, settings: &SettingsInner) {
+        self.capture = Some(FrameCapture {
+            frame,
+            settings: settings.clone(),
+        });
+    }
+
+    /// the low latency path: call before the input of the next frame is handled
+    ///
+    /// the GPU was already waited for after the previous submit,
+    /// this waits for the swapchain, so the input and the camera
+    /// are read after both and right before the frame is recorded
+    pub fn begin_frame(&mut self) {
+        if !self.low_latency || self.acquired.is_some() || self.resize.is_pending() {
+            return;
+        }
+        let Some(surface) = self.surface.as_mut() else {
+            return;
+        };
+        match surface.try_acquire() {
+            Ok(texture) => self.acquired = texture,
+            Err(err) => tracing::warn!("Failed to acquire the next frame early: {err}"),
+        }
+        self.resize_if_stale();
+    }
+
+    /// attach or detach the GPU side of the profiler, detaching frees the
+    /// timer's queries and buffers unless dynamic resolution or the frame budget use it
+    pub fn set_profiling(&mut self, profiling: bool) {
+        self.profiling = profiling;
+        let needed = profiling || self.dynamic_resolution.is_some() || self.budget.is_some();
+        if !needed {
+            self.gpu_timer = None;
+            self.gpu_time = None;
+        } else if self.gpu_timer.is_none() {
+            self.gpu_timer = Some(GpuTimer::new(&self.device, &self.queue, 2));
+        }
+    }
+
+    /// the latest GPU time of a frame, while profiling
+    pub fn gpu_time(&self) -> Option<Duration> {
+        self.gpu_time.filter(|_| self.profiling)
+    }
+
+    /// the part of a `size` output that isn't hidden, offset and size,
+    /// to lay out anything drawn over the image
+    pub fn safe_rect(&self, size: (u32, u32)) -> [f32; 4] {
+        self.safe_area.rect(size)
+    }
+
+    /// the newest pixel read back for [`Self::inspect`], a frame or two late
+    pub fn inspected(&mut self) -> Option<PixelSample> {
+        self.inspector.as_mut()?.poll()
+    }
+
+    /// the monitor refresh period with vsync, if the OS says
+    pub fn refresh_period(&self) -> Option<Duration> {
+        self.pacing.period()
+    }
+
+    pub fn frame(&mut self, settings: &RuntimeSettings, state: &SimState) {
+        self.state = *state;
+        self.apply_resize(false);
+
+        let Some(surface) = self.surface.as_mut() else {
+            return;
+        };
+        let window = surface.window.clone();
+        let texture = match self.acquired.take() {
+            Some(texture) => Some(texture),
+            None => surface
+                .try_acquire()
+                .expect("Failed to acquire the next frame"),
+        };
+        self.resize_if_stale();
+        // outdated, skipped until the debounced reconfigure
+        let Some(texture) = texture else {
+            return;
+        };
+        let Some(surface) = self.surface.as_mut() else {
+            return;
+        };
+
+        let views = OutputViews {
+            linear: surface.view(&texture, ViewFlavor::Linear),
+            encoded: surface.view(&texture, ViewFlavor::Encoded),
+        };
 
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor { ..<_>::default() });
+        self.crash.begin_frame(self.frame_index);
+
+        let size = window.inner_size();
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.begin(&mut encoder);
+        }
+        self.render(&mut encoder, &views, (size.width, size.height), settings);
+        let content = self.pixel_art.unwrap_or((size.width, size.height));
+        let mirror_frame = self
+            .mirror
+            .as_mut()
+            .and_then(|mirror| mirror.blit(&mut encoder, &self.post, content));
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.end(&mut encoder);
+        }
+        let frame_index = self.frame_index;
+        let capturing = self
+            .capture
+            .as_ref()
+            .is_some_and(|capture| capture.frame == frame_index);
+        let copyable = self.surface.as_ref().is_some_and(Surface::can_copy);
+        let presented =
+            (capturing && copyable).then(|| self.copy_presented(&mut encoder, &texture.texture));
+
+        // independent compute work first, the render commands can depend on it
+        self.crash.marker("submit compute");
+        self.compute.submit(&self.queue);
+        self.crash.marker("submit render");
+        let submission = self.queue.submit([encoder.finish()]);
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.submitted(&self.queue);
+        }
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.submitted();
+        }
+        self.objects.end_frame();
+        self.share.publish(&self.device, &self.queue);
+        // the submit and the readbacks mapped after it
+        self.wake_poll();
+
+        self.crash.marker("present");
+        texture.present();
+        if let Some(mirror_frame) = mirror_frame {
+            mirror_frame.present();
+        }
+        window.set_visible(true);
+        self.limit_in_flight(submission);
+        self.paced(Instant::now());
+
+        if let Some(capture) = self.capture.take_if(|capture| capture.frame == frame_index) {
+            let path = capture.path();
+            match self.capture(&capture, settings, (size.width, size.height), presented) {
+                Ok(()) => tracing::info!("frame {} captured to {}", capture.frame, path.display()),
+                Err(err) => tracing::error!("Failed to capture frame {}: {err}", capture.frame),
+            }
+        }
+
+        if self.frame_index % 600 == 599 && self.pacing.period().is_some() {
+            let stats = self.pacing.stats();
+            tracing::debug!(
+                "frame pacing: {} of {} frames missed vsync, {:?} average, {:?} worst",
+                stats.missed,
+                stats.frames,
+                stats.average(),
+                stats.worst
+            );
+        }
+        if self.frame_index % 600 == 599 && self.compute.stats().submissions != 0 {
+            let stats = self.compute.stats();
+            tracing::debug!(
+                "compute: {} submissions, {:?} average (async: {})",
+                stats.submissions,
+                stats.average(),
+                self.compute.is_async()
+            );
+        }
+
+        self.frame_index += 1;
+    }
+
+    /// block until at most `max_frames_in_flight` frames are queued on the GPU
+    fn limit_in_flight(&mut self, submission: SubmissionIndex) {
+        if self.max_frames_in_flight == 0 {
+            return;
+        }
+        self.in_flight.push_back(submission);
+        while self.in_flight.len() > self.max_frames_in_flight - 1 {
+            let oldest = self.in_flight.pop_front().unwrap();
+            self.device.poll(Maintain::WaitForSubmissionIndex(oldest));
+        }
+    }
+
+    /// count missed vsyncs and adjust the render scale if it's allowed to
+    fn paced(&mut self, now: Instant) {
+        self.pacing.presented(now);
+
+        let gpu_time = self.gpu_timer.as_mut().and_then(GpuTimer::poll);
+        if gpu_time.is_some() {
+            self.gpu_time = gpu_time;
+        }
+        if let (Some(budget), Some(frame), Some(timer)) =
+            (self.budget.as_mut(), gpu_time, self.gpu_timer.as_ref())
+        {
+            if let Some(shedding) = budget.update(frame, timer.spans()) {
+                tracing::info!("{shedding}");
+            }
+        }
+
+        if let Some(dynamic) = self.dynamic_resolution.as_mut() {
+            if let Some(scale) = gpu_time.and_then(|t| dynamic.update(t, self.render_scale)) {
+                tracing::debug!(
+                    "GPU time {gpu_time:?} (budget {:?}), render scale {:.2} -> {scale:.2}",
+                    dynamic.budget(),
+                    self.render_scale
+                );
+                self.render_scale = scale;
+            }
+            return;
+        }
+
+        let Some(missed) = self.pacing.missed_per_second(now) else {
+            return;
+        };
+
+        let pacing = self.pacing_settings;
+        if !pacing.reduce_render_scale || missed <= pacing.max_missed_per_second {
+            return;
+        }
+        let scale = (self.render_scale - 0.1).max(pacing.min_render_scale);
+        if scale < self.render_scale {
+            tracing::info!(
+                "{missed} frames missed vsync in a second, render scale {:.2} -> {scale:.2}",
+                self.render_scale
+            );
+            self.render_scale = scale;
+        }
+    }
+
+    fn clear_color(&self, settings: &RuntimeSettings) -> Color {
+        if settings.high_contrast {
+            // see-through windows make everything harder to read
+            return Color::BLACK;
+        }
 
         // let a = 1.0 / (1.0 + (-0.5 + self.value as f64).exp());
-        self.value = self.value.max(0.0).min(10.0);
         let a = self.value as f64 / 10.0;
+        Color {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a,
+        }
+    }
+
+    /// draw the scene into the scene target and post-process it into `views`
+    fn render(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        views: &OutputViews,
+        size: (u32, u32),
+        settings: &RuntimeSettings,
+    ) {
+        let (scene_size, camera, mut uv_rect, viewport) = match self.pixel_art {
+            Some((w, h)) => {
+                // a one pixel border to shift into for sub-pixel camera movement
+                let scene_size = (w + 2, h + 2);
+                let (camera, subpixel) = self.camera.snapped(scene_size.1 as f32);
+                let (sw, sh) = (scene_size.0 as f32, scene_size.1 as f32);
+                let uv_rect = Vec4::new(
+                    (1.0 + subpixel.x) / sw,
+                    (1.0 + subpixel.y) / sh,
+                    w as f32 / sw,
+                    h as f32 / sh,
+                );
+                (
+                    scene_size,
+                    camera,
+                    uv_rect,
+                    Some(integer_viewport((w, h), size)),
+                )
+            }
+            None => {
+                let scale = self
+                    .max_render_scale
+                    .map_or(self.render_scale, |max| self.render_scale.min(max))
+                    .min(self.thermal.max_render_scale());
+                let scaled = |v: u32| ((v as f32 * scale).round() as u32).max(1);
+                let scene_size = (scaled(size.0), scaled(size.1));
+                (
+                    scene_size,
+                    self.camera,
+                    PostUniforms::default().uv_rect,
+                    None,
+                )
+            }
+        };
+
+        // once per frame, shared by the object transforms and the push constants
+        let sim = SimGlobals::new(&self.state);
+        self.objects.set(self.triangle, sim.model);
+        self.objects
+            .upload(&self.device, &self.queue, camera.position.extend(0.0));
+
+        if self.indexed {
+            self.post
+                .set_palette(&self.queue, &Palette::BUILTIN[settings.palette]);
+        }
+
+        let dither = match self.budget.as_ref().map_or(0, |b| b.level("post")) {
+            0 => self.dither,
+            _ => Dither::Off,
+        };
+        // the idle orbit moves the image by whole output pixels
+        let shown = viewport.map_or(Vec2::new(size.0 as f32, size.1 as f32), |[.., w, h]| {
+            Vec2::new(w, h)
+        });
+        let shift = self.idle.offset / shown.max(Vec2::ONE) * Vec2::new(uv_rect.z, uv_rect.w);
+        uv_rect -= shift.extend(0.0).extend(0.0);
+        let brightness = Vec3::splat(self.idle.brightness).extend(1.0);
+
+        let flash = if self.latency_flash {
+            // the top left corner of the image, or of the safe area if that's further in
+            let [x, y, ..] = viewport.unwrap_or_default();
+            let [sx, sy, ..] = self.safe_area.rect(size);
+            let (x, y) = (x.max(sx), y.max(sy));
+            let side = LatencyTester::FLASH_SIZE as f32;
+            Vec4::new(x, y, side, side)
+        } else {
+            Vec4::ZERO
+        };
+        // every window converts to its own color space,
+        // the mirror's display isn't known so it gets no peak
+        let uniforms = |color_space: ColorSpace, hdr: HdrLevels| PostUniforms {
+            color_matrix: Mat4::from_diagonal(if color_space.is_extended() {
+                brightness * Vec3::splat(hdr.scale()).extend(1.0)
+            } else {
+                brightness
+            }) * Mat4::from_mat3(
+                color_space.gamut() * settings.color_vision.matrix(),
+            ),
+            uv_rect,
+            dither: if color_space.is_extended() {
+                Dither::Off
+            } else {
+                dither
+            } as u32,
+            srgb_output: self.format.is_srgb() as u32,
+            extended_output: color_space.is_extended() as u32,
+            hdr_peak: hdr.peak(),
+            flash,
+        };
+        let color_space = self
+            .surface
+            .as_ref()
+            .map_or(ColorSpace::Srgb, Surface::color_space);
+        self.post.prepare(
+            &self.device,
+            &self.queue,
+            scene_size,
+            &uniforms(color_space, self.hdr),
+        );
+        if let Some(mirror) = self.mirror.as_ref() {
+            self.post.set_uniforms(
+                &self.queue,
+                PostOutput::Mirror,
+                &uniforms(
+                    mirror.color_space(),
+                    HdrLevels {
+                        max: None,
+                        ..self.hdr
+                    },
+                ),
+            );
+        }
+
+        let scene = self.post.scene().unwrap();
+        self.crash.pass(encoder, "main");
+        self.draw(encoder, scene, scene_size, &sim, &camera, settings);
+        encoder.pop_debug_group();
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.split(encoder);
+        }
+
+        // the window pixel through the viewport and `uv_rect` into the scene target
+        let inspected = self.inspect.and_then(|at| {
+            let [x, y, w, h] = viewport.unwrap_or([0.0, 0.0, size.0 as f32, size.1 as f32]);
+            let uv = (at - Vec2::new(x, y)) / Vec2::new(w, h);
+            let uv = Vec2::new(uv_rect.x, uv_rect.y) + uv * Vec2::new(uv_rect.z, uv_rect.w);
+            let pixel = uv * Vec2::new(scene_size.0 as f32, scene_size.1 as f32);
+            let inside = pixel.cmpge(Vec2::ZERO).all()
+                && pixel.x < scene_size.0 as f32
+                && pixel.y < scene_size.1 as f32;
+            inside.then(|| pixel.as_uvec2())
+        });
+        if let Some(pixel) = inspected {
+            self.crash.pass(encoder, "pixel inspector");
+            self.inspector
+                .get_or_insert_with(|| PixelInspector::new(&self.device))
+                .probe(
+                    &self.device,
+                    &self.queue,
+                    encoder,
+                    scene,
+                    pixel,
+                    &uniforms(color_space, self.hdr),
+                );
+            encoder.pop_debug_group();
+        }
+
+        self.crash.pass(encoder, "post");
+        self.post.blit(encoder, &views.linear, viewport);
+        self.share.blit(
+            &self.device,
+            encoder,
+            &self.post,
+            self.format,
+            size,
+            viewport,
+        );
+        // last, over everything else
+        if let Some(cursor) = self.cursor.as_mut() {
+            cursor.draw(&self.queue, encoder, &views.encoded, size);
+        }
+        encoder.pop_debug_group();
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut CommandEncoder,
+        target: &SceneTarget,
+        size: (u32, u32),
+        sim: &SimGlobals,
+        camera: &Camera2d,
+        settings: &RuntimeSettings,
+    ) {
         let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
             color_attachments: &[Some(RenderPassColorAttachment {
-                view: &texture_view,
+                view: &target.view,
                 resolve_target: None,
                 /* ops: Operations {
                     load: LoadOp::Load, // no clear
                     store: true,
                 }, */
                 ops: Operations {
-                    load: LoadOp::Clear(Color {
-                        r: 0.0,
-                        g: 0.0,
-                        b: 0.0,
-                        a,
-                    }),
+                    load: LoadOp::Clear(self.clear_color(settings)),
                     store: true,
                 },
             })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &target.depth,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(self.depth.clear()),
+                    store: true,
+                }),
+                stencil_ops: Some(stencil_clear_ops()),
+            }),
             ..<_>::default()
         });
 
-        pass.set_pipeline(&self.pipeline);
+        pass.set_pipeline(self.pipelines.get(self.blend));
 
-        let size = self.surface.window.inner_size().cast::<f32>();
-        let aspect = size.width / size.height;
-        let push = PushConstant {
-            mvp: Mat4::orthographic_rh(-aspect, aspect, 1.0, -1.0, -1.0, 1.0)
-                * Mat4::from_rotation_z(self.boot.elapsed().as_secs_f32()),
+        let aspect = size.0 as f32 / size.1 as f32;
+        // the camera is latched here, as late as the frame allows
+        let present = PresentGlobals {
+            camera: camera.position.extend(0.0),
+            view_proj: camera.view_proj(aspect, self.depth),
+            palette_len: Palette::BUILTIN[settings.palette].colors.len().max(1) as u32,
+            log_depth_coef: self.log_depth.map_or(0.0, |log| log.coef()),
+            reversed_z: self.depth == DepthMode::Reversed,
+            high_contrast: settings.high_contrast,
         };
+        let push = PushConstant::latch(sim, &present);
 
-        pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::cast_slice(&[push]));
+        match &self.push {
+            PushBinding::Constants => pass.set_push_constants(
+                ShaderStages::VERTEX_FRAGMENT,
+                0,
+                bytemuck::cast_slice(&[push]),
+            ),
+            PushBinding::Uniform { buffer, bind_group } => {
+                // one draw per frame, written before the submit
+                self.queue
+                    .write_buffer(buffer, 0, bytemuck::cast_slice(&[push]));
+                pass.set_bind_group(0, bind_group, &[]);
+            }
+        }
         pass.set_vertex_buffer(0, self.vbo.slice(..));
 
         pass.draw(0..3, 0..1);
+    }
+
+    /// render `state` into a new `size` texture and read it back,
+    /// independent of the window and its size
+    pub fn render_offscreen(
+        &mut self,
+        settings: &RuntimeSettings,
+        state: &SimState,
+        size: (u32, u32),
+    ) -> Result<CapturedImage> {
+        self.state = *state;
+        let target = self.offscreen_target(size, settings);
+        CapturedImage::read(&self.device, &self.queue, &target)
+    }
 
-        drop(pass);
+    /// a `size` texture with the current state rendered into it
+    fn offscreen_target(&mut self, size: (u32, u32), settings: &RuntimeSettings) -> Texture {
+        let target = self.device.create_texture(&TextureDescriptor {
+            label: Some("offscreen target"),
+            size: Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[self.ui_format],
+        });
+        let view = |format| {
+            target.create_view(&TextureViewDescriptor {
+                format: Some(format),
+                ..<_>::default()
+            })
+        };
+        let views = OutputViews {
+            linear: view(self.format),
+            encoded: view(self.ui_format),
+        };
 
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { ..<_>::default() });
+        self.render(&mut encoder, &views, size, settings);
         self.queue.submit([encoder.finish()]);
+        self.wake_poll();
 
-        texture.present();
-        self.surface.window.set_visible(true);
+        target
+    }
+
+    fn wake_poll(&self) {
+        if let Some(poll) = self.poll.as_ref() {
+            poll.wake();
+        }
+    }
+
+    /// a copy of the acquired surface texture, recorded after the last pass
+    fn copy_presented(&self, encoder: &mut CommandEncoder, texture: &Texture) -> Texture {
+        let copy = self.device.create_texture(&TextureDescriptor {
+            label: Some("presented frame"),
+            size: texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: texture.format(),
+            usage: TextureUsages::COPY_DST | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        encoder.copy_texture_to_texture(
+            texture.as_image_copy(),
+            copy.as_image_copy(),
+            texture.size(),
+        );
+        copy
+    }
+
+    /// dump the scene target and the `presented` copy of the surface texture,
+    /// the scene still holds the frame that was just presented
+    ///
+    /// surfaces that can't be copied from are re-rendered into an offscreen target
+    fn capture(
+        &mut self,
+        capture: &FrameCapture,
+        runtime: &RuntimeSettings,
+        size: (u32, u32),
+        presented: Option<Texture>,
+    ) -> Result<()> {
+        let scene = &self.post.scene().unwrap().texture;
+        let scene = CapturedImage::read(&self.device, &self.queue, scene)?;
+        let target = presented.unwrap_or_else(|| {
+            tracing::warn!("the surface can't be copied from, capturing a re-rendered frame");
+            self.offscreen_target(size, runtime)
+        });
+        let image = CapturedImage::read(&self.device, &self.queue, &target)?;
+
+        let Color { r, g, b, a } = self.clear_color(runtime);
+        let desc = CaptureDesc {
+            frame: capture.frame,
+            build: BUILD,
+            adapter: self.info.name.clone(),
+            backend: format!("{:?}", self.info.backend),
+            passes: vec![
+                PassDesc {
+                    name: "main",
+                    target: "main.png".into(),
+                    format: format!("{:?}", scene.format),
+                    size: (scene.width, scene.height),
+                    clear: Some([r, g, b, a]),
+                    draws: 1,
+                },
+                PassDesc {
+                    name: "post",
+                    target: "post.png".into(),
+                    format: format!("{:?}", image.format),
+                    size,
+                    clear: Some([0.0; 4]),
+                    draws: 1,
+                },
+            ],
+            settings: &capture.settings,
+            runtime,
+            sim: &self.state,
+        };
+
+        let mut writer = CaptureWriter::create(&capture.path())?;
+        writer.add_image("main.png", &scene)?;
+        writer.add_image("post.png", &image)?;
+        writer.add_desc(&desc)?;
+        writer.finish()
     }
 }
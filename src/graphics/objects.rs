@@ -0,0 +1,119 @@
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{DAffine3, DMat4, DVec3, Mat4};
+use wgpu::*;
+
+//
+
+/// per object transforms for the GPU, with the previous frame's transform next to
+/// the current one so motion vectors can follow moving objects and not just the camera
+///
+/// the transforms are world space `f64` on the CPU and camera relative `f32`
+/// in the buffer, both matrices relative to the current camera position
+pub struct ObjectTransforms {
+    current: Vec<DAffine3>,
+    previous: Vec<DAffine3>,
+    staging: Vec<ObjectData>,
+    buffer: Buffer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectId(u32);
+
+/// one element of the object buffer
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct ObjectData {
+    pub model: Mat4,
+    pub prev_model: Mat4,
+}
+
+//
+
+impl ObjectTransforms {
+    pub fn new(device: &Device, capacity: usize) -> Self {
+        Self {
+            current: Vec::new(),
+            previous: Vec::new(),
+            staging: Vec::new(),
+            buffer: Self::create_buffer(device, capacity),
+        }
+    }
+
+    /// the object doesn't move in its first frame
+    pub fn insert(&mut self, transform: DAffine3) -> ObjectId {
+        self.current.push(transform);
+        self.previous.push(transform);
+        ObjectId(self.current.len() as u32 - 1)
+    }
+
+    pub fn set(&mut self, id: ObjectId, transform: DAffine3) {
+        self.current[id.0 as usize] = transform;
+    }
+
+    /// move without motion, for respawns and cuts
+    pub fn teleport(&mut self, id: ObjectId, transform: DAffine3) {
+        self.current[id.0 as usize] = transform;
+        self.previous[id.0 as usize] = transform;
+    }
+
+    /// write the transforms relative to `camera`, the buffer grows if needed
+    pub fn upload(&mut self, device: &Device, queue: &Queue, camera: DVec3) {
+        let relative = |transform: &DAffine3| {
+            let mut transform = *transform;
+            transform.translation -= camera;
+            DMat4::from(transform).as_mat4()
+        };
+        self.staging.clear();
+        self.staging.extend(
+            self.current
+                .iter()
+                .zip(&self.previous)
+                .map(|(current, previous)| ObjectData {
+                    model: relative(current),
+                    prev_model: relative(previous),
+                }),
+        );
+
+        let size = (self.staging.len() * size_of::<ObjectData>()) as u64;
+        if size > self.buffer.size() {
+            self.buffer = Self::create_buffer(device, self.staging.len().next_power_of_two());
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.staging));
+    }
+
+    /// the current transforms become the previous ones, call after the frame is submitted
+    ///
+    /// per frame, not per simulation tick: with interpolation between ticks
+    /// the motion between two frames is what ends up on the screen
+    pub fn end_frame(&mut self) {
+        self.previous.copy_from_slice(&self.current);
+    }
+
+    /// array of [`ObjectData`] indexed by [`ObjectId::index`],
+    /// replaced when it grows
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// as of the last [`Self::upload`]
+    pub fn get(&self, id: ObjectId) -> ObjectData {
+        self.staging[id.0 as usize]
+    }
+
+    fn create_buffer(device: &Device, capacity: usize) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: Some("objects"),
+            size: (capacity.max(1) * size_of::<ObjectData>()) as _,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+impl ObjectId {
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
@@ -0,0 +1,212 @@
+use std::time::{Duration, Instant};
+
+//
+
+/// present to present timing, counts missed vsync intervals
+///
+/// with vsync a frame that takes longer than one refresh period is shown
+/// a whole period late, which looks like a stutter even at a high average frame rate
+#[derive(Debug)]
+pub struct FramePacing {
+    /// refresh period of the monitor, `None` without vsync or if the OS doesn't say
+    period: Option<Duration>,
+    last: Option<Instant>,
+    stats: PacingStats,
+    /// misses in the current one second window
+    window: (Instant, u32),
+}
+
+/// caps the frame rate by telling the event loop when the next frame is due
+#[derive(Debug, Clone, Copy)]
+pub struct FrameLimiter {
+    period: Option<Duration>,
+    next: Instant,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PacingStats {
+    pub frames: u64,
+    pub missed: u64,
+    pub total: Duration,
+    pub worst: Duration,
+}
+
+//
+
+impl FramePacing {
+    pub fn new(refresh_millihertz: Option<u32>, vsync: bool) -> Self {
+        let period = refresh_millihertz
+            .filter(|&mhz| vsync && mhz != 0)
+            .map(|mhz| Duration::from_secs_f64(1000.0 / mhz as f64));
+        tracing::debug!("frame pacing: refresh period {period:?}");
+
+        Self {
+            period,
+            last: None,
+            stats: PacingStats::default(),
+            window: (Instant::now(), 0),
+        }
+    }
+
+    pub fn period(&self) -> Option<Duration> {
+        self.period
+    }
+
+    /// call right after presenting, returns if the previous frame missed a vsync
+    pub fn presented(&mut self, now: Instant) -> bool {
+        let Some(last) = self.last.replace(now) else {
+            return false;
+        };
+        let interval = now - last;
+
+        self.stats.frames += 1;
+        self.stats.total += interval;
+        self.stats.worst = self.stats.worst.max(interval);
+
+        // half a period of slack for timer jitter
+        let missed = self
+            .period
+            .is_some_and(|period| interval > period + period / 2);
+        if missed {
+            self.stats.missed += 1;
+            self.window.1 += 1;
+        }
+        missed
+    }
+
+    /// misses in the last full second, once per second
+    pub fn missed_per_second(&mut self, now: Instant) -> Option<u32> {
+        if now - self.window.0 < Duration::from_secs(1) {
+            return None;
+        }
+        let missed = self.window.1;
+        self.window = (now, 0);
+        Some(missed)
+    }
+
+    pub fn stats(&self) -> PacingStats {
+        self.stats
+    }
+
+    /// after a resize or a hitch that isn't the renderer's fault
+    pub fn reset_interval(&mut self) {
+        self.last = None;
+    }
+}
+
+impl FrameLimiter {
+    /// `max_fps` 0 is no limit
+    pub fn new(max_fps: f32) -> Self {
+        Self {
+            period: (max_fps > 0.0).then(|| Duration::from_secs_f32(1.0 / max_fps)),
+            next: Instant::now(),
+        }
+    }
+
+    pub fn max_fps(&self) -> Option<f32> {
+        self.period.map(|period| 1.0 / period.as_secs_f32())
+    }
+
+    /// `Ok` if a frame should be rendered now, otherwise when the next one is due
+    pub fn ready(&mut self, now: Instant) -> Result<(), Instant> {
+        let Some(period) = self.period else {
+            return Ok(());
+        };
+        if now < self.next {
+            return Err(self.next);
+        }
+        // a late frame doesn't make the next ones early
+        self.next = (self.next + period).max(now);
+        Ok(())
+    }
+}
+
+impl PacingStats {
+    pub fn average(&self) -> Duration {
+        self.total
+            .checked_div(self.frames as u32)
+            .unwrap_or_default()
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn counts_intervals_past_one_and_a_half_periods() {
+        // 60 Hz, a 16.67 ms period and 25 ms until a frame counts as missed
+        let mut pacing = FramePacing::new(Some(60_000), true);
+        let start = Instant::now();
+        assert!(!pacing.presented(start), "the first frame has no interval");
+
+        let mut t = start;
+        let missed: Vec<bool> = [16, 33, 24, 26, 17]
+            .into_iter()
+            .map(|ms| {
+                t += ms * MS;
+                pacing.presented(t)
+            })
+            .collect();
+        assert_eq!(missed, [false, true, false, true, false]);
+
+        let stats = pacing.stats();
+        assert_eq!((stats.frames, stats.missed), (5, 2));
+        assert_eq!(stats.worst, 33 * MS);
+        assert_eq!(stats.average(), 116 * MS / 5);
+
+        // a resize doesn't count as a slow frame
+        pacing.reset_interval();
+        assert!(!pacing.presented(t + 200 * MS));
+        assert_eq!(pacing.stats().frames, 5);
+
+        // without vsync nothing is missed
+        let mut free = FramePacing::new(Some(60_000), false);
+        assert_eq!(free.period(), None);
+        free.presented(start);
+        assert!(!free.presented(start + 100 * MS));
+    }
+
+    #[test]
+    fn misses_are_reported_once_per_second() {
+        let mut pacing = FramePacing::new(Some(100_000), true);
+        let start = Instant::now();
+        pacing.presented(start);
+        pacing.presented(start + 30 * MS);
+        pacing.presented(start + 60 * MS);
+
+        assert_eq!(pacing.missed_per_second(start + 500 * MS), None);
+        let second = start + 1000 * MS;
+        assert_eq!(pacing.missed_per_second(second), Some(2));
+        // a new window starts
+        assert_eq!(pacing.missed_per_second(second + 500 * MS), None);
+        assert_eq!(pacing.missed_per_second(second + 1000 * MS), Some(0));
+    }
+
+    #[test]
+    fn limiter_waits_for_the_next_period() {
+        let mut unlimited = FrameLimiter::new(0.0);
+        assert_eq!(unlimited.max_fps(), None);
+        let now = Instant::now();
+        assert!((0..10).all(|_| unlimited.ready(now).is_ok()));
+
+        let mut limiter = FrameLimiter::new(50.0);
+        assert!((limiter.max_fps().unwrap() - 50.0).abs() < 1e-3);
+        let start = Instant::now();
+        assert_eq!(limiter.ready(start), Ok(()));
+        let due = limiter.ready(start + 5 * MS).unwrap_err();
+        assert!(due > start + 5 * MS && due <= start + 20 * MS);
+        assert_eq!(limiter.ready(due), Ok(()));
+        assert_eq!(limiter.ready(due + MS), Err(due + 20 * MS));
+
+        // after a hitch the late frame and one more, not the five that were due
+        let late = due + 100 * MS;
+        let immediate = (0..10).filter(|_| limiter.ready(late).is_ok()).count();
+        assert_eq!(immediate, 2);
+        assert_eq!(limiter.ready(late), Err(late + 20 * MS));
+    }
+}
@@ -0,0 +1,177 @@
+use wgpu::TextureFormat;
+
+//
+
+/// the byte order of the 8 bit color formats that get read back or written out
+///
+/// surfaces are `Bgra8` on most desktop platforms and `Rgba8` elsewhere,
+/// everything on the CPU side (PNG, video frames) works with RGBA,
+/// so every copy between the two goes through this
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelLayout {
+    Rgba8,
+    Bgra8,
+    /// one channel, palette indices or masks, red in RGBA
+    R8,
+}
+
+//
+
+impl PixelLayout {
+    /// `None` for formats without a supported 8 bit layout
+    pub fn of(format: TextureFormat) -> Option<Self> {
+        match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Some(Self::Rgba8),
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => Some(Self::Bgra8),
+            TextureFormat::R8Unorm => Some(Self::R8),
+            _ => None,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> u32 {
+        match self {
+            Self::Rgba8 | Self::Bgra8 => 4,
+            Self::R8 => 1,
+        }
+    }
+
+    /// tightly packed pixels in this layout to RGBA, single channels become opaque gray
+    pub fn to_rgba8(self, mut pixels: Vec<u8>) -> Vec<u8> {
+        match self {
+            Self::Rgba8 => pixels,
+            Self::Bgra8 => {
+                swap_red_blue(&mut pixels);
+                pixels
+            }
+            Self::R8 => pixels.into_iter().flat_map(|v| [v, v, v, 255]).collect(),
+        }
+    }
+
+    /// RGBA pixels to this layout, for uploads, single channels keep red
+    pub fn from_rgba8(self, rgba: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Rgba8 => rgba.to_vec(),
+            Self::Bgra8 => {
+                let mut pixels = rgba.to_vec();
+                swap_red_blue(&mut pixels);
+                pixels
+            }
+            Self::R8 => rgba.chunks_exact(4).map(|px| px[0]).collect(),
+        }
+    }
+}
+
+fn swap_red_blue(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec4;
+    use wgpu::*;
+
+    use super::*;
+    use crate::graphics::{
+        capture::CapturedImage,
+        post::{PostProcess, PostUniforms},
+        test_util::test_device,
+    };
+
+    #[test]
+    fn conversions_roundtrip() {
+        let rgba = [255, 128, 0, 255, 1, 2, 3, 4];
+        assert_eq!(
+            PixelLayout::Bgra8.from_rgba8(&rgba),
+            [0, 128, 255, 255, 3, 2, 1, 4]
+        );
+        for layout in [PixelLayout::Rgba8, PixelLayout::Bgra8] {
+            assert_eq!(layout.to_rgba8(layout.from_rgba8(&rgba)), rgba);
+        }
+        assert_eq!(
+            PixelLayout::R8.to_rgba8(PixelLayout::R8.from_rgba8(&rgba)),
+            [255, 255, 255, 255, 1, 1, 1, 255]
+        );
+        assert_eq!(PixelLayout::of(TextureFormat::Rgba16Float), None);
+    }
+
+    /// the final pass into either order reads back as the same RGBA,
+    /// with and without sRGB formats
+    #[test]
+    fn blit_and_readback_in_both_orders() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let size = (4, 4);
+        // linear, distinct channels so a swap shows
+        let color = Color {
+            r: 1.0,
+            g: 0.2,
+            b: 0.0,
+            a: 1.0,
+        };
+
+        let blit = |format: TextureFormat| {
+            let mut post = PostProcess::new(&device, format, false, FilterMode::Nearest);
+            let uniforms = PostUniforms {
+                srgb_output: format.is_srgb() as u32,
+                uv_rect: Vec4::new(0.0, 0.0, 1.0, 1.0),
+                ..PostUniforms::default()
+            };
+            post.prepare(&device, &queue, size, &uniforms);
+
+            let output = device.create_texture(&TextureDescriptor {
+                label: None,
+                size: Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+            encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &post.scene().unwrap().view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(color),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            post.blit(
+                &mut encoder,
+                &output.create_view(&TextureViewDescriptor::default()),
+                None,
+            );
+            queue.submit([encoder.finish()]);
+
+            CapturedImage::read(&device, &queue, &output).unwrap().rgba
+        };
+
+        for (rgba, bgra) in [
+            (TextureFormat::Rgba8Unorm, TextureFormat::Bgra8Unorm),
+            (TextureFormat::Rgba8UnormSrgb, TextureFormat::Bgra8UnormSrgb),
+        ] {
+            let (rgba, bgra) = (blit(rgba), blit(bgra));
+            assert_eq!(rgba, bgra);
+            let [r, g, b, a] = [rgba[0], rgba[1], rgba[2], rgba[3]];
+            assert!(
+                r == 255 && g > 0 && g < 255 && b == 0 && a == 255,
+                "{rgba:?}"
+            );
+        }
+    }
+}
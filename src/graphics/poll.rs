@@ -0,0 +1,117 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use wgpu::{Device, Maintain};
+
+use crate::threads;
+
+//
+
+/// polls the device on its own thread, so buffer mapping callbacks and
+/// resource cleanup happen without the frame loop
+///
+/// sleeps while the device is idle, [`Self::wake`] it after submitting or mapping
+///
+/// stopped and joined when dropped, before the device can go away
+pub struct PollThread {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+//
+
+impl PollThread {
+    pub fn spawn(device: Arc<Device>) -> io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = threads::spawn("wgpu poll", {
+            let stop = stop.clone();
+            move || {
+                while !stop.load(Ordering::Acquire) {
+                    // waits for the submitted work, returns right away without any
+                    let idle = device.poll(Maintain::Wait);
+                    if idle {
+                        // a wake before this makes it return right away
+                        thread::park();
+                    }
+                }
+            }
+        })?;
+
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// there is new work to wait for
+    pub fn wake(&self) {
+        if let Some(handle) = self.handle.as_ref() {
+            handle.thread().unpark();
+        }
+    }
+}
+
+impl Drop for PollThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            if handle.join().is_err() {
+                tracing::error!("the wgpu poll thread panicked");
+            }
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    use wgpu::*;
+
+    use super::*;
+    use crate::graphics::test_util::test_device;
+
+    #[test]
+    fn maps_without_manual_polls_and_stops() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let device = Arc::new(device);
+        let poll = PollThread::spawn(device.clone()).unwrap();
+
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&buffer, 0, &[1, 2, 3, 4]);
+        queue.submit([]);
+
+        let (tx, rx) = mpsc::channel();
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        poll.wake();
+        rx.recv_timeout(Duration::from_secs(10)).unwrap().unwrap();
+        assert_eq!(&*buffer.slice(..).get_mapped_range(), &[1, 2, 3, 4]);
+
+        let start = Instant::now();
+        drop(poll);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // the thread's clone is gone
+        assert_eq!(Arc::strong_count(&device), 1);
+    }
+}
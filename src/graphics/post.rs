@@ -0,0 +1,433 @@
+use std::{borrow::Cow, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec4};
+use serde::{Deserialize, Serialize};
+use wgpu::*;
+
+use super::stencil::depth_stencil_format;
+use crate::color::Palette;
+
+//
+
+/// the final pass: copies the scene target to the surface,
+/// applying full-screen color effects on the way
+pub struct PostProcess {
+    /// the scene target format
+    format: TextureFormat,
+    depth_format: TextureFormat,
+    layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+    sampler: Sampler,
+    /// one [`PostUniforms`] per [`PostOutput`], `uniform_stride` apart
+    uniforms: Buffer,
+    uniform_stride: u64,
+    /// 256x1 lookup table for indexed color
+    palette: (Texture, TextureView),
+    current_palette: Option<Palette>,
+
+    target: Option<SceneTarget>,
+}
+
+/// what the scene is rendered into before post-processing
+pub struct SceneTarget {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub depth: TextureView,
+    /// the depth aspect alone, for reading depth in shaders
+    pub depth_only: TextureView,
+    bind_group: BindGroup,
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct PostUniforms {
+    /// applied to the linear RGBA color
+    pub color_matrix: Mat4,
+    /// the part of the scene target shown: offset in `xy`, size in `zw`
+    pub uv_rect: Vec4,
+    pub dither: u32,
+    /// the output format does the sRGB encoding
+    pub srgb_output: u32,
+    /// a float output in linear extended sRGB, no clamping or encoding
+    pub extended_output: u32,
+    /// with `extended_output`, the display peak in scRGB that brighter
+    /// colors roll off towards, 0 for none
+    pub hdr_peak: f32,
+    /// drawn solid white, in output pixels: offset in `xy`, size in `zw`,
+    /// for the latency tester
+    pub flash: Vec4,
+}
+
+/// the windows the final pass draws into, each with its own uniforms,
+/// like for a different color space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostOutput {
+    Main,
+    Mirror,
+}
+
+/// noise added in the final pass to hide banding on 8 bit outputs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dither {
+    Off,
+    /// 4x4 Bayer matrix
+    Ordered,
+    /// interleaved gradient noise
+    #[default]
+    Noise,
+}
+
+//
+
+impl PostProcess {
+    /// scene target format in indexed color mode, palette index / 255
+    pub const INDEX_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+    /// `format` is the output format,
+    /// `indexed` makes the scene target hold palette indices instead of colors,
+    /// `filter` is used when the scene is scaled up
+    pub fn new(device: &Device, format: TextureFormat, indexed: bool, filter: FilterMode) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("post"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("./post.wgsl"))),
+        });
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("post"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: BufferSize::new(size_of::<PostUniforms>() as _),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("post"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("post"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: <_>::default(),
+            depth_stencil: None,
+            multisample: <_>::default(),
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: if indexed { "fs_indexed" } else { "fs_main" },
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        // nearest for pixel art scaled up by whole numbers,
+        // linear for a lower render scale
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("post"),
+            mag_filter: filter,
+            min_filter: filter,
+            ..<_>::default()
+        });
+
+        let uniform_stride = (size_of::<PostUniforms>() as u64)
+            .next_multiple_of(device.limits().min_uniform_buffer_offset_alignment as u64);
+        let uniforms = device.create_buffer(&BufferDescriptor {
+            label: Some("post"),
+            size: uniform_stride * 2,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let palette = device.create_texture(&TextureDescriptor {
+            label: Some("palette"),
+            size: Extent3d {
+                width: 256,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_view = palette.create_view(&TextureViewDescriptor::default());
+
+        Self {
+            format: if indexed { Self::INDEX_FORMAT } else { format },
+            depth_format: depth_stencil_format(device.features()),
+            layout,
+            pipeline,
+            sampler,
+            uniforms,
+            uniform_stride,
+            palette: (palette, palette_view),
+            current_palette: None,
+
+            target: None,
+        }
+    }
+
+    /// (re)create the scene target to match `size` and upload the uniforms
+    /// of [`PostOutput::Main`]
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        size: (u32, u32),
+        uniforms: &PostUniforms,
+    ) {
+        let stale = self
+            .target
+            .as_ref()
+            .is_none_or(|target| (target.texture.width(), target.texture.height()) != size);
+
+        if stale {
+            self.target = Some(self.create_target(device, size));
+        }
+
+        self.set_uniforms(queue, PostOutput::Main, uniforms);
+    }
+
+    /// the uniforms of one output, [`Self::prepare`] sets the main ones
+    pub fn set_uniforms(&self, queue: &Queue, output: PostOutput, uniforms: &PostUniforms) {
+        queue.write_buffer(
+            &self.uniforms,
+            output as u64 * self.uniform_stride,
+            bytemuck::bytes_of(uniforms),
+        );
+    }
+
+    /// upload the lookup table for indexed color, if it changed
+    pub fn set_palette(&mut self, queue: &Queue, palette: &Palette) {
+        if self.current_palette.as_ref() == Some(palette) {
+            return;
+        }
+
+        let mut texels = [[0u8, 0, 0, 255]; 256];
+        for (texel, [r, g, b]) in texels.iter_mut().zip(palette.colors) {
+            *texel = [*r, *g, *b, 255];
+        }
+
+        queue.write_texture(
+            self.palette.0.as_image_copy(),
+            bytemuck::cast_slice(&texels),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(256 * 4),
+                rows_per_image: None,
+            },
+            self.palette.0.size(),
+        );
+        self.current_palette = Some(*palette);
+    }
+
+    pub fn scene_format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// the depth and stencil format of the scene target
+    pub fn depth_format(&self) -> TextureFormat {
+        self.depth_format
+    }
+
+    /// the scene target from the last [`Self::prepare`]
+    pub fn scene(&self) -> Option<&SceneTarget> {
+        self.target.as_ref()
+    }
+
+    /// copy the scene target into `output` with the effects applied,
+    /// into the `[x, y, width, height]` pixel rect `viewport` or all of it
+    pub fn blit(
+        &self,
+        encoder: &mut CommandEncoder,
+        output: &TextureView,
+        viewport: Option<[f32; 4]>,
+    ) {
+        self.blit_to(encoder, output, viewport, PostOutput::Main);
+    }
+
+    /// [`Self::blit`] with the uniforms of `target`
+    pub fn blit_to(
+        &self,
+        encoder: &mut CommandEncoder,
+        output: &TextureView,
+        viewport: Option<[f32; 4]>,
+        target: PostOutput,
+    ) {
+        let offset = (target as u64 * self.uniform_stride) as u32;
+        let Some(scene) = self.target.as_ref() else {
+            return;
+        };
+
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("post"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        if let Some([x, y, w, h]) = viewport {
+            pass.set_viewport(x, y, w, h, 0.0, 1.0);
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &scene.bind_group, &[offset]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn create_target(&self, device: &Device, (width, height): (u32, u32)) -> SceneTarget {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("scene"),
+            size: Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.format,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        let depth = device.create_texture(&TextureDescriptor {
+            label: Some("scene depth"),
+            size: texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.depth_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let depth_only = depth.create_view(&TextureViewDescriptor {
+            aspect: TextureAspect::DepthOnly,
+            ..TextureViewDescriptor::default()
+        });
+        let depth = depth.create_view(&TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("post"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &self.uniforms,
+                        offset: 0,
+                        size: BufferSize::new(size_of::<PostUniforms>() as _),
+                    }),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(&self.palette.1),
+                },
+            ],
+        });
+
+        SceneTarget {
+            texture,
+            view,
+            depth,
+            depth_only,
+            bind_group,
+        }
+    }
+}
+
+/// the largest whole number scaled, centered rect of a `scene` sized image in `output`
+///
+/// falls back to shrinking it to fit if `output` is smaller than `scene`
+pub fn integer_viewport(scene: (u32, u32), output: (u32, u32)) -> [f32; 4] {
+    let scale = (output.0 / scene.0.max(1)).min(output.1 / scene.1.max(1));
+    let (w, h) = if scale == 0 {
+        let fit = (output.0 as f32 / scene.0 as f32).min(output.1 as f32 / scene.1 as f32);
+        (scene.0 as f32 * fit, scene.1 as f32 * fit)
+    } else {
+        ((scene.0 * scale) as f32, (scene.1 * scale) as f32)
+    };
+
+    [
+        ((output.0 as f32 - w) * 0.5).floor(),
+        ((output.1 as f32 - h) * 0.5).floor(),
+        w,
+        h,
+    ]
+}
+
+impl Default for PostUniforms {
+    fn default() -> Self {
+        Self {
+            color_matrix: Mat4::IDENTITY,
+            uv_rect: Vec4::new(0.0, 0.0, 1.0, 1.0),
+            dither: Dither::Off as u32,
+            srgb_output: 0,
+            extended_output: 0,
+            hdr_peak: 0.0,
+            flash: Vec4::ZERO,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+struct FragmentInput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+struct Uniforms {
+    color_matrix: mat4x4<f32>,
+    uv_rect: vec4<f32>,
+    // 0: off, 1: ordered, 2: noise
+    dither: u32,
+    // the output format encodes to sRGB itself
+    srgb_output: u32,
+    // a float output in linear extended sRGB
+    extended_output: u32,
+    // the display peak the extended output rolls off towards, 0 for none
+    hdr_peak: f32,
+    // solid white, offset and size in output pixels
+    flash: vec4<f32>,
+};
+
+@group(0) @binding(0) var scene: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+@group(0) @binding(2) var<uniform> uniforms: Uniforms;
+@group(0) @binding(3) var palette: texture_2d<f32>;
+
+// fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> FragmentInput {
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+
+    var fin: FragmentInput;
+    fin.pos = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    fin.uv = uv;
+    return fin;
+}
+
+fn scene_uv(uv: vec2<f32>) -> vec2<f32> {
+    return uniforms.uv_rect.xy + uv * uniforms.uv_rect.zw;
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+// 0..1 threshold for this pixel
+fn dither_threshold(pos: vec2<f32>) -> f32 {
+    if uniforms.dither == 1u {
+        // 4x4 Bayer matrix
+        let p = vec2<u32>(pos) % 4u;
+        var bayer = array<u32, 16>(0u, 8u, 2u, 10u, 12u, 4u, 14u, 6u, 3u, 11u, 1u, 9u, 15u, 7u, 13u, 5u);
+        return (f32(bayer[p.y * 4u + p.x]) + 0.5) / 16.0;
+    }
+    // interleaved gradient noise, blue-noise-like and cheap
+    return fract(52.9829189 * fract(dot(pos, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+// compress the brightest channel above 80% of the display peak towards the
+// peak, keeping the hue, instead of the display clipping each channel
+fn hdr_rolloff(rgb: vec3<f32>) -> vec3<f32> {
+    let peak = uniforms.hdr_peak;
+    let m = max(rgb.r, max(rgb.g, rgb.b));
+    let knee = peak * 0.8;
+    if peak <= 0.0 || m <= knee {
+        return rgb;
+    }
+    let range = peak - knee;
+    let rolled = knee + range * (1.0 - exp(-(m - knee) / range));
+    return rgb * (rolled / m);
+}
+
+// spread the 8 bit quantization error so gradients don't band,
+// and encode to sRGB when the output format doesn't
+fn output(col: vec4<f32>, pos: vec2<f32>, dithered: bool) -> vec4<f32> {
+    let flash = pos - uniforms.flash.xy;
+    if all(flash >= vec2<f32>(0.0)) && all(flash < uniforms.flash.zw) {
+        return vec4<f32>(1.0);
+    }
+    if uniforms.extended_output != 0u {
+        // no 8 bit quantization, and colors past 1 are the point
+        return vec4<f32>(hdr_rolloff(max(col.rgb, vec3<f32>(0.0))), col.a);
+    }
+    var rgb = clamp(col.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    var offset = 0.0;
+    if dithered && uniforms.dither != 0u {
+        offset = (dither_threshold(pos) - 0.5) / 255.0;
+    }
+
+    if uniforms.srgb_output != 0u {
+        // quantization happens after the hardware sRGB encoding
+        if offset != 0.0 {
+            rgb = srgb_to_linear(clamp(linear_to_srgb(rgb) + offset, vec3<f32>(0.0), vec3<f32>(1.0)));
+        }
+    } else {
+        // a non-sRGB output stores what it gets, unencoded colors would come out too dark
+        rgb = linear_to_srgb(rgb) + offset;
+    }
+    return vec4<f32>(rgb, col.a);
+}
+
+@fragment
+fn fs_main(fin: FragmentInput) -> @location(0) vec4<f32> {
+    let col = textureSample(scene, scene_sampler, scene_uv(fin.uv));
+    return output(uniforms.color_matrix * col, fin.pos.xy, true);
+}
+
+// the scene holds palette indices
+@fragment
+fn fs_indexed(fin: FragmentInput) -> @location(0) vec4<f32> {
+    let index = i32(round(textureSample(scene, scene_sampler, scene_uv(fin.uv)).r * 255.0));
+    let col = textureLoad(palette, vec2<i32>(index, 0), 0);
+    // exact palette colors, no dithering
+    return output(uniforms.color_matrix * col, fin.pos.xy, false);
+}
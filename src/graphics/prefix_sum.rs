@@ -0,0 +1,366 @@
+use std::{borrow::Cow, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::*;
+
+//
+
+/// running totals of a `u32` buffer on the GPU, in place,
+/// for compaction, culling and anything else that turns counts into offsets
+///
+/// each workgroup scans a block of 256, the block sums are scanned one level up
+/// and added back down, so any length up to 256 × the workgroup limit works;
+/// the scan within one workgroup is `workgroup_scan.wgsl` for other shaders to prepend
+pub struct PrefixSum {
+    layout: BindGroupLayout,
+    scan_blocks: ComputePipeline,
+    add_sums: ComputePipeline,
+}
+
+/// the block sums for scanning one buffer
+pub struct ScanBuffers {
+    capacity: u32,
+    params: Buffer,
+    /// per level, the data is the caller's buffer and then the previous level's sums
+    levels: Vec<BindGroup>,
+    /// per level, the last one is the total
+    sums: Vec<Buffer>,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Params {
+    count: u32,
+    inclusive: u32,
+    _pad: [u32; 2],
+}
+
+//
+
+impl PrefixSum {
+    const WORKGROUP: u32 = 256;
+    /// the largest `min_uniform_buffer_offset_alignment` allowed
+    const PARAMS_STRIDE: u64 = 256;
+
+    /// the scan within one workgroup, for shaders that need it
+    pub const WORKGROUP_SCAN: &'static str = include_str!("./workgroup_scan.wgsl");
+
+    pub fn new(device: &Device) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("prefix sum"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./workgroup_scan.wgsl"),
+                include_str!("./prefix_sum.wgsl")
+            ))),
+        });
+
+        let storage = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("prefix sum"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: BufferSize::new(size_of::<Params>() as _),
+                    },
+                    count: None,
+                },
+                storage(1),
+                storage(2),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("prefix sum"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("prefix sum"),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point,
+            })
+        };
+
+        Self {
+            scan_blocks: pipeline("scan_blocks"),
+            add_sums: pipeline("add_sums"),
+            layout,
+        }
+    }
+
+    /// block sums for scanning up to `capacity` elements of `data`,
+    /// which needs `STORAGE` usage
+    pub fn buffers(&self, device: &Device, data: &Buffer, capacity: u32) -> ScanBuffers {
+        let capacity = capacity.max(1);
+        assert!(data.size() >= capacity as u64 * 4);
+        assert!(
+            capacity.div_ceil(Self::WORKGROUP)
+                <= device.limits().max_compute_workgroups_per_dimension,
+            "too many elements to scan"
+        );
+
+        // down to a single sum, the total
+        let mut sizes = vec![capacity];
+        while *sizes.last().unwrap() > 1 {
+            sizes.push(sizes.last().unwrap().div_ceil(Self::WORKGROUP));
+        }
+        if sizes.len() == 1 {
+            sizes.push(1);
+        }
+
+        let params = device.create_buffer(&BufferDescriptor {
+            label: Some("prefix sum params"),
+            size: Self::PARAMS_STRIDE * (sizes.len() - 1) as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sums: Vec<Buffer> = sizes[1..]
+            .iter()
+            .map(|&size| {
+                device.create_buffer(&BufferDescriptor {
+                    label: Some("prefix sum block sums"),
+                    size: size as u64 * 4,
+                    usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+
+        let levels = (0..sums.len())
+            .map(|level| {
+                let data = if level == 0 { data } else { &sums[level - 1] };
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("prefix sum"),
+                    layout: &self.layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::Buffer(BufferBinding {
+                                buffer: &params,
+                                offset: 0,
+                                size: BufferSize::new(size_of::<Params>() as _),
+                            }),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: data.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: sums[level].as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        ScanBuffers {
+            capacity,
+            params,
+            levels,
+            sums,
+        }
+    }
+
+    /// scan the first `count` elements in place,
+    /// inclusive sums include the element itself, exclusive ones start at 0
+    ///
+    /// the params are written through the queue,
+    /// so one scan per [`ScanBuffers`] per submission
+    pub fn scan(
+        &self,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        buffers: &ScanBuffers,
+        count: u32,
+        inclusive: bool,
+    ) {
+        assert!(count <= buffers.capacity);
+        if count == 0 {
+            encoder.clear_buffer(buffers.total(), 0, None);
+            return;
+        }
+
+        // every level runs, the ones above a single block just carry the total up
+        let mut counts = Vec::with_capacity(buffers.levels.len());
+        let mut level_count = count;
+        for level in 0..buffers.levels.len() {
+            counts.push(level_count);
+            let params = Params {
+                count: level_count,
+                inclusive: (level == 0 && inclusive) as u32,
+                _pad: [0; 2],
+            };
+            queue.write_buffer(
+                &buffers.params,
+                level as u64 * Self::PARAMS_STRIDE,
+                bytemuck::bytes_of(&params),
+            );
+            level_count = level_count.div_ceil(Self::WORKGROUP);
+        }
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("prefix sum"),
+        });
+        let offset = |level: usize| [(level as u64 * Self::PARAMS_STRIDE) as u32];
+        pass.set_pipeline(&self.scan_blocks);
+        for (level, bind_group) in buffers.levels.iter().enumerate() {
+            pass.set_bind_group(0, bind_group, &offset(level));
+            pass.dispatch_workgroups(counts[level].div_ceil(Self::WORKGROUP), 1, 1);
+        }
+        // the last level's sums are the total, not offsets
+        pass.set_pipeline(&self.add_sums);
+        for (level, bind_group) in buffers.levels.iter().enumerate().rev().skip(1) {
+            let blocks = counts[level].div_ceil(Self::WORKGROUP);
+            if blocks > 1 {
+                pass.set_bind_group(0, bind_group, &offset(level));
+                pass.dispatch_workgroups(blocks, 1, 1);
+            }
+        }
+    }
+}
+
+impl ScanBuffers {
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// a single `u32`, the sum of every scanned element
+    pub fn total(&self) -> &Buffer {
+        self.sums.last().unwrap()
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+    use super::*;
+    use crate::graphics::test_util::{read_buffer, test_device};
+
+    fn check(len: usize, inclusive: bool) {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let values: Vec<u32> = (0..len as u32)
+            .map(|i| i.wrapping_mul(2654435761) % 1000)
+            .collect();
+        let data = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(if values.is_empty() { &[0] } else { &values }),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+        });
+        let readback = |size| {
+            device.create_buffer(&BufferDescriptor {
+                label: None,
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let (data_readback, total_readback) = (readback(data.size()), readback(4));
+
+        let prefix_sum = PrefixSum::new(&device);
+        let buffers = prefix_sum.buffers(&device, &data, len as u32);
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        prefix_sum.scan(&queue, &mut encoder, &buffers, len as u32, inclusive);
+        encoder.copy_buffer_to_buffer(&data, 0, &data_readback, 0, data.size());
+        encoder.copy_buffer_to_buffer(buffers.total(), 0, &total_readback, 0, 4);
+        queue.submit([encoder.finish()]);
+
+        let mut sum = 0u32;
+        let expected: Vec<u32> = values
+            .iter()
+            .map(|&value| {
+                let exclusive = sum;
+                sum = sum.wrapping_add(value);
+                if inclusive {
+                    sum
+                } else {
+                    exclusive
+                }
+            })
+            .collect();
+
+        let scanned: Vec<u32> = read_buffer(&device, &data_readback);
+        assert_eq!(&scanned[..len], &expected[..], "{len} elements");
+        assert_eq!(read_buffer::<u32>(&device, &total_readback), [sum]);
+    }
+
+    #[test]
+    fn single_block() {
+        for len in [0, 1, 2, 255, 256] {
+            check(len, true);
+            check(len, false);
+        }
+    }
+
+    #[test]
+    fn two_levels() {
+        for len in [257, 1000, 65536] {
+            check(len, true);
+            check(len, false);
+        }
+    }
+
+    #[test]
+    fn three_levels() {
+        check(65536 + 300, true);
+        check(200_000, false);
+    }
+
+    #[test]
+    fn fewer_than_capacity() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let data = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[1u32; 1000]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+        });
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: data.size(),
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let prefix_sum = PrefixSum::new(&device);
+        let buffers = prefix_sum.buffers(&device, &data, 1000);
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        prefix_sum.scan(&queue, &mut encoder, &buffers, 600, false);
+        encoder.copy_buffer_to_buffer(&data, 0, &readback, 0, data.size());
+        queue.submit([encoder.finish()]);
+
+        let scanned: Vec<u32> = read_buffer(&device, &readback);
+        assert!((0..600).all(|i| scanned[i] == i as u32));
+        // the rest is untouched
+        assert!(scanned[600..].iter().all(|&v| v == 1));
+    }
+}
@@ -0,0 +1,44 @@
+// device wide prefix sum: scan each block of 256, scan the block sums
+// one level up, then add the scanned sums back down
+
+struct Params {
+    count: u32,
+    inclusive: u32,
+};
+
+const WG: u32 = 256u;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> data: array<u32>;
+@group(0) @binding(2) var<storage, read_write> sums: array<u32>;
+
+@compute @workgroup_size(256)
+fn scan_blocks(
+    @builtin(local_invocation_id) lid: vec3<u32>,
+    @builtin(workgroup_id) wid: vec3<u32>,
+) {
+    let i = wid.x * WG + lid.x;
+    var value = 0u;
+    if i < params.count {
+        value = data[i];
+    }
+
+    let scan = workgroup_scan(lid.x, value);
+    if i < params.count {
+        data[i] = select(scan.inclusive - value, scan.inclusive, params.inclusive != 0u);
+    }
+    if lid.x == 0u {
+        sums[wid.x] = scan.total;
+    }
+}
+
+@compute @workgroup_size(256)
+fn add_sums(
+    @builtin(local_invocation_id) lid: vec3<u32>,
+    @builtin(workgroup_id) wid: vec3<u32>,
+) {
+    let i = wid.x * WG + lid.x;
+    if i < params.count {
+        data[i] += sums[wid.x];
+    }
+}
@@ -0,0 +1,423 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec4;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    *,
+};
+
+use super::workgroup::WorkgroupSizes;
+use crate::rng::RngService;
+
+//
+
+/// textures generated on the GPU from a [`TextureRecipe`], for materials and terrain
+///
+/// the recipes are plain data, so they can come from settings or assets and
+/// be edited while running: [`ProceduralTexture::update`] regenerates on change
+pub struct ProceduralTextures {
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+    workgroups: WorkgroupSizes,
+}
+
+/// one generated texture and the recipe it was made from
+pub struct ProceduralTexture {
+    recipe: TextureRecipe,
+    texture: Texture,
+    view: TextureView,
+    params: Buffer,
+    bind_group: BindGroup,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pattern {
+    /// smooth gradient noise on a square grid
+    #[default]
+    Perlin,
+    /// gradient noise on a triangle grid, fewer grid artifacts
+    Simplex,
+    /// distance to the closest random point, cells and cracks
+    Worley,
+    /// left to right, `frequency` and `octaves` are ignored
+    LinearGradient,
+    /// from the center out
+    RadialGradient,
+}
+
+/// what a [`ProceduralTexture`] is filled with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextureRecipe {
+    pub pattern: Pattern,
+    pub size: (u32, u32),
+    pub seed: u32,
+    /// noise cells across the texture
+    pub frequency: f32,
+    /// fractal noise layers, each `lacunarity` times finer and `gain` times weaker
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+    /// wrap around without seams, the frequency of every octave is rounded to a whole number
+    pub tiling: bool,
+    /// the colors at 0 and 1, written as is into an `Rgba8Unorm` texture
+    pub low: Vec4,
+    pub high: Vec4,
+}
+
+/// `Params` in `procedural.wgsl`
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Params {
+    pattern: u32,
+    seed: u32,
+    octaves: u32,
+    tiling: u32,
+    size: [u32; 2],
+    frequency: f32,
+    lacunarity: f32,
+    gain: f32,
+    _pad: [f32; 3],
+    low: Vec4,
+    high: Vec4,
+}
+
+//
+
+impl ProceduralTextures {
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+    pub fn new(device: &Device, workgroups: &WorkgroupSizes) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("procedural textures"),
+            source: ShaderSource::Wgsl(Cow::Owned(
+                workgroups.specialize_tile(include_str!("./procedural.wgsl")),
+            )),
+        });
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("procedural textures"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: Self::FORMAT,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("procedural textures"),
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("procedural textures"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            module: &module,
+            entry_point: "generate",
+        });
+
+        Self {
+            layout,
+            pipeline,
+            workgroups: *workgroups,
+        }
+    }
+
+    /// a new texture, filled when `encoder` is submitted
+    pub fn generate(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        recipe: &TextureRecipe,
+    ) -> ProceduralTexture {
+        let (width, height) = (recipe.size.0.max(1), recipe.size.1.max(1));
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("procedural texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: TextureUsages::STORAGE_BINDING
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&<_>::default());
+        let params = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("procedural texture params"),
+            contents: bytemuck::bytes_of(&Params::new(recipe)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("procedural texture"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&view),
+                },
+            ],
+        });
+
+        let texture = ProceduralTexture {
+            recipe: *recipe,
+            texture,
+            view,
+            params,
+            bind_group,
+        };
+        self.dispatch(encoder, &texture);
+        texture
+    }
+
+    fn dispatch(&self, encoder: &mut CommandEncoder, texture: &ProceduralTexture) {
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("procedural texture"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &texture.bind_group, &[]);
+        let size = texture.texture.size();
+        let (x, y) = self.workgroups.tile_groups(size.width, size.height);
+        pass.dispatch_workgroups(x, y, 1);
+    }
+}
+
+impl ProceduralTexture {
+    pub fn recipe(&self) -> &TextureRecipe {
+        &self.recipe
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// regenerate if `recipe` changed, a new size makes a new texture
+    /// (bind groups using the old one have to be remade)
+    ///
+    /// returns if anything was regenerated
+    pub fn update(
+        &mut self,
+        generator: &ProceduralTextures,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        recipe: &TextureRecipe,
+    ) -> bool {
+        if *recipe == self.recipe {
+            return false;
+        }
+        if recipe.size != self.recipe.size {
+            *self = generator.generate(device, encoder, recipe);
+            return true;
+        }
+
+        self.recipe = *recipe;
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&Params::new(recipe)));
+        generator.dispatch(encoder, self);
+        true
+    }
+}
+
+impl TextureRecipe {
+    /// the seed from the RNG stream of `name`, the same texture for the same run seed
+    pub fn seeded(self, rng: &RngService, name: &str) -> Self {
+        Self {
+            seed: rng.stream(name).next_u32(),
+            ..self
+        }
+    }
+}
+
+impl Default for TextureRecipe {
+    fn default() -> Self {
+        Self {
+            pattern: Pattern::Perlin,
+            size: (256, 256),
+            seed: 0,
+            frequency: 4.0,
+            octaves: 4,
+            lacunarity: 2.0,
+            gain: 0.5,
+            tiling: true,
+            low: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            high: Vec4::ONE,
+        }
+    }
+}
+
+impl Params {
+    fn new(recipe: &TextureRecipe) -> Self {
+        let whole = |v: f32| if recipe.tiling { v.round().max(1.0) } else { v };
+        Self {
+            pattern: recipe.pattern as u32,
+            seed: recipe.seed,
+            octaves: recipe.octaves.clamp(1, 16),
+            tiling: recipe.tiling as u32,
+            size: [recipe.size.0.max(1), recipe.size.1.max(1)],
+            frequency: whole(recipe.frequency),
+            lacunarity: whole(recipe.lacunarity),
+            gain: recipe.gain,
+            _pad: [0.0; 3],
+            low: recipe.low,
+            high: recipe.high,
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::test_util::{read_buffer, test_device};
+
+    const SIZE: u32 = 64;
+
+    /// the red channel, row by row
+    fn generate(recipes: &[TextureRecipe]) -> Option<Vec<Vec<u8>>> {
+        let (device, queue) = test_device()?;
+        if device.limits().max_storage_textures_per_shader_stage == 0 {
+            return None;
+        }
+
+        let generator = ProceduralTextures::new(&device, &WorkgroupSizes::default());
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        let mut texture = generator.generate(&device, &mut encoder, &recipes[0]);
+        queue.submit([encoder.finish()]);
+
+        let mut images = Vec::new();
+        for recipe in recipes {
+            let mut encoder = device.create_command_encoder(&<_>::default());
+            texture.update(&generator, &device, &queue, &mut encoder, recipe);
+
+            let readback = device.create_buffer(&BufferDescriptor {
+                label: None,
+                size: (SIZE * SIZE * 4) as _,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                texture.texture().as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &readback,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(SIZE * 4),
+                        rows_per_image: None,
+                    },
+                },
+                texture.texture().size(),
+            );
+            queue.submit([encoder.finish()]);
+            let texels: Vec<[u8; 4]> = read_buffer(&device, &readback);
+            images.push(texels.iter().map(|texel| texel[0]).collect());
+        }
+        Some(images)
+    }
+
+    fn recipe(pattern: Pattern, seed: u32) -> TextureRecipe {
+        TextureRecipe {
+            pattern,
+            size: (SIZE, SIZE),
+            seed,
+            ..<_>::default()
+        }
+    }
+
+    /// the largest step between neighbours, inside and across the wrap
+    fn steps(image: &[u8]) -> (u8, u8) {
+        let at = |x: u32, y: u32| image[((y % SIZE) * SIZE + x % SIZE) as usize];
+        let (mut inside, mut wrap) = (0, 0);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let step = at(x, y)
+                    .abs_diff(at(x + 1, y))
+                    .max(at(x, y).abs_diff(at(x, y + 1)));
+                if x == SIZE - 1 || y == SIZE - 1 {
+                    wrap = step.max(wrap);
+                } else {
+                    inside = step.max(inside);
+                }
+            }
+        }
+        (inside, wrap)
+    }
+
+    #[test]
+    fn noise_is_seeded_and_tiles() {
+        for pattern in [Pattern::Perlin, Pattern::Simplex, Pattern::Worley] {
+            let Some(images) =
+                generate(&[recipe(pattern, 1), recipe(pattern, 2), recipe(pattern, 1)])
+            else {
+                return;
+            };
+
+            assert_eq!(images[0], images[2], "{pattern:?}");
+            assert_ne!(images[0], images[1], "{pattern:?}");
+            let (min, max) = (images[0].iter().min(), images[0].iter().max());
+            assert!(
+                max.unwrap() - min.unwrap() > 64,
+                "{pattern:?} {min:?} {max:?}"
+            );
+
+            let (inside, wrap) = steps(&images[0]);
+            assert!(
+                wrap <= inside + 2,
+                "{pattern:?}: {wrap} across the seam, {inside} inside"
+            );
+        }
+    }
+
+    #[test]
+    fn gradients() {
+        let Some(images) = generate(&[
+            recipe(Pattern::LinearGradient, 0),
+            recipe(Pattern::RadialGradient, 0),
+        ]) else {
+            return;
+        };
+
+        let linear = &images[0];
+        assert!(linear[0] < 4 && linear[SIZE as usize - 1] > 251);
+        assert!(linear
+            .windows(2)
+            .take(SIZE as usize - 1)
+            .all(|w| w[0] <= w[1]));
+
+        let radial = &images[1];
+        let center = (SIZE / 2 * SIZE + SIZE / 2) as usize;
+        assert!(radial[center] < 8 && radial[0] == 255);
+    }
+}
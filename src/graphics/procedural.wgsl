@@ -0,0 +1,156 @@
+// procedural textures: seeded fractal noise and gradients, mapped to a color ramp
+
+const SIMPLEX: u32 = 1u;
+const WORLEY: u32 = 2u;
+const LINEAR_GRADIENT: u32 = 3u;
+const RADIAL_GRADIENT: u32 = 4u;
+
+const TAU: f32 = 6.283185307;
+
+struct Params {
+    pattern: u32,
+    seed: u32,
+    octaves: u32,
+    tiling: u32,
+    size: vec2<u32>,
+    // cells across the texture, whole numbers when tiling
+    frequency: f32,
+    lacunarity: f32,
+    gain: f32,
+    low: vec4<f32>,
+    high: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var output: texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size({workgroup_size})
+fn generate(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size.x || id.y >= params.size.y {
+        return;
+    }
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(params.size);
+
+    var value: f32;
+    if params.pattern == LINEAR_GRADIENT {
+        value = uv.x;
+    } else if params.pattern == RADIAL_GRADIENT {
+        value = length(uv - 0.5) * 2.0;
+    } else {
+        value = fractal(uv);
+    }
+    textureStore(output, vec2<i32>(id.xy), mix(params.low, params.high, saturate(value)));
+}
+
+// octaves of noise, each `lacunarity` times finer and `gain` times weaker, in 0..1
+fn fractal(uv: vec2<f32>) -> f32 {
+    var sum = 0.0;
+    var total = 0.0;
+    var amplitude = 1.0;
+    var frequency = params.frequency;
+    for (var octave = 0u; octave < max(params.octaves, 1u); octave++) {
+        sum += noise(uv, frequency, octave) * amplitude;
+        total += amplitude;
+        amplitude *= params.gain;
+        frequency *= params.lacunarity;
+    }
+    return sum / max(total, 1e-6);
+}
+
+fn noise(uv: vec2<f32>, frequency: f32, octave: u32) -> f32 {
+    let p = uv * frequency;
+    // 0 is no wrapping
+    let period = select(0, i32(round(frequency)), params.tiling != 0u);
+    let seed = hash(params.seed ^ hash(octave));
+
+    if params.pattern == SIMPLEX {
+        if period == 0 {
+            return simplex(p, seed);
+        }
+        // simplex cells don't line up with a square period,
+        // crossfade with the copies one period over instead
+        let f = f32(period);
+        return mix(
+            mix(simplex(p, seed), simplex(p - vec2<f32>(f, 0.0), seed), uv.x),
+            mix(simplex(p - vec2<f32>(0.0, f), seed), simplex(p - f, seed), uv.x),
+            uv.y,
+        );
+    }
+    if params.pattern == WORLEY {
+        return worley(p, period, seed);
+    }
+    return perlin(p, period, seed);
+}
+
+fn perlin(p: vec2<f32>, period: i32, seed: u32) -> f32 {
+    let cell = vec2<i32>(floor(p));
+    let f = fract(p);
+    let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    let g00 = dot(gradient(wrap(cell, period), seed), f);
+    let g10 = dot(gradient(wrap(cell + vec2<i32>(1, 0), period), seed), f - vec2<f32>(1.0, 0.0));
+    let g01 = dot(gradient(wrap(cell + vec2<i32>(0, 1), period), seed), f - vec2<f32>(0.0, 1.0));
+    let g11 = dot(gradient(wrap(cell + vec2<i32>(1, 1), period), seed), f - 1.0);
+    // -sqrt(0.5)..sqrt(0.5)
+    return mix(mix(g00, g10, u.x), mix(g01, g11, u.x), u.y) * 0.70710678 + 0.5;
+}
+
+fn simplex(p: vec2<f32>, seed: u32) -> f32 {
+    let F2 = 0.366025404;
+    let G2 = 0.211324865;
+
+    let cell = floor(p + (p.x + p.y) * F2);
+    let x0 = p - cell + (cell.x + cell.y) * G2;
+    let i1 = select(vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), x0.x > x0.y);
+    let x1 = x0 - i1 + G2;
+    let x2 = x0 - 1.0 + 2.0 * G2;
+
+    let c = vec2<i32>(cell);
+    let n = corner(x0, gradient(c, seed))
+        + corner(x1, gradient(c + vec2<i32>(i1), seed))
+        + corner(x2, gradient(c + 1, seed));
+    return saturate(n * 35.0 + 0.5);
+}
+
+fn corner(x: vec2<f32>, gradient: vec2<f32>) -> f32 {
+    let t = max(0.5 - dot(x, x), 0.0);
+    return t * t * t * t * dot(gradient, x);
+}
+
+// distance to the closest feature point, one per cell
+fn worley(p: vec2<f32>, period: i32, seed: u32) -> f32 {
+    let cell = vec2<i32>(floor(p));
+    var closest = 1.5;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = cell + vec2<i32>(x, y);
+            let h = hash_cell(wrap(neighbour, period), seed);
+            let feature = vec2<f32>(neighbour) + vec2<f32>(f32(h & 0xffffu), f32(h >> 16u)) / 65535.0;
+            closest = min(closest, distance(p, feature));
+        }
+    }
+    return closest;
+}
+
+fn wrap(cell: vec2<i32>, period: i32) -> vec2<i32> {
+    if period <= 0 {
+        return cell;
+    }
+    return ((cell % period) + period) % period;
+}
+
+fn gradient(cell: vec2<i32>, seed: u32) -> vec2<f32> {
+    let angle = f32(hash_cell(cell, seed)) / 4294967296.0 * TAU;
+    return vec2<f32>(cos(angle), sin(angle));
+}
+
+fn hash_cell(cell: vec2<i32>, seed: u32) -> u32 {
+    return hash(bitcast<u32>(cell.x) ^ hash(bitcast<u32>(cell.y) ^ seed));
+}
+
+// PCG, https://jcgt.org/published/0009/03/02/
+fn hash(x: u32) -> u32 {
+    let state = x * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
@@ -0,0 +1,377 @@
+use std::{borrow::Cow, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::*;
+
+//
+
+/// stable GPU sort of `u32` keys with a `u32` value each,
+/// for anything that orders work on the GPU: particles back to front,
+/// transparent fragments, lights into clusters
+///
+/// least significant digit first, 4 bits per pass, in plain compute shaders
+/// without subgroups so it runs on every adapter with compute
+pub struct RadixSort {
+    layout: BindGroupLayout,
+    count: ComputePipeline,
+    scan: ComputePipeline,
+    scatter: ComputePipeline,
+}
+
+/// the scratch space for sorting one pair of key and value buffers
+pub struct SortBuffers {
+    capacity: u32,
+    params: Buffer,
+    /// keys and values ping pong between the caller's buffers and these
+    _keys: Buffer,
+    _values: Buffer,
+    _histogram: Buffer,
+    bind_groups: [BindGroup; 2],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Params {
+    count: u32,
+    shift: u32,
+    blocks: u32,
+    _pad: u32,
+}
+
+//
+
+impl RadixSort {
+    const WORKGROUP: u32 = 256;
+    const BITS: u32 = 4;
+    const RADIX: u32 = 1 << Self::BITS;
+    const PASSES: u32 = 32 / Self::BITS;
+    /// the largest `min_uniform_buffer_offset_alignment` allowed
+    const PARAMS_STRIDE: u64 = 256;
+
+    pub fn new(device: &Device) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("radix sort"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./workgroup_scan.wgsl"),
+                include_str!("./radix_sort.wgsl")
+            ))),
+        });
+
+        let storage = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("radix sort"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: BufferSize::new(size_of::<Params>() as _),
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+                storage(4, false),
+                storage(5, false),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("radix sort"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("radix sort"),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point,
+            })
+        };
+
+        Self {
+            count: pipeline("count"),
+            scan: pipeline("scan"),
+            scatter: pipeline("scatter"),
+            layout,
+        }
+    }
+
+    /// scratch space for sorting up to `capacity` elements of `keys` and `values`,
+    /// both need `STORAGE` usage and room for `capacity` `u32`s
+    pub fn buffers(
+        &self,
+        device: &Device,
+        keys: &Buffer,
+        values: &Buffer,
+        capacity: u32,
+    ) -> SortBuffers {
+        let capacity = capacity.max(1);
+        let size = capacity as u64 * 4;
+        assert!(keys.size() >= size && values.size() >= size);
+        let blocks = capacity.div_ceil(Self::WORKGROUP);
+        assert!(
+            blocks <= device.limits().max_compute_workgroups_per_dimension,
+            "too many elements to sort"
+        );
+
+        let buffer = |label, size, usage| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let params = buffer(
+            "radix sort params",
+            Self::PARAMS_STRIDE * Self::PASSES as u64,
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        );
+        let tmp_keys = buffer("radix sort keys", size, BufferUsages::STORAGE);
+        let tmp_values = buffer("radix sort values", size, BufferUsages::STORAGE);
+        let histogram = buffer(
+            "radix sort histogram",
+            (blocks * Self::RADIX) as u64 * 4,
+            BufferUsages::STORAGE,
+        );
+
+        let bind_group = |keys_in: &Buffer, values_in: &Buffer, keys_out, values_out| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("radix sort"),
+                layout: &self.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: &params,
+                            offset: 0,
+                            size: BufferSize::new(size_of::<Params>() as _),
+                        }),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: keys_in.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: values_in.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: keys_out,
+                    },
+                    BindGroupEntry {
+                        binding: 4,
+                        resource: values_out,
+                    },
+                    BindGroupEntry {
+                        binding: 5,
+                        resource: histogram.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [
+            bind_group(
+                keys,
+                values,
+                tmp_keys.as_entire_binding(),
+                tmp_values.as_entire_binding(),
+            ),
+            bind_group(
+                &tmp_keys,
+                &tmp_values,
+                keys.as_entire_binding(),
+                values.as_entire_binding(),
+            ),
+        ];
+
+        SortBuffers {
+            capacity,
+            params,
+            _keys: tmp_keys,
+            _values: tmp_values,
+            _histogram: histogram,
+            bind_groups,
+        }
+    }
+
+    /// sort the first `count` elements by their lowest `key_bits` bits,
+    /// the sorted keys and values end up back in the same buffers
+    ///
+    /// the params are written through the queue,
+    /// so one sort per [`SortBuffers`] per submission
+    pub fn sort(
+        &self,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        buffers: &SortBuffers,
+        count: u32,
+        key_bits: u32,
+    ) {
+        assert!(count <= buffers.capacity);
+        if count <= 1 {
+            return;
+        }
+
+        // whole bytes, an even number of passes ends in the caller's buffers
+        let passes = key_bits.clamp(1, 32).div_ceil(8) * 8 / Self::BITS;
+        let blocks = count.div_ceil(Self::WORKGROUP);
+        for pass in 0..passes {
+            let params = Params {
+                count,
+                shift: pass * Self::BITS,
+                blocks,
+                _pad: 0,
+            };
+            queue.write_buffer(
+                &buffers.params,
+                pass as u64 * Self::PARAMS_STRIDE,
+                bytemuck::bytes_of(&params),
+            );
+        }
+
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("radix sort"),
+        });
+        for i in 0..passes {
+            let offset = (i as u64 * Self::PARAMS_STRIDE) as u32;
+            pass.set_bind_group(0, &buffers.bind_groups[i as usize % 2], &[offset]);
+            pass.set_pipeline(&self.count);
+            pass.dispatch_workgroups(blocks, 1, 1);
+            pass.set_pipeline(&self.scan);
+            pass.dispatch_workgroups(1, 1, 1);
+            pass.set_pipeline(&self.scatter);
+            pass.dispatch_workgroups(blocks, 1, 1);
+        }
+    }
+}
+
+impl SortBuffers {
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+    use super::*;
+    use crate::graphics::test_util::{read_buffer, test_device};
+
+    fn sort_on_gpu(keys: &[u32], key_bits: u32) -> Option<(Vec<u32>, Vec<u32>)> {
+        let (device, queue) = test_device()?;
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return None;
+        }
+
+        let values: Vec<u32> = (0..keys.len() as u32).collect();
+        let init = |contents: &[u32]| {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(if contents.is_empty() { &[0] } else { contents }),
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            })
+        };
+        let (key_buffer, value_buffer) = (init(keys), init(&values));
+        let readback = |buffer: &Buffer| {
+            device.create_buffer(&BufferDescriptor {
+                label: None,
+                size: buffer.size(),
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let readbacks = [
+            (readback(&key_buffer), &key_buffer),
+            (readback(&value_buffer), &value_buffer),
+        ];
+
+        let sort = RadixSort::new(&device);
+        let buffers = sort.buffers(&device, &key_buffer, &value_buffer, keys.len() as u32);
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        sort.sort(&queue, &mut encoder, &buffers, keys.len() as u32, key_bits);
+        for (readback, buffer) in &readbacks {
+            encoder.copy_buffer_to_buffer(buffer, 0, readback, 0, readback.size());
+        }
+        queue.submit([encoder.finish()]);
+
+        let [keys_out, values_out] =
+            readbacks.map(|(readback, _)| read_buffer::<u32>(&device, &readback));
+        Some((
+            keys_out[..keys.len()].to_vec(),
+            values_out[..keys.len()].to_vec(),
+        ))
+    }
+
+    fn check(keys: &[u32], key_bits: u32) {
+        let Some((sorted_keys, sorted_values)) = sort_on_gpu(keys, key_bits) else {
+            return;
+        };
+
+        // a stable sort of the indices is the only right answer
+        let mask = u32::MAX >> (32 - key_bits);
+        let mut expected: Vec<u32> = (0..keys.len() as u32).collect();
+        expected.sort_by_key(|&i| keys[i as usize] & mask);
+
+        assert_eq!(sorted_values, expected);
+        let expected_keys: Vec<u32> = expected.iter().map(|&i| keys[i as usize]).collect();
+        assert_eq!(sorted_keys, expected_keys);
+    }
+
+    fn keys(len: usize, seed: u64) -> Vec<u32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                // splitmix64
+                state = state.wrapping_add(0x9e3779b97f4a7c15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+                (z ^ (z >> 31)) as u32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorts_full_keys() {
+        check(&keys(3000, 1), 32);
+    }
+
+    #[test]
+    fn sorts_within_one_block() {
+        check(&keys(200, 2), 32);
+        check(&[7], 32);
+        check(&[], 32);
+    }
+
+    #[test]
+    fn stable_with_duplicates() {
+        let keys: Vec<u32> = keys(5000, 3).into_iter().map(|k| k % 37).collect();
+        check(&keys, 32);
+    }
+
+    #[test]
+    fn sorts_low_bits_only() {
+        // the high bits are ignored and keep their order within equal low bits
+        check(&keys(1000, 4), 16);
+        check(&keys(1000, 5), 8);
+    }
+}
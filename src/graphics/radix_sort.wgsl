@@ -0,0 +1,94 @@
+// 4 bit LSD radix sort pass: count digits per block, scan the counts, scatter
+//
+// the histogram is digit major (digit * blocks + block),
+// so its exclusive prefix sum is where each block's digits go
+
+struct Params {
+    count: u32,
+    shift: u32,
+    blocks: u32,
+};
+
+const WG: u32 = 256u;
+const RADIX: u32 = 16u;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> keys_in: array<u32>;
+@group(0) @binding(2) var<storage, read> values_in: array<u32>;
+@group(0) @binding(3) var<storage, read_write> keys_out: array<u32>;
+@group(0) @binding(4) var<storage, read_write> values_out: array<u32>;
+@group(0) @binding(5) var<storage, read_write> histogram: array<u32>;
+
+var<workgroup> counts: array<atomic<u32>, 16>;
+
+@compute @workgroup_size(256)
+fn count(
+    @builtin(local_invocation_id) lid: vec3<u32>,
+    @builtin(workgroup_id) wid: vec3<u32>,
+) {
+    if lid.x < RADIX {
+        atomicStore(&counts[lid.x], 0u);
+    }
+    workgroupBarrier();
+
+    let i = wid.x * WG + lid.x;
+    if i < params.count {
+        atomicAdd(&counts[(keys_in[i] >> params.shift) & 15u], 1u);
+    }
+    workgroupBarrier();
+
+    if lid.x < RADIX {
+        histogram[lid.x * params.blocks + wid.x] = atomicLoad(&counts[lid.x]);
+    }
+}
+
+// one workgroup walks the whole histogram
+@compute @workgroup_size(256)
+fn scan(@builtin(local_invocation_id) lid: vec3<u32>) {
+    let n = RADIX * params.blocks;
+    var carry = 0u;
+    for (var base = 0u; base < n; base += WG) {
+        let i = base + lid.x;
+        var value = 0u;
+        if i < n {
+            value = histogram[i];
+        }
+
+        let scan = workgroup_scan(lid.x, value);
+        if i < n {
+            histogram[i] = carry + scan.inclusive - value;
+        }
+        carry += scan.total;
+    }
+}
+
+@compute @workgroup_size(256)
+fn scatter(
+    @builtin(local_invocation_id) lid: vec3<u32>,
+    @builtin(workgroup_id) wid: vec3<u32>,
+) {
+    let i = wid.x * WG + lid.x;
+    let valid = i < params.count;
+    var key = 0u;
+    // out of range invocations match no digit
+    var digit = RADIX;
+    if valid {
+        key = keys_in[i];
+        digit = (key >> params.shift) & 15u;
+    }
+
+    // rank among the same digits earlier in the block keeps the sort stable
+    var rank = 0u;
+    for (var d = 0u; d < RADIX; d++) {
+        let scan = workgroup_scan(lid.x, select(0u, 1u, digit == d));
+        if digit == d {
+            rank = scan.inclusive - 1u;
+        }
+    }
+
+    if valid {
+        let dst = histogram[digit * params.blocks + wid.x] + rank;
+        keys_out[dst] = key;
+        values_out[dst] = values_in[i];
+    }
+}
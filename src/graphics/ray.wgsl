@@ -0,0 +1,92 @@
+// brute force ray casts against every triangle of a mesh,
+// prepended to the baking shaders
+
+@group(1) @binding(0) var<storage, read> ray_positions: array<vec4<f32>>;
+@group(1) @binding(1) var<storage, read> ray_indices: array<u32>;
+
+struct Hit {
+    t: f32,
+    triangle: u32,
+    barycentric: vec2<f32>,
+};
+
+const NO_HIT: f32 = 3.4e38;
+
+fn ray_triangle_count() -> u32 {
+    return arrayLength(&ray_indices) / 3u;
+}
+
+fn ray_vertex(triangle: u32, corner: u32) -> vec3<f32> {
+    return ray_positions[ray_indices[triangle * 3u + corner]].xyz;
+}
+
+// Möller-Trumbore, both sides
+fn intersect(origin: vec3<f32>, dir: vec3<f32>, triangle: u32) -> Hit {
+    let a = ray_vertex(triangle, 0u);
+    let ab = ray_vertex(triangle, 1u) - a;
+    let ac = ray_vertex(triangle, 2u) - a;
+    let p = cross(dir, ac);
+    let det = dot(ab, p);
+    let miss = Hit(NO_HIT, 0u, vec2<f32>(0.0));
+    if abs(det) < 1e-12 {
+        return miss;
+    }
+    let inv = 1.0 / det;
+    let s = origin - a;
+    let u = dot(s, p) * inv;
+    let q = cross(s, ab);
+    let v = dot(dir, q) * inv;
+    if u < 0.0 || v < 0.0 || u + v > 1.0 {
+        return miss;
+    }
+    return Hit(dot(ac, q) * inv, triangle, vec2<f32>(u, v));
+}
+
+// the hit nearest to `target_t` within `min_t..max_t`
+fn trace_nearest(origin: vec3<f32>, dir: vec3<f32>, min_t: f32, max_t: f32, target_t: f32) -> Hit {
+    var best = Hit(NO_HIT, 0u, vec2<f32>(0.0));
+    for (var i = 0u; i < ray_triangle_count(); i++) {
+        let hit = intersect(origin, dir, i);
+        if hit.t >= min_t && hit.t <= max_t && abs(hit.t - target_t) < abs(best.t - target_t) {
+            best = hit;
+        }
+    }
+    return best;
+}
+
+fn occluded(origin: vec3<f32>, dir: vec3<f32>, max_t: f32) -> bool {
+    for (var i = 0u; i < ray_triangle_count(); i++) {
+        let t = intersect(origin, dir, i).t;
+        if t > 0.0 && t < max_t {
+            return true;
+        }
+    }
+    return false;
+}
+
+// facing the same side as `towards`
+fn face_normal(triangle: u32, towards: vec3<f32>) -> vec3<f32> {
+    let a = ray_vertex(triangle, 0u);
+    let n = normalize(cross(ray_vertex(triangle, 1u) - a, ray_vertex(triangle, 2u) - a));
+    return select(n, -n, dot(n, towards) < 0.0);
+}
+
+fn radical_inverse(bits_in: u32) -> f32 {
+    var bits = (bits_in << 16u) | (bits_in >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return f32(bits) * 2.3283064365386963e-10;
+}
+
+// sample `s` of `count`, cosine weighted around `normal`, Hammersley
+fn cosine_hemisphere(normal: vec3<f32>, s: u32, count: u32) -> vec3<f32> {
+    let up = select(vec3<f32>(0.0, 0.0, 1.0), vec3<f32>(1.0, 0.0, 0.0), abs(normal.z) > 0.9);
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+    let u = (f32(s) + 0.5) / f32(count);
+    let r = sqrt(u);
+    let phi = 6.2831853 * radical_inverse(s);
+    return tangent * (r * cos(phi)) + bitangent * (r * sin(phi)) + normal * sqrt(1.0 - u);
+}
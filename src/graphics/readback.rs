@@ -0,0 +1,144 @@
+use std::{
+    marker::PhantomData,
+    mem::size_of,
+    sync::{Arc, OnceLock},
+};
+
+use bytemuck::Pod;
+use wgpu::*;
+
+//
+
+/// double-buffered GPU -> CPU readback that never waits for the GPU
+///
+/// each frame copies into one buffer while the other one is being mapped,
+/// the results arrive a frame or two late:
+///
+/// ```ignore
+/// readback.copy_from(&mut encoder, &stats_buffer, 0);
+/// queue.submit([encoder.finish()]);
+/// readback.submitted();
+///
+/// if let Some(stats) = readback.poll() { /* from an earlier frame */ }
+/// ```
+pub struct Readback<T> {
+    slots: [Slot; 2],
+    /// the slot the next copy goes into
+    write: usize,
+    len: usize,
+    _p: PhantomData<T>,
+}
+
+struct Slot {
+    buffer: Buffer,
+    state: SlotState,
+}
+
+enum SlotState {
+    Free,
+    /// a copy was recorded, not submitted yet
+    Copied,
+    /// `map_async` was called, set to whether it succeeded once it's done
+    Mapping(Arc<OnceLock<bool>>),
+}
+
+//
+
+impl<T: Pod> Readback<T> {
+    pub fn new(device: &Device, len: usize, label: &str) -> Self {
+        let slot = || Slot {
+            buffer: device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size: (len * size_of::<T>()) as _,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            state: SlotState::Free,
+        };
+
+        Self {
+            slots: [slot(), slot()],
+            write: 0,
+            len,
+            _p: PhantomData,
+        }
+    }
+
+    /// the buffer for this frame's copy, `None` if both are still in flight
+    ///
+    /// for copies [`Self::copy_from`] doesn't cover, like texture to buffer
+    pub fn target(&mut self) -> Option<&Buffer> {
+        let slot = &mut self.slots[self.write];
+        match slot.state {
+            SlotState::Free | SlotState::Copied => {
+                slot.state = SlotState::Copied;
+                Some(&slot.buffer)
+            }
+            SlotState::Mapping(_) => None,
+        }
+    }
+
+    /// record a copy of `len` elements from `src`,
+    /// skipped (returns false) if the GPU hasn't caught up with the previous reads
+    pub fn copy_from(&mut self, encoder: &mut CommandEncoder, src: &Buffer, offset: u64) -> bool {
+        let size = (self.len * size_of::<T>()) as u64;
+        let Some(dst) = self.target() else {
+            return false;
+        };
+
+        encoder.copy_buffer_to_buffer(src, offset, dst, 0, size);
+        true
+    }
+
+    /// call after submitting the encoder the copy was recorded into
+    pub fn submitted(&mut self) {
+        let slot = &mut self.slots[self.write];
+        if !matches!(slot.state, SlotState::Copied) {
+            return;
+        }
+
+        let done = Arc::new(OnceLock::new());
+        slot.buffer.slice(..).map_async(MapMode::Read, {
+            let done = done.clone();
+            move |res| {
+                if let Err(err) = &res {
+                    tracing::error!("Failed to map a readback buffer: {err}");
+                }
+                _ = done.set(res.is_ok());
+            }
+        });
+        slot.state = SlotState::Mapping(done);
+        self.write = 1 - self.write;
+    }
+
+    /// the newest finished readback, if any
+    ///
+    /// mapping only progresses when the device is polled,
+    /// which presenting a frame does
+    pub fn poll(&mut self) -> Option<Vec<T>> {
+        // the older slot first, so the newer one wins
+        let mut result = None;
+        for i in [self.write, 1 - self.write] {
+            let slot = &mut self.slots[i];
+            let SlotState::Mapping(done) = &slot.state else {
+                continue;
+            };
+            match done.get() {
+                None => continue,
+                Some(false) => {
+                    slot.state = SlotState::Free;
+                    continue;
+                }
+                Some(true) => {}
+            }
+
+            {
+                let view = slot.buffer.slice(..).get_mapped_range();
+                result = Some(bytemuck::cast_slice(&view).to_vec());
+            }
+            slot.buffer.unmap();
+            slot.state = SlotState::Free;
+        }
+        result
+    }
+}
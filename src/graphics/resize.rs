@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+//
+
+/// coalesces a storm of window resizes into one swapchain reconfigure,
+/// once the size has settled
+#[derive(Debug)]
+pub struct ResizeDebounce {
+    debounce: Duration,
+    /// the latest size and when it came
+    pending: Option<((u32, u32), Instant)>,
+}
+
+//
+
+impl ResizeDebounce {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            pending: None,
+        }
+    }
+
+    /// the window is `size` since `now`, replaces any earlier pending size
+    pub fn resized(&mut self, size: (u32, u32), now: Instant) {
+        self.pending = Some((size, now));
+    }
+
+    /// the swapchain has to be reconfigured at the `window` size,
+    /// unless a resize is on its way anyway
+    pub fn stale(&mut self, window: (u32, u32), now: Instant) {
+        // minimized, the `Resized` of restoring it reconfigures it
+        if self.pending.is_none() && can_render(window) {
+            self.pending = Some((window, now));
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending = None;
+    }
+
+    /// the size to configure the `current` swapchain to at `now`, once the pending
+    /// size has settled or right away if `force`d, a `stale` swapchain is
+    /// reconfigured even at the same size
+    ///
+    /// a settled size is used up either way, like one of a minimized window
+    pub fn take(
+        &mut self,
+        now: Instant,
+        force: bool,
+        current: (u32, u32),
+        stale: bool,
+    ) -> Option<(u32, u32)> {
+        let (size, at) = self.pending?;
+        if !force && now.saturating_duration_since(at) < self.debounce {
+            return None;
+        }
+
+        self.pending = None;
+        (can_render(size) && (size != current || stale)).then_some(size)
+    }
+}
+
+/// minimized windows can be 0 wide or high, and can't have a swapchain then
+pub fn can_render(size: (u32, u32)) -> bool {
+    size.0 != 0 && size.1 != 0
+}
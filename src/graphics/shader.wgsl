@@ -11,6 +11,12 @@ struct FragmentInput {
 
 struct Push {
     mvp: mat4x4<f32>,
+    palette_len: u32,
+    // 1 / log2(far + 1), 0 disables logarithmic depth
+    log_depth_coef: f32,
+    reversed_z: u32,
+    // everything white on the black background
+    high_contrast: u32,
 };
 
 var<push_constant> push: Push;
@@ -19,7 +25,18 @@ var<push_constant> push: Push;
 fn vs_main(vin: VertexInput) -> FragmentInput {
     var fin: FragmentInput;
     fin.pos = push.mvp * vec4<f32>(vin.pos, 0.0, 1.0);
+    if push.log_depth_coef > 0.0 {
+        // per vertex, long triangles crossing the camera plane can be slightly off
+        var depth = log2(max(1e-6, 1.0 + fin.pos.w)) * push.log_depth_coef;
+        if push.reversed_z != 0u {
+            depth = 1.0 - depth;
+        }
+        fin.pos.z = depth * fin.pos.w;
+    }
     fin.col = vin.col;
+    if push.high_contrast != 0u {
+        fin.col = vec4<f32>(1.0);
+    }
     return fin;
 }
 
@@ -27,3 +44,11 @@ fn vs_main(vin: VertexInput) -> FragmentInput {
 fn fs_main(fin: FragmentInput) -> @location(0) vec4<f32> {
     return fin.col;
 }
+
+// indexed color: the brightness picks one of the palette colors
+@fragment
+fn fs_index(fin: FragmentInput) -> @location(0) vec4<f32> {
+    let luma = dot(fin.col.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let index = round(luma * f32(push.palette_len - 1u));
+    return vec4<f32>(index / 255.0, 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,317 @@
+use std::{collections::BTreeMap, fmt, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{IVec4, UVec2, UVec4, Vec4};
+use wgpu::*;
+
+use super::readback::Readback;
+
+//
+
+/// printf debugging for shaders on backends that have none
+///
+/// a shader prepends [`ShaderDebug::wgsl`], binds [`ShaderDebug::bind_group`]
+/// and calls `debug_f32(tag, pos, value)` (or `_u32`, `_i32`) for the pixel or
+/// thread at `pos`, the values arrive through a [`Readback`] a frame or two later:
+///
+/// ```ignore
+/// debug.begin(&queue);
+/// // passes using the bind group
+/// debug.end(&mut encoder);
+/// queue.submit([encoder.finish()]);
+/// debug.submitted();
+///
+/// if let Some(records) = debug.poll() { debug.log(&records) }
+/// ```
+///
+/// only the [`Self::select`]ed pixel prints, so a fragment shader doesn't
+/// flood the buffer, prints past the capacity are counted and dropped
+pub struct ShaderDebug {
+    layout: BindGroupLayout,
+    bind_group: BindGroup,
+    buffer: Buffer,
+    capacity: u32,
+    selected: Option<UVec2>,
+    readback: Readback<u32>,
+    names: BTreeMap<u32, String>,
+}
+
+/// one `debug_*` call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugRecord {
+    pub tag: u32,
+    /// the pixel or thread it was printed for
+    pub pos: UVec2,
+    pub value: DebugValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugValue {
+    F32(Vec4),
+    U32(UVec4),
+    I32(IVec4),
+}
+
+/// `ShaderDebug` in `shader_debug.wgsl`, without the records
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Header {
+    count: u32,
+    capacity: u32,
+    selected: [u32; 2],
+}
+
+/// `ShaderDebugRecord` in `shader_debug.wgsl`
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Record {
+    tag: u32,
+    kind: u32,
+    pos: [u32; 2],
+    value: [u32; 4],
+}
+
+//
+
+impl ShaderDebug {
+    const TEMPLATE: &'static str = include_str!("./shader_debug.wgsl");
+    const WORDS: usize = size_of::<Record>() / 4;
+    const HEADER_WORDS: usize = size_of::<Header>() / 4;
+
+    /// room for `capacity` prints a frame, the bind group is visible to `stages`,
+    /// fragment shaders can only write it where the downlevel flags allow
+    /// (not on WebGL2)
+    pub fn new(device: &Device, capacity: u32, stages: ShaderStages) -> Self {
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("shader debug"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: stages,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let words = Self::HEADER_WORDS + capacity.max(1) as usize * Self::WORDS;
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("shader debug"),
+            size: (words * 4) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("shader debug"),
+            layout: &layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            layout,
+            bind_group,
+            buffer,
+            capacity: capacity.max(1),
+            selected: None,
+            readback: Readback::new(device, words, "shader debug"),
+            names: BTreeMap::new(),
+        }
+    }
+
+    /// the WGSL to prepend to a shader that prints, with the buffer in bind group `group`
+    pub fn wgsl(group: u32) -> String {
+        Self::TEMPLATE.replace("{group}", &group.to_string())
+    }
+
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// only print for the pixel or thread at `pos`, `None` prints everywhere
+    pub fn select(&mut self, pos: Option<UVec2>) {
+        self.selected = pos;
+    }
+
+    /// a name to log the prints with `tag` under
+    pub fn name(&mut self, tag: u32, name: impl Into<String>) {
+        self.names.insert(tag, name.into());
+    }
+
+    /// empty the buffer, before the passes that print
+    pub fn begin(&self, queue: &Queue) {
+        let header = Header {
+            count: 0,
+            capacity: self.capacity,
+            selected: self.selected.map_or([u32::MAX; 2], |pos| pos.to_array()),
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&header));
+    }
+
+    /// record the readback, after the passes that print
+    pub fn end(&mut self, encoder: &mut CommandEncoder) {
+        self.readback.copy_from(encoder, &self.buffer, 0);
+    }
+
+    /// call after submitting the encoder of [`Self::end`]
+    pub fn submitted(&mut self) {
+        self.readback.submitted();
+    }
+
+    /// the prints of the newest finished frame, in no particular order
+    pub fn poll(&mut self) -> Option<Vec<DebugRecord>> {
+        let words = self.readback.poll()?;
+        let header: Header =
+            bytemuck::pod_read_unaligned(bytemuck::cast_slice(&words[..Self::HEADER_WORDS]));
+        if header.count > self.capacity {
+            tracing::warn!(
+                "{} shader prints dropped, the debug buffer holds {}",
+                header.count - self.capacity,
+                self.capacity
+            );
+        }
+
+        let records: &[Record] = bytemuck::cast_slice(&words[Self::HEADER_WORDS..]);
+        Some(
+            records[..header.count.min(self.capacity) as usize]
+                .iter()
+                .map(|record| DebugRecord {
+                    tag: record.tag,
+                    pos: record.pos.into(),
+                    value: match record.kind {
+                        0 => DebugValue::F32(Vec4::from_array(bytemuck::cast(record.value))),
+                        2 => DebugValue::I32(IVec4::from_array(bytemuck::cast(record.value))),
+                        _ => DebugValue::U32(record.value.into()),
+                    },
+                })
+                .collect(),
+        )
+    }
+
+    /// print the records to the console, sorted by position and tag
+    pub fn log(&self, records: &[DebugRecord]) {
+        let mut records = records.to_vec();
+        records.sort_by_key(|record| (record.pos.y, record.pos.x, record.tag));
+        for record in records {
+            match self.names.get(&record.tag) {
+                Some(name) => tracing::info!(
+                    "shader {name} at {} {}: {}",
+                    record.pos.x,
+                    record.pos.y,
+                    record.value
+                ),
+                None => tracing::info!("shader {record}"),
+            }
+        }
+    }
+}
+
+impl fmt::Display for DebugRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} at {} {}: {}",
+            self.tag, self.pos.x, self.pos.y, self.value
+        )
+    }
+}
+
+impl fmt::Display for DebugValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugValue::F32(v) => write!(f, "[{}, {}, {}, {}]", v.x, v.y, v.z, v.w),
+            DebugValue::U32(v) => write!(f, "[{}u, {}u, {}u, {}u]", v.x, v.y, v.z, v.w),
+            DebugValue::I32(v) => write!(f, "[{}i, {}i, {}i, {}i]", v.x, v.y, v.z, v.w),
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::graphics::test_util::test_device;
+
+    #[test]
+    fn prints_from_a_compute_shader() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+
+        let mut debug = ShaderDebug::new(&device, 3, ShaderStages::COMPUTE);
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Owned(
+                ShaderDebug::wgsl(0)
+                    + "
+                    @compute @workgroup_size(4)
+                    fn main(@builtin(local_invocation_id) id: vec3<u32>) {
+                        let pos = vec2(id.x, 0u);
+                        debug_f32(1u, pos, vec4(f32(id.x) * 0.5, -1.0, 0.0, 1.0));
+                        debug_i32(2u, pos, vec4(-i32(id.x)));
+                    }
+                    ",
+            )),
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[debug.layout()],
+                push_constant_ranges: &[],
+            })),
+            module: &module,
+            entry_point: "main",
+        });
+
+        let run = |debug: &mut ShaderDebug| {
+            debug.begin(&queue);
+            let mut encoder = device.create_command_encoder(&<_>::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&<_>::default());
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, debug.bind_group(), &[]);
+                pass.dispatch_workgroups(1, 1, 1);
+            }
+            debug.end(&mut encoder);
+            queue.submit([encoder.finish()]);
+            debug.submitted();
+            device.poll(Maintain::Wait);
+            debug.poll().unwrap()
+        };
+
+        debug.select(Some(UVec2::new(3, 0)));
+        let mut records = run(&mut debug);
+        records.sort_by_key(|record| record.tag);
+        assert_eq!(
+            records,
+            [
+                DebugRecord {
+                    tag: 1,
+                    pos: UVec2::new(3, 0),
+                    value: DebugValue::F32(Vec4::new(1.5, -1.0, 0.0, 1.0)),
+                },
+                DebugRecord {
+                    tag: 2,
+                    pos: UVec2::new(3, 0),
+                    value: DebugValue::I32(IVec4::splat(-3)),
+                },
+            ]
+        );
+        assert_eq!(records[0].to_string(), "#1 at 3 0: [1.5, -1, 0, 1]");
+
+        // 8 prints, room for 3
+        debug.select(None);
+        assert_eq!(run(&mut debug).len(), 3);
+    }
+}
@@ -0,0 +1,49 @@
+// printf for shaders: values appended to a storage buffer that's read back
+// and logged, prepended to the shaders that use it with `{group}` replaced
+
+struct ShaderDebugRecord {
+    tag: u32,
+    // 0 f32, 1 u32, 2 i32
+    kind: u32,
+    pos: vec2<u32>,
+    value: vec4<u32>,
+};
+
+struct ShaderDebug {
+    count: atomic<u32>,
+    capacity: u32,
+    // 0xffffffff in x prints everywhere
+    selected: vec2<u32>,
+    records: array<ShaderDebugRecord>,
+};
+
+@group({group}) @binding(0)
+var<storage, read_write> shader_debug: ShaderDebug;
+
+// if `pos` is the pixel or thread picked to print
+fn debug_selected(pos: vec2<u32>) -> bool {
+    return shader_debug.selected.x == 0xffffffffu || all(pos == shader_debug.selected);
+}
+
+fn debug_push(tag: u32, kind: u32, pos: vec2<u32>, value: vec4<u32>) {
+    if !debug_selected(pos) {
+        return;
+    }
+    let i = atomicAdd(&shader_debug.count, 1u);
+    if i < shader_debug.capacity {
+        shader_debug.records[i] = ShaderDebugRecord(tag, kind, pos, value);
+    }
+}
+
+// `tag` tells the prints apart, WGSL has no strings
+fn debug_f32(tag: u32, pos: vec2<u32>, value: vec4<f32>) {
+    debug_push(tag, 0u, pos, bitcast<vec4<u32>>(value));
+}
+
+fn debug_u32(tag: u32, pos: vec2<u32>, value: vec4<u32>) {
+    debug_push(tag, 1u, pos, value);
+}
+
+fn debug_i32(tag: u32, pos: vec2<u32>, value: vec4<i32>) {
+    debug_push(tag, 2u, pos, bitcast<vec4<u32>>(value));
+}
@@ -0,0 +1,166 @@
+use wgpu::*;
+
+use super::post::PostProcess;
+
+//
+
+/// receives the final frame every frame without a readback,
+/// texture sharing outputs like Spout or Syphon are sinks
+pub trait FrameSink: Send {
+    fn name(&self) -> &str;
+
+    /// called after the frame is submitted, `texture` holds the final frame
+    /// in the surface format until the next frame
+    fn publish(&mut self, device: &Device, queue: &Queue, texture: &Texture);
+}
+
+/// a copy of the final frame in a texture other applications and the [`FrameSink`]s
+/// can read on the GPU
+///
+/// the final pass runs once more into it, the surface textures themselves
+/// can only be rendered to
+#[derive(Default)]
+pub struct FrameShare {
+    texture: Option<Texture>,
+    sinks: Vec<Box<dyn FrameSink>>,
+}
+
+//
+
+impl FrameShare {
+    pub const USAGE: TextureUsages = TextureUsages::RENDER_ATTACHMENT
+        .union(TextureUsages::TEXTURE_BINDING)
+        .union(TextureUsages::COPY_SRC);
+
+    pub fn add(&mut self, sink: Box<dyn FrameSink>) {
+        tracing::info!("sharing frames with {}", sink.name());
+        self.sinks.push(sink);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// the final pass into the shared texture, the same way it goes into the surface
+    pub fn blit(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        post: &PostProcess,
+        format: TextureFormat,
+        (width, height): (u32, u32),
+        viewport: Option<[f32; 4]>,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+
+        let stale = self.texture.as_ref().is_none_or(|texture| {
+            (texture.width(), texture.height(), texture.format()) != (width, height, format)
+        });
+        if stale {
+            self.texture = Some(device.create_texture(&TextureDescriptor {
+                label: Some("shared frame"),
+                size: Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: Self::USAGE,
+                view_formats: &[],
+            }));
+        }
+
+        let texture = self.texture.as_ref().unwrap();
+        post.blit(
+            encoder,
+            &texture.create_view(&TextureViewDescriptor::default()),
+            viewport,
+        );
+    }
+
+    /// hand the submitted frame to every sink
+    pub fn publish(&mut self, device: &Device, queue: &Queue) {
+        let Some(texture) = self.texture.as_ref() else {
+            return;
+        };
+        for sink in self.sinks.iter_mut() {
+            sink.publish(device, queue, texture);
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::graphics::{capture::CapturedImage, post::PostUniforms, test_util::test_device};
+
+    struct Readback(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl FrameSink for Readback {
+        fn name(&self) -> &str {
+            "test readback"
+        }
+
+        fn publish(&mut self, device: &Device, queue: &Queue, texture: &Texture) {
+            let image = CapturedImage::read(device, queue, texture).unwrap();
+            self.0.lock().unwrap().push(image.rgba);
+        }
+    }
+
+    #[test]
+    fn sinks_get_the_final_frame() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let format = TextureFormat::Bgra8UnormSrgb;
+        let size = (4, 2);
+
+        let mut post = PostProcess::new(&device, format, false, FilterMode::Nearest);
+        post.prepare(
+            &device,
+            &queue,
+            size,
+            &PostUniforms {
+                srgb_output: 1,
+                ..PostUniforms::default()
+            },
+        );
+
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let mut share = FrameShare::default();
+        share.add(Box::new(Readback(frames.clone())));
+
+        for _ in 0..2 {
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+            encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &post.scene().unwrap().view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLUE),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            share.blit(&device, &mut encoder, &post, format, size, None);
+            queue.submit([encoder.finish()]);
+            share.publish(&device, &queue);
+        }
+
+        let frames = frames.lock().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].len(), 4 * 2 * 4);
+        assert_eq!(frames[1][..4], [0, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,398 @@
+use std::{borrow::Cow, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec4};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    *,
+};
+
+use super::workgroup::WorkgroupSizes;
+
+//
+
+/// skinning and blend shapes in a compute shader
+///
+/// the deformed vertices are written to a buffer once per frame,
+/// the shadow and main passes draw the same result as a plain vertex buffer
+pub struct SkinningPipeline {
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+    workgroups: WorkgroupSizes,
+}
+
+/// GPU side of one skinned mesh
+pub struct SkinnedMesh {
+    vertex_count: u32,
+    morph_count: u32,
+    joint_count: u32,
+    joints: Buffer,
+    morph_weights: Buffer,
+    deformed: Buffer,
+    bind_group: BindGroup,
+}
+
+/// rest pose vertex, blend shape delta and deformed vertex
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct SkinVertex {
+    /// w is 1 for positions, 0 for deltas
+    pub position: Vec4,
+    pub normal: Vec4,
+}
+
+/// up to four joints per vertex, the weights add up to 1
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct Influence {
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Params {
+    vertex_count: u32,
+    morph_count: u32,
+    _pad: [u32; 2],
+}
+
+//
+
+impl SkinningPipeline {
+    pub fn new(device: &Device, workgroups: &WorkgroupSizes) -> Self {
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("skinning"),
+            source: ShaderSource::Wgsl(Cow::Owned(
+                workgroups.specialize_linear(include_str!("./skinning.wgsl")),
+            )),
+        });
+
+        let storage = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("skinning"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, true),
+                storage(4, true),
+                storage(5, true),
+                storage(6, false),
+            ],
+        });
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("skinning"),
+            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("skinning"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            })),
+            module: &module,
+            entry_point: "skin",
+        });
+
+        Self {
+            layout,
+            pipeline,
+            workgroups: *workgroups,
+        }
+    }
+
+    /// deform every mesh in one compute pass
+    pub fn dispatch(&self, encoder: &mut CommandEncoder, meshes: &[&SkinnedMesh]) {
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("skinning"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        for mesh in meshes {
+            pass.set_bind_group(0, &mesh.bind_group, &[]);
+            pass.dispatch_workgroups(self.workgroups.linear_groups(mesh.vertex_count), 1, 1);
+        }
+    }
+}
+
+impl SkinnedMesh {
+    /// `morphs` has one delta per vertex for each blend shape
+    pub fn new(
+        device: &Device,
+        pipeline: &SkinningPipeline,
+        rest: &[SkinVertex],
+        influences: &[Influence],
+        morphs: &[Vec<SkinVertex>],
+        joint_count: u32,
+    ) -> Self {
+        assert_eq!(rest.len(), influences.len());
+        assert!(morphs.iter().all(|morph| morph.len() == rest.len()));
+        assert!(influences
+            .iter()
+            .all(|i| i.joints.iter().all(|&j| j < joint_count.max(1))));
+
+        let vertex_count = rest.len() as u32;
+        let morph_count = morphs.len() as u32;
+
+        // storage buffers can't be empty
+        let init = |label, contents: &[u8], usage| {
+            let padding = [0u8; size_of::<SkinVertex>()];
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(label),
+                contents: if contents.is_empty() {
+                    &padding
+                } else {
+                    contents
+                },
+                usage,
+            })
+        };
+        let deltas: Vec<SkinVertex> = morphs.concat();
+
+        let params = init(
+            "skinning params",
+            bytemuck::bytes_of(&Params {
+                vertex_count,
+                morph_count,
+                _pad: [0; 2],
+            }),
+            BufferUsages::UNIFORM,
+        );
+        let rest = init(
+            "rest pose",
+            bytemuck::cast_slice(rest),
+            BufferUsages::STORAGE,
+        );
+        let influences = init(
+            "joint influences",
+            bytemuck::cast_slice(influences),
+            BufferUsages::STORAGE,
+        );
+        let deltas = init(
+            "blend shapes",
+            bytemuck::cast_slice(&deltas),
+            BufferUsages::STORAGE,
+        );
+        let morph_weights = init(
+            "blend shape weights",
+            bytemuck::cast_slice(&vec![0.0f32; morph_count as usize]),
+            BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        );
+        let joints = init(
+            "joints",
+            bytemuck::cast_slice(&vec![Mat4::IDENTITY; joint_count.max(1) as usize]),
+            BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        );
+        let deformed = device.create_buffer(&BufferDescriptor {
+            label: Some("deformed vertices"),
+            size: (vertex_count.max(1) as usize * size_of::<SkinVertex>()) as _,
+            usage: BufferUsages::STORAGE | BufferUsages::VERTEX | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let buffers = [
+            &params,
+            &rest,
+            &influences,
+            &deltas,
+            &morph_weights,
+            &joints,
+            &deformed,
+        ];
+        let entries: Vec<BindGroupEntry> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("skinning"),
+            layout: &pipeline.layout,
+            entries: &entries,
+        });
+
+        Self {
+            vertex_count,
+            morph_count,
+            joint_count,
+            joints,
+            morph_weights,
+            deformed,
+            bind_group,
+        }
+    }
+
+    /// joint matrices (model space, with the inverse bind matrices applied)
+    /// and blend shape weights for the next dispatch
+    pub fn set_pose(&self, queue: &Queue, joints: &[Mat4], morph_weights: &[f32]) {
+        assert!(joints.len() <= self.joint_count as usize);
+        assert!(morph_weights.len() <= self.morph_count as usize);
+        if !joints.is_empty() {
+            queue.write_buffer(&self.joints, 0, bytemuck::cast_slice(joints));
+        }
+        if !morph_weights.is_empty() {
+            queue.write_buffer(&self.morph_weights, 0, bytemuck::cast_slice(morph_weights));
+        }
+    }
+
+    /// [`SkinVertex`] vertex buffer, valid after the dispatch
+    pub fn deformed(&self) -> &Buffer {
+        &self.deformed
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+}
+
+impl SkinVertex {
+    pub const ATTRIBUTES: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+
+    pub fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as _,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// the same deformation on the CPU, for devices without compute shaders
+pub fn skin_cpu(
+    rest: &[SkinVertex],
+    influences: &[Influence],
+    morphs: &[Vec<SkinVertex>],
+    morph_weights: &[f32],
+    joints: &[Mat4],
+) -> Vec<SkinVertex> {
+    rest.iter()
+        .zip(influences)
+        .enumerate()
+        .map(|(i, (vertex, influence))| {
+            let (mut position, mut normal) = (vertex.position.truncate(), vertex.normal.truncate());
+            for (morph, &weight) in morphs.iter().zip(morph_weights) {
+                if weight != 0.0 {
+                    position += morph[i].position.truncate() * weight;
+                    normal += morph[i].normal.truncate() * weight;
+                }
+            }
+
+            let skin = influence
+                .joints
+                .iter()
+                .zip(influence.weights)
+                .fold(Mat4::ZERO, |skin, (&joint, weight)| {
+                    skin + joints[joint as usize] * weight
+                });
+
+            SkinVertex {
+                position: skin.transform_point3(position).extend(1.0),
+                normal: skin
+                    .transform_vector3(normal)
+                    .normalize_or_zero()
+                    .extend(0.0),
+            }
+        })
+        .collect()
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+    use crate::graphics::test_util::{read_buffer, test_device};
+
+    fn mesh() -> (Vec<SkinVertex>, Vec<Influence>, Vec<Vec<SkinVertex>>) {
+        let rest: Vec<SkinVertex> = (0..100)
+            .map(|i| SkinVertex {
+                position: Vec4::new(i as f32 * 0.1, 1.0, 0.0, 1.0),
+                normal: Vec4::new(0.0, 1.0, 0.0, 0.0),
+            })
+            .collect();
+        let influences = (0..100)
+            .map(|i| {
+                let t = i as f32 / 99.0;
+                Influence {
+                    joints: [0, 1, 0, 0],
+                    weights: [1.0 - t, t, 0.0, 0.0],
+                }
+            })
+            .collect();
+        let morph = rest
+            .iter()
+            .map(|v| SkinVertex {
+                position: Vec4::new(0.0, v.position.x * 0.5, 0.0, 0.0),
+                normal: Vec4::ZERO,
+            })
+            .collect();
+        (rest, influences, vec![morph])
+    }
+
+    #[test]
+    fn gpu_matches_cpu() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        if device.limits().max_compute_workgroups_per_dimension == 0 {
+            return;
+        }
+
+        let (rest, influences, morphs) = mesh();
+        let joints = [
+            Mat4::IDENTITY,
+            Mat4::from_rotation_translation(Quat::from_rotation_z(0.7), Vec3::new(0.0, 2.0, 0.0)),
+        ];
+        let weights = [0.6];
+
+        // not the size the shader was written with
+        let workgroups = WorkgroupSizes {
+            linear: 32,
+            ..WorkgroupSizes::default()
+        };
+        let pipeline = SkinningPipeline::new(&device, &workgroups);
+        let mesh = SkinnedMesh::new(&device, &pipeline, &rest, &influences, &morphs, 2);
+        mesh.set_pose(&queue, &joints, &weights);
+
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: mesh.deformed().size(),
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&<_>::default());
+        pipeline.dispatch(&mut encoder, &[&mesh]);
+        encoder.copy_buffer_to_buffer(mesh.deformed(), 0, &readback, 0, readback.size());
+        queue.submit([encoder.finish()]);
+
+        let gpu: Vec<SkinVertex> = read_buffer(&device, &readback);
+        let cpu = skin_cpu(&rest, &influences, &morphs, &weights, &joints);
+        for (gpu, cpu) in gpu.iter().zip(&cpu) {
+            assert!(
+                gpu.position.abs_diff_eq(cpu.position, 1e-4),
+                "{gpu:?} {cpu:?}"
+            );
+            assert!(gpu.normal.abs_diff_eq(cpu.normal, 1e-4), "{gpu:?} {cpu:?}");
+        }
+    }
+}
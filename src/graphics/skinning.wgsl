@@ -0,0 +1,55 @@
+struct SkinVertex {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+};
+
+struct Influence {
+    joints: vec4<u32>,
+    weights: vec4<f32>,
+};
+
+struct Params {
+    vertex_count: u32,
+    morph_count: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> rest: array<SkinVertex>;
+@group(0) @binding(2) var<storage, read> influences: array<Influence>;
+// target t of vertex i is at t * vertex_count + i
+@group(0) @binding(3) var<storage, read> morphs: array<SkinVertex>;
+@group(0) @binding(4) var<storage, read> morph_weights: array<f32>;
+@group(0) @binding(5) var<storage, read> joints: array<mat4x4<f32>>;
+@group(0) @binding(6) var<storage, read_write> deformed: array<SkinVertex>;
+
+@compute @workgroup_size({workgroup_size})
+fn skin(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.vertex_count {
+        return;
+    }
+
+    // blend shapes first, in the rest pose
+    var position = rest[i].position.xyz;
+    var normal = rest[i].normal.xyz;
+    for (var t = 0u; t < params.morph_count; t++) {
+        let weight = morph_weights[t];
+        if weight != 0.0 {
+            let delta = morphs[t * params.vertex_count + i];
+            position += delta.position.xyz * weight;
+            normal += delta.normal.xyz * weight;
+        }
+    }
+
+    // linear blend skinning
+    let influence = influences[i];
+    let skin = joints[influence.joints.x] * influence.weights.x
+        + joints[influence.joints.y] * influence.weights.y
+        + joints[influence.joints.z] * influence.weights.z
+        + joints[influence.joints.w] * influence.weights.w;
+
+    deformed[i].position = vec4<f32>((skin * vec4<f32>(position, 1.0)).xyz, 1.0);
+    // exact for rigid joints, close enough with a little non-uniform scale
+    let n = (skin * vec4<f32>(normal, 0.0)).xyz;
+    deformed[i].normal = vec4<f32>(n * inverseSqrt(max(dot(n, n), 1e-12)), 0.0);
+}
@@ -0,0 +1,300 @@
+use wgpu::{
+    ColorWrites, CompareFunction, DepthStencilState, Features, LoadOp, Operations,
+    StencilFaceState, StencilOperation, StencilState, TextureFormat,
+};
+
+use crate::camera::DepthMode;
+
+//
+
+/// how a pipeline uses the stencil buffer of the scene target
+///
+/// masking takes two pipelines of the same pass: one with [`StencilMode::Write`]
+/// draws the mask shape, one with [`StencilMode::Inside`] or [`StencilMode::Outside`]
+/// draws the masked content, both with the same `RenderPass::set_stencil_reference`;
+/// UI clipping and portals draw inside, outlines draw a scaled up copy outside
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StencilMode {
+    #[default]
+    Off,
+    /// write the reference where the draw covers, without colors
+    Write,
+    /// increment where the draw covers, without colors, nested clips test
+    /// `Inside` with the nesting depth as the reference
+    Increment,
+    /// draw only where the stencil equals the reference
+    Inside,
+    /// draw only where the stencil doesn't equal the reference
+    Outside,
+}
+
+/// the depth stencil state of a pipeline drawing into the scene target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthStencil {
+    pub format: TextureFormat,
+    /// `None` ignores depth
+    pub depth: Option<DepthMode>,
+    pub depth_write: bool,
+    pub stencil: StencilMode,
+}
+
+//
+
+/// the scene depth buffer, 32 bit float depth when the device has it,
+/// the stencil is 8 bits either way
+pub fn depth_stencil_format(features: Features) -> TextureFormat {
+    if features.contains(Features::DEPTH32FLOAT_STENCIL8) {
+        TextureFormat::Depth32FloatStencil8
+    } else {
+        TextureFormat::Depth24PlusStencil8
+    }
+}
+
+/// stencil ops of the scene pass, cleared to 0 for every frame
+pub fn stencil_clear_ops() -> Operations<u32> {
+    Operations {
+        load: LoadOp::Clear(0),
+        store: true,
+    }
+}
+
+impl StencilMode {
+    pub fn to_stencil_state(self) -> StencilState {
+        let face = |compare, pass_op| StencilFaceState {
+            compare,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op,
+        };
+        let face = match self {
+            Self::Off => return StencilState::default(),
+            Self::Write => face(CompareFunction::Always, StencilOperation::Replace),
+            Self::Increment => face(CompareFunction::Always, StencilOperation::IncrementClamp),
+            Self::Inside => face(CompareFunction::Equal, StencilOperation::Keep),
+            Self::Outside => face(CompareFunction::NotEqual, StencilOperation::Keep),
+        };
+        StencilState {
+            front: face,
+            back: face,
+            read_mask: 0xff,
+            write_mask: if self.writes() { 0xff } else { 0 },
+        }
+    }
+
+    /// mask shapes only write the stencil
+    pub fn color_writes(self) -> ColorWrites {
+        if self.writes() {
+            ColorWrites::empty()
+        } else {
+            ColorWrites::ALL
+        }
+    }
+
+    fn writes(self) -> bool {
+        matches!(self, Self::Write | Self::Increment)
+    }
+}
+
+impl DepthStencil {
+    /// depth test and write with `depth`, no stencil
+    pub fn new(format: TextureFormat, depth: Option<DepthMode>) -> Self {
+        Self {
+            format,
+            depth,
+            depth_write: depth.is_some(),
+            stencil: StencilMode::Off,
+        }
+    }
+
+    pub fn with_stencil(self, stencil: StencilMode) -> Self {
+        Self { stencil, ..self }
+    }
+
+    /// test depth without writing it, for overlays and mask shapes
+    pub fn read_only(self) -> Self {
+        Self {
+            depth_write: false,
+            ..self
+        }
+    }
+
+    pub fn to_state(self) -> DepthStencilState {
+        DepthStencilState {
+            format: self.format,
+            depth_write_enabled: self.depth_write && self.depth.is_some(),
+            depth_compare: self
+                .depth
+                .map_or(CompareFunction::Always, |depth| depth.compare()),
+            stencil: self.stencil.to_stencil_state(),
+            bias: <_>::default(),
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use wgpu::*;
+
+    use super::*;
+    use crate::graphics::{capture::CapturedImage, test_util::test_device};
+
+    const SHADER: &str = "
+        fn quad(i: u32, left: f32) -> vec4<f32> {
+            let uv = vec2<f32>(f32(i & 1u), f32(i >> 1u));
+            return vec4<f32>(mix(left, 1.0, uv.x), uv.y * 2.0 - 1.0, 0.5, 1.0);
+        }
+
+        @vertex
+        fn vs_full(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+            return quad(i, -1.0);
+        }
+
+        // the right half
+        @vertex
+        fn vs_half(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+            return quad(i, 0.0);
+        }
+
+        @fragment
+        fn fs_red() -> @location(0) vec4<f32> {
+            return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+        }
+
+        @fragment
+        fn fs_green() -> @location(0) vec4<f32> {
+            return vec4<f32>(0.0, 1.0, 0.0, 1.0);
+        }
+    ";
+
+    #[test]
+    fn states() {
+        assert_eq!(StencilMode::Off.to_stencil_state(), StencilState::default());
+        let inside = StencilMode::Inside.to_stencil_state();
+        assert!(inside.is_enabled());
+        assert_eq!(inside.write_mask, 0);
+        assert_eq!(inside.front.compare, CompareFunction::Equal);
+        assert_eq!(StencilMode::Write.color_writes(), ColorWrites::empty());
+
+        let state = DepthStencil::new(TextureFormat::Depth24PlusStencil8, None)
+            .with_stencil(StencilMode::Write)
+            .to_state();
+        assert!(!state.depth_write_enabled);
+        assert_eq!(state.depth_compare, CompareFunction::Always);
+        assert_eq!(state.stencil.write_mask, 0xff);
+    }
+
+    /// a mask over the right half, then red inside and green outside of it
+    #[test]
+    fn mask_then_masked_draws() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        let (width, height) = (8, 4);
+        let format = TextureFormat::Rgba8Unorm;
+        let depth_format = depth_stencil_format(device.features());
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let pipeline = |stencil: StencilMode, vertex, fragment| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: None,
+                layout: None,
+                vertex: VertexState {
+                    module: &module,
+                    entry_point: vertex,
+                    buffers: &[],
+                },
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleStrip,
+                    ..<_>::default()
+                },
+                depth_stencil: Some(
+                    DepthStencil::new(depth_format, None)
+                        .with_stencil(stencil)
+                        .to_state(),
+                ),
+                multisample: <_>::default(),
+                fragment: Some(FragmentState {
+                    module: &module,
+                    entry_point: fragment,
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: stencil.color_writes(),
+                    })],
+                }),
+                multiview: None,
+            })
+        };
+        let mask = pipeline(StencilMode::Write, "vs_half", "fs_red");
+        let inside = pipeline(StencilMode::Inside, "vs_full", "fs_red");
+        let outside = pipeline(StencilMode::Outside, "vs_full", "fs_green");
+
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = |format, usage| {
+            device.create_texture(&TextureDescriptor {
+                label: None,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let color = texture(
+            format,
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        );
+        let depth = texture(depth_format, TextureUsages::RENDER_ATTACHMENT);
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        {
+            let color_view = color.create_view(&TextureViewDescriptor::default());
+            let depth_view = depth.create_view(&TextureViewDescriptor::default());
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &color_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: None,
+                    stencil_ops: Some(stencil_clear_ops()),
+                }),
+            });
+            pass.set_stencil_reference(1);
+            pass.set_pipeline(&mask);
+            pass.draw(0..4, 0..1);
+            pass.set_pipeline(&inside);
+            pass.draw(0..4, 0..1);
+            pass.set_pipeline(&outside);
+            pass.draw(0..4, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+
+        let image = CapturedImage::read(&device, &queue, &color).unwrap();
+        let pixel = |x: u32| {
+            let i = ((height / 2 * width + x) * 4) as usize;
+            [image.rgba[i], image.rgba[i + 1], image.rgba[i + 2]]
+        };
+        assert_eq!(pixel(1), [0, 255, 0]);
+        assert_eq!(pixel(width - 2), [255, 0, 0]);
+    }
+}
@@ -0,0 +1,197 @@
+use std::mem::size_of;
+
+use anyhow::{anyhow, Result};
+use wgpu::{DownlevelCapabilities, DownlevelFlags, Features, Limits, TextureFormat};
+
+use super::{stencil::depth_stencil_format, PushConstant};
+
+//
+
+/// the smallest 2D texture limit the renderer runs with,
+/// `Limits::downlevel_webgl2_defaults` has 2048
+pub const MIN_TEXTURE_SIZE: u32 = 2048;
+
+/// which optional parts of the renderer the device can run
+///
+/// everything else is required and checked by [`Support::new`],
+/// subsystems check their flag and fall back or turn themselves off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Support {
+    /// the scene shader gets its per draw data as push constants,
+    /// otherwise from a uniform buffer
+    pub push_constants: bool,
+    /// [`super::compute::ComputeStream`] accepts work
+    pub compute: bool,
+    /// 32 bit float depth next to the stencil, otherwise `Depth24PlusStencil8`
+    pub depth32_stencil: bool,
+    /// largest scene target, limits the pixel art resolution
+    pub max_texture_size: u32,
+}
+
+//
+
+impl Support {
+    pub fn new(
+        features: Features,
+        limits: &Limits,
+        downlevel: &DownlevelCapabilities,
+    ) -> Result<Self> {
+        if limits.max_texture_dimension_2d < MIN_TEXTURE_SIZE {
+            return Err(anyhow!(
+                "the GPU supports textures up to {}, {MIN_TEXTURE_SIZE} is needed",
+                limits.max_texture_dimension_2d
+            ));
+        }
+        // the post process bind group
+        if limits.max_bind_groups < 1 || limits.max_sampled_textures_per_shader_stage < 2 {
+            return Err(anyhow!("the GPU doesn't support enough bound textures"));
+        }
+        if limits.max_vertex_attributes < 2 {
+            return Err(anyhow!("the GPU doesn't support enough vertex attributes"));
+        }
+
+        Ok(Self {
+            push_constants: features.contains(Features::PUSH_CONSTANTS)
+                && limits.max_push_constant_size as usize >= size_of::<PushConstant>(),
+            compute: downlevel.flags.contains(DownlevelFlags::COMPUTE_SHADERS)
+                && limits.max_compute_workgroups_per_dimension != 0
+                && limits.max_storage_buffers_per_shader_stage != 0,
+            depth32_stencil: features.contains(Features::DEPTH32FLOAT_STENCIL8),
+            max_texture_size: limits.max_texture_dimension_2d,
+        })
+    }
+
+    /// the features to request, the optional ones the adapter has
+    pub fn features(self) -> Features {
+        let mut features = Features::empty();
+        features.set(Features::PUSH_CONSTANTS, self.push_constants);
+        features.set(Features::DEPTH32FLOAT_STENCIL8, self.depth32_stencil);
+        features
+    }
+
+    /// the scene depth buffer format on a device with [`Self::features`]
+    pub fn depth_format(self) -> TextureFormat {
+        depth_stencil_format(self.features())
+    }
+
+    /// what runs in a reduced form, for the log
+    pub fn fallbacks(self) -> Vec<&'static str> {
+        let mut fallbacks = Vec::new();
+        if !self.push_constants {
+            fallbacks.push("uniform buffer instead of push constants");
+        }
+        if !self.compute {
+            fallbacks.push("no compute shaders");
+        }
+        if !self.depth32_stencil {
+            fallbacks.push("24 bit depth buffer");
+        }
+        fallbacks
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use wgpu::*;
+
+    use super::*;
+    use crate::{
+        camera::DepthMode,
+        graphics::{
+            post::PostProcess,
+            test_util::{block_on, test_device_with},
+            Graphics,
+        },
+    };
+
+    fn webgl2() -> (Limits, DownlevelCapabilities) {
+        let downlevel = DownlevelCapabilities {
+            flags: DownlevelFlags::empty(),
+            shader_model: ShaderModel::Sm2,
+            ..<_>::default()
+        };
+        (Limits::downlevel_webgl2_defaults(), downlevel)
+    }
+
+    fn downlevel() -> (Limits, DownlevelCapabilities) {
+        (
+            Limits::downlevel_defaults(),
+            DownlevelCapabilities::default(),
+        )
+    }
+
+    #[test]
+    fn webgl2_disables_optional_parts() {
+        let (limits, downlevel) = webgl2();
+        let support = Support::new(Features::empty(), &limits, &downlevel).unwrap();
+        assert!(!support.push_constants);
+        assert!(!support.compute);
+        assert_eq!(support.features(), Features::empty());
+        assert_eq!(support.fallbacks().len(), 3);
+    }
+
+    #[test]
+    fn downlevel_keeps_compute() {
+        let (limits, downlevel) = downlevel();
+        let support = Support::new(Features::empty(), &limits, &downlevel).unwrap();
+        assert!(!support.push_constants);
+        assert!(support.compute);
+    }
+
+    #[test]
+    fn push_constants_need_the_feature_and_the_limit() {
+        let (mut limits, downlevel) = downlevel();
+        let support = Support::new(Features::PUSH_CONSTANTS, &limits, &downlevel).unwrap();
+        assert!(!support.push_constants, "the downlevel limit is 0");
+
+        limits.max_push_constant_size = 128;
+        let support = Support::new(Features::PUSH_CONSTANTS, &limits, &downlevel).unwrap();
+        assert!(support.push_constants);
+        assert!(!support
+            .fallbacks()
+            .contains(&"uniform buffer instead of push constants"));
+
+        let support = Support::new(Features::empty(), &limits, &downlevel).unwrap();
+        assert!(!support.push_constants);
+    }
+
+    #[test]
+    fn too_small_textures_are_an_error() {
+        let (mut limits, downlevel) = webgl2();
+        limits.max_texture_dimension_2d = 1024;
+        assert!(Support::new(Features::empty(), &limits, &downlevel).is_err());
+    }
+
+    /// builds every pipeline against the downlevel limits on a real device,
+    /// skipped when there is no GPU or software adapter
+    #[test]
+    fn pipelines_build_with_downlevel_limits() {
+        for (limits, downlevel) in [webgl2(), downlevel()] {
+            let support = Support::new(Features::empty(), &limits, &downlevel).unwrap();
+            let Some((device, _queue)) = test_device_with(support.features(), |_| limits) else {
+                continue;
+            };
+
+            device.push_error_scope(ErrorFilter::Validation);
+            for indexed in [false, true] {
+                let post = PostProcess::new(
+                    &device,
+                    TextureFormat::Rgba8UnormSrgb,
+                    indexed,
+                    FilterMode::Linear,
+                );
+                Graphics::create_pipeline(
+                    &device,
+                    post.scene_format(),
+                    indexed,
+                    DepthMode::Reversed,
+                    support,
+                );
+            }
+            let error = block_on(device.pop_error_scope());
+            assert!(error.is_none(), "{error:?}");
+        }
+    }
+}
@@ -1,21 +1,28 @@
 use std::{
+    error, fmt,
     ops::{Deref, DerefMut},
     sync::Arc,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
 use wgpu::{
-    Adapter, CompositeAlphaMode, Device, Instance, PresentMode, SurfaceCapabilities,
-    SurfaceConfiguration, SurfaceError, SurfaceTexture, TextureFormat, TextureUsages,
+    Adapter, Backends, CompositeAlphaMode, CreateSurfaceError, Device, DownlevelFlags, Instance,
+    PresentMode, SurfaceCapabilities, SurfaceConfiguration, SurfaceError, SurfaceTexture,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
 };
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::settings::GraphicsSettings;
+use crate::{color::ColorSpace, settings::GraphicsSettings};
 
 //
 
 pub struct SurfaceBuilder {
     instance: Arc<Instance>,
+    /// the ones `instance` was created with, for the errors
+    backends: Backends,
 
     // in Rust, this one is dropped ...
     pub surface: wgpu::Surface,
@@ -28,44 +35,145 @@ pub struct Surface {
 
     inner: SurfaceBuilder,
     vsync: bool,
+    /// `graphics.advanced.present_mode`, if it's supported
+    present_mode: Option<PresentMode>,
     format: TextureFormat,
+    /// `format` and its sRGB or non-sRGB twin, where the platform allows it
+    view_formats: Vec<TextureFormat>,
+    color_space: ColorSpace,
+    size: (u32, u32),
+    /// render attachment, and copy source where the platform allows it
+    usage: TextureUsages,
+    /// the platform called the swapchain suboptimal or outdated,
+    /// whoever debounces the resizes reconfigures it
+    stale: bool,
 
     alpha_modes: Vec<CompositeAlphaMode>,
 }
 
+/// which view of a surface texture a pass draws through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewFlavor {
+    /// an sRGB view where there is one: shaders write linear colors and the
+    /// hardware encodes them, for the scene and compositing
+    Linear,
+    /// a non-sRGB view where there is one: written as is, for UI and images
+    /// that are sRGB encoded already
+    Encoded,
+}
+
+/// why a window didn't get a surface
+#[derive(Debug)]
+pub enum SurfaceCreationError {
+    /// the window or display handle can't be used,
+    /// like a window that was already destroyed
+    InvalidHandle {
+        platform: &'static str,
+        reason: &'static str,
+    },
+    /// none of the allowed backends could create a surface for the window
+    Rejected {
+        platform: &'static str,
+        backends: Backends,
+        source: CreateSurfaceError,
+    },
+}
+
 //
 
 impl SurfaceBuilder {
-    pub fn new(instance: Arc<Instance>, window: Arc<Window>) -> Result<Self> {
+    pub fn new(
+        instance: Arc<Instance>,
+        backends: Backends,
+        window: Arc<Window>,
+    ) -> Result<Self, SurfaceCreationError> {
+        let platform = validate_handles(window.raw_window_handle(), window.raw_display_handle())?;
+
         // SAFETY: safe as `window` is freed only after the surface is
-        // and the Arc makes sure the data the pointer points to is never moved
+        // and the Arc makes sure the data the pointer points to is never moved,
+        // the handles were checked to be non-null and from the same platform above
         //
         // look at the struct definition
-        let surface = unsafe { instance.create_surface(window.as_ref()) }?;
+        let surface = unsafe { instance.create_surface(window.as_ref()) }.map_err(|source| {
+            SurfaceCreationError::Rejected {
+                platform,
+                backends,
+                source,
+            }
+        })?;
 
         Ok(SurfaceBuilder {
             instance,
+            backends,
             surface,
             window,
         })
     }
 
-    pub fn build(self, settings: &GraphicsSettings, gpu: &Adapter, device: Arc<Device>) -> Surface {
+    /// an extended `color_space` needs a float format, it falls back to sRGB without one
+    pub fn build(
+        self,
+        settings: &GraphicsSettings,
+        gpu: &Adapter,
+        device: Arc<Device>,
+        color_space: ColorSpace,
+    ) -> Surface {
         let SurfaceCapabilities {
             formats,
             alpha_modes,
-            ..
-            // present_modes,
+            present_modes,
+            usages,
         } = self.surface.get_capabilities(gpu);
 
-        let format = *formats.first().expect("Surface is incompatible somehow");
+        assert!(!formats.is_empty(), "Surface is incompatible somehow");
+        let float = formats.contains(&TextureFormat::Rgba16Float);
+        let (format, color_space) = match color_space {
+            ColorSpace::ExtendedSrgb if float => (TextureFormat::Rgba16Float, color_space),
+            ColorSpace::ExtendedSrgb => {
+                tracing::warn!("{color_space:?} needs a float surface, supported: {formats:?}");
+                (formats[0], ColorSpace::Srgb)
+            }
+            _ => (formats[0], color_space),
+        };
+        // both flavors of 8 bit formats, float formats don't have an sRGB twin
+        let mut view_formats = vec![format];
+        if gpu
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::SURFACE_VIEW_FORMATS)
+        {
+            for twin in [format.add_srgb_suffix(), format.remove_srgb_suffix()] {
+                if !view_formats.contains(&twin) {
+                    view_formats.push(twin);
+                }
+            }
+        }
+        tracing::debug!("surface format {format:?} in {color_space:?}, views {view_formats:?}");
+
+        let present_mode = settings
+            .advanced
+            .present_mode
+            .map(|mode| mode.to_present_mode())
+            .filter(|mode| {
+                let supported = present_modes.contains(mode);
+                if !supported {
+                    tracing::warn!("{mode:?} is not supported, supported: {present_modes:?}");
+                }
+                supported
+            });
 
         let mut surface = Surface {
             device,
 
             inner: self,
             vsync: settings.vsync,
+            present_mode,
             format,
+            view_formats,
+            color_space,
+            size: (0, 0),
+            usage: surface_usage(usages),
+            stale: false,
 
             alpha_modes,
         };
@@ -80,23 +188,73 @@ impl Surface {
         self.format
     }
 
+    /// the format of [`Self::view`]s of `flavor`, the surface format
+    /// if the platform can't view it another way
+    pub fn view_format(&self, flavor: ViewFlavor) -> TextureFormat {
+        let srgb = flavor == ViewFlavor::Linear;
+        self.view_formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb() == srgb)
+            .unwrap_or(self.format)
+    }
+
+    /// a view of an acquired `texture` in the format of `flavor`
+    pub fn view(&self, texture: &SurfaceTexture, flavor: ViewFlavor) -> TextureView {
+        texture.texture.create_view(&TextureViewDescriptor {
+            format: Some(self.view_format(flavor)),
+            ..<_>::default()
+        })
+    }
+
+    /// what the final pass converts to, only [`ColorSpace::ExtendedSrgb`]
+    /// changes the format
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// acquired textures can be copied from, for captures of the presented frame
+    pub fn can_copy(&self) -> bool {
+        self.usage.contains(TextureUsages::COPY_SRC)
+    }
+
+    /// the size the swapchain was last configured to
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Mailbox counts as vsync, it doesn't tear
+    pub fn vsync(&self) -> bool {
+        match self.present_mode {
+            Some(mode) => mode != PresentMode::Immediate,
+            None => self.vsync,
+        }
+    }
+
+    /// reconfigures the swapchain with the other present mode,
+    /// replacing the one from the settings
+    pub fn set_vsync(&mut self, vsync: bool) {
+        if vsync != self.vsync || self.present_mode.is_some() {
+            self.vsync = vsync;
+            self.present_mode = None;
+            self.configure(Some(self.size));
+        }
+    }
+
     pub fn configure(&mut self, size: Option<(u32, u32)>) {
-        let present_mode = if self.vsync {
-            PresentMode::AutoVsync
-        } else {
-            PresentMode::AutoNoVsync
+        let present_mode = match self.present_mode {
+            Some(mode) => mode,
+            None if self.vsync => PresentMode::AutoVsync,
+            None => PresentMode::AutoNoVsync,
         };
 
-        /* let view_formats = if format.is_srgb() {
-            vec![format]
-        } else {
-            vec![format, format.add_srgb_suffix()]
-        }; */
-        let view_formats = vec![self.format];
+        let view_formats = self.view_formats.clone();
 
         let (width, height) = size.unwrap_or_else(|| {
             let PhysicalSize { width, height } = self.inner.window.inner_size();
-            (width, height)
+            // Wayland windows are 0x0 until the compositor configures them,
+            // the first `Resized` fixes it up
+            (width.max(1), height.max(1))
         });
 
         let mut alpha_mode = CompositeAlphaMode::Auto;
@@ -113,11 +271,13 @@ impl Surface {
         };
 
         // tracing::debug!("surface configured to {width}x{height}");
+        self.size = (width, height);
+        self.stale = false;
 
         self.inner.surface.configure(
             &self.device,
             &SurfaceConfiguration {
-                usage: TextureUsages::RENDER_ATTACHMENT,
+                usage: self.usage,
                 format: self.format,
                 width,
                 height,
@@ -128,29 +288,63 @@ impl Surface {
         );
     }
 
+    /// move the swapchain to another window, like one recreated with other
+    /// attributes, the old window's surface is dropped
+    ///
+    /// fails if the new surface can't use the same format,
+    /// everything that renders to it is built for that one
+    pub fn replace_window(&mut self, gpu: &Adapter, window: Arc<Window>) -> Result<()> {
+        let builder = SurfaceBuilder::new(self.instance.clone(), self.backends, window)?;
+        let capabilities = builder.surface.get_capabilities(gpu);
+        if !capabilities.formats.contains(&self.format) {
+            bail!(
+                "the new window doesn't support {:?}, only {:?}",
+                self.format,
+                capabilities.formats
+            );
+        }
+
+        self.inner = builder;
+        self.alpha_modes = capabilities.alpha_modes;
+        self.usage = surface_usage(capabilities.usages);
+        self.configure(None);
+        Ok(())
+    }
+
+    /// needs to be reconfigured, even if the window size didn't change
+    pub fn stale(&self) -> bool {
+        self.stale
+    }
+
     pub fn recreate(&mut self) -> Result<()> {
-        self.inner = SurfaceBuilder::new(self.instance.clone(), self.window.clone())?;
+        self.inner =
+            SurfaceBuilder::new(self.instance.clone(), self.backends, self.window.clone())?;
         self.configure(None);
 
         Ok(())
     }
 
+    /// blocks until there is a frame, reconfiguring an outdated swapchain
+    /// right away, for callers outside of the debounced frame loop
     pub fn acquire(&mut self) -> Result<SurfaceTexture> {
         loop {
             if let Some(texture) = self.try_acquire()? {
                 return Ok(texture);
             }
+            if self.stale {
+                self.configure(None);
+            }
         }
     }
 
+    /// suboptimal frames are still returned and outdated ones skipped,
+    /// both only mark the surface [`Self::stale`]
     pub fn try_acquire(&mut self) -> Result<Option<SurfaceTexture>> {
         match self.inner.surface.get_current_texture() {
             Ok(texture) => {
                 if texture.suboptimal {
                     // tracing::debug!("Surface suboptimal");
-                    drop(texture);
-                    self.configure(None);
-                    return Ok(None);
+                    self.stale = true;
                 }
 
                 Ok(Some(texture))
@@ -173,7 +367,7 @@ impl Surface {
 
             Err(SurfaceError::Outdated) => {
                 tracing::debug!("Surface outdated");
-                self.configure(None);
+                self.stale = true;
                 Ok(None)
             }
         }
@@ -193,3 +387,187 @@ impl DerefMut for Surface {
         &mut self.inner
     }
 }
+
+impl fmt::Display for SurfaceCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHandle { platform, reason } => {
+                write!(f, "invalid {platform} window handle: {reason}")
+            }
+            Self::Rejected {
+                platform,
+                backends,
+                source,
+            } => {
+                write!(
+                    f,
+                    "no backend of {backends:?} can render to the {platform} window: {source}"
+                )?;
+                if *platform == "Wayland" && !backends.contains(Backends::VULKAN) {
+                    f.write_str(" (GL on Wayland needs libwayland-egl, or try `force_x11`)")?;
+                } else if *backends != Backends::all() {
+                    f.write_str(" (more can be allowed in `[graphics.allowed_backends]`)")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for SurfaceCreationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidHandle { .. } => None,
+            Self::Rejected { source, .. } => Some(source),
+        }
+    }
+}
+
+//
+
+fn surface_usage(supported: TextureUsages) -> TextureUsages {
+    TextureUsages::RENDER_ATTACHMENT | (supported & TextureUsages::COPY_SRC)
+}
+
+/// check that the handles wgpu dereferences are there and belong together,
+/// returns the platform name
+fn validate_handles(
+    window: RawWindowHandle,
+    display: RawDisplayHandle,
+) -> Result<&'static str, SurfaceCreationError> {
+    let invalid = |platform, reason| Err(SurfaceCreationError::InvalidHandle { platform, reason });
+
+    match (window, display) {
+        (RawWindowHandle::Xlib(window), RawDisplayHandle::Xlib(display)) => {
+            if display.display.is_null() {
+                return invalid("Xlib", "no X display connection");
+            }
+            if window.window == 0 {
+                return invalid("Xlib", "the window was destroyed or never created");
+            }
+            Ok("Xlib")
+        }
+        (RawWindowHandle::Xcb(window), RawDisplayHandle::Xcb(display)) => {
+            if display.connection.is_null() {
+                return invalid("XCB", "no XCB connection");
+            }
+            if window.window == 0 {
+                return invalid("XCB", "the window was destroyed or never created");
+            }
+            Ok("XCB")
+        }
+        (RawWindowHandle::Wayland(window), RawDisplayHandle::Wayland(display)) => {
+            if display.display.is_null() {
+                return invalid("Wayland", "no wl_display");
+            }
+            if window.surface.is_null() {
+                return invalid("Wayland", "no wl_surface");
+            }
+            Ok("Wayland")
+        }
+        (RawWindowHandle::Win32(window), RawDisplayHandle::Windows(_)) => {
+            if window.hwnd.is_null() {
+                return invalid("Win32", "no HWND");
+            }
+            Ok("Win32")
+        }
+        (RawWindowHandle::AppKit(window), RawDisplayHandle::AppKit(_)) => {
+            if window.ns_view.is_null() {
+                return invalid("AppKit", "no NSView");
+            }
+            Ok("AppKit")
+        }
+        (RawWindowHandle::Web(window), RawDisplayHandle::Web(_)) => {
+            if window.id == 0 {
+                return invalid("web", "the canvas has no `data-raw-handle` id");
+            }
+            Ok("web")
+        }
+        (
+            RawWindowHandle::Xlib(_)
+            | RawWindowHandle::Xcb(_)
+            | RawWindowHandle::Wayland(_)
+            | RawWindowHandle::Win32(_)
+            | RawWindowHandle::AppKit(_)
+            | RawWindowHandle::Web(_),
+            _,
+        ) => invalid(
+            "mixed",
+            "the window and the display are from different platforms",
+        ),
+        // wgpu checks the rest itself
+        _ => Ok("native"),
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::NonNull;
+
+    use raw_window_handle::{
+        WaylandDisplayHandle, WaylandWindowHandle, XlibDisplayHandle, XlibWindowHandle,
+    };
+
+    use super::*;
+
+    #[test]
+    fn handles_are_validated() {
+        let display = NonNull::<u8>::dangling().as_ptr().cast();
+
+        let mut xlib_display = XlibDisplayHandle::empty();
+        let mut xlib_window = XlibWindowHandle::empty();
+        let validate =
+            |window, display| validate_handles(window, display).map_err(|e| e.to_string());
+
+        assert_eq!(
+            validate(
+                RawWindowHandle::Xlib(xlib_window),
+                RawDisplayHandle::Xlib(xlib_display)
+            ),
+            Err("invalid Xlib window handle: no X display connection".into())
+        );
+        xlib_display.display = display;
+        assert!(validate(
+            RawWindowHandle::Xlib(xlib_window),
+            RawDisplayHandle::Xlib(xlib_display)
+        )
+        .is_err());
+        xlib_window.window = 1;
+        assert_eq!(
+            validate(
+                RawWindowHandle::Xlib(xlib_window),
+                RawDisplayHandle::Xlib(xlib_display)
+            ),
+            Ok("Xlib")
+        );
+
+        let mut wayland_display = WaylandDisplayHandle::empty();
+        wayland_display.display = display;
+        let mut wayland_window = WaylandWindowHandle::empty();
+        assert_eq!(
+            validate(
+                RawWindowHandle::Wayland(wayland_window),
+                RawDisplayHandle::Wayland(wayland_display)
+            ),
+            Err("invalid Wayland window handle: no wl_surface".into())
+        );
+        wayland_window.surface = display;
+        assert_eq!(
+            validate(
+                RawWindowHandle::Wayland(wayland_window),
+                RawDisplayHandle::Wayland(wayland_display)
+            ),
+            Ok("Wayland")
+        );
+
+        // a Wayland window on an X display
+        assert!(validate(
+            RawWindowHandle::Wayland(wayland_window),
+            RawDisplayHandle::Xlib(xlib_display)
+        )
+        .unwrap_err()
+        .contains("different platforms"));
+    }
+}
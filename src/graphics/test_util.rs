@@ -0,0 +1,116 @@
+//! helpers for tests that need a GPU
+
+use std::future::Future;
+
+use bytemuck::Pod;
+use glam::{Vec2, Vec3};
+use wgpu::*;
+
+use super::bake::BakeMesh;
+
+//
+
+/// owned geometry for the baking tests
+#[derive(Debug, Clone, Default)]
+pub struct TestMesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub indices: Vec<u32>,
+}
+
+//
+
+/// a device on whatever adapter there is (a software one on CI),
+/// `None` skips the test
+pub fn test_device() -> Option<(Device, Queue)> {
+    test_device_with(Features::empty(), |adapter| adapter.limits())
+}
+
+/// [`test_device`] with `features` and the limits `limits` picks for the adapter,
+/// `None` also when the adapter can't provide them
+pub fn test_device_with(
+    features: Features,
+    limits: impl FnOnce(&Adapter) -> Limits,
+) -> Option<(Device, Queue)> {
+    let instance = Instance::default();
+    let Some(adapter) = block_on(instance.request_adapter(&RequestAdapterOptions::default()))
+    else {
+        eprintln!("no adapter, skipping");
+        return None;
+    };
+    let device = block_on(adapter.request_device(
+        &DeviceDescriptor {
+            label: None,
+            features,
+            limits: limits(&adapter),
+        },
+        None,
+    ));
+    if device.is_err() {
+        eprintln!("the adapter doesn't support the features or limits, skipping");
+    }
+    device.ok()
+}
+
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// wait for the GPU and copy a `MAP_READ` buffer
+pub fn read_buffer<T: Pod>(device: &Device, buffer: &Buffer) -> Vec<T> {
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, |res| res.unwrap());
+    device.poll(Maintain::Wait);
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    buffer.unmap();
+    data
+}
+
+/// a quad on z = `z` over `-extent..1 + extent`, split `n` times each way,
+/// with UVs over `0..1`
+pub fn plane(n: u32, z: f32, extent: f32, normal: Vec3) -> TestMesh {
+    let mut mesh = TestMesh::default();
+    for y in 0..=n {
+        for x in 0..=n {
+            let uv = Vec2::new(x as f32, y as f32) / n as f32;
+            mesh.positions
+                .push((uv * (1.0 + 2.0 * extent) - extent).extend(z));
+            mesh.normals.push(normal.normalize());
+            mesh.uvs.push(uv);
+        }
+    }
+    mesh.indices = (0..n)
+        .flat_map(|y| {
+            (0..n).flat_map(move |x| {
+                let i = y * (n + 1) + x;
+                [i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]
+            })
+        })
+        .collect();
+    mesh
+}
+
+impl TestMesh {
+    pub fn mesh(&self) -> BakeMesh<'_> {
+        BakeMesh {
+            positions: &self.positions,
+            normals: &self.normals,
+            uvs: &self.uvs,
+            indices: &self.indices,
+        }
+    }
+
+    pub fn merge(mut self, other: &Self) -> Self {
+        let offset = self.positions.len() as u32;
+        self.positions.extend(&other.positions);
+        self.normals.extend(&other.normals);
+        self.uvs.extend(&other.uvs);
+        self.indices
+            .extend(other.indices.iter().map(|i| i + offset));
+        self
+    }
+}
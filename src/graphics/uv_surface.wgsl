@@ -0,0 +1,34 @@
+// a mesh drawn at its UVs into position and normal targets,
+// one texel per point on the surface for the bakers
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct Surface {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct SurfaceTargets {
+    @location(0) position: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+};
+
+@vertex
+fn vs_surface(in: Vertex) -> Surface {
+    var out: Surface;
+    out.clip = vec4<f32>(in.uv.x * 2.0 - 1.0, 1.0 - in.uv.y * 2.0, 0.0, 1.0);
+    out.position = in.position;
+    out.normal = in.normal;
+    return out;
+}
+
+@fragment
+fn fs_surface(in: Surface) -> SurfaceTargets {
+    // w marks the texel as covered
+    return SurfaceTargets(vec4<f32>(in.position, 1.0), vec4<f32>(normalize(in.normal), 0.0));
+}
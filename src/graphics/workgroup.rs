@@ -0,0 +1,291 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use glam::{Mat4, Vec4};
+use wgpu::*;
+
+use super::{
+    procedural::{ProceduralTextures, TextureRecipe},
+    skinning::{Influence, SkinVertex, SkinnedMesh, SkinningPipeline},
+};
+
+//
+
+/// compute workgroup sizes picked for the adapter
+///
+/// shaders with `@workgroup_size({workgroup_size})` are specialized with
+/// [`Self::specialize_linear`] or [`Self::specialize_tile`] before compiling;
+/// the shaders whose shared memory is sized for a fixed workgroup
+/// (prefix sum, radix sort, compaction) keep theirs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkgroupSizes {
+    /// invocations per workgroup over a 1D range, a power of two
+    pub linear: u32,
+    /// width and height per workgroup over a 2D grid
+    pub tile: (u32, u32),
+}
+
+/// one size in [`benchmark`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkgroupTiming {
+    pub kernel: &'static str,
+    pub size: (u32, u32),
+    /// per dispatch, submission to completion
+    pub time: Duration,
+}
+
+//
+
+impl WorkgroupSizes {
+    const PLACEHOLDER: &'static str = "{workgroup_size}";
+
+    /// wgpu 0.17 doesn't report the subgroup (wave, warp, SIMD) width,
+    /// the vendor is the hint: a few subgroups per workgroup keeps the
+    /// scheduler busy without running into the register limits
+    pub fn for_adapter(info: &AdapterInfo, limits: &Limits) -> Self {
+        let subgroup = match (info.device_type, info.vendor) {
+            // llvmpipe, WARP and SwiftShader run a workgroup per thread
+            (DeviceType::Cpu, _) => 16,
+            // AMD, wave64 on GCN (RDNA runs wave32 and wave64)
+            (_, 0x1002) => 64,
+            // NVIDIA and Apple
+            (_, 0x10de | 0x106b) => 32,
+            // Intel, SIMD8 to SIMD32
+            (_, 0x8086) => 16,
+            // ARM, Qualcomm, Imagination and anything else
+            _ => 16,
+        };
+        Self::from_linear(subgroup * 4).clamped(limits)
+    }
+
+    /// `linear` invocations, the tile as square as a power of two allows
+    fn from_linear(linear: u32) -> Self {
+        let linear = linear.max(1).next_power_of_two();
+        let height = 1 << (linear.trailing_zeros() / 2);
+        Self {
+            linear,
+            tile: (linear / height, height),
+        }
+    }
+
+    /// within the device limits, halving the longer side until the tile fits
+    pub fn clamped(self, limits: &Limits) -> Self {
+        let max = prev_power_of_two(limits.max_compute_invocations_per_workgroup);
+        let linear = self
+            .linear
+            .min(max)
+            .min(prev_power_of_two(limits.max_compute_workgroup_size_x));
+
+        let (mut width, mut height) = self.tile;
+        width = width.min(prev_power_of_two(limits.max_compute_workgroup_size_x));
+        height = height.min(prev_power_of_two(limits.max_compute_workgroup_size_y));
+        while width * height > max {
+            if width >= height {
+                width /= 2;
+            } else {
+                height /= 2;
+            }
+        }
+
+        Self {
+            linear,
+            tile: (width, height),
+        }
+    }
+
+    /// `source` with `{workgroup_size}` replaced by the linear size
+    pub fn specialize_linear(&self, source: &str) -> String {
+        source.replace(Self::PLACEHOLDER, &self.linear.to_string())
+    }
+
+    /// `source` with `{workgroup_size}` replaced by the tile size
+    pub fn specialize_tile(&self, source: &str) -> String {
+        let (width, height) = self.tile;
+        source.replace(Self::PLACEHOLDER, &format!("{width}, {height}"))
+    }
+
+    /// workgroups to dispatch over `count` invocations
+    pub fn linear_groups(&self, count: u32) -> u32 {
+        count.div_ceil(self.linear)
+    }
+
+    /// workgroups to dispatch over a `width` × `height` grid
+    pub fn tile_groups(&self, width: u32, height: u32) -> (u32, u32) {
+        (width.div_ceil(self.tile.0), height.div_ceil(self.tile.1))
+    }
+}
+
+/// what the compute utilities used before they were specialized
+impl Default for WorkgroupSizes {
+    fn default() -> Self {
+        Self {
+            linear: 64,
+            tile: (8, 8),
+        }
+    }
+}
+
+impl fmt::Display for WorkgroupSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} linear, {}x{} tiles",
+            self.linear, self.tile.0, self.tile.1
+        )
+    }
+}
+
+impl fmt::Display for WorkgroupTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = self.size;
+        let size = if height == 1 {
+            width.to_string()
+        } else {
+            format!("{width}x{height}")
+        };
+        write!(f, "{} {size:>5}: {:?}", self.kernel, self.time)
+    }
+}
+
+fn prev_power_of_two(n: u32) -> u32 {
+    if n == 0 {
+        0
+    } else {
+        1 << (31 - n.leading_zeros())
+    }
+}
+
+/// `--bench-workgroups`: times skinning and procedural textures with every
+/// linear size from 16 up to the limit, to compare with the picked one
+///
+/// the time is measured on the CPU from submission to completion,
+/// so it only tells sizes apart with enough work per dispatch
+pub fn benchmark(device: &Device, queue: &Queue) -> Vec<WorkgroupTiming> {
+    const RUNS: u32 = 20;
+    const VERTICES: u32 = 1 << 20;
+    const TEXTURE: u32 = 2048;
+
+    let limits = device.limits();
+    let time = |f: &dyn Fn(&mut CommandEncoder)| {
+        // the first run compiles and allocates
+        let mut best = Duration::MAX;
+        for _ in 0..=RUNS {
+            let mut encoder = device.create_command_encoder(&<_>::default());
+            f(&mut encoder);
+            let start = Instant::now();
+            queue.submit([encoder.finish()]);
+            device.poll(Maintain::Wait);
+            best = best.min(start.elapsed());
+        }
+        best
+    };
+
+    let rest = vec![
+        SkinVertex {
+            position: Vec4::W,
+            normal: Vec4::Y,
+        };
+        VERTICES as usize
+    ];
+    let influences = vec![
+        Influence {
+            joints: [0, 1, 0, 0],
+            weights: [0.5, 0.5, 0.0, 0.0],
+        };
+        VERTICES as usize
+    ];
+
+    let mut timings = Vec::new();
+    let mut linear = 16;
+    while linear <= prev_power_of_two(limits.max_compute_invocations_per_workgroup) {
+        let sizes = WorkgroupSizes::from_linear(linear).clamped(&limits);
+        let skinning = SkinningPipeline::new(device, &sizes);
+        let mesh = SkinnedMesh::new(device, &skinning, &rest, &influences, &[], 2);
+        mesh.set_pose(queue, &[Mat4::IDENTITY; 2], &[]);
+        timings.push(WorkgroupTiming {
+            kernel: "skinning",
+            size: (sizes.linear, 1),
+            time: time(&|encoder| skinning.dispatch(encoder, &[&mesh])),
+        });
+
+        let procedural = ProceduralTextures::new(device, &sizes);
+        let recipe = TextureRecipe {
+            size: (TEXTURE, TEXTURE),
+            ..TextureRecipe::default()
+        };
+        timings.push(WorkgroupTiming {
+            kernel: "procedural",
+            size: sizes.tile,
+            time: time(&|encoder| {
+                procedural.generate(device, encoder, &recipe);
+            }),
+        });
+        linear *= 2;
+    }
+    timings
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(device_type: DeviceType, vendor: u32) -> AdapterInfo {
+        AdapterInfo {
+            name: String::new(),
+            vendor,
+            device: 0,
+            device_type,
+            driver: String::new(),
+            driver_info: String::new(),
+            backend: Backend::Vulkan,
+        }
+    }
+
+    #[test]
+    fn picks_by_vendor_within_the_limits() {
+        let limits = Limits::default();
+        let amd = WorkgroupSizes::for_adapter(&info(DeviceType::DiscreteGpu, 0x1002), &limits);
+        assert_eq!(amd.linear, 256);
+        assert_eq!(amd.tile, (16, 16));
+
+        let nvidia = WorkgroupSizes::for_adapter(&info(DeviceType::DiscreteGpu, 0x10de), &limits);
+        assert_eq!(nvidia.linear, 128);
+        assert_eq!(nvidia.tile, (16, 8));
+
+        let cpu = WorkgroupSizes::for_adapter(&info(DeviceType::Cpu, 0x10005), &limits);
+        assert_eq!(cpu.linear, 64);
+        assert_eq!(cpu.tile, (8, 8));
+
+        let small = Limits {
+            max_compute_invocations_per_workgroup: 100,
+            max_compute_workgroup_size_y: 4,
+            ..Limits::default()
+        };
+        let amd = WorkgroupSizes::for_adapter(&info(DeviceType::DiscreteGpu, 0x1002), &small);
+        assert_eq!(amd.linear, 64);
+        assert_eq!(amd.tile, (16, 4));
+    }
+
+    #[test]
+    fn specializes_the_placeholder() {
+        let sizes = WorkgroupSizes {
+            linear: 128,
+            tile: (16, 8),
+        };
+        let source = "@compute @workgroup_size({workgroup_size})";
+        assert_eq!(
+            sizes.specialize_linear(source),
+            "@compute @workgroup_size(128)"
+        );
+        assert_eq!(
+            sizes.specialize_tile(source),
+            "@compute @workgroup_size(16, 8)"
+        );
+        assert_eq!(sizes.linear_groups(129), 2);
+        assert_eq!(sizes.tile_groups(16, 9), (1, 2));
+    }
+}
@@ -0,0 +1,27 @@
+// prefix sum across one workgroup of 256 invocations,
+// prepended to the shaders that use it
+
+struct WorkgroupScan {
+    inclusive: u32,
+    total: u32,
+};
+
+var<workgroup> scan_scratch: array<u32, 256>;
+
+// Hillis-Steele, every invocation of the workgroup has to call it
+fn workgroup_scan(lid: u32, value: u32) -> WorkgroupScan {
+    scan_scratch[lid] = value;
+    workgroupBarrier();
+    for (var offset = 1u; offset < 256u; offset *= 2u) {
+        var add = 0u;
+        if lid >= offset {
+            add = scan_scratch[lid - offset];
+        }
+        workgroupBarrier();
+        scan_scratch[lid] += add;
+        workgroupBarrier();
+    }
+    let result = WorkgroupScan(scan_scratch[lid], scan_scratch[255]);
+    workgroupBarrier();
+    return result;
+}
@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::ColorVision,
+    scene::{Node, NodeId, Scene, Transform},
+    RuntimeSettings,
+};
+
+//
+
+/// undo and redo stacks of editor [`Command`]s
+///
+/// serializable, so it can be saved next to a scene snapshot
+/// and keep working after the scene is loaded again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    /// the oldest commands are dropped past this
+    limit: usize,
+}
+
+/// what commands edit
+pub struct Editable<'a> {
+    pub scene: &'a mut Scene,
+    pub settings: &'a mut RuntimeSettings,
+}
+
+/// one undoable edit, holding both the old and the new state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    SetTransform {
+        id: NodeId,
+        from: Transform,
+        to: Transform,
+    },
+    /// it was `!visible` before, from [`Command::toggle_visible`]
+    SetVisible { id: NodeId, visible: bool },
+    /// a new node, from [`Command::insert`]
+    Insert { id: NodeId, node: Node },
+    /// a node and its descendants, from [`Command::remove`]
+    Remove { nodes: Vec<(NodeId, Node)> },
+    Setting {
+        from: SettingValue,
+        to: SettingValue,
+    },
+    /// applied and undone together, in order and in reverse
+    Group(Vec<Command>),
+}
+
+/// a [`RuntimeSettings`] field and its value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SettingValue {
+    EnableUv(bool),
+    Interpolate(bool),
+    HighContrast(bool),
+    ColorVision(ColorVision),
+    Palette(usize),
+}
+
+//
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// apply `command` and make it undoable, anything undone can't be redone anymore
+    pub fn apply(&mut self, target: &mut Editable, command: Command) {
+        command.apply(target);
+        self.undo.push(command);
+        self.redo.clear();
+        if self.undo.len() > self.limit {
+            self.undo.remove(0);
+        }
+    }
+
+    /// false if there was nothing to undo
+    pub fn undo(&mut self, target: &mut Editable) -> bool {
+        let Some(command) = self.undo.pop() else {
+            return false;
+        };
+        command.revert(target);
+        self.redo.push(command);
+        true
+    }
+
+    /// false if there was nothing to redo
+    pub fn redo(&mut self, target: &mut Editable) -> bool {
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(target);
+        self.undo.push(command);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Command {
+    /// set the transform of `id`, `None` if there is no such node
+    pub fn set_transform(scene: &Scene, id: NodeId, to: Transform) -> Option<Self> {
+        Some(Self::SetTransform {
+            id,
+            from: scene.get(id)?.transform,
+            to,
+        })
+    }
+
+    /// show a hidden node or hide a shown one
+    pub fn toggle_visible(scene: &Scene, id: NodeId) -> Option<Self> {
+        Some(Self::SetVisible {
+            id,
+            visible: !scene.get(id)?.visible,
+        })
+    }
+
+    /// create `node` under a new id
+    pub fn insert(scene: &mut Scene, node: Node) -> Self {
+        // taken now, so redoing puts the node back under the same id
+        Self::Insert {
+            id: scene.reserve(),
+            node,
+        }
+    }
+
+    /// delete `id` and its descendants
+    pub fn remove(scene: &Scene, id: NodeId) -> Self {
+        let nodes = scene
+            .subtree(id)
+            .into_iter()
+            .map(|id| (id, scene.get(id).unwrap().clone()))
+            .collect();
+        Self::Remove { nodes }
+    }
+
+    /// change a setting from what it is now
+    pub fn setting(settings: &RuntimeSettings, to: SettingValue) -> Self {
+        Self::Setting {
+            from: to.read(settings),
+            to,
+        }
+    }
+
+    pub fn apply(&self, target: &mut Editable) {
+        match self {
+            Command::SetTransform { id, to, .. } => {
+                if let Some(node) = target.scene.get_mut(*id) {
+                    node.transform = *to;
+                }
+            }
+            Command::SetVisible { id, visible } => {
+                if let Some(node) = target.scene.get_mut(*id) {
+                    node.visible = *visible;
+                }
+            }
+            Command::Insert { id, node } => target.scene.restore(vec![(*id, node.clone())]),
+            Command::Remove { nodes } => {
+                if let Some((id, _)) = nodes.first() {
+                    target.scene.remove(*id);
+                }
+            }
+            Command::Setting { to, .. } => to.write(target.settings),
+            Command::Group(commands) => {
+                for command in commands {
+                    command.apply(target);
+                }
+            }
+        }
+    }
+
+    pub fn revert(&self, target: &mut Editable) {
+        match self {
+            Command::SetTransform { id, from, .. } => {
+                if let Some(node) = target.scene.get_mut(*id) {
+                    node.transform = *from;
+                }
+            }
+            Command::SetVisible { id, visible } => {
+                if let Some(node) = target.scene.get_mut(*id) {
+                    node.visible = !*visible;
+                }
+            }
+            Command::Insert { id, .. } => {
+                target.scene.remove(*id);
+            }
+            Command::Remove { nodes } => target.scene.restore(nodes.clone()),
+            Command::Setting { from, .. } => from.write(target.settings),
+            Command::Group(commands) => {
+                for command in commands.iter().rev() {
+                    command.revert(target);
+                }
+            }
+        }
+    }
+}
+
+impl SettingValue {
+    /// the current value of the same field
+    pub fn read(self, settings: &RuntimeSettings) -> Self {
+        match self {
+            Self::EnableUv(_) => Self::EnableUv(settings.enable_uv),
+            Self::Interpolate(_) => Self::Interpolate(settings.interpolate),
+            Self::HighContrast(_) => Self::HighContrast(settings.high_contrast),
+            Self::ColorVision(_) => Self::ColorVision(settings.color_vision),
+            Self::Palette(_) => Self::Palette(settings.palette),
+        }
+    }
+
+    pub fn write(self, settings: &mut RuntimeSettings) {
+        match self {
+            Self::EnableUv(v) => settings.enable_uv = v,
+            Self::Interpolate(v) => settings.interpolate = v,
+            Self::HighContrast(v) => settings.high_contrast = v,
+            Self::ColorVision(v) => settings.color_vision = v,
+            Self::Palette(v) => settings.palette = v,
+        }
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::*;
+
+    fn settings() -> RuntimeSettings {
+        RuntimeSettings {
+            enable_uv: false,
+            interpolate: true,
+            high_contrast: false,
+            color_vision: ColorVision::Normal,
+            palette: 0,
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut scene = Scene::default();
+        let mut settings = settings();
+        let root = scene.insert("root", None);
+        let mut history = History::default();
+        let mut target = Editable {
+            scene: &mut scene,
+            settings: &mut settings,
+        };
+
+        let moved = Transform {
+            translation: DVec3::X,
+            ..Transform::IDENTITY
+        };
+        let command = Command::set_transform(target.scene, root, moved).unwrap();
+        history.apply(&mut target, command);
+        let command = Command::insert(
+            target.scene,
+            Node {
+                parent: Some(root),
+                ..target.scene.get(root).unwrap().clone()
+            },
+        );
+        history.apply(&mut target, command);
+        let child = target.scene.children(root).next().unwrap();
+        let command = Command::setting(target.settings, SettingValue::Palette(3));
+        history.apply(&mut target, command);
+        let command = Command::remove(target.scene, root);
+        history.apply(&mut target, command);
+        assert!(target.scene.is_empty());
+        assert_eq!(target.settings.palette, 3);
+
+        let after = (target.scene.clone(), target.settings.palette);
+        while history.undo(&mut target) {}
+        assert_eq!(target.scene.len(), 1);
+        assert_eq!(
+            target.scene.get(root).unwrap().transform,
+            Transform::IDENTITY
+        );
+        assert_eq!(target.settings.palette, 0);
+
+        // survives a save and load
+        let mut history: History =
+            serde_json::from_str(&serde_json::to_string(&history).unwrap()).unwrap();
+        assert!(!history.can_undo());
+        assert!(history.redo(&mut target));
+        assert!(history.redo(&mut target));
+        assert_eq!(target.scene.children(root).next(), Some(child));
+        while history.redo(&mut target) {}
+        assert_eq!((target.scene.clone(), target.settings.palette), after);
+
+        // a new edit drops what could be redone
+        history.undo(&mut target);
+        let command = Command::setting(target.settings, SettingValue::EnableUv(true));
+        history.apply(&mut target, command);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn drops_the_oldest() {
+        let mut scene = Scene::default();
+        let mut settings = settings();
+        let mut target = Editable {
+            scene: &mut scene,
+            settings: &mut settings,
+        };
+        let mut history = History::new(2);
+        for palette in 1..=3 {
+            let command = Command::setting(target.settings, SettingValue::Palette(palette));
+            history.apply(&mut target, command);
+        }
+        while history.undo(&mut target) {}
+        assert_eq!(target.settings.palette, 1);
+    }
+}
@@ -0,0 +1,196 @@
+use std::time::{Duration, Instant};
+
+use glam::Vec2;
+use winit::event::{DeviceEvent, Event, WindowEvent};
+
+use crate::settings::{IdleMode, IdleSettings};
+
+//
+
+/// anti burn-in for kiosks and installations: after a while without input
+/// the output fades to a dim level, or orbits a few pixels so no edge stays put
+#[derive(Debug)]
+pub struct IdleDetector {
+    settings: IdleSettings,
+    last_input: Instant,
+}
+
+/// what the final pass does to the output while idle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdleEffect {
+    /// multiplies the output color
+    pub brightness: f32,
+    /// moves the output, in output pixels
+    pub offset: Vec2,
+}
+
+//
+
+impl IdleDetector {
+    /// a full orbit takes this long, slow enough to go unnoticed
+    const ORBIT_PERIOD: f32 = 120.0;
+
+    pub fn new(settings: &IdleSettings, now: Instant) -> Self {
+        Self {
+            settings: settings.clone(),
+            last_input: now,
+        }
+    }
+
+    /// the user did something, returns if that ended the effect
+    pub fn input(&mut self, now: Instant) -> bool {
+        let active = self.effect(now) != IdleEffect::NONE;
+        self.last_input = now;
+        active
+    }
+
+    /// since when there was no input, `None` while there was some recently
+    fn idle_for(&self, now: Instant) -> Option<Duration> {
+        if !self.settings.enabled {
+            return None;
+        }
+        let after = Duration::from_secs_f32(self.settings.after_minutes.max(0.0) * 60.0);
+        (now - self.last_input).checked_sub(after)
+    }
+
+    pub fn effect(&self, now: Instant) -> IdleEffect {
+        let Some(idle) = self.idle_for(now) else {
+            return IdleEffect::NONE;
+        };
+        match self.settings.mode {
+            IdleMode::Dim => {
+                let fade = (idle.as_secs_f32() / self.settings.fade_seconds.max(0.001)).min(1.0);
+                let dim = self.settings.brightness.clamp(0.0, 1.0);
+                IdleEffect {
+                    brightness: 1.0 + (dim - 1.0) * fade,
+                    offset: Vec2::ZERO,
+                }
+            }
+            IdleMode::Orbit => {
+                let angle = idle.as_secs_f32() / Self::ORBIT_PERIOD * std::f32::consts::TAU;
+                // starts at the center instead of jumping to the edge of the orbit
+                let offset = (Vec2::from_angle(angle) - Vec2::X) * 0.5;
+                IdleEffect {
+                    brightness: 1.0,
+                    offset: (offset * self.settings.orbit_pixels).round(),
+                }
+            }
+        }
+    }
+
+    /// the effect changes from frame to frame, rendering can't wait for input
+    pub fn is_animating(&self, now: Instant) -> bool {
+        match (self.idle_for(now), self.settings.mode) {
+            (None, _) => false,
+            (Some(idle), IdleMode::Dim) => idle.as_secs_f32() < self.settings.fade_seconds,
+            (Some(_), IdleMode::Orbit) => true,
+        }
+    }
+
+    /// when the effect starts, to wake up an event loop waiting for input
+    pub fn starts_at(&self) -> Option<Instant> {
+        self.settings.enabled.then(|| {
+            self.last_input + Duration::from_secs_f32(self.settings.after_minutes.max(0.0) * 60.0)
+        })
+    }
+}
+
+impl IdleEffect {
+    pub const NONE: Self = Self {
+        brightness: 1.0,
+        offset: Vec2::ZERO,
+    };
+}
+
+impl Default for IdleEffect {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// keys, buttons, pointer and touch input, anything a person in front of the screen does
+pub fn is_input<T>(event: &Event<T>) -> bool {
+    match event {
+        Event::WindowEvent { event, .. } => matches!(
+            event,
+            WindowEvent::KeyboardInput { .. }
+                | WindowEvent::ReceivedCharacter(_)
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::CursorMoved { .. }
+                | WindowEvent::Touch(_)
+        ),
+        Event::DeviceEvent { event, .. } => matches!(
+            event,
+            DeviceEvent::MouseMotion { .. } | DeviceEvent::Button { .. } | DeviceEvent::Key(_)
+        ),
+        _ => false,
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn dims_after_the_timeout() {
+        let settings = IdleSettings {
+            enabled: true,
+            after_minutes: 5.0,
+            mode: IdleMode::Dim,
+            brightness: 0.2,
+            fade_seconds: 2.0,
+            ..IdleSettings::default()
+        };
+        let start = Instant::now();
+        let mut idle = IdleDetector::new(&settings, start);
+
+        assert_eq!(idle.effect(start + 4 * MINUTE), IdleEffect::NONE);
+        assert!(!idle.is_animating(start + 4 * MINUTE));
+        assert_eq!(idle.starts_at(), Some(start + 5 * MINUTE));
+
+        let fading = start + 5 * MINUTE + Duration::from_secs(1);
+        assert!((idle.effect(fading).brightness - 0.6).abs() < 1e-5);
+        assert!(idle.is_animating(fading));
+
+        let dimmed = start + 6 * MINUTE;
+        assert!((idle.effect(dimmed).brightness - 0.2).abs() < 1e-5);
+        assert!(!idle.is_animating(dimmed));
+
+        assert!(idle.input(dimmed));
+        assert_eq!(idle.effect(dimmed), IdleEffect::NONE);
+        assert!(!idle.input(dimmed + MINUTE));
+    }
+
+    #[test]
+    fn orbits_from_the_center() {
+        let settings = IdleSettings {
+            enabled: true,
+            after_minutes: 1.0,
+            mode: IdleMode::Orbit,
+            orbit_pixels: 8.0,
+            ..IdleSettings::default()
+        };
+        let start = Instant::now();
+        let idle = IdleDetector::new(&settings, start);
+
+        assert_eq!(idle.effect(start + MINUTE).offset, Vec2::ZERO);
+        // half way around is the far side
+        let half = start + MINUTE + Duration::from_secs(60);
+        assert_eq!(idle.effect(half).offset, Vec2::new(-8.0, 0.0));
+        assert_eq!(idle.effect(half).brightness, 1.0);
+        assert!(idle.is_animating(half));
+    }
+
+    #[test]
+    fn disabled_does_nothing() {
+        let start = Instant::now();
+        let idle = IdleDetector::new(&IdleSettings::default(), start);
+        assert_eq!(idle.effect(start + 600 * MINUTE), IdleEffect::NONE);
+        assert_eq!(idle.starts_at(), None);
+    }
+}
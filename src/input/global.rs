@@ -0,0 +1,213 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use global_hotkey::{
+    hotkey::{Code, HotKey, Modifiers},
+    GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState,
+};
+use winit::{event::VirtualKeyCode, event_loop::EventLoopProxy};
+
+use super::{Action, Binding, Chord};
+use crate::UserEvent;
+
+//
+
+/// OS level hotkeys, triggering actions even while the window is unfocused or hidden
+///
+/// the actions come back as [`UserEvent::GlobalHotkey`]; on Linux this is X11 only,
+/// Wayland compositors don't let clients grab keys
+pub struct GlobalHotkeys {
+    // unregisters everything when dropped
+    _manager: GlobalHotKeyManager,
+}
+
+//
+
+impl GlobalHotkeys {
+    /// bindings that can't be registered are skipped with a warning,
+    /// `None` if nothing got registered
+    pub fn new(
+        bindings: &BTreeMap<Action, Binding>,
+        proxy: EventLoopProxy<UserEvent>,
+    ) -> Option<Self> {
+        if bindings.is_empty() {
+            return None;
+        }
+
+        let manager = GlobalHotKeyManager::new()
+            .map_err(|err| tracing::warn!("Global hotkeys are not available: {err}"))
+            .ok()?;
+
+        let mut actions = BTreeMap::new();
+        for (action, binding) in bindings {
+            let registered = hotkey(binding).and_then(|hotkey| {
+                manager.register(hotkey)?;
+                Ok(hotkey)
+            });
+            match registered {
+                Ok(hotkey) => _ = actions.insert(hotkey.id(), *action),
+                Err(err) => tracing::warn!("Global hotkey `{binding}` for {action:?}: {err}"),
+            }
+        }
+        if actions.is_empty() {
+            return None;
+        }
+
+        // the handler has to be Sync, the proxy isn't on every platform
+        let proxy = Mutex::new(proxy);
+        GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+            if event.state() != HotKeyState::Pressed {
+                return;
+            }
+            if let Some(action) = actions.get(&event.id()) {
+                // the event loop is gone when the app is exiting
+                _ = proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(UserEvent::GlobalHotkey(*action));
+            }
+        }));
+
+        Some(Self { _manager: manager })
+    }
+}
+
+/// only single chord bindings, the OS reports each hotkey on its own
+pub fn hotkey(binding: &Binding) -> Result<HotKey> {
+    let [chord] = binding.0.as_slice() else {
+        return Err(anyhow!("global hotkeys can't be key sequences"));
+    };
+    let Chord {
+        ctrl,
+        shift,
+        alt,
+        logo,
+        key,
+    } = *chord;
+
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::CONTROL, ctrl);
+    modifiers.set(Modifiers::SHIFT, shift);
+    modifiers.set(Modifiers::ALT, alt);
+    modifiers.set(Modifiers::SUPER, logo);
+
+    let code = code(key).ok_or_else(|| anyhow!("{key:?} can't be a global hotkey"))?;
+    Ok(HotKey::new(Some(modifiers), code))
+}
+
+fn code(key: VirtualKeyCode) -> Option<Code> {
+    use VirtualKeyCode as K;
+    Some(match key {
+        K::A => Code::KeyA,
+        K::B => Code::KeyB,
+        K::C => Code::KeyC,
+        K::D => Code::KeyD,
+        K::E => Code::KeyE,
+        K::F => Code::KeyF,
+        K::G => Code::KeyG,
+        K::H => Code::KeyH,
+        K::I => Code::KeyI,
+        K::J => Code::KeyJ,
+        K::K => Code::KeyK,
+        K::L => Code::KeyL,
+        K::M => Code::KeyM,
+        K::N => Code::KeyN,
+        K::O => Code::KeyO,
+        K::P => Code::KeyP,
+        K::Q => Code::KeyQ,
+        K::R => Code::KeyR,
+        K::S => Code::KeyS,
+        K::T => Code::KeyT,
+        K::U => Code::KeyU,
+        K::V => Code::KeyV,
+        K::W => Code::KeyW,
+        K::X => Code::KeyX,
+        K::Y => Code::KeyY,
+        K::Z => Code::KeyZ,
+        K::Key0 => Code::Digit0,
+        K::Key1 => Code::Digit1,
+        K::Key2 => Code::Digit2,
+        K::Key3 => Code::Digit3,
+        K::Key4 => Code::Digit4,
+        K::Key5 => Code::Digit5,
+        K::Key6 => Code::Digit6,
+        K::Key7 => Code::Digit7,
+        K::Key8 => Code::Digit8,
+        K::Key9 => Code::Digit9,
+        K::F1 => Code::F1,
+        K::F2 => Code::F2,
+        K::F3 => Code::F3,
+        K::F4 => Code::F4,
+        K::F5 => Code::F5,
+        K::F6 => Code::F6,
+        K::F7 => Code::F7,
+        K::F8 => Code::F8,
+        K::F9 => Code::F9,
+        K::F10 => Code::F10,
+        K::F11 => Code::F11,
+        K::F12 => Code::F12,
+        K::Escape => Code::Escape,
+        K::Space => Code::Space,
+        K::Tab => Code::Tab,
+        K::Return => Code::Enter,
+        K::Back => Code::Backspace,
+        K::Delete => Code::Delete,
+        K::Insert => Code::Insert,
+        K::Home => Code::Home,
+        K::End => Code::End,
+        K::PageUp => Code::PageUp,
+        K::PageDown => Code::PageDown,
+        K::Up => Code::ArrowUp,
+        K::Down => Code::ArrowDown,
+        K::Left => Code::ArrowLeft,
+        K::Right => Code::ArrowRight,
+        K::Snapshot => Code::PrintScreen,
+        K::Scroll => Code::ScrollLock,
+        K::Pause => Code::Pause,
+        K::Minus => Code::Minus,
+        K::Equals => Code::Equal,
+        K::Comma => Code::Comma,
+        K::Period => Code::Period,
+        K::Slash => Code::Slash,
+        K::Backslash => Code::Backslash,
+        K::Semicolon => Code::Semicolon,
+        K::Apostrophe => Code::Quote,
+        K::Grave => Code::Backquote,
+        K::LBracket => Code::BracketLeft,
+        K::RBracket => Code::BracketRight,
+        K::Numpad0 => Code::Numpad0,
+        K::Numpad1 => Code::Numpad1,
+        K::Numpad2 => Code::Numpad2,
+        K::Numpad3 => Code::Numpad3,
+        K::Numpad4 => Code::Numpad4,
+        K::Numpad5 => Code::Numpad5,
+        K::Numpad6 => Code::Numpad6,
+        K::Numpad7 => Code::Numpad7,
+        K::Numpad8 => Code::Numpad8,
+        K::Numpad9 => Code::Numpad9,
+        _ => return None,
+    })
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_to_hotkeys() {
+        let parsed = hotkey(&"Ctrl+Shift+F10".parse().unwrap()).unwrap();
+        assert_eq!(
+            parsed,
+            HotKey::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::F10)
+        );
+        assert_eq!(
+            hotkey(&"Snapshot".parse().unwrap()).unwrap(),
+            HotKey::new(None, Code::PrintScreen)
+        );
+
+        assert!(hotkey(&"Ctrl+K Ctrl+S".parse().unwrap()).is_err());
+        assert!(hotkey(&"Logo+Mute".parse().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,533 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use serde::{de::IntoDeserializer, Deserialize, Deserializer, Serialize};
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+use crate::settings::InputSettings;
+
+pub use self::{
+    global::GlobalHotkeys,
+    touch::{Gesture, GestureRecognizer},
+};
+
+//
+
+pub mod global;
+pub mod touch;
+
+//
+
+/// everything a key binding can trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    ToggleUv,
+    ToggleInterpolation,
+    CycleColorVision,
+    CyclePalette,
+    ShowAbout,
+    ShowBindings,
+    Undo,
+    Redo,
+    SaveProject,
+    OpenFiles,
+    OpenProject,
+    SaveScreenshot,
+    ToggleWindow,
+    ToggleTransparency,
+    ToggleLatencyTester,
+    ToggleProfiler,
+    /// acts while the key is held, see [`InputMap::key_released`]
+    InspectPixel,
+    Exit,
+}
+
+/// modifiers + a key, like `Ctrl+Shift+Z`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub logo: bool,
+    pub key: VirtualKeyCode,
+}
+
+/// chords pressed one after another, like `Ctrl+K Ctrl+S`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Binding(pub Vec<Chord>);
+
+/// key bindings -> actions
+pub struct InputMap {
+    /// any of the bindings of an action triggers it
+    bindings: BTreeMap<Action, Vec<Binding>>,
+    modifiers: ModifiersState,
+    /// chords of a sequence typed so far
+    pending: Vec<Chord>,
+}
+
+/// two actions that can't both be triggered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub a: (Action, Binding),
+    pub b: (Action, Binding),
+}
+
+//
+
+impl InputMap {
+    pub fn new(settings: &InputSettings) -> Self {
+        // the bindings of an action in the settings replace all of its defaults
+        let mut bindings = Self::default_bindings();
+        bindings.extend(settings.bindings.clone());
+
+        let map = Self {
+            bindings,
+            modifiers: ModifiersState::empty(),
+            pending: Vec::new(),
+        };
+
+        for conflict in map.conflicts() {
+            tracing::warn!("{conflict}");
+        }
+
+        map
+    }
+
+    pub fn default_bindings() -> BTreeMap<Action, Vec<Binding>> {
+        [
+            (Action::ToggleUv, "F1"),
+            (Action::ToggleInterpolation, "F2"),
+            (Action::CycleColorVision, "F3"),
+            (Action::CyclePalette, "F4"),
+            (Action::ShowAbout, "F9"),
+            (Action::ShowBindings, "F12"),
+            (Action::Undo, "Ctrl+Z"),
+            (Action::Redo, "Ctrl+Shift+Z"),
+            (Action::SaveProject, "Ctrl+S"),
+            (Action::OpenFiles, "Ctrl+O"),
+            (Action::OpenProject, "Ctrl+Shift+O"),
+            (Action::SaveScreenshot, "Ctrl+Shift+S"),
+            (Action::ToggleTransparency, "F8"),
+            (Action::ToggleLatencyTester, "F7"),
+            (Action::ToggleProfiler, "F6"),
+            (Action::InspectPixel, "F5"),
+            (Action::Exit, "Escape"),
+        ]
+        .into_iter()
+        .map(|(action, binding)| (action, vec![binding.parse().unwrap()]))
+        .collect()
+    }
+
+    /// every action with its bindings, unbound actions have none
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, &[Binding])> {
+        self.bindings
+            .iter()
+            .map(|(action, bindings)| (*action, bindings.as_slice()))
+    }
+
+    /// every binding of every action, an action with several appears once for each
+    fn each_binding(&self) -> impl Iterator<Item = (Action, &Binding)> {
+        self.bindings
+            .iter()
+            .flat_map(|(action, bindings)| bindings.iter().map(|binding| (*action, binding)))
+    }
+
+    /// bindings of different actions that are equal, or where one is a prefix
+    /// of the other (so the longer one can never be typed)
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let bindings: Vec<_> = self.each_binding().collect();
+        let mut conflicts = Vec::new();
+
+        for (i, (a, a_binding)) in bindings.iter().enumerate() {
+            for (b, b_binding) in &bindings[i + 1..] {
+                if a == b {
+                    continue;
+                }
+                if a_binding.0.starts_with(&b_binding.0) || b_binding.0.starts_with(&a_binding.0) {
+                    conflicts.push(Conflict {
+                        a: (*a, (*a_binding).clone()),
+                        b: (*b, (*b_binding).clone()),
+                    });
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// all bindings as a printable table
+    pub fn describe(&self) -> String {
+        let bound = || self.bindings().filter(|(_, bindings)| !bindings.is_empty());
+        let width = bound()
+            .map(|(action, _)| format!("{action:?}").len())
+            .max()
+            .unwrap_or(0);
+
+        bound()
+            .map(|(action, bindings)| {
+                let bindings: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+                format!(
+                    "{:width$}  {}\n",
+                    format!("{action:?}"),
+                    bindings.join(", ")
+                )
+            })
+            .collect()
+    }
+
+    pub fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    /// feed a key press, returns the action if a binding was completed
+    pub fn key_pressed(&mut self, key: VirtualKeyCode) -> Option<Action> {
+        // modifier presses only change the state for the next key
+        if Chord::is_modifier(key) {
+            return None;
+        }
+
+        let chord = Chord::new(self.modifiers, key);
+        let had_pending = !self.pending.is_empty();
+        self.pending.push(chord);
+        if let Some(action) = self.feed() {
+            return Some(action);
+        }
+
+        // the sequence didn't lead anywhere, maybe this chord starts a new one
+        if had_pending && self.pending.is_empty() {
+            self.pending.push(chord);
+            return self.feed();
+        }
+
+        None
+    }
+
+    /// the action of a single chord binding that `key` ends, for actions
+    /// that act while their key is held, modifiers are ignored
+    /// so releasing them first doesn't keep the action going
+    pub fn key_released(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.each_binding()
+            .find(|(_, binding)| matches!(&binding.0[..], [chord] if chord.key == key))
+            .map(|(action, _)| action)
+    }
+
+    fn feed(&mut self) -> Option<Action> {
+        let mut prefix = false;
+        let mut complete = None;
+        for (action, binding) in self.each_binding() {
+            if binding.0 == self.pending {
+                complete = Some(action);
+                break;
+            }
+            prefix |= binding.0.starts_with(&self.pending);
+        }
+
+        if complete.is_some() || !prefix {
+            self.pending.clear();
+        }
+        complete
+    }
+}
+
+/// the bindings of each action in the settings, `Action = "F1"` or
+/// `Action = ["F1", "Ctrl+K Ctrl+U"]`, an empty list unbinds the action
+pub fn deserialize_bindings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<Action, Vec<Binding>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Binding),
+        Many(Vec<Binding>),
+    }
+
+    let bindings = BTreeMap::<Action, OneOrMany>::deserialize(deserializer)?;
+    Ok(bindings
+        .into_iter()
+        .map(|(action, bindings)| match bindings {
+            OneOrMany::One(binding) => (action, vec![binding]),
+            OneOrMany::Many(bindings) => (action, bindings),
+        })
+        .collect())
+}
+
+impl Action {
+    /// human readable, for screen readers and menus
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::ToggleUv => "Toggle UV colors",
+            Action::ToggleInterpolation => "Toggle simulation interpolation",
+            Action::CycleColorVision => "Cycle color vision simulation",
+            Action::CyclePalette => "Cycle indexed color palettes",
+            Action::ShowAbout => "Show build info",
+            Action::ShowBindings => "Show key bindings",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::SaveProject => "Save the project",
+            Action::OpenFiles => "Open files",
+            Action::OpenProject => "Open a project",
+            Action::SaveScreenshot => "Save a screenshot as",
+            Action::ToggleWindow => "Hide or show the window",
+            Action::ToggleTransparency => "Toggle window transparency",
+            Action::ToggleLatencyTester => "Toggle the click to photon latency tester",
+            Action::ToggleProfiler => "Attach or detach the profiler",
+            Action::InspectPixel => "Hold to read back the pixel under the cursor",
+            Action::Exit => "Exit",
+        }
+    }
+}
+
+impl Chord {
+    pub fn new(modifiers: ModifiersState, key: VirtualKeyCode) -> Self {
+        Self {
+            ctrl: modifiers.ctrl(),
+            shift: modifiers.shift(),
+            alt: modifiers.alt(),
+            logo: modifiers.logo(),
+            key,
+        }
+    }
+
+    fn is_modifier(key: VirtualKeyCode) -> bool {
+        use VirtualKeyCode::*;
+        matches!(
+            key,
+            LControl | RControl | LShift | RShift | LAlt | RAlt | LWin | RWin
+        )
+    }
+}
+
+impl FromStr for Chord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chord = Chord::new(ModifiersState::empty(), VirtualKeyCode::Escape);
+        let mut key = None;
+
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" => chord.alt = true,
+                "logo" | "super" | "win" | "cmd" => chord.logo = true,
+                _ if key.is_some() => return Err(anyhow!("`{s}` has more than one key")),
+                _ => {
+                    let de: serde::de::value::StrDeserializer<serde::de::value::Error> =
+                        part.into_deserializer();
+                    key = Some(
+                        VirtualKeyCode::deserialize(de)
+                            .map_err(|_| anyhow!("unknown key `{part}`"))?,
+                    );
+                }
+            }
+        }
+
+        chord.key = key.ok_or_else(|| anyhow!("`{s}` has no key"))?;
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+            (self.logo, "Logo+"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+impl FromStr for Binding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let chords = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Chord>>>()?;
+
+        if chords.is_empty() {
+            return Err(anyhow!("empty key binding"));
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(value: Binding) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, chord) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{chord}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ((a, a_binding), (b, b_binding)) = (&self.a, &self.b);
+        if a_binding == b_binding {
+            write!(
+                f,
+                "key binding conflict: {a:?} and {b:?} are both bound to `{a_binding}`"
+            )
+        } else {
+            write!(
+                f,
+                "key binding conflict: {a:?} (`{a_binding}`) and {b:?} (`{b_binding}`) overlap"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(bindings: &[(Action, &[&str])]) -> InputMap {
+        let mut settings = InputSettings::default();
+        for &(action, list) in bindings {
+            let list = list
+                .iter()
+                .map(|binding| binding.parse().unwrap())
+                .collect();
+            settings.bindings.insert(action, list);
+        }
+        InputMap::new(&settings)
+    }
+
+    fn press(map: &mut InputMap, chord: &str) -> Option<Action> {
+        let chord: Chord = chord.parse().unwrap();
+        let mut modifiers = ModifiersState::empty();
+        modifiers.set(ModifiersState::CTRL, chord.ctrl);
+        modifiers.set(ModifiersState::SHIFT, chord.shift);
+        modifiers.set(ModifiersState::ALT, chord.alt);
+        modifiers.set(ModifiersState::LOGO, chord.logo);
+        map.modifiers_changed(modifiers);
+        map.key_pressed(chord.key)
+    }
+
+    #[test]
+    fn chord_sequences() {
+        let mut map = map(&[
+            (Action::ToggleUv, &["Ctrl+K Ctrl+U", "F1"]),
+            (Action::ToggleProfiler, &["Ctrl+K P"]),
+        ]);
+
+        assert_eq!(press(&mut map, "Ctrl+K"), None);
+        assert_eq!(press(&mut map, "Ctrl+U"), Some(Action::ToggleUv));
+        assert_eq!(press(&mut map, "F1"), Some(Action::ToggleUv));
+        assert_eq!(press(&mut map, "Ctrl+K"), None);
+        assert_eq!(press(&mut map, "P"), Some(Action::ToggleProfiler));
+
+        // a dead end drops the sequence, the chord that ended it can start a binding
+        assert_eq!(press(&mut map, "Ctrl+K"), None);
+        assert_eq!(press(&mut map, "Escape"), Some(Action::Exit));
+        assert_eq!(press(&mut map, "Ctrl+K"), None);
+        assert_eq!(press(&mut map, "Q"), None);
+        assert_eq!(press(&mut map, "Ctrl+U"), None);
+
+        // modifiers alone don't break a sequence
+        assert_eq!(press(&mut map, "Ctrl+K"), None);
+        map.modifiers_changed(ModifiersState::CTRL);
+        assert_eq!(map.key_pressed(VirtualKeyCode::LControl), None);
+        assert_eq!(press(&mut map, "Ctrl+U"), Some(Action::ToggleUv));
+
+        // the settings replace all default bindings of an action
+        assert_eq!(press(&mut map, "Ctrl+Z"), Some(Action::Undo));
+        let mut map = self::map(&[(Action::Undo, &[])]);
+        assert_eq!(press(&mut map, "Ctrl+Z"), None);
+        assert!(!map.describe().contains("Undo"));
+    }
+
+    #[test]
+    fn conflicting_bindings() {
+        assert_eq!(map(&[]).conflicts(), []);
+
+        let conflicts = map(&[
+            // the default F6 of ToggleProfiler is replaced, so it's free
+            (Action::ToggleUv, &["F6", "Ctrl+K"]),
+            (Action::ToggleProfiler, &["F10"]),
+            (Action::CyclePalette, &["Ctrl+K P"]),
+            // an action doesn't conflict with itself
+            (Action::ShowAbout, &["F11", "F11 F11"]),
+        ])
+        .conflicts();
+        let conflict = |a: Action, a_binding: &str, b: Action, b_binding: &str| Conflict {
+            a: (a, a_binding.parse().unwrap()),
+            b: (b, b_binding.parse().unwrap()),
+        };
+        assert_eq!(
+            conflicts,
+            [conflict(
+                Action::ToggleUv,
+                "Ctrl+K",
+                Action::CyclePalette,
+                "Ctrl+K P"
+            )]
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "key binding conflict: ToggleUv (`Ctrl+K`) and CyclePalette (`Ctrl+K P`) overlap"
+        );
+
+        let conflicts = map(&[(Action::ToggleUv, &["F12"])]).conflicts();
+        assert_eq!(
+            conflicts,
+            [conflict(
+                Action::ToggleUv,
+                "F12",
+                Action::ShowBindings,
+                "F12"
+            )]
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "key binding conflict: ToggleUv and ShowBindings are both bound to `F12`"
+        );
+    }
+
+    #[test]
+    fn one_or_many_bindings_in_the_settings() {
+        #[derive(Deserialize)]
+        struct Input {
+            #[serde(deserialize_with = "deserialize_bindings")]
+            bindings: BTreeMap<Action, Vec<Binding>>,
+        }
+
+        let input: Input = toml_edit::de::from_str(
+            "[bindings]\nUndo = \"Ctrl+Z\"\nRedo = [\"Ctrl+Y\", \"Ctrl+Shift+Z\"]\nExit = []\n",
+        )
+        .unwrap();
+        let parse = |binding: &str| binding.parse::<Binding>().unwrap();
+        assert_eq!(input.bindings[&Action::Undo], [parse("Ctrl+Z")]);
+        assert_eq!(
+            input.bindings[&Action::Redo],
+            [parse("Ctrl+Y"), parse("Ctrl+Shift+Z")]
+        );
+        assert_eq!(input.bindings[&Action::Exit], []);
+    }
+}
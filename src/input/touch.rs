@@ -0,0 +1,242 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use glam::Vec2;
+use winit::event::{Touch, TouchPhase};
+
+//
+
+/// gestures synthesized from raw touch events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// a short touch that didn't move
+    Tap { position: Vec2 },
+    /// one finger moved by `delta` pixels
+    Drag { delta: Vec2 },
+    /// the distance between two fingers changed by `scale` around `center`
+    Pinch { scale: f32, center: Vec2 },
+    /// the center of two fingers moved by `delta` pixels
+    Pan { delta: Vec2 },
+}
+
+/// turns `WindowEvent::Touch` events into [`Gesture`]s
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    touches: BTreeMap<u64, TouchPoint>,
+    /// more than one finger was down during the current gesture,
+    /// lifting fingers one by one shouldn't turn into taps or drags
+    multi: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TouchPoint {
+    start: Vec2,
+    position: Vec2,
+    started: Instant,
+}
+
+//
+
+impl GestureRecognizer {
+    /// movement (in pixels) below which a touch still counts as a tap
+    const TAP_SLOP: f32 = 10.0;
+    const TAP_TIME: Duration = Duration::from_millis(300);
+
+    pub fn touch(&mut self, touch: &Touch) -> Option<Gesture> {
+        let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(
+                    touch.id,
+                    TouchPoint {
+                        start: position,
+                        position,
+                        started: Instant::now(),
+                    },
+                );
+                self.multi |= self.touches.len() > 1;
+                None
+            }
+            TouchPhase::Moved => self.moved(touch.id, position),
+            TouchPhase::Ended => {
+                let point = self.touches.remove(&touch.id)?;
+                let tap = !self.multi
+                    && point.started.elapsed() <= Self::TAP_TIME
+                    && point.start.distance(position) <= Self::TAP_SLOP;
+
+                if self.touches.is_empty() {
+                    self.multi = false;
+                }
+
+                tap.then_some(Gesture::Tap { position })
+            }
+            TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+                if self.touches.is_empty() {
+                    self.multi = false;
+                }
+                None
+            }
+        }
+    }
+
+    fn moved(&mut self, id: u64, position: Vec2) -> Option<Gesture> {
+        let before: Vec<Vec2> = self.touches.values().map(|t| t.position).collect();
+        let point = self.touches.get_mut(&id)?;
+        let delta = position - point.position;
+        point.position = position;
+
+        match before.as_slice() {
+            [_] if !self.multi => {
+                // wait for the tap slop before starting to drag
+                if point.start.distance(position) <= Self::TAP_SLOP {
+                    return None;
+                }
+                Some(Gesture::Drag { delta })
+            }
+            [_, _] => {
+                let after: Vec<Vec2> = self.touches.values().map(|t| t.position).collect();
+                let (d0, d1) = (before[0].distance(before[1]), after[0].distance(after[1]));
+                let (c0, c1) = ((before[0] + before[1]) * 0.5, (after[0] + after[1]) * 0.5);
+
+                // report the dominant motion,
+                // fingers moving apart also moves their center a bit
+                if (d1 - d0).abs() > (c1 - c0).length() && d0 > f32::EPSILON {
+                    Some(Gesture::Pinch {
+                        scale: d1 / d0,
+                        center: c1,
+                    })
+                } else {
+                    Some(Gesture::Pan { delta: c1 - c0 })
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{dpi::PhysicalPosition, event::DeviceId};
+
+    use super::*;
+
+    fn touch(
+        gestures: &mut GestureRecognizer,
+        id: u64,
+        phase: TouchPhase,
+        (x, y): (f64, f64),
+    ) -> Option<Gesture> {
+        gestures.touch(&Touch {
+            // SAFETY: only compared, never passed to the platform
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition::new(x, y),
+            force: None,
+            id,
+        })
+    }
+
+    #[test]
+    fn taps_and_drags() {
+        use TouchPhase::*;
+        let mut gestures = GestureRecognizer::default();
+
+        // a wobble inside the slop is still a tap
+        assert_eq!(touch(&mut gestures, 0, Started, (100.0, 100.0)), None);
+        assert_eq!(touch(&mut gestures, 0, Moved, (104.0, 103.0)), None);
+        assert_eq!(
+            touch(&mut gestures, 0, Ended, (104.0, 103.0)),
+            Some(Gesture::Tap {
+                position: Vec2::new(104.0, 103.0)
+            })
+        );
+
+        // past the slop it's a drag, and no tap when lifted
+        assert_eq!(touch(&mut gestures, 1, Started, (100.0, 100.0)), None);
+        assert_eq!(touch(&mut gestures, 1, Moved, (105.0, 100.0)), None);
+        assert_eq!(
+            touch(&mut gestures, 1, Moved, (120.0, 100.0)),
+            Some(Gesture::Drag {
+                delta: Vec2::new(15.0, 0.0)
+            })
+        );
+        assert_eq!(touch(&mut gestures, 1, Ended, (120.0, 100.0)), None);
+
+        // events of unknown fingers are ignored
+        assert_eq!(touch(&mut gestures, 7, Moved, (0.0, 0.0)), None);
+        assert_eq!(touch(&mut gestures, 7, Ended, (0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn pinches_and_pans() {
+        use TouchPhase::*;
+        let mut gestures = GestureRecognizer::default();
+
+        touch(&mut gestures, 0, Started, (100.0, 100.0));
+        touch(&mut gestures, 1, Started, (200.0, 100.0));
+
+        // apart: the distance doubles around the new center
+        assert_eq!(
+            touch(&mut gestures, 1, Moved, (300.0, 100.0)),
+            Some(Gesture::Pinch {
+                scale: 2.0,
+                center: Vec2::new(200.0, 100.0)
+            })
+        );
+        // together again
+        assert_eq!(
+            touch(&mut gestures, 0, Moved, (200.0, 100.0)),
+            Some(Gesture::Pinch {
+                scale: 0.5,
+                center: Vec2::new(250.0, 100.0)
+            })
+        );
+        // sideways: the center moves by half a finger
+        assert_eq!(
+            touch(&mut gestures, 0, Moved, (200.0, 140.0)),
+            Some(Gesture::Pan {
+                delta: Vec2::new(0.0, 20.0)
+            })
+        );
+
+        // lifting the fingers one by one is neither a tap nor a drag
+        assert_eq!(touch(&mut gestures, 0, Ended, (200.0, 140.0)), None);
+        assert_eq!(touch(&mut gestures, 1, Moved, (340.0, 100.0)), None);
+        assert_eq!(touch(&mut gestures, 1, Ended, (340.0, 100.0)), None);
+
+        // a new single finger gesture starts from scratch
+        touch(&mut gestures, 2, Started, (10.0, 10.0));
+        assert!(matches!(
+            touch(&mut gestures, 2, Ended, (10.0, 10.0)),
+            Some(Gesture::Tap { .. })
+        ));
+    }
+
+    #[test]
+    fn cancelled_touches() {
+        use TouchPhase::*;
+        let mut gestures = GestureRecognizer::default();
+
+        // a cancelled touch doesn't end in a tap
+        touch(&mut gestures, 0, Started, (50.0, 50.0));
+        assert_eq!(touch(&mut gestures, 0, Cancelled, (50.0, 50.0)), None);
+        assert_eq!(touch(&mut gestures, 0, Ended, (50.0, 50.0)), None);
+
+        // cancelling one finger of a pinch leaves the other lifted without a tap
+        touch(&mut gestures, 1, Started, (0.0, 0.0));
+        touch(&mut gestures, 2, Started, (100.0, 0.0));
+        assert_eq!(touch(&mut gestures, 2, Cancelled, (100.0, 0.0)), None);
+        assert_eq!(touch(&mut gestures, 1, Ended, (0.0, 0.0)), None);
+
+        // and once all are gone, taps work again
+        touch(&mut gestures, 3, Started, (20.0, 20.0));
+        assert!(matches!(
+            touch(&mut gestures, 3, Ended, (20.0, 20.0)),
+            Some(Gesture::Tap { .. })
+        ));
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    env,
+    net::UdpSocket,
+    process::{Child, Command, ExitStatus},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use winit::window::{Fullscreen, Window};
+
+use crate::settings::{KioskSettings, RenderMode, SettingsInner};
+
+//
+
+/// set in the relaunched app, the port the supervisor listens for heartbeats on
+const HEARTBEAT_VAR: &str = "WGPU_TEMPLATE_KIOSK_HEARTBEAT";
+
+/// the main loop's side of the watchdog, sends a heartbeat to the supervisor
+#[derive(Debug)]
+pub struct Heartbeat {
+    socket: UdpSocket,
+    last: Instant,
+}
+
+/// relaunches the app after it crashed or stopped sending heartbeats
+struct Supervisor {
+    socket: UdpSocket,
+    watchdog: Duration,
+    /// a crash right after starting waits longer before the next try
+    backoff: Duration,
+}
+
+//
+
+/// the kiosk preset over the settings file: nothing that would show the desktop
+pub fn apply_preset(settings: &mut SettingsInner) {
+    if !settings.kiosk.enabled {
+        return;
+    }
+    settings.window.tray = false;
+    settings.window.transparent = false;
+    // the watchdog needs a main loop that never sleeps for long
+    settings.window.render_mode = RenderMode::Continuous;
+}
+
+/// exclusive fullscreen in the monitor's largest, then fastest mode,
+/// borderless if it has none, and no cursor
+pub fn lock_window(window: &Window, settings: &KioskSettings) {
+    let monitor = window
+        .current_monitor()
+        .or_else(|| window.primary_monitor());
+    let mode = monitor.as_ref().and_then(|monitor| {
+        monitor.video_modes().max_by_key(|mode| {
+            let size = mode.size();
+            (size.width * size.height, mode.refresh_rate_millihertz())
+        })
+    });
+    let fullscreen = match mode.filter(|_| settings.exclusive_fullscreen) {
+        Some(mode) => {
+            tracing::info!("kiosk: exclusive fullscreen {mode}");
+            Fullscreen::Exclusive(mode)
+        }
+        None => Fullscreen::Borderless(monitor),
+    };
+    window.set_fullscreen(Some(fullscreen));
+    if settings.hide_cursor {
+        window.set_cursor_visible(false);
+    }
+}
+
+/// in the kiosk mode with `relaunch`, run the app as a child process and
+/// relaunch it whenever it crashes (a lost GPU device panics) or hangs,
+/// returns when it exits normally
+///
+/// `None` if this process should run the app itself
+pub fn supervise(settings: &KioskSettings) -> Option<ExitStatus> {
+    if !settings.enabled || !settings.relaunch || env::var_os(HEARTBEAT_VAR).is_some() {
+        return None;
+    }
+
+    let mut supervisor = match Supervisor::new(settings) {
+        Ok(supervisor) => supervisor,
+        Err(err) => {
+            tracing::error!("Failed to start the kiosk supervisor, running unsupervised: {err:#}");
+            return None;
+        }
+    };
+    loop {
+        match supervisor.run_once() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) => {}
+            Err(err) => {
+                tracing::error!("Failed to launch the app: {err:#}");
+                supervisor.wait_backoff(Duration::ZERO);
+            }
+        }
+    }
+}
+
+impl Heartbeat {
+    /// `None` unless this process was launched by [`supervise`]
+    pub fn from_env() -> Option<Self> {
+        let port: u16 = env::var(HEARTBEAT_VAR).ok()?.parse().ok()?;
+        let socket = UdpSocket::bind(("127.0.0.1", 0))
+            .and_then(|socket| socket.connect(("127.0.0.1", port)).map(|()| socket))
+            .map_err(|err| tracing::error!("Failed to connect to the kiosk supervisor: {err}"))
+            .ok()?;
+        _ = socket.set_nonblocking(true);
+        Some(Self {
+            socket,
+            last: Instant::now() - Duration::from_secs(1),
+        })
+    }
+
+    /// call every main loop iteration, sends at most ten heartbeats per second
+    pub fn beat(&mut self) {
+        let now = Instant::now();
+        if now - self.last >= Duration::from_millis(100) {
+            self.last = now;
+            _ = self.socket.send(&[0]);
+        }
+    }
+}
+
+impl Supervisor {
+    /// startup, shader compilation and asset loading, before the first heartbeat
+    const STARTUP_GRACE: Duration = Duration::from_secs(60);
+    /// a run shorter than this counts as a crash loop
+    const STABLE: Duration = Duration::from_secs(30);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    fn new(settings: &KioskSettings) -> Result<Self> {
+        let socket = UdpSocket::bind(("127.0.0.1", 0)).context("heartbeat socket")?;
+        Ok(Self {
+            socket,
+            watchdog: Duration::from_secs_f32(settings.watchdog_seconds.max(1.0)),
+            backoff: Duration::ZERO,
+        })
+    }
+
+    fn launch(&self) -> Result<Child> {
+        let port = self.socket.local_addr()?.port();
+        Command::new(env::current_exe()?)
+            .args(env::args_os().skip(1))
+            .env(HEARTBEAT_VAR, port.to_string())
+            .spawn()
+            .context("spawning the app")
+    }
+
+    /// one run of the app, `Some` if it exited normally
+    fn run_once(&mut self) -> Result<Option<ExitStatus>> {
+        let started = Instant::now();
+        let mut child = self.launch()?;
+        tracing::info!("kiosk: app started, pid {}", child.id());
+
+        let mut deadline = started + Self::STARTUP_GRACE.max(self.watchdog);
+        let mut buf = [0u8; 16];
+        let crashed = loop {
+            if let Some(status) = child.try_wait()? {
+                if status.success() {
+                    return Ok(Some(status));
+                }
+                tracing::error!("kiosk: the app exited with {status}, relaunching");
+                break true;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                tracing::error!("kiosk: no heartbeat for {:?}, relaunching", self.watchdog);
+                _ = child.kill();
+                _ = child.wait();
+                break true;
+            }
+            // wakes up often enough to see the child exit
+            let timeout = (deadline - now).min(Duration::from_millis(500));
+            self.socket.set_read_timeout(Some(timeout))?;
+            if self.socket.recv(&mut buf).is_ok() {
+                deadline = Instant::now() + self.watchdog;
+            }
+        };
+
+        if crashed {
+            self.wait_backoff(started.elapsed());
+        }
+        Ok(None)
+    }
+
+    fn wait_backoff(&mut self, ran: Duration) {
+        self.backoff = Self::next_backoff(self.backoff, ran);
+        thread::sleep(self.backoff);
+    }
+
+    /// the wait before relaunching an app that crashed after running for `ran`:
+    /// a second after a stable run, doubling while it keeps crashing
+    fn next_backoff(backoff: Duration, ran: Duration) -> Duration {
+        if ran >= Self::STABLE {
+            Duration::from_secs(1)
+        } else {
+            (backoff * 2).clamp(Duration::from_secs(1), Self::MAX_BACKOFF)
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_loops_back_off_up_to_a_minute() {
+        let s = Duration::from_secs;
+        let mut backoff = Duration::ZERO;
+        let mut waits = Vec::new();
+        for _ in 0..8 {
+            backoff = Supervisor::next_backoff(backoff, s(2));
+            waits.push(backoff.as_secs());
+        }
+        assert_eq!(waits, [1, 2, 4, 8, 16, 32, 60, 60]);
+
+        // a stable run starts over, a failed launch counts as a crash
+        assert_eq!(Supervisor::next_backoff(s(60), Supervisor::STABLE), s(1));
+        assert_eq!(Supervisor::next_backoff(s(1), Duration::ZERO), s(2));
+    }
+
+    #[test]
+    fn preset_overrides_only_when_enabled() {
+        let mut settings = SettingsInner::default();
+        settings.window.tray = true;
+        settings.window.transparent = true;
+        settings.window.render_mode = RenderMode::Reactive;
+
+        let mut disabled = settings.clone();
+        apply_preset(&mut disabled);
+        assert!(disabled.window.tray && disabled.window.transparent);
+        assert_eq!(disabled.window.render_mode, RenderMode::Reactive);
+
+        settings.kiosk.enabled = true;
+        apply_preset(&mut settings);
+        assert!(!settings.window.tray && !settings.window.transparent);
+        assert_eq!(settings.window.render_mode, RenderMode::Continuous);
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    fmt,
+    fs::File,
+    io::Read,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    time::{Duration, Instant},
+};
+
+use crate::{settings::LatencySettings, threads};
+
+//
+
+/// click-to-photon latency tester: a click flashes a white quad in the
+/// corner of the window, and the time until it's on screen is measured
+///
+/// without a photodiode the time until the frame was presented plus a refresh
+/// period is an estimate, a photodiode over the quad on a serial port
+/// (any byte it sends is a flash seen) measures the real thing;
+/// filming the mouse and the screen with a high speed camera works too,
+/// the quad is the marker to count frames to
+pub struct LatencyTester {
+    enabled: bool,
+    photodiode: Option<PathBuf>,
+    /// light detected, from the photodiode thread
+    light: Option<Receiver<Instant>>,
+    /// monitor refresh period, for the estimate
+    refresh: Option<Duration>,
+    pending: Option<Pending>,
+    samples: Vec<LatencySample>,
+}
+
+/// one click and what became of it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencySample {
+    /// until the frame with the flash was presented
+    pub present: Duration,
+    /// until the photodiode saw the flash
+    pub photon: Option<Duration>,
+}
+
+/// what the samples so far add up to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyReport {
+    pub samples: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub max: Duration,
+    /// from the photodiode, estimated from the present times otherwise
+    pub measured: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    clicked: Instant,
+    presented: Option<Instant>,
+    photon: Option<Instant>,
+    /// frames the flash is still shown for
+    flash_frames: u32,
+}
+
+//
+
+impl LatencyTester {
+    /// frames the quad stays white, so slow photodiodes and cameras catch it
+    const FLASH_FRAMES: u32 = 4;
+    /// a photodiode that didn't see the flash by then missed it
+    const PHOTON_TIMEOUT: Duration = Duration::from_millis(500);
+    /// the quad in the top left corner, in pixels
+    pub const FLASH_SIZE: u32 = 96;
+
+    pub fn new(settings: &LatencySettings) -> Self {
+        Self {
+            enabled: false,
+            photodiode: settings.photodiode.clone(),
+            light: None,
+            refresh: None,
+            pending: None,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// start or stop testing, starting opens the photodiode
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.pending = None;
+        self.samples.clear();
+        if !self.enabled {
+            self.light = None;
+            tracing::info!("latency tester off");
+            return;
+        }
+
+        tracing::info!("latency tester on, click to measure");
+        if let Some(path) = self.photodiode.clone() {
+            match open_photodiode(path.clone()) {
+                Ok(light) => self.light = Some(light),
+                Err(err) => tracing::error!("Failed to open the photodiode {path:?}: {err}"),
+            }
+        }
+    }
+
+    /// a mouse button went down at `now`
+    pub fn click(&mut self, now: Instant) {
+        if self.enabled && self.pending.is_none() {
+            self.pending = Some(Pending {
+                clicked: now,
+                presented: None,
+                photon: None,
+                flash_frames: Self::FLASH_FRAMES,
+            });
+        }
+    }
+
+    /// a click is waiting for its flash to be presented or seen
+    pub fn is_measuring(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// the next frame should show the flash
+    pub fn flashing(&self) -> bool {
+        self.pending.is_some_and(|pending| pending.flash_frames > 0)
+    }
+
+    /// a frame was presented at `now`, a finished sample updates the report,
+    /// `refresh` is the monitor refresh period if known
+    pub fn presented(&mut self, now: Instant, refresh: Option<Duration>) -> Option<LatencyReport> {
+        self.refresh = refresh;
+        let pending = self.pending.as_mut()?;
+        if pending.flash_frames > 0 {
+            pending.flash_frames -= 1;
+            pending.presented.get_or_insert(now);
+        }
+
+        if let Some(light) = self.light.as_ref() {
+            loop {
+                match light.try_recv() {
+                    // light from before the click is a previous flash fading
+                    Ok(at) if at > pending.clicked => {
+                        pending.photon.get_or_insert(at);
+                    }
+                    Ok(_) => {}
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        tracing::warn!("the photodiode went away");
+                        self.light = None;
+                        break;
+                    }
+                }
+            }
+        }
+
+        let waiting_for_light = self.light.is_some()
+            && pending.photon.is_none()
+            && now - pending.clicked < Self::PHOTON_TIMEOUT;
+        if pending.flash_frames > 0 || waiting_for_light {
+            return None;
+        }
+
+        let pending = self.pending.take()?;
+        let sample = LatencySample {
+            present: pending.presented? - pending.clicked,
+            photon: pending.photon.map(|photon| photon - pending.clicked),
+        };
+        if self.light.is_some() && sample.photon.is_none() {
+            tracing::warn!("the photodiode didn't see the flash");
+        }
+        tracing::info!("latency sample: {sample:?}");
+        self.samples.push(sample);
+        self.report()
+    }
+
+    /// click-to-photon over the samples so far, measured if every sample
+    /// has a photodiode time
+    pub fn report(&self) -> Option<LatencyReport> {
+        let measured = self.samples.iter().all(|sample| sample.photon.is_some());
+        // the frame is scanned out at the next vblank after presenting
+        let scanout = self.refresh.unwrap_or(Duration::from_micros(16_667));
+        let times: Vec<Duration> = self
+            .samples
+            .iter()
+            .map(|sample| match sample.photon {
+                Some(photon) if measured => photon,
+                _ => sample.present + scanout,
+            })
+            .collect();
+
+        Some(LatencyReport {
+            samples: times.len(),
+            min: *times.iter().min()?,
+            mean: times.iter().sum::<Duration>() / times.len() as u32,
+            max: *times.iter().max()?,
+            measured,
+        })
+    }
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "click to photon {}{:.1} ms (min {:.1}, max {:.1}, {} samples)",
+            if self.measured { "" } else { "~" },
+            ms(self.mean),
+            ms(self.min),
+            ms(self.max),
+            self.samples
+        )
+    }
+}
+
+/// every byte read from `path` is the time light was detected
+///
+/// a USB serial device like an Arduino with a photodiode, the port settings
+/// are left as they are (CDC ACM devices ignore the baud rate)
+fn open_photodiode(path: PathBuf) -> std::io::Result<Receiver<Instant>> {
+    let mut file = File::open(&path)?;
+    let (send, recv) = mpsc::channel();
+    threads::spawn("photodiode", move || {
+        let mut buf = [0u8; 64];
+        loop {
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => {
+                    if send.send(Instant::now()).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Failed to read the photodiode {path:?}: {err}");
+                    break;
+                }
+            }
+        }
+    })?;
+    Ok(recv)
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn estimates_from_present_times() {
+        let mut tester = LatencyTester::new(&LatencySettings::default());
+        let start = Instant::now();
+        tester.click(start);
+        assert!(!tester.flashing(), "off until toggled");
+
+        tester.toggle();
+        tester.click(start);
+        // a second click while measuring is ignored
+        tester.click(start + MS);
+        assert!(tester.flashing());
+
+        let mut report = None;
+        for frame in 1..=LatencyTester::FLASH_FRAMES {
+            assert!(report.is_none());
+            report = tester.presented(start + frame * 8 * MS, Some(10 * MS));
+        }
+        assert!(!tester.flashing());
+        let report = report.unwrap();
+        assert_eq!(report.samples, 1);
+        assert_eq!(report.mean, 18 * MS);
+        assert!(!report.measured);
+        assert!(report.to_string().starts_with("click to photon ~18.0 ms"));
+    }
+
+    #[test]
+    fn measures_with_a_photodiode() {
+        let mut tester = LatencyTester::new(&LatencySettings::default());
+        tester.toggle();
+        let (send, recv) = mpsc::channel();
+        tester.light = Some(recv);
+
+        let start = Instant::now();
+        // a fading flash from before the click
+        send.send(start).unwrap();
+        tester.click(start + MS);
+        for frame in 1..=LatencyTester::FLASH_FRAMES {
+            if frame == 3 {
+                send.send(start + 30 * MS).unwrap();
+            }
+            let report = tester.presented(start + frame * 10 * MS, None);
+            if frame < LatencyTester::FLASH_FRAMES {
+                assert_eq!(report, None);
+            } else {
+                let report = report.unwrap();
+                assert!(report.measured);
+                assert_eq!(report.mean, 29 * MS);
+            }
+        }
+    }
+}
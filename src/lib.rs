@@ -0,0 +1,84 @@
+use accesskit_winit::ActionRequestEvent;
+use serde::Serialize;
+
+use crate::{
+    color::ColorVision,
+    input::Action,
+    platform::{
+        dialogs::DialogResult,
+        power::{PowerSource, ThermalState},
+        tray::TrayAction,
+    },
+};
+
+//
+
+pub mod accessibility;
+pub mod args;
+pub mod assets;
+pub mod build_info;
+pub mod camera;
+pub mod color;
+pub mod coords;
+pub mod dirs;
+pub mod events;
+pub mod graphics;
+pub mod history;
+pub mod idle;
+pub mod input;
+pub mod kiosk;
+pub mod latency;
+pub mod migrate;
+pub mod offline;
+pub mod platform;
+pub mod prelude;
+pub mod profiler;
+pub mod project;
+pub mod redraw;
+pub mod rng;
+pub mod scene;
+pub mod settings;
+pub mod sim;
+pub mod spline;
+pub mod threads;
+pub mod tour;
+pub mod update;
+
+//
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeSettings {
+    pub enable_uv: bool,
+    /// render the state interpolated between ticks instead of the latest tick
+    pub interpolate: bool,
+    pub high_contrast: bool,
+    pub color_vision: ColorVision,
+    /// index into [`Palette::BUILTIN`]
+    pub palette: usize,
+}
+
+/// events sent to the event loop from other threads
+#[derive(Debug)]
+pub enum UserEvent {
+    ScreenReader(ActionRequestEvent),
+    /// a file dialog was closed
+    Dialog(DialogResult),
+    /// picked from the tray icon's menu
+    Tray(TrayAction),
+    /// a global hotkey was pressed, the window may not be focused
+    GlobalHotkey(Action),
+    /// switched between AC and battery
+    PowerSource(PowerSource),
+    /// a thermal throttling hint from the OS
+    Thermal(ThermalState),
+}
+
+//
+
+//
+
+impl From<ActionRequestEvent> for UserEvent {
+    fn from(event: ActionRequestEvent) -> Self {
+        Self::ScreenReader(event)
+    }
+}
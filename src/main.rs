@@ -1,30 +1,70 @@
-use std::{env, sync::Arc};
+use std::{
+    env,
+    fs::File,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use glam::Vec2;
 use winit::{
-    dpi::LogicalSize,
-    event::{ElementState, Event, KeyboardInput, MouseScrollDelta, VirtualKeyCode, WindowEvent},
-    event_loop::EventLoopBuilder,
+    dpi::{LogicalPosition, LogicalSize},
+    event::{
+        DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent,
+    },
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopWindowTarget},
     platform::{wayland::EventLoopBuilderExtWayland, x11::EventLoopBuilderExtX11},
-    window::WindowBuilder,
+    window::{Fullscreen, Window, WindowBuilder},
 };
 
-use crate::settings::GlobalSettings;
-
-//
-
-pub mod graphics;
-pub mod settings;
+use wgpu_template::{
+    accessibility::{Accessibility, ScreenReader},
+    args::Args,
+    assets::Assets,
+    build_info::BUILD,
+    color::{HdrLevels, Palette},
+    events,
+    graphics::{self, capture::TickCapture, pacing::FrameLimiter},
+    history::{Command, Editable, History, SettingValue},
+    idle::IdleDetector,
+    input::{Action, Binding, Gesture, GestureRecognizer, GlobalHotkeys, InputMap},
+    kiosk,
+    latency::LatencyTester,
+    migrate,
+    offline::OfflineRender,
+    platform::{
+        dialogs::{DialogPurpose, DialogResult, Dialogs},
+        display::DisplayInfo,
+        power::{PowerSource, ThermalState},
+        safe_area::SafeArea,
+        tray::{Tray, TrayAction, TrayState},
+    },
+    profiler::{LoopProfiler, Track},
+    project::{Project, RecentProjects},
+    redraw::Redraw,
+    rng::RngService,
+    scene::Scene,
+    settings::{GlobalSettings, PowerSettings, WindowSettings},
+    sim::Simulation,
+    threads,
+    tour::Tour,
+    update, RuntimeSettings, UserEvent,
+};
 
 //
 
-pub struct RuntimeSettings {
-    pub enable_uv: bool,
+fn main() {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("tokio worker")
+        .build()
+        .expect("Failed to start the async runtime")
+        .block_on(run());
 }
 
-//
+async fn run() {
+    let args = Args::parse();
 
-#[tokio::main]
-async fn main() {
     const SILENCE_WGPU: &str = "wgpu_core=error,wgpu_hal=error,naga=error,debug";
 
     let log = env::var("RUST_LOG")
@@ -37,14 +77,75 @@ async fn main() {
     } */
 
     tracing_subscriber::fmt::init();
+    threads::install_panic_hook();
+
+    tracing::info!(
+        "{} {} ({}, {})",
+        BUILD.name,
+        BUILD.version,
+        BUILD.git_hash,
+        BUILD.profile
+    );
+
+    migrate::migrate();
 
     let settings = GlobalSettings::load();
     settings.autosave();
 
+    // before anything the relaunched app does again
+    if let Some(status) = kiosk::supervise(&settings.kiosk) {
+        std::process::exit(status.code().unwrap_or(0));
+    }
+
+    let project = args.project.as_deref().map(|root| {
+        Project::open(root).unwrap_or_else(|err| {
+            tracing::error!("Failed to open the project: {err:#}");
+            std::process::exit(1);
+        })
+    });
+    let mut settings = match &project {
+        Some(project) => project.settings(&settings).unwrap_or_else(|err| {
+            tracing::error!("Failed to load the project settings: {err:#}");
+            settings
+        }),
+        None => settings,
+    };
+    if let Some(project) = &project {
+        tracing::info!("project {}", project.manifest.name);
+        add_recent(project);
+    }
+
+    kiosk::apply_preset(&mut settings);
+
+    if settings.updates.check {
+        let settings = settings.updates.clone();
+        tokio::spawn(async move {
+            match update::check(&settings, &BUILD).await {
+                Ok(Some(update)) => {
+                    tracing::info!("version {} is available: {}", update.version, update.url)
+                }
+                Ok(None) => {}
+                Err(err) => tracing::warn!("Failed to check for updates: {err}"),
+            }
+        });
+    }
+
     tracing::debug!("{:#?}", &*settings);
 
+    let rng = RngService::from_settings(&settings.rng, args.seed);
+    // mounting archives and checking downloads happens while the GPU is set up
+    let assets = tokio::task::spawn_blocking({
+        let settings = settings.assets.clone();
+        move || Assets::new(&settings)
+    });
+    // the OS preferences come from gsettings or defaults processes
+    let accessibility = tokio::task::spawn_blocking({
+        let settings = settings.accessibility;
+        move || Accessibility::resolve(&settings)
+    });
+
     // use winit::platform::{wayland::*, x11::*};
-    let mut events = EventLoopBuilder::new();
+    let mut events = EventLoopBuilder::with_user_event();
     let events = if settings.window.force_wayland {
         events.with_wayland().build()
     } else if settings.window.force_x11 {
@@ -59,52 +160,399 @@ async fn main() {
             settings.window.resolution.0,
             settings.window.resolution.1,
         ))
-        .with_transparent(true)
+        .with_transparent(settings.window.transparent)
         /* .with_fullscreen(Some(Fullscreen::Exclusive(VideoMode::
         ))) */
         .with_visible(false)
         .build(&events)
         .expect("Failed to open a window");
 
-    let window = Arc::new(window);
+    let monitors: Vec<String> = window
+        .available_monitors()
+        .filter_map(|monitor| monitor.name())
+        .collect();
+    tracing::info!("monitors: {monitors:?}");
+
+    let monitor = window
+        .current_monitor()
+        .or_else(|| window.primary_monitor());
+    let monitor = monitor.and_then(|monitor| monitor.name());
+    if let Some(o) = monitor
+        .as_deref()
+        .and_then(|name| settings.window.monitor(name))
+    {
+        tracing::debug!("using the window settings for `{}`", o.name);
+        if let Some((width, height)) = o.resolution {
+            window.set_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some((x, y)) = o.position {
+            window.set_outer_position(LogicalPosition::new(x, y));
+        }
+    }
+
+    if settings.kiosk.enabled {
+        kiosk::lock_window(&window, &settings.kiosk);
+    }
+    let mut window = Arc::new(window);
+
+    let mirror = settings.window.mirror.enabled.then(|| {
+        let mirror = &settings.window.mirror;
+        let monitor = mirror.monitor.as_ref().and_then(|name| {
+            let monitor = window
+                .available_monitors()
+                .find(|monitor| monitor.name().as_deref() == Some(&**name));
+            if monitor.is_none() {
+                tracing::warn!("no monitor `{name}` for the mirror, opening a window");
+            }
+            monitor
+        });
+        let window = WindowBuilder::new()
+            .with_title(format!("{} (mirror)", settings.window.title))
+            .with_inner_size(LogicalSize::new(mirror.resolution.0, mirror.resolution.1))
+            .with_fullscreen(monitor.map(|monitor| Fullscreen::Borderless(Some(monitor))))
+            .build(&events)
+            .expect("Failed to open the mirror window");
+        Arc::new(window)
+    });
+
+    // the adapter is picked once, so only starting on battery gets the low power GPU
+    let power = PowerSource::current();
+    let power_settings = settings.graphics.power;
+    if power_settings.battery_saver && power.on_battery() {
+        settings.graphics.gpu_preference = power_settings.gpu_preference;
+    }
+
+    // the GPU is picked, a cleared frame presented and the pipelines compiled
+    // while the rest of the app starts up
+    let graphics =
+        tokio::spawn(graphics::Graphics::start(&settings, &rng, window.clone(), mirror).unwrap());
+
+    let accessibility = accessibility.await.unwrap();
+    let mut sim = Simulation::new(&settings.simulation);
+    sim.reduced_motion = accessibility.reduced_motion;
+    let mut input = InputMap::new(&settings.input);
+    let mut gestures = GestureRecognizer::default();
+
+    let mut runtime = RuntimeSettings {
+        enable_uv: false,
+        interpolate: true,
+        high_contrast: accessibility.high_contrast,
+        color_vision: settings.accessibility.color_vision,
+        palette: Palette::find(&settings.graphics.indexed.palette).unwrap_or_else(|| {
+            tracing::warn!("unknown palette `{}`", settings.graphics.indexed.palette);
+            0
+        }),
+    };
+
+    // the editable state, the template's own scene has no nodes yet
+    let mut editor = Editor {
+        scene: match &project {
+            Some(project) => project.load_scene().unwrap_or_else(|err| {
+                tracing::error!("Failed to load the project scene: {err:#}");
+                Scene::default()
+            }),
+            None => Scene::default(),
+        },
+        history: History::default(),
+        project,
+        dialogs: Dialogs::new(events.create_proxy()),
+    };
+
+    // AccessKit has to be set up before the window is first shown
+    let mut screen_reader = ScreenReader::new(
+        &window,
+        settings.window.title.clone(),
+        screen_reader_actions(&input),
+        &runtime,
+        events.create_proxy(),
+    );
+
+    let mut graphics = graphics.await.unwrap().unwrap();
+    let mut assets = assets.await.unwrap();
+    graphics.hdr = hdr_levels(&settings.window, monitor.as_deref());
+    graphics.safe_area = SafeArea::of_window(&window, &settings.window);
+
+    let captures = settings.features.captures;
+    if !captures && (args.capture_frame.is_some() || !args.capture_ticks.is_empty()) {
+        tracing::warn!("captures are turned off in [features], ignoring --capture-*");
+    }
+    if let Some(frame) = args.capture_frame.filter(|_| captures) {
+        graphics.capture_at(frame, &settings);
+    }
+
+    if args.gamma_audit {
+        let (report, image) = match graphics.gamma_audit() {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Gamma audit failed: {err}");
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = File::create("gamma-audit.png")
+            .map_err(Into::into)
+            .and_then(|file| image.write_png(file))
+        {
+            tracing::error!("Failed to write gamma-audit.png: {err}");
+        }
+
+        tracing::info!("gamma audit: {report}");
+        let problems = report.problems();
+        for problem in problems.iter() {
+            tracing::warn!("gamma audit: {problem}");
+        }
+        std::process::exit(if problems.is_empty() { 0 } else { 1 });
+    }
 
-    let mut graphics = graphics::Graphics::init(&settings, window.clone())
-        .await
-        .unwrap();
+    if args.bench_workgroups {
+        tracing::info!("picked compute workgroups: {}", graphics.workgroups);
+        for timing in graphics.bench_workgroups() {
+            tracing::info!("{timing}");
+        }
+        std::process::exit(0);
+    }
 
-    let mut settings = RuntimeSettings { enable_uv: false };
+    let tour = args.tour.then(Tour::demo);
+    // a recorded tour renders as many frames as it lasts
+    let tour_frames = tour
+        .as_ref()
+        .filter(|_| args.out.is_some())
+        .map(|tour| 0..(tour.duration().as_secs_f64() * args.fps.unwrap_or(60.0)).ceil() as u64);
+    if let Some(frames) = args.render_frames.clone().or(tour_frames) {
+        let offline = OfflineRender {
+            frames,
+            fps: args.fps.unwrap_or(60.0),
+            out: args.out.clone().unwrap_or_else(|| "frames".into()),
+            size: args.size.unwrap_or(settings.window.resolution),
+            tour,
+        };
+        if let Err(err) = offline.run(&mut graphics, &mut sim, &mut runtime) {
+            tracing::error!("Offline rendering failed: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut tick_capture =
+        TickCapture::new(args.capture_ticks.iter().copied().filter(|_| captures));
+    tick_capture.start(&mut sim);
+    let capture_size = args.size;
 
     window.set_visible(true);
+    let hide_cursor =
+        graphics.cursor.is_some() || (settings.kiosk.enabled && settings.kiosk.hide_cursor);
+    window.set_cursor_visible(!hide_cursor);
+
+    let tray_state = TrayState {
+        window_visible: true,
+        vsync: graphics.vsync(),
+    };
+    let mut shell = Shell {
+        tray: if settings.window.tray {
+            Tray::spawn(&settings.window.title, tray_state, events.create_proxy()).await
+        } else {
+            None
+        },
+        tray_state,
+        transparent: settings.window.transparent,
+        recreate_window: false,
+        toggle_profiler: false,
+        allow_exit: !settings.kiosk.enabled || settings.kiosk.allow_exit,
+        inspecting: false,
+        title: settings.window.title.clone(),
+        latency: settings
+            .features
+            .latency_tester
+            .then(|| LatencyTester::new(&settings.input.latency)),
+        _hotkeys: GlobalHotkeys::new(&settings.input.global, events.create_proxy()),
+    };
+    let proxy = events.create_proxy();
+
+    let mut power = PowerState {
+        source: power,
+        thermal: ThermalState::Nominal,
+    };
+    let mut limiter = FrameLimiter::new(0.0);
+    power.apply(&power_settings, &mut graphics, &mut limiter);
+    PowerSource::watch(events.create_proxy());
+    let mut heartbeat = kiosk::Heartbeat::from_env();
+    // physical pixels, for the pixel inspector
+    let mut cursor_position = None;
+    let mut idle = IdleDetector::new(&settings.window.idle, Instant::now());
+    let mut profiler = if settings.features.profiler {
+        LoopProfiler::new(Duration::from_secs(10))
+    } else {
+        LoopProfiler::disabled()
+    };
+    let mut redraw = Redraw::new(settings.window.render_mode);
+    // the start and the previous frame's time into the tour
+    let mut tour = tour.map(|tour| (tour, Instant::now(), None));
 
-    events.run(move |event, _events, control| {
+    events.run(move |event, target, control| {
         control.set_poll();
+        let event_start = Instant::now();
+        let per_frame = matches!(event, Event::MainEventsCleared | Event::NewEvents(_));
+        let action = events::dispatch(
+            &event,
+            window.id(),
+            event_start,
+            &mut input,
+            &mut idle,
+            &mut redraw,
+        );
+
+        if let Event::WindowEvent { window_id, event } = &event {
+            if Some(*window_id) == graphics.mirror_window_id() {
+                match event {
+                    WindowEvent::Resized(s) => graphics.mirror_resized((s.width, s.height)),
+                    WindowEvent::CloseRequested => graphics.close_mirror(),
+                    _ => {}
+                }
+                return;
+            }
+        }
+
+        if matches!(event, Event::NewEvents(_)) && shell.tray_state.window_visible {
+            // waits for the swapchain in the low latency mode
+            profiler.time(Track::Render, || graphics.begin_frame());
+        }
+
+        if let Event::WindowEvent { event, .. } = &event {
+            if screen_reader.window_event(&window, event) {
+                return;
+            }
+        }
+        if let Some(action) = action {
+            run_action(
+                action,
+                &mut runtime,
+                &mut editor,
+                &mut shell,
+                &input,
+                &window,
+                control,
+            );
+            screen_reader.update(&runtime);
+        }
 
         match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => control.set_exit(),
+            } => {
+                // with a tray icon, closing hides the window and Quit exits
+                if !shell.allow_exit {
+                    tracing::info!("kiosk: closing the window is disabled");
+                } else if shell.tray.is_some() {
+                    shell.set_window_visible(&window, false);
+                } else {
+                    control.set_exit();
+                }
+            }
             Event::WindowEvent {
                 event:
                     WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
-                                state: ElementState::Pressed,
+                                state: ElementState::Released,
                                 virtual_keycode: Some(key),
                                 ..
                             },
                         ..
                     },
                 ..
-            } => match key {
-                VirtualKeyCode::F1 => {
-                    settings.enable_uv = !settings.enable_uv;
+            } if shell.inspecting && input.key_released(key) == Some(Action::InspectPixel) => {
+                shell.inspecting = false;
+                window.set_title(&shell.title);
+            }
+            Event::UserEvent(UserEvent::Dialog(DialogResult { purpose, paths })) => {
+                match (purpose, paths.first()) {
+                    (_, None) => {}
+                    (DialogPurpose::OpenFiles | DialogPurpose::OpenProject, _) => {
+                        for path in &paths {
+                            editor.open_path(path);
+                        }
+                    }
+                    (DialogPurpose::SaveScreenshot, Some(path)) => {
+                        let size = window.inner_size();
+                        let state = sim.render_state(runtime.interpolate);
+                        let saved = graphics
+                            .render_offscreen(&runtime, &state, (size.width, size.height))
+                            .and_then(|image| image.write_png(File::create(path)?));
+                        match saved {
+                            Ok(()) => tracing::info!("screenshot saved to {}", path.display()),
+                            Err(err) => tracing::error!("Failed to save the screenshot: {err}"),
+                        }
+                    }
                 }
-                VirtualKeyCode::Escape => {
-                    control.set_exit();
+            }
+            Event::UserEvent(UserEvent::Tray(action)) => match action {
+                TrayAction::ToggleWindow => {
+                    shell.set_window_visible(&window, !shell.tray_state.window_visible);
+                }
+                TrayAction::ToggleVsync => {
+                    graphics.set_vsync(!graphics.vsync());
+                    shell.tray_state.vsync = graphics.vsync();
+                    shell.update_tray();
                 }
-                _ => {}
+                TrayAction::Quit => control.set_exit(),
             },
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
+                ..
+            } => editor.open_path(&path),
+            Event::UserEvent(UserEvent::PowerSource(source)) => {
+                power.source = source;
+                power.apply(&power_settings, &mut graphics, &mut limiter);
+            }
+            Event::UserEvent(UserEvent::Thermal(thermal)) => {
+                power.thermal = thermal;
+                power.apply(&power_settings, &mut graphics, &mut limiter);
+            }
+            Event::UserEvent(UserEvent::GlobalHotkey(action)) => {
+                run_action(
+                    action,
+                    &mut runtime,
+                    &mut editor,
+                    &mut shell,
+                    &input,
+                    &window,
+                    control,
+                );
+                screen_reader.update(&runtime);
+            }
+            Event::UserEvent(UserEvent::ScreenReader(request)) => {
+                if let Some(action) = screen_reader.action_requested(&request) {
+                    run_action(
+                        action,
+                        &mut runtime,
+                        &mut editor,
+                        &mut shell,
+                        &input,
+                        &window,
+                        control,
+                    );
+                    screen_reader.update(&runtime);
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Touch(touch),
+                ..
+            } => {
+                let size = window.inner_size();
+                let viewport = Vec2::new(size.width as f32, size.height as f32);
+                match gestures.touch(&touch) {
+                    Some(Gesture::Drag { delta } | Gesture::Pan { delta }) => {
+                        graphics.camera.pan_pixels(delta, viewport.y);
+                    }
+                    Some(Gesture::Pinch { scale, center }) => {
+                        graphics.camera.zoom_at(scale, center, viewport);
+                    }
+                    Some(Gesture::Tap { position }) => {
+                        tracing::debug!("tap at {position}");
+                    }
+                    None => {}
+                }
+            }
             Event::WindowEvent {
                 event:
                     WindowEvent::MouseWheel {
@@ -115,14 +563,497 @@ async fn main() {
             } => {
                 graphics.scrolled((x, y));
             }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                cursor_position = Some(Vec2::new(position.x as f32, position.y as f32));
+                if let Some(cursor) = graphics.cursor.as_mut() {
+                    cursor.pointer.moved(position.into());
+                }
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(latency) = shell.latency.as_mut() {
+                    latency.click(Instant::now());
+                    redraw.invalidate();
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. },
+                ..
+            } => {
+                cursor_position = None;
+                if let Some(cursor) = graphics.cursor.as_mut() {
+                    cursor.pointer.left();
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                if let Some(cursor) = graphics.cursor.as_mut() {
+                    cursor.pointer.raw_motion(delta);
+                    redraw.invalidate();
+                }
+            }
             Event::WindowEvent {
                 event: WindowEvent::Resized(s),
                 ..
             } => {
-                graphics.resized((s.width, s.height));
+                // going fullscreen or to another monitor
+                graphics.safe_area = SafeArea::of_window(&window, &settings.window);
+                if cfg!(any(target_os = "windows", target_os = "macos")) {
+                    graphics.resized_redraw(
+                        (s.width, s.height),
+                        &runtime,
+                        &sim.render_state(runtime.interpolate),
+                    );
+                } else {
+                    graphics.resized((s.width, s.height));
+                }
+            }
+            Event::MainEventsCleared => {
+                if let Some(heartbeat) = heartbeat.as_mut() {
+                    heartbeat.beat();
+                }
+                if std::mem::take(&mut shell.recreate_window) {
+                    let recreated =
+                        recreate_window(&window, &settings.window.title, shell.transparent, target)
+                            .and_then(|new| {
+                                let new = Arc::new(new);
+                                // AccessKit has to be set up before the window is first shown
+                                let reader = ScreenReader::new(
+                                    &new,
+                                    settings.window.title.clone(),
+                                    screen_reader_actions(&input),
+                                    &runtime,
+                                    proxy.clone(),
+                                );
+                                graphics.replace_window(new.clone())?;
+                                Ok((new, reader))
+                            });
+                    match recreated {
+                        Ok((new, reader)) => {
+                            window = new;
+                            screen_reader = reader;
+                            window.set_cursor_visible(!hide_cursor);
+                            window.set_visible(shell.tray_state.window_visible);
+                            redraw.invalidate();
+                            tracing::info!("window transparency: {}", shell.transparent);
+                        }
+                        Err(err) => {
+                            tracing::error!("Failed to recreate the window: {err}");
+                            shell.transparent = !shell.transparent;
+                        }
+                    }
+                }
+
+                if std::mem::take(&mut shell.toggle_profiler) {
+                    if !settings.features.profiler {
+                        tracing::info!("the profiler is turned off in [features]");
+                    } else if profiler.is_enabled() {
+                        profiler = LoopProfiler::disabled();
+                        graphics.set_profiling(false);
+                        tracing::info!("profiler detached");
+                    } else {
+                        profiler = LoopProfiler::new(Duration::from_secs(10));
+                        graphics.set_profiling(true);
+                        tracing::info!("profiler attached");
+                    }
+                }
+
+                profiler.time(Track::Assets, || {
+                    for asset in assets.poll_changes() {
+                        tracing::info!("reloading {asset}");
+                    }
+                });
+
+                profiler.time(Track::Update, || sim.update());
+                if !tick_capture.is_done() {
+                    let size = window.inner_size();
+                    let size = capture_size.unwrap_or((size.width, size.height));
+                    match tick_capture.update(&mut graphics, &mut sim, &runtime, size) {
+                        Ok(Some(tick)) => tracing::info!(
+                            "tick {tick} captured to {}",
+                            TickCapture::path(tick).display()
+                        ),
+                        Ok(None) => {}
+                        Err(err) => tracing::error!("Failed to capture a tick: {err}"),
+                    }
+                }
+                if let Some((tour, start, last)) = tour.as_mut() {
+                    let elapsed = start.elapsed();
+                    tour.update(*last, elapsed, &mut graphics, &mut runtime);
+                    *last = Some(elapsed);
+                    if elapsed > tour.duration() {
+                        tracing::info!("tour finished");
+                        control.set_exit();
+                    }
+                }
+                if shell.tray_state.window_visible {
+                    let now = Instant::now();
+                    graphics.idle = idle.effect(now);
+                    let animating = sim.is_animating()
+                        || idle.is_animating(now)
+                        || graphics.resize_pending()
+                        || tour.is_some()
+                        || shell.inspecting
+                        || shell
+                            .latency
+                            .as_ref()
+                            .is_some_and(LatencyTester::is_measuring);
+                    match redraw.take(animating) {
+                        // nothing changed, sleep until the next event
+                        None => match idle.starts_at().filter(|&at| at > now) {
+                            Some(at) => control.set_wait_until(at),
+                            None => control.set_wait(),
+                        },
+                        Some(damage) => match limiter.ready(Instant::now()) {
+                            Ok(()) => {
+                                graphics.latency_flash =
+                                    shell.latency.as_ref().is_some_and(LatencyTester::flashing);
+                                graphics.inspect = cursor_position.filter(|_| shell.inspecting);
+                                profiler.time(Track::Render, || {
+                                    graphics.frame(&runtime, &sim.render_state(runtime.interpolate))
+                                });
+                                let refresh = graphics.refresh_period();
+                                if let Some(report) = shell
+                                    .latency
+                                    .as_mut()
+                                    .and_then(|latency| latency.presented(Instant::now(), refresh))
+                                {
+                                    tracing::info!("{report}");
+                                    window.set_title(&format!("{} - {report}", shell.title));
+                                }
+                                if let Some(sample) =
+                                    graphics.inspected().filter(|_| shell.inspecting)
+                                {
+                                    tracing::debug!("{sample}");
+                                    window.set_title(&format!("{} - {sample}", shell.title));
+                                }
+                            }
+                            Err(next) => {
+                                redraw.defer(damage);
+                                control.set_wait_until(next);
+                            }
+                        },
+                    }
+                } else {
+                    // hidden in the tray, nothing to present
+                    control.set_wait_timeout(Duration::from_millis(50));
+                }
+
+                if let Some(report) = profiler.end_frame(Instant::now()) {
+                    match graphics.gpu_time() {
+                        Some(gpu) => tracing::debug!("{report}, GPU {gpu:?}"),
+                        None => tracing::debug!("{report}"),
+                    }
+                }
             }
-            Event::MainEventsCleared => graphics.frame(&settings),
             _ => {}
         };
+
+        if !per_frame {
+            profiler.record(Track::Events, event_start.elapsed());
+        }
     });
 }
+
+/// the editable state and where it's saved
+struct Editor {
+    scene: Scene,
+    history: History,
+    project: Option<Project>,
+    dialogs: Dialogs,
+}
+
+/// how the app can be reached while the window is hidden or unfocused
+struct Shell {
+    tray: Option<Tray>,
+    /// also tracks the window visibility without a tray
+    tray_state: TrayState,
+    /// the main window's transparency, changing it needs a new window
+    transparent: bool,
+    /// recreate the main window before the next frame
+    recreate_window: bool,
+    /// attach or detach the profiler before the next frame
+    toggle_profiler: bool,
+    /// not in the kiosk mode, unless `kiosk.allow_exit`
+    allow_exit: bool,
+    /// the pixel inspector key is held
+    inspecting: bool,
+    /// the main window's title, latency reports are shown after it
+    title: Arc<str>,
+    /// `None` with `features.latency_tester` off
+    latency: Option<LatencyTester>,
+    // unregistered when dropped
+    _hotkeys: Option<GlobalHotkeys>,
+}
+
+fn run_action(
+    action: Action,
+    settings: &mut RuntimeSettings,
+    editor: &mut Editor,
+    shell: &mut Shell,
+    input: &InputMap,
+    window: &Window,
+    control: &mut ControlFlow,
+) {
+    let scene = &mut editor.scene;
+    let history = &mut editor.history;
+    // setting changes go through the history, so they can be undone
+    let change = match action {
+        Action::ToggleUv => SettingValue::EnableUv(!settings.enable_uv),
+        Action::ToggleInterpolation => SettingValue::Interpolate(!settings.interpolate),
+        Action::CycleColorVision => SettingValue::ColorVision(settings.color_vision.next()),
+        Action::CyclePalette => {
+            SettingValue::Palette((settings.palette + 1) % Palette::BUILTIN.len())
+        }
+        Action::Undo => {
+            let mut target = Editable { scene, settings };
+            if !history.undo(&mut target) {
+                tracing::info!("nothing to undo");
+            }
+            return;
+        }
+        Action::Redo => {
+            let mut target = Editable { scene, settings };
+            if !history.redo(&mut target) {
+                tracing::info!("nothing to redo");
+            }
+            return;
+        }
+        Action::SaveProject => {
+            match editor
+                .project
+                .as_ref()
+                .map(|project| project.save_scene(scene))
+            {
+                Some(Ok(())) => tracing::info!("project saved"),
+                Some(Err(err)) => tracing::error!("Failed to save the project: {err:#}"),
+                None => tracing::info!("no project open"),
+            }
+            return;
+        }
+        Action::OpenFiles | Action::OpenProject | Action::SaveScreenshot => {
+            let purpose = match action {
+                Action::OpenFiles => DialogPurpose::OpenFiles,
+                Action::OpenProject => DialogPurpose::OpenProject,
+                _ => DialogPurpose::SaveScreenshot,
+            };
+            if !editor.dialogs.open(purpose, window) {
+                tracing::info!("a file dialog is already open");
+            }
+            return;
+        }
+        Action::ToggleWindow => {
+            shell.set_window_visible(window, !shell.tray_state.window_visible);
+            return;
+        }
+        Action::ToggleTransparency => {
+            shell.transparent = !shell.transparent;
+            shell.recreate_window = true;
+            return;
+        }
+        Action::InspectPixel => {
+            shell.inspecting = true;
+            return;
+        }
+        Action::ToggleProfiler => {
+            shell.toggle_profiler = true;
+            return;
+        }
+        Action::ToggleLatencyTester => {
+            let Some(latency) = shell.latency.as_mut() else {
+                tracing::info!("the latency tester is turned off in [features]");
+                return;
+            };
+            latency.toggle();
+            if !latency.is_enabled() {
+                window.set_title(&shell.title);
+            }
+            return;
+        }
+        Action::ShowAbout => {
+            tracing::info!("about:\n{BUILD}");
+            return;
+        }
+        Action::ShowBindings => {
+            tracing::info!("key bindings:\n{}", input.describe());
+            return;
+        }
+        Action::Exit if !shell.allow_exit => {
+            tracing::info!("kiosk: exiting is disabled");
+            return;
+        }
+        Action::Exit => {
+            control.set_exit();
+            return;
+        }
+    };
+
+    let command = Command::setting(settings, change);
+    history.apply(&mut Editable { scene, settings }, command);
+    match change {
+        SettingValue::Interpolate(interpolate) => {
+            tracing::info!("simulation interpolation: {interpolate}");
+        }
+        SettingValue::ColorVision(color_vision) => {
+            tracing::info!("color vision: {color_vision:?}");
+        }
+        SettingValue::Palette(palette) => {
+            tracing::info!("palette: {}", Palette::BUILTIN[palette].name);
+        }
+        _ => {}
+    }
+}
+
+/// the extended output levels for `monitor`: the monitor override,
+/// then `window.hdr.max_nits`, then what the display reports
+fn hdr_levels(settings: &WindowSettings, monitor: Option<&str>) -> HdrLevels {
+    let configured = monitor
+        .and_then(|name| settings.monitor(name))
+        .and_then(|o| o.max_nits)
+        .or(settings.hdr.max_nits);
+    let max = configured.or_else(|| monitor.and_then(DisplayInfo::find)?.max_nits);
+    if settings.color_space.is_extended() {
+        match max {
+            Some(max) => tracing::info!(
+                "HDR output: {} nits paper white, {max} nits peak",
+                settings.hdr.paper_white_nits
+            ),
+            None => tracing::info!("HDR output: the display peak is unknown, not rolled off"),
+        }
+    }
+    HdrLevels {
+        paper_white: settings.hdr.paper_white_nits,
+        max,
+    }
+}
+
+/// the actions AccessKit offers, with their bindings
+fn screen_reader_actions(input: &InputMap) -> Vec<(Action, String)> {
+    input
+        .bindings()
+        .filter(|(_, bindings)| !bindings.is_empty())
+        .map(|(action, bindings)| {
+            let bindings: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+            (action, bindings.join(", "))
+        })
+        .collect()
+}
+
+/// a new hidden window in place of `old`, for attributes that
+/// can only be set when a window is created
+fn recreate_window(
+    old: &Window,
+    title: &str,
+    transparent: bool,
+    target: &EventLoopWindowTarget<UserEvent>,
+) -> anyhow::Result<Window> {
+    let mut builder = WindowBuilder::new()
+        .with_title(title)
+        .with_inner_size(old.inner_size())
+        .with_fullscreen(old.fullscreen())
+        .with_maximized(old.is_maximized())
+        .with_decorations(old.is_decorated())
+        .with_transparent(transparent)
+        .with_visible(false);
+    if let Ok(position) = old.outer_position() {
+        builder = builder.with_position(position);
+    }
+    Ok(builder.build(target)?)
+}
+
+/// what limits the frame rate and render scale: the battery saver and thermal throttling
+#[derive(Debug, Clone, Copy)]
+struct PowerState {
+    source: PowerSource,
+    thermal: ThermalState,
+}
+
+impl PowerState {
+    fn apply(
+        &self,
+        settings: &PowerSettings,
+        graphics: &mut graphics::Graphics,
+        limiter: &mut FrameLimiter,
+    ) {
+        let saving = settings.battery_saver && self.source.on_battery();
+        let max_fps = [
+            saving.then_some(settings.max_fps).filter(|&fps| fps > 0.0),
+            self.thermal.max_fps(),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(f32::min);
+
+        *limiter = FrameLimiter::new(max_fps.unwrap_or(0.0));
+        graphics.set_max_render_scale(saving.then_some(settings.max_render_scale));
+        graphics.set_thermal(self.thermal);
+        tracing::info!(
+            "power source: {:?}, battery saver: {saving}, frame rate limit: {max_fps:?}",
+            self.source
+        );
+    }
+}
+
+impl Shell {
+    fn set_window_visible(&mut self, window: &Window, visible: bool) {
+        self.tray_state.window_visible = visible;
+        window.set_visible(visible);
+        self.update_tray();
+    }
+
+    fn update_tray(&self) {
+        if let Some(tray) = &self.tray {
+            tray.update(self.tray_state);
+        }
+    }
+}
+
+impl Editor {
+    /// a dropped or picked file or directory,
+    /// projects replace the open one, there are no importers for other files yet
+    fn open_path(&mut self, path: &Path) {
+        let root = if path.file_name() == Some(Project::MANIFEST.as_ref()) {
+            path.parent().unwrap_or(path)
+        } else {
+            path
+        };
+        if !root.join(Project::MANIFEST).is_file() {
+            tracing::info!("nothing opens {}", path.display());
+            return;
+        }
+
+        match Project::open(root).and_then(|project| Ok((project.load_scene()?, project))) {
+            Ok((scene, project)) => {
+                tracing::info!(
+                    "project {}, its settings apply after a restart",
+                    project.manifest.name
+                );
+                add_recent(&project);
+                self.scene = scene;
+                self.history.clear();
+                self.project = Some(project);
+            }
+            Err(err) => tracing::error!("Failed to open the project: {err:#}"),
+        }
+    }
+}
+
+fn add_recent(project: &Project) {
+    let mut recent = RecentProjects::load();
+    recent.opened(project.root());
+    if let Err(err) = recent.save() {
+        tracing::warn!("Failed to save the recent projects: {err}");
+    }
+}
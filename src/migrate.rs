@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use directories::ProjectDirs;
+
+use crate::{
+    build_info::BUILD,
+    dirs::{AppDirs, APP_DIRS},
+};
+
+//
+
+/// `ProjectDirs` identities (qualifier, organization, application) this app had before,
+/// newest first
+///
+/// when renaming a project made from this template, add the old identity here
+/// so the settings and data of existing users move over on the first start
+pub const PREVIOUS_IDENTITIES: &[(&str, &str, &str)] = &[];
+
+/// the version that last ran, stored in the config dir
+const VERSION_FILE: &str = "last-version";
+
+//
+
+/// move data from the previous identities and record the running version
+///
+/// runs before the settings are loaded
+pub fn migrate() {
+    if let Err(err) = try_migrate() {
+        tracing::error!("Failed to migrate data directories: {err}");
+    }
+}
+
+fn try_migrate() -> Result<()> {
+    let Some(dirs) = APP_DIRS.as_ref() else {
+        return Ok(());
+    };
+
+    // portable installs start fresh
+    if !dirs.is_portable() && !dirs.config_dir().exists() {
+        for (qualifier, organization, application) in PREVIOUS_IDENTITIES {
+            let Some(old) = ProjectDirs::from(qualifier, organization, application) else {
+                continue;
+            };
+            if old.config_dir().exists() || old.data_dir().exists() {
+                tracing::info!("migrating data from {organization}/{application}");
+                move_dirs(&old, dirs)?;
+                break;
+            }
+        }
+    }
+
+    let version_file = dirs.config_dir().join(VERSION_FILE);
+    let last = fs::read_to_string(&version_file).ok();
+    let last = last.as_deref().map(str::trim);
+    if last != Some(BUILD.version) {
+        if let Some(last) = last {
+            migrate_version(last, BUILD.version)?;
+        }
+        fs::create_dir_all(dirs.config_dir())?;
+        fs::write(version_file, BUILD.version)?;
+    }
+
+    Ok(())
+}
+
+/// format changes between versions go here
+fn migrate_version(from: &str, to: &str) -> Result<()> {
+    tracing::info!("upgrading from {from} to {to}");
+    Ok(())
+}
+
+fn move_dirs(old: &ProjectDirs, new: &AppDirs) -> Result<()> {
+    // the cache is regenerated anyway
+    let pairs = [
+        (old.config_dir(), new.config_dir()),
+        (old.data_dir(), new.data_dir()),
+        (old.data_local_dir(), new.data_local_dir()),
+    ];
+
+    for (from, to) in pairs {
+        // on some platforms config, data and local data are the same directory
+        if from.exists() && !to.exists() {
+            move_dir(from, to)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// rename, or copy + remove across file systems
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_dir_whole(from, to)?;
+    fs::remove_dir_all(from)?;
+    Ok(())
+}
+
+/// copy into a temporary dir next to `to` and rename it into place, so a copy that
+/// fails halfway doesn't leave a `to` that stops the next start from trying again
+fn copy_dir_whole(from: &Path, to: &Path) -> Result<()> {
+    let mut name = to.file_name().unwrap_or_default().to_os_string();
+    name.push(".migrating");
+    let temp = to.with_file_name(name);
+    // left over from an earlier attempt
+    if temp.exists() {
+        fs::remove_dir_all(&temp)?;
+    }
+
+    copy_dir(from, &temp)?;
+    fs::rename(&temp, to)?;
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target: PathBuf = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_land_whole() {
+        let root = std::env::temp_dir().join(format!("migrate-{}", std::process::id()));
+        let (from, to) = (root.join("old"), root.join("new"));
+        fs::create_dir_all(from.join("nested")).unwrap();
+        fs::write(from.join("settings.toml"), "vsync = true").unwrap();
+        fs::write(from.join("nested/save"), [1, 2, 3]).unwrap();
+        // a copy that failed before
+        fs::create_dir_all(root.join("new.migrating/stale")).unwrap();
+
+        copy_dir_whole(&from, &to).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(to.join("settings.toml")).unwrap(),
+            "vsync = true"
+        );
+        assert_eq!(fs::read(to.join("nested/save")).unwrap(), [1, 2, 3]);
+        assert!(!to.join("stale").exists());
+        assert!(!root.join("new.migrating").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    ops::Range,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{graphics::Graphics, sim::Simulation, tour::Tour, RuntimeSettings};
+
+//
+
+/// `--render-frames`: step the simulation at a fixed frame rate
+/// and write every frame to `out/frame-NNNNNN.png`
+pub struct OfflineRender {
+    pub frames: Range<u64>,
+    pub fps: f64,
+    pub out: PathBuf,
+    pub size: (u32, u32),
+    /// moves the camera and switches the effects over time
+    pub tour: Option<Tour>,
+}
+
+//
+
+impl OfflineRender {
+    pub fn run(
+        &self,
+        graphics: &mut Graphics,
+        sim: &mut Simulation,
+        settings: &mut RuntimeSettings,
+    ) -> Result<()> {
+        if !self.fps.is_finite() || self.fps <= 0.0 {
+            return Err(anyhow!("invalid frame rate {}", self.fps));
+        }
+        fs::create_dir_all(&self.out)?;
+
+        tracing::info!(
+            "rendering frames {}..{} at {} fps, {}x{}, into {}",
+            self.frames.start,
+            self.frames.end,
+            self.fps,
+            self.size.0,
+            self.size.1,
+            self.out.display()
+        );
+
+        let started = Instant::now();
+        for frame in 0..self.frames.end {
+            // from the start of the simulation, so the steps don't drift
+            if frame != 0 {
+                sim.advance(self.time(frame) - self.time(frame - 1));
+            }
+            if let Some(tour) = &self.tour {
+                let from = frame.checked_sub(1).map(|frame| self.time(frame));
+                tour.update(from, self.time(frame), graphics, settings);
+            }
+            if frame < self.frames.start {
+                continue;
+            }
+
+            let state = sim.render_state(settings.interpolate);
+            let image = graphics.render_offscreen(settings, &state, self.size)?;
+
+            let path = self.out.join(format!("frame-{frame:06}.png"));
+            image.write_png(BufWriter::new(File::create(&path)?))?;
+            tracing::debug!("wrote {}", path.display());
+        }
+
+        tracing::info!(
+            "rendered {} frames in {:.2?}",
+            self.frames.end - self.frames.start,
+            started.elapsed()
+        );
+        Ok(())
+    }
+
+    fn time(&self, frame: u64) -> Duration {
+        Duration::from_secs_f64(frame as f64 / self.fps)
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use rfd::{AsyncFileDialog, FileHandle};
+use winit::{event_loop::EventLoopProxy, window::Window};
+
+use crate::UserEvent;
+
+//
+
+/// native open and save dialogs that don't block the event loop
+///
+/// the dialog runs on the tokio runtime, the chosen paths come back
+/// as a [`UserEvent::Dialog`]; one dialog at a time
+pub struct Dialogs {
+    proxy: EventLoopProxy<UserEvent>,
+    open: Arc<AtomicBool>,
+}
+
+/// what a dialog was opened for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogPurpose {
+    /// files to open, the same as dropping them on the window
+    OpenFiles,
+    /// a project directory
+    OpenProject,
+    /// where to write a PNG of the current frame
+    SaveScreenshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogResult {
+    pub purpose: DialogPurpose,
+    /// empty if the dialog was cancelled
+    pub paths: Vec<PathBuf>,
+}
+
+//
+
+impl Dialogs {
+    pub fn new(proxy: EventLoopProxy<UserEvent>) -> Self {
+        Self {
+            proxy,
+            open: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// false if another dialog is still open
+    pub fn open(&self, purpose: DialogPurpose, parent: &Window) -> bool {
+        if self.open.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        let dialog = AsyncFileDialog::new().set_parent(parent);
+        let (proxy, open) = (self.proxy.clone(), self.open.clone());
+        tokio::spawn(async move {
+            let paths = purpose.run(dialog).await;
+            open.store(false, Ordering::Release);
+            // the event loop is gone when the app is exiting
+            _ = proxy.send_event(UserEvent::Dialog(DialogResult { purpose, paths }));
+        });
+        true
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::Acquire)
+    }
+}
+
+impl DialogPurpose {
+    async fn run(self, dialog: AsyncFileDialog) -> Vec<PathBuf> {
+        let paths = |handles: Option<Vec<FileHandle>>| {
+            handles
+                .into_iter()
+                .flatten()
+                .map(|handle| handle.path().to_path_buf())
+                .collect()
+        };
+
+        match self {
+            DialogPurpose::OpenFiles => paths(dialog.set_title("Open").pick_files().await),
+            DialogPurpose::OpenProject => paths(
+                dialog
+                    .set_title("Open project")
+                    .pick_folder()
+                    .await
+                    .map(|handle| vec![handle]),
+            ),
+            DialogPurpose::SaveScreenshot => {
+                let name = format!(
+                    "screenshot-{}.png",
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs()
+                );
+                paths(
+                    dialog
+                        .set_title("Save screenshot")
+                        .add_filter("PNG image", &["png"])
+                        .set_file_name(name)
+                        .save_file()
+                        .await
+                        .map(|handle| vec![handle]),
+                )
+            }
+        }
+    }
+}
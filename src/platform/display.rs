@@ -0,0 +1,163 @@
+use std::{fs, path::Path};
+
+//
+
+/// what a display says about itself in its EDID
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DisplayInfo {
+    /// the monitor name descriptor
+    pub name: Option<String>,
+    /// desired content max luminance from the CTA-861 HDR static metadata
+    pub max_nits: Option<f32>,
+}
+
+//
+
+impl DisplayInfo {
+    /// the display connected as `monitor`, which is either the connector
+    /// (`DP-1`, what X11 calls monitors) or the name in the EDID
+    ///
+    /// only Linux is supported, elsewhere there is no sysfs and it's `None`;
+    /// Windows (`IDXGIOutput6::GetDesc1`) and macOS (`NSScreen` EDR values)
+    /// need APIs winit doesn't wrap
+    pub fn find(monitor: &str) -> Option<Self> {
+        Self::from_sysfs(Path::new("/sys/class/drm"), monitor)
+    }
+
+    fn from_sysfs(dir: &Path, monitor: &str) -> Option<Self> {
+        let mut found = None;
+        for entry in fs::read_dir(dir).ok()?.flatten() {
+            let dir_name = entry.file_name();
+            let dir_name = dir_name.to_string_lossy();
+            // `card0-DP-1`
+            let Some((_, connector)) = dir_name.split_once('-') else {
+                continue;
+            };
+            let Some(info) = fs::read(entry.path().join("edid"))
+                .ok()
+                .and_then(|edid| Self::parse_edid(&edid))
+            else {
+                continue;
+            };
+
+            if connector == monitor {
+                return Some(info);
+            }
+            // Wayland compositors name monitors after the make and model
+            if info
+                .name
+                .as_deref()
+                .is_some_and(|name| monitor.contains(name))
+            {
+                found = Some(info);
+            }
+        }
+        found
+    }
+
+    /// the base block and its CTA-861 extensions, `None` if it isn't an EDID
+    pub fn parse_edid(edid: &[u8]) -> Option<Self> {
+        const HEADER: [u8; 8] = [0, 255, 255, 255, 255, 255, 255, 0];
+        if edid.len() < 128 || edid[..8] != HEADER {
+            return None;
+        }
+
+        let name = [54, 72, 90, 108].into_iter().find_map(|offset| {
+            let descriptor = &edid[offset..offset + 18];
+            (descriptor[..3] == [0; 3] && descriptor[3] == 0xfc).then(|| {
+                let text = &descriptor[5..];
+                let end = text.iter().position(|&b| b == b'\n').unwrap_or(text.len());
+                String::from_utf8_lossy(&text[..end]).trim().to_owned()
+            })
+        });
+
+        let max_nits = edid[128..]
+            .chunks_exact(128)
+            .filter(|block| block[0] == 0x02)
+            .find_map(cta_max_luminance);
+
+        Some(Self { name, max_nits })
+    }
+}
+
+/// the HDR static metadata data block of a CTA-861 extension block
+fn cta_max_luminance(block: &[u8]) -> Option<f32> {
+    // data blocks up to the first detailed timing descriptor
+    let end = (block[2] as usize).clamp(4, 127);
+    let mut i = 4;
+    while i < end {
+        let tag = block[i] >> 5;
+        let len = (block[i] & 0x1f) as usize;
+        let payload = block.get(i + 1..(i + 1 + len).min(end))?;
+        // extended tag 6, then EOTFs, metadata types and the luminance values
+        if tag == 7 && payload.first() == Some(&6) {
+            let code = *payload.get(3)?;
+            // CTA-861.3: 50 × 2^(code / 32)
+            return (code != 0).then(|| 50.0 * (code as f32 / 32.0).exp2());
+        }
+        i += 1 + len;
+    }
+    None
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a base block named `name` and a CTA block with HDR metadata
+    fn edid(name: &str, max_code: u8) -> Vec<u8> {
+        let mut edid = vec![0u8; 256];
+        edid[..8].copy_from_slice(&[0, 255, 255, 255, 255, 255, 255, 0]);
+        edid[126] = 1;
+        edid[72 + 3] = 0xfc;
+        edid[72 + 5..72 + 5 + name.len()].copy_from_slice(name.as_bytes());
+        edid[72 + 5 + name.len()] = b'\n';
+
+        let cta = &mut edid[128..];
+        cta[0] = 0x02;
+        cta[1] = 3;
+        // a 3 byte audio block first, then the HDR static metadata
+        cta[4..8].copy_from_slice(&[0x23, 0x09, 0x07, 0x07]);
+        cta[8..14].copy_from_slice(&[0xe5, 0x06, 0x0d, 0x01, max_code, 0x50]);
+        cta[2] = 14;
+        edid
+    }
+
+    #[test]
+    fn reads_the_name_and_peak_luminance() {
+        let info = DisplayInfo::parse_edid(&edid("LG HDR 4K", 0x70)).unwrap();
+        assert_eq!(info.name.as_deref(), Some("LG HDR 4K"));
+        // 50 × 2^3.5
+        assert!((info.max_nits.unwrap() - 565.69).abs() < 0.01);
+
+        let mut sdr = edid("SDR", 0);
+        sdr[128] = 0;
+        assert_eq!(DisplayInfo::parse_edid(&sdr).unwrap().max_nits, None);
+        assert_eq!(DisplayInfo::parse_edid(&[0; 128]), None);
+    }
+
+    #[test]
+    fn finds_the_connector_or_the_name() {
+        let dir = std::env::temp_dir().join(format!("drm-test-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        for (connector, name) in [
+            ("card0-DP-1", "DELL U2720Q"),
+            ("card0-HDMI-A-1", "LG HDR 4K"),
+        ] {
+            fs::create_dir_all(dir.join(connector)).unwrap();
+            fs::write(dir.join(connector).join("edid"), edid(name, 0x70)).unwrap();
+        }
+        fs::create_dir_all(dir.join("card0")).unwrap();
+
+        let find = |monitor| DisplayInfo::from_sysfs(&dir, monitor).and_then(|info| info.name);
+        assert_eq!(find("DP-1").as_deref(), Some("DELL U2720Q"));
+        assert_eq!(
+            find("LG Electronics LG HDR 4K").as_deref(),
+            Some("LG HDR 4K")
+        );
+        assert_eq!(find("DP-2"), None);
+        _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod dialogs;
+pub mod display;
+pub mod power;
+pub mod safe_area;
+pub mod tray;
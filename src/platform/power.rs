@@ -0,0 +1,163 @@
+use std::{path::Path, time::Duration};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::UserEvent;
+
+//
+
+/// where the power comes from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    /// plugged in, or a desktop without a battery
+    Ac,
+    Battery,
+    /// the OS doesn't say, treated like AC
+    #[default]
+    Unknown,
+}
+
+/// how hot the device is running, from the OS's thermal throttling hints
+///
+/// the levels of iOS' `ProcessInfo.ThermalState`, Android's thermal status
+/// maps onto them (light -> `Fair`, moderate and severe -> `Serious`, worse -> `Critical`);
+/// the platform glue sends them as [`UserEvent::Thermal`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThermalState {
+    #[default]
+    Nominal,
+    Fair,
+    Serious,
+    Critical,
+}
+
+//
+
+impl ThermalState {
+    /// render scale limit, cooling down needs fewer pixels
+    pub fn max_render_scale(self) -> f32 {
+        match self {
+            Self::Nominal => 2.0,
+            Self::Fair => 1.0,
+            Self::Serious => 0.75,
+            Self::Critical => 0.5,
+        }
+    }
+
+    /// frame rate limit, `None` for no limit
+    pub fn max_fps(self) -> Option<f32> {
+        match self {
+            Self::Nominal | Self::Fair => None,
+            Self::Serious => Some(45.0),
+            Self::Critical => Some(30.0),
+        }
+    }
+}
+
+impl PowerSource {
+    /// checked once, see [`PowerSource::watch`] for changes
+    ///
+    /// only Linux is supported, elsewhere there is no sysfs and it's unknown
+    pub fn current() -> Self {
+        Self::from_sysfs(Path::new("/sys/class/power_supply"))
+    }
+
+    /// poll the power source every few seconds, changes come back as
+    /// [`UserEvent::PowerSource`]
+    pub fn watch(proxy: EventLoopProxy<UserEvent>) {
+        let mut last = Self::current();
+        if last == Self::Unknown {
+            tracing::debug!("the power source is unknown, not watching it");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                let source = tokio::task::spawn_blocking(Self::current)
+                    .await
+                    .unwrap_or_default();
+                if source == last {
+                    continue;
+                }
+                last = source;
+                if proxy.send_event(UserEvent::PowerSource(source)).is_err() {
+                    // the event loop is gone
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn on_battery(self) -> bool {
+        self == Self::Battery
+    }
+
+    /// any online mains adapter means AC, a battery without one means battery
+    fn from_sysfs(dir: &Path) -> Self {
+        let Ok(supplies) = std::fs::read_dir(dir) else {
+            return Self::Unknown;
+        };
+        let read = |supply: &Path, file: &str| {
+            std::fs::read_to_string(supply.join(file))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let mut battery = false;
+        for supply in supplies.flatten().map(|entry| entry.path()) {
+            match read(&supply, "type").as_str() {
+                "Mains" | "USB" if read(&supply, "online") == "1" => return Self::Ac,
+                // scope "Device" is a mouse or a keyboard, not what powers the computer
+                "Battery" if read(&supply, "scope") != "Device" => battery = true,
+                _ => {}
+            }
+        }
+
+        if battery {
+            Self::Battery
+        } else {
+            Self::Ac
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn sysfs_power_supplies() {
+        let dir = std::env::temp_dir().join(format!("power-test-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            for (file, value) in files {
+                fs::write(dir.join(name).join(file), format!("{value}\n")).unwrap();
+            }
+        };
+
+        assert_eq!(PowerSource::from_sysfs(&dir), PowerSource::Unknown);
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(PowerSource::from_sysfs(&dir), PowerSource::Ac);
+
+        supply(
+            "hidpp_battery_0",
+            &[("type", "Battery"), ("scope", "Device")],
+        );
+        assert_eq!(PowerSource::from_sysfs(&dir), PowerSource::Ac);
+        supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+        assert_eq!(PowerSource::from_sysfs(&dir), PowerSource::Battery);
+        supply("AC", &[("type", "Mains"), ("online", "0")]);
+        assert_eq!(PowerSource::from_sysfs(&dir), PowerSource::Battery);
+        supply("AC", &[("online", "1")]);
+        assert_eq!(PowerSource::from_sysfs(&dir), PowerSource::Ac);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use winit::window::Window;
+
+use crate::settings::WindowSettings;
+
+//
+
+/// the edges of the window that are hidden behind a notch, camera cutout
+/// or rounded corners, UI drawn over the image stays inside them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafeArea {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+//
+
+impl SafeArea {
+    pub const NONE: Self = Self {
+        top: 0.0,
+        right: 0.0,
+        bottom: 0.0,
+        left: 0.0,
+    };
+
+    /// in physical pixels: the `[[window.monitor]]` override of the monitor
+    /// the window is on, otherwise what the platform says
+    ///
+    /// winit 0.28 only reports it on iOS, as the inner rect within the outer one;
+    /// notched MacBooks are recognized by their panel resolution in fullscreen,
+    /// Android cutouts need the override
+    pub fn of_window(window: &Window, settings: &WindowSettings) -> Self {
+        let scale = window.scale_factor() as f32;
+        let monitor = window.current_monitor();
+        let configured = monitor
+            .as_ref()
+            .and_then(|monitor| monitor.name())
+            .and_then(|name| settings.monitor(&name)?.safe_area);
+        if let Some(safe_area) = configured {
+            return safe_area.scaled(scale);
+        }
+
+        if cfg!(target_os = "ios") {
+            let (Ok(inner), Ok(outer)) = (window.inner_position(), window.outer_position()) else {
+                return Self::NONE;
+            };
+            let (inner_size, outer_size) = (window.inner_size(), window.outer_size());
+            let left = (inner.x - outer.x) as f32;
+            let top = (inner.y - outer.y) as f32;
+            return Self {
+                top,
+                left,
+                right: outer_size.width as f32 - inner_size.width as f32 - left,
+                bottom: outer_size.height as f32 - inner_size.height as f32 - top,
+            };
+        }
+
+        let notched = monitor
+            .map(|monitor| monitor.size())
+            .is_some_and(|size| Self::is_notched_macbook(size.width, size.height));
+        if cfg!(target_os = "macos") && window.fullscreen().is_some() && notched {
+            return Self {
+                top: Self::MACBOOK_NOTCH * scale,
+                ..Self::NONE
+            };
+        }
+        Self::NONE
+    }
+
+    /// the menu bar height next to the notch, in points
+    const MACBOOK_NOTCH: f32 = 37.0;
+
+    /// the native panels of the MacBooks with a notch, 14" and 16" Pro, 13" and 15" Air
+    fn is_notched_macbook(width: u32, height: u32) -> bool {
+        matches!(
+            (width, height),
+            (3024, 1964) | (3456, 2234) | (2560, 1664) | (2880, 1864)
+        )
+    }
+
+    pub fn scaled(self, scale: f32) -> Self {
+        Self {
+            top: self.top * scale,
+            right: self.right * scale,
+            bottom: self.bottom * scale,
+            left: self.left * scale,
+        }
+    }
+
+    /// the visible part of a `size` output: offset and size, like a viewport
+    pub fn rect(&self, size: (u32, u32)) -> [f32; 4] {
+        let (w, h) = (size.0 as f32, size.1 as f32);
+        let left = self.left.clamp(0.0, w);
+        let top = self.top.clamp(0.0, h);
+        [
+            left,
+            top,
+            (w - left - self.right.max(0.0)).max(0.0),
+            (h - top - self.bottom.max(0.0)).max(0.0),
+        ]
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_inside_the_insets() {
+        let notch = SafeArea {
+            top: 37.0,
+            ..SafeArea::NONE
+        }
+        .scaled(2.0);
+        assert_eq!(notch.rect((3024, 1964)), [0.0, 74.0, 3024.0, 1890.0]);
+
+        let cutouts = SafeArea {
+            left: 100.0,
+            right: 100.0,
+            bottom: 40.0,
+            ..SafeArea::NONE
+        };
+        assert_eq!(cutouts.rect((2400, 1080)), [100.0, 0.0, 2200.0, 1040.0]);
+        // never negative
+        assert_eq!(cutouts.rect((150, 20)), [100.0, 0.0, 0.0, 0.0]);
+        assert!(SafeArea::is_notched_macbook(3456, 2234));
+    }
+}
@@ -0,0 +1,151 @@
+use winit::event_loop::EventLoopProxy;
+
+use crate::UserEvent;
+
+//
+
+/// a system tray icon with quick actions, sent to the event loop as [`UserEvent::Tray`]
+///
+/// the menu mirrors the state it's given through the setters
+pub struct Tray {
+    #[cfg(target_os = "linux")]
+    handle: ksni::Handle<linux::TrayMenu>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    ToggleWindow,
+    ToggleVsync,
+    Quit,
+}
+
+/// what the menu shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrayState {
+    pub window_visible: bool,
+    pub vsync: bool,
+}
+
+//
+
+impl Tray {
+    /// `None` if there is no tray on this platform or desktop
+    pub async fn spawn(
+        title: &str,
+        state: TrayState,
+        proxy: EventLoopProxy<UserEvent>,
+    ) -> Option<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            use ksni::TrayMethods;
+
+            let menu = linux::TrayMenu {
+                title: title.to_string(),
+                state,
+                proxy,
+            };
+            match menu.spawn().await {
+                Ok(handle) => Some(Self { handle }),
+                Err(err) => {
+                    tracing::warn!("System tray unavailable: {err}");
+                    None
+                }
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            _ = (title, state, proxy);
+            tracing::warn!("System tray isn't supported on this platform yet");
+            None
+        }
+    }
+
+    /// refresh the menu in the background
+    pub fn update(&self, state: TrayState) {
+        #[cfg(target_os = "linux")]
+        {
+            let handle = self.handle.clone();
+            tokio::spawn(async move { handle.update(|menu| menu.state = state).await });
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            _ = state;
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use ksni::{
+        menu::{CheckmarkItem, StandardItem},
+        MenuItem,
+    };
+    use winit::event_loop::EventLoopProxy;
+
+    use super::{TrayAction, TrayState};
+    use crate::UserEvent;
+
+    pub struct TrayMenu {
+        pub title: String,
+        pub state: TrayState,
+        pub proxy: EventLoopProxy<UserEvent>,
+    }
+
+    impl TrayMenu {
+        fn send(&self, action: TrayAction) {
+            // the event loop is gone when the app is exiting
+            _ = self.proxy.send_event(UserEvent::Tray(action));
+        }
+    }
+
+    impl ksni::Tray for TrayMenu {
+        fn id(&self) -> String {
+            env!("CARGO_PKG_NAME").into()
+        }
+
+        fn title(&self) -> String {
+            self.title.clone()
+        }
+
+        fn icon_name(&self) -> String {
+            "applications-graphics".into()
+        }
+
+        /// a left click
+        fn activate(&mut self, _x: i32, _y: i32) {
+            self.send(TrayAction::ToggleWindow);
+        }
+
+        fn menu(&self) -> Vec<MenuItem<Self>> {
+            vec![
+                StandardItem {
+                    label: if self.state.window_visible {
+                        "Hide window"
+                    } else {
+                        "Show window"
+                    }
+                    .into(),
+                    activate: Box::new(|this: &mut Self| this.send(TrayAction::ToggleWindow)),
+                    ..<_>::default()
+                }
+                .into(),
+                CheckmarkItem {
+                    label: "VSync".into(),
+                    checked: self.state.vsync,
+                    activate: Box::new(|this: &mut Self| this.send(TrayAction::ToggleVsync)),
+                    ..<_>::default()
+                }
+                .into(),
+                MenuItem::Separator,
+                StandardItem {
+                    label: "Quit".into(),
+                    icon_name: "application-exit".into(),
+                    activate: Box::new(|this: &mut Self| this.send(TrayAction::Quit)),
+                    ..<_>::default()
+                }
+                .into(),
+            ]
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! the types an app built on the template uses, `use wgpu_template::prelude::*`
+//!
+//! this is the supported API: a change to anything re-exported here bumps the
+//! minor version while the crate is 0.x (the major one after that), so projects
+//! made from the template can pull in a newer core and only fix what the
+//! changelog lists; the rest of the crate is public for the template's own
+//! binary and tests and can change in any release
+//!
+//! there is no `App` or `Layer` trait yet, the main loop lives in `main.rs`
+//! and apps edit it in place
+
+pub use crate::{
+    assets::{AssetId, AssetSource, Assets},
+    camera::{Camera2d, Camera3d, Projection},
+    color::{ColorSpace, ColorVision, HdrLevels, Palette},
+    graphics::{objects::ObjectId, readback::Readback, workgroup::WorkgroupSizes, Graphics},
+    history::{Command, History},
+    input::{Action, Binding, InputMap},
+    project::Project,
+    rng::RngService,
+    scene::{Node, NodeId, Scene, Transform},
+    settings::{GlobalSettings, SettingsInner},
+    sim::{SimState, Simulation},
+    RuntimeSettings, UserEvent,
+};
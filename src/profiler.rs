@@ -0,0 +1,193 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+//
+
+/// where the main loop spends its CPU time, to tell if the simulation or the rendering
+/// is the bottleneck
+///
+/// the render track includes waiting for the swapchain, GPU time is measured
+/// separately by [`crate::graphics::gpu_timer::GpuTimer`]
+#[derive(Debug)]
+pub struct LoopProfiler {
+    enabled: bool,
+    interval: Duration,
+    window_start: Instant,
+    frames: u32,
+    tracks: [TrackStats; Track::ALL.len()],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Track {
+    /// window and input events, everything but the per frame work
+    Events,
+    /// simulation ticks
+    Update,
+    /// asset hot reloading and streaming
+    Assets,
+    /// recording, submitting and presenting the frame
+    Render,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrackStats {
+    pub total: Duration,
+    /// the slowest single measurement
+    pub worst: Duration,
+    pub count: u32,
+}
+
+/// one reporting interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileReport {
+    pub frames: u32,
+    pub tracks: [(Track, TrackStats); Track::ALL.len()],
+}
+
+//
+
+impl LoopProfiler {
+    /// reports every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self::starting_at(interval, Instant::now())
+    }
+
+    /// times nothing and never reports, the profiler detached
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::new(Duration::MAX)
+        }
+    }
+
+    fn starting_at(interval: Duration, now: Instant) -> Self {
+        Self {
+            enabled: true,
+            interval,
+            window_start: now,
+            frames: 0,
+            tracks: <_>::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record(&mut self, track: Track, duration: Duration) {
+        if !self.enabled {
+            return;
+        }
+        let stats = &mut self.tracks[track as usize];
+        stats.total += duration;
+        stats.worst = stats.worst.max(duration);
+        stats.count += 1;
+    }
+
+    /// run `f` and record how long it took
+    pub fn time<R>(&mut self, track: Track, f: impl FnOnce() -> R) -> R {
+        if !self.enabled {
+            return f();
+        }
+        let start = Instant::now();
+        let result = f();
+        self.record(track, start.elapsed());
+        result
+    }
+
+    /// call once per main loop iteration, returns the stats and starts over
+    /// once the interval is over
+    pub fn end_frame(&mut self, now: Instant) -> Option<ProfileReport> {
+        if !self.enabled {
+            return None;
+        }
+        self.frames += 1;
+        if now - self.window_start < self.interval {
+            return None;
+        }
+
+        let report = ProfileReport {
+            frames: self.frames,
+            tracks: Track::ALL
+                .map(|track| (track, std::mem::take(&mut self.tracks[track as usize]))),
+        };
+        self.frames = 0;
+        self.window_start = now;
+        Some(report)
+    }
+}
+
+impl Track {
+    pub const ALL: [Self; 4] = [Self::Events, Self::Update, Self::Assets, Self::Render];
+}
+
+impl ProfileReport {
+    /// average time per frame
+    pub fn average(&self, track: Track) -> Duration {
+        self.tracks[track as usize].1.total / self.frames.max(1)
+    }
+
+    /// the track that took the most time
+    pub fn bottleneck(&self) -> Track {
+        self.tracks
+            .iter()
+            .max_by_key(|(_, stats)| stats.total)
+            .map(|&(track, _)| track)
+            .unwrap()
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "main loop over {} frames:", self.frames)?;
+        for (track, stats) in self.tracks {
+            write!(
+                f,
+                " {track:?} {:?} avg {:?} worst,",
+                self.average(track),
+                stats.worst
+            )?;
+        }
+        write!(f, " bottleneck: {:?}", self.bottleneck())
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_per_interval() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut profiler = LoopProfiler::starting_at(ms(1000), start);
+
+        for i in 0..4 {
+            profiler.record(Track::Update, ms(2));
+            profiler.record(Track::Render, ms(5 + i));
+            profiler.record(Track::Events, Duration::ZERO);
+            assert_eq!(profiler.end_frame(start + ms(i * 100)), None);
+        }
+        profiler.record(Track::Update, ms(2));
+        let report = profiler.end_frame(start + ms(1000)).unwrap();
+
+        assert_eq!(report.frames, 5);
+        assert_eq!(report.average(Track::Update), ms(2));
+        assert_eq!(report.tracks[Track::Render as usize].1.worst, ms(8));
+        assert_eq!(report.tracks[Track::Events as usize].1.count, 4);
+        assert_eq!(report.average(Track::Assets), Duration::ZERO);
+        assert_eq!(report.bottleneck(), Track::Render);
+
+        // starts over
+        let report = profiler.end_frame(start + ms(2000)).unwrap();
+        assert_eq!(report.frames, 1);
+        assert_eq!(
+            report.tracks[Track::Render as usize].1,
+            TrackStats::default()
+        );
+    }
+}
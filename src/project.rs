@@ -0,0 +1,256 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use toml_edit::Document;
+
+use crate::{dirs::APP_DIRS, scene::Scene, settings::GlobalSettings};
+
+//
+
+/// a directory for tools built on the template:
+///
+/// - `project.toml`, the [`ProjectManifest`]
+/// - the scene, `scene.json` by default
+/// - `assets/`, the asset root while the project is open
+/// - `settings.toml`, optional, layered over the user's settings
+#[derive(Debug, Clone)]
+pub struct Project {
+    root: PathBuf,
+    pub manifest: ProjectManifest,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectManifest {
+    pub name: String,
+    /// relative to the project directory
+    pub scene: PathBuf,
+    /// relative to the project directory
+    pub assets: PathBuf,
+}
+
+/// the most recently opened projects, for a File menu
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentProjects {
+    /// most recent first
+    pub paths: Vec<PathBuf>,
+}
+
+//
+
+impl Project {
+    pub const MANIFEST: &'static str = "project.toml";
+    pub const SETTINGS: &'static str = "settings.toml";
+
+    /// a new project with an empty scene, `root` is created if needed
+    /// and must not already hold a project
+    pub fn create(root: &Path, name: &str) -> Result<Self> {
+        if root.join(Self::MANIFEST).exists() {
+            return Err(anyhow!("{} already is a project", root.display()));
+        }
+
+        let project = Self {
+            root: root.to_path_buf(),
+            manifest: ProjectManifest {
+                name: name.to_string(),
+                ..<_>::default()
+            },
+        };
+        fs::create_dir_all(project.assets_dir())?;
+        project.save_manifest()?;
+        project.save_scene(&Scene::default())?;
+        Ok(project)
+    }
+
+    pub fn open(root: &Path) -> Result<Self> {
+        let manifest = root.join(Self::MANIFEST);
+        let manifest = fs::read_to_string(&manifest)
+            .with_context(|| format!("no project in {}", root.display()))?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            manifest: toml_edit::de::from_str(&manifest)?,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn scene_path(&self) -> PathBuf {
+        self.root.join(&self.manifest.scene)
+    }
+
+    pub fn assets_dir(&self) -> PathBuf {
+        self.root.join(&self.manifest.assets)
+    }
+
+    pub fn save_manifest(&self) -> Result<()> {
+        fs::write(
+            self.root.join(Self::MANIFEST),
+            toml_edit::ser::to_string_pretty(&self.manifest)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn load_scene(&self) -> Result<Scene> {
+        let path = self.scene_path();
+        let json = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// written next to the old scene first, so a failed save doesn't lose it
+    pub fn save_scene(&self, scene: &Scene) -> Result<()> {
+        let path = self.scene_path();
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(scene)?)?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    /// `global` with the project's settings file on top and the asset root in the project
+    pub fn settings(&self, global: &GlobalSettings) -> Result<GlobalSettings> {
+        let overrides = match fs::read_to_string(self.root.join(Self::SETTINGS)) {
+            Ok(overrides) => overrides
+                .parse()
+                .map_err(|err| anyhow!("project settings are invalid:\n{err}"))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Document::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut settings = global.with_overrides(overrides)?;
+        settings.assets.root = self.assets_dir();
+        Ok(settings)
+    }
+}
+
+impl RecentProjects {
+    pub const LIMIT: usize = 10;
+
+    /// empty if there is no list yet
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        fs::read(&path)
+            .ok()
+            .and_then(|json| {
+                serde_json::from_slice(&json)
+                    .map_err(|err| tracing::warn!("Ignoring the recent projects: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Could not get project dirs"))?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// move `root` to the top
+    pub fn opened(&mut self, root: &Path) {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        self.paths.retain(|path| *path != root);
+        self.paths.insert(0, root);
+        self.paths.truncate(Self::LIMIT);
+    }
+
+    /// drop projects that were moved or deleted
+    pub fn prune(&mut self) {
+        self.paths
+            .retain(|path| path.join(Project::MANIFEST).is_file());
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(APP_DIRS.as_ref()?.data_dir().join("recent_projects.json"))
+    }
+}
+
+impl Default for ProjectManifest {
+    fn default() -> Self {
+        Self {
+            name: "Untitled".to_string(),
+            scene: "scene.json".into(),
+            assets: "assets".into(),
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("project-test-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn create_save_open() {
+        let root = temp_dir("roundtrip");
+        let project = Project::create(&root, "test").unwrap();
+        assert!(project.assets_dir().is_dir());
+        assert!(Project::create(&root, "again").is_err());
+
+        let mut scene = project.load_scene().unwrap();
+        let parent = scene.insert("parent", None);
+        scene.insert("child", Some(parent));
+        project.save_scene(&scene).unwrap();
+
+        let opened = Project::open(&root).unwrap();
+        assert_eq!(opened.manifest.name, "test");
+        assert_eq!(opened.load_scene().unwrap(), scene);
+
+        fs::remove_dir_all(&root).unwrap();
+        assert!(Project::open(&root).is_err());
+    }
+
+    #[test]
+    fn settings_layer_over_the_global_ones() {
+        let root = temp_dir("settings");
+        let project = Project::create(&root, "test").unwrap();
+        let global = GlobalSettings::from_document(
+            "[simulation]\ntick_rate = 45.0\n[window]\ntitle = \"global\"\n"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let settings = project.settings(&global).unwrap();
+        assert_eq!(settings.simulation.tick_rate, 45.0);
+        assert_eq!(settings.assets.root, root.join("assets"));
+
+        fs::write(
+            root.join(Project::SETTINGS),
+            "[window]\ntitle = \"project\"\n",
+        )
+        .unwrap();
+        let settings = project.settings(&global).unwrap();
+        assert_eq!(&*settings.window.title, "project");
+        assert_eq!(settings.simulation.tick_rate, 45.0);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn recent_projects_move_to_the_top() {
+        let mut recent = RecentProjects::default();
+        for i in 0..12 {
+            recent.opened(Path::new(&format!("/nonexistent/{i}")));
+        }
+        recent.opened(Path::new("/nonexistent/5"));
+        assert_eq!(recent.paths.len(), RecentProjects::LIMIT);
+        assert_eq!(recent.paths[0], Path::new("/nonexistent/5"));
+        assert_eq!(recent.paths[1], Path::new("/nonexistent/11"));
+        recent.prune();
+        assert!(recent.paths.is_empty());
+    }
+}
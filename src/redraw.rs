@@ -0,0 +1,121 @@
+use crate::{graphics::clip::ClipRect, settings::RenderMode};
+
+//
+
+/// decides which main loop iterations render
+///
+/// in [`RenderMode::Reactive`] a frame is only rendered after something
+/// invalidated the window, or while something animates
+#[derive(Debug)]
+pub struct Redraw {
+    mode: RenderMode,
+    damage: Option<Damage>,
+}
+
+/// the part of the window that changed since the last frame
+///
+/// wgpu presents whole surface textures, there is no way to hand these to the
+/// compositor (`eglSwapBuffersWithDamage`, `wl_surface.damage_buffer`, `Present1`) yet,
+/// so for now only the decision to render at all is made from them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Damage {
+    Full,
+    /// only this rect in logical pixels, like a UI widget that changed
+    Region(ClipRect),
+}
+
+//
+
+impl Redraw {
+    pub fn new(mode: RenderMode) -> Self {
+        Self {
+            mode,
+            // the first frame always renders
+            damage: Some(Damage::Full),
+        }
+    }
+
+    pub fn mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    /// the window contents are out of date: input, resizes,
+    /// `RedrawRequested` from the OS or `Window::request_redraw`
+    pub fn invalidate(&mut self) {
+        self.damage = Some(Damage::Full);
+    }
+
+    /// only `rect` is out of date, the regions add up until the next frame
+    pub fn invalidate_rect(&mut self, rect: ClipRect) {
+        if rect.is_empty() {
+            return;
+        }
+        self.damage = Some(match self.damage {
+            None => Damage::Region(rect),
+            Some(Damage::Region(damage)) => Damage::Region(damage.union(rect)),
+            Some(Damage::Full) => Damage::Full,
+        });
+    }
+
+    /// put back the damage of a frame that wasn't rendered after all
+    pub fn defer(&mut self, damage: Damage) {
+        match damage {
+            Damage::Full => self.invalidate(),
+            Damage::Region(rect) => self.invalidate_rect(rect),
+        }
+    }
+
+    /// `Some` if this iteration should render, with what changed,
+    /// clears the damage
+    pub fn take(&mut self, animating: bool) -> Option<Damage> {
+        let damage = self.damage.take();
+        match self.mode {
+            _ if animating => Some(Damage::Full),
+            RenderMode::Continuous => Some(damage.unwrap_or(Damage::Full)),
+            RenderMode::Reactive => damage,
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reactive_renders_only_when_invalidated() {
+        let mut redraw = Redraw::new(RenderMode::Reactive);
+        assert_eq!(redraw.take(false), Some(Damage::Full));
+        assert_eq!(redraw.take(false), None);
+        assert_eq!(redraw.take(true), Some(Damage::Full));
+
+        redraw.invalidate();
+        redraw.invalidate();
+        assert_eq!(redraw.take(false), Some(Damage::Full));
+        assert_eq!(redraw.take(false), None);
+
+        let mut redraw = Redraw::new(RenderMode::Continuous);
+        assert!(redraw.take(false).is_some());
+        assert_eq!(redraw.take(false), Some(Damage::Full));
+    }
+
+    #[test]
+    fn damaged_regions_add_up() {
+        let mut redraw = Redraw::new(RenderMode::Reactive);
+        redraw.take(false);
+
+        redraw.invalidate_rect(ClipRect::new(10.0, 10.0, 20.0, 20.0));
+        redraw.invalidate_rect(ClipRect::new(50.0, 0.0, 10.0, 10.0));
+        redraw.invalidate_rect(ClipRect::new(0.0, 0.0, 0.0, 0.0));
+        assert_eq!(
+            redraw.take(false),
+            Some(Damage::Region(ClipRect::new(10.0, 0.0, 50.0, 30.0)))
+        );
+
+        redraw.invalidate_rect(ClipRect::new(10.0, 10.0, 20.0, 20.0));
+        redraw.invalidate();
+        redraw.invalidate_rect(ClipRect::new(10.0, 10.0, 20.0, 20.0));
+        assert_eq!(redraw.take(false), Some(Damage::Full));
+    }
+}
@@ -0,0 +1,61 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::settings::RngSettings;
+
+//
+
+/// the RNG type handed out by [`RngService`]
+///
+/// ChaCha is used because its output is specified,
+/// so the same seed gives the same values on every platform and `rand` version
+pub type Rng = ChaCha8Rng;
+
+/// seeded source of per-system random streams
+///
+/// every system asks for its own stream by name,
+/// so adding random calls to one system doesn't shift the values another one sees
+#[derive(Debug, Clone, Copy)]
+pub struct RngService {
+    seed: u64,
+}
+
+//
+
+impl RngService {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// `seed` overrides the settings file seed (from `--seed`)
+    ///
+    /// without either, a random seed is picked and logged so the run can be reproduced
+    pub fn from_settings(settings: &RngSettings, seed: Option<u64>) -> Self {
+        let seed = seed
+            .or(settings.seed)
+            .unwrap_or_else(|| rand::thread_rng().next_u64());
+
+        tracing::info!("RNG seed: {seed}");
+        Self::new(seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// the random stream for `system`
+    ///
+    /// returns a fresh copy of the stream on every call
+    pub fn stream(&self, system: &str) -> Rng {
+        let mut rng = Rng::seed_from_u64(self.seed);
+        rng.set_stream(fnv1a(system.as_bytes()));
+        rng
+    }
+}
+
+/// stable across platforms and Rust versions, unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+use super::{DrawStats, NodeId, Scene, Transform};
+
+//
+
+/// the state of a hierarchy inspector panel, independent of how it's drawn:
+/// which nodes are collapsed and selected, and the rows to show
+#[derive(Debug, Default, Clone)]
+pub struct Inspector {
+    collapsed: HashSet<NodeId>,
+    selected: Option<NodeId>,
+}
+
+/// one line of the tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectorRow {
+    pub id: NodeId,
+    pub name: String,
+    pub depth: usize,
+    pub has_children: bool,
+    pub collapsed: bool,
+    pub visible: bool,
+    /// hidden by a parent even though `visible`
+    pub shown: bool,
+    pub selected: bool,
+    /// the node alone
+    pub stats: DrawStats,
+    /// the node and its shown descendants
+    pub subtree_stats: DrawStats,
+}
+
+//
+
+impl Inspector {
+    /// depth first, children of collapsed nodes left out
+    pub fn rows(&self, scene: &Scene) -> Vec<InspectorRow> {
+        let mut rows = Vec::new();
+        let mut stack: Vec<(NodeId, usize)> = scene.roots().map(|id| (id, 0)).collect();
+        stack.reverse();
+        while let Some((id, depth)) = stack.pop() {
+            let node = scene.get(id).unwrap();
+            let children: Vec<NodeId> = scene.children(id).collect();
+            let collapsed = self.collapsed.contains(&id);
+            rows.push(InspectorRow {
+                id,
+                name: node.name.clone(),
+                depth,
+                has_children: !children.is_empty(),
+                collapsed,
+                visible: node.visible,
+                shown: scene.is_shown(id),
+                selected: self.selected == Some(id),
+                stats: node.stats,
+                subtree_stats: scene.subtree_stats(id),
+            });
+            if !collapsed {
+                stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+            }
+        }
+        rows
+    }
+
+    pub fn toggle_collapsed(&mut self, id: NodeId) {
+        if !self.collapsed.remove(&id) {
+            self.collapsed.insert(id);
+        }
+    }
+
+    /// from a click on a row or a pick in the viewport, the parents expand to show it
+    pub fn select(&mut self, scene: &Scene, id: Option<NodeId>) {
+        self.selected = id;
+        let mut parent = id.and_then(|id| scene.get(id)?.parent);
+        while let Some(id) = parent {
+            self.collapsed.remove(&id);
+            parent = scene.get(id).and_then(|node| node.parent);
+        }
+    }
+
+    /// dropped when the node is gone
+    pub fn selected(&mut self, scene: &Scene) -> Option<NodeId> {
+        self.selected = self.selected.filter(|&id| scene.get(id).is_some());
+        self.selected
+    }
+
+    /// the previous visibility
+    pub fn toggle_visible(&self, scene: &mut Scene, id: NodeId) -> Option<bool> {
+        let node = scene.get_mut(id)?;
+        node.visible = !node.visible;
+        Some(!node.visible)
+    }
+
+    /// the previous transform
+    pub fn set_transform(
+        &self,
+        scene: &mut Scene,
+        id: NodeId,
+        transform: Transform,
+    ) -> Option<Transform> {
+        let node = scene.get_mut(id)?;
+        Some(std::mem::replace(&mut node.transform, transform))
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_follow_the_tree() {
+        let mut scene = Scene::default();
+        let a = scene.insert("a", None);
+        let b = scene.insert("b", None);
+        let a1 = scene.insert("a1", Some(a));
+        let a1x = scene.insert("a1x", Some(a1));
+        scene.get_mut(a1x).unwrap().stats = DrawStats {
+            draws: 2,
+            triangles: 12,
+        };
+
+        let mut inspector = Inspector::default();
+        let rows = inspector.rows(&scene);
+        let tree: Vec<_> = rows
+            .iter()
+            .map(|row| (row.name.as_str(), row.depth))
+            .collect();
+        assert_eq!(tree, [("a", 0), ("a1", 1), ("a1x", 2), ("b", 0)]);
+        assert_eq!(rows[0].subtree_stats.triangles, 12);
+        assert_eq!(rows[0].stats, DrawStats::default());
+
+        inspector.toggle_collapsed(a);
+        assert_eq!(inspector.rows(&scene).len(), 2);
+
+        // picking something inside expands its parents
+        inspector.select(&scene, Some(a1x));
+        let rows = inspector.rows(&scene);
+        assert_eq!(rows.len(), 4);
+        assert!(rows[2].selected);
+
+        assert_eq!(inspector.toggle_visible(&mut scene, a1), Some(true));
+        let rows = inspector.rows(&scene);
+        assert!(!rows[2].shown && rows[2].visible);
+        assert_eq!(rows[0].subtree_stats, DrawStats::default());
+
+        scene.remove(a);
+        assert_eq!(inspector.selected(&scene), None);
+        assert_eq!(inspector.rows(&scene)[0].id, b);
+    }
+}
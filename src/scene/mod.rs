@@ -0,0 +1,267 @@
+use std::ops::Add;
+
+use glam::{DAffine3, DVec3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+pub mod inspector;
+
+//
+
+/// a hierarchy of named nodes with transforms, the editable side of a scene
+///
+/// ids stay valid until their node is removed and are never reused,
+/// so removed nodes can be put back where they were
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    nodes: Vec<Option<Node>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    /// relative to the parent
+    pub transform: Transform,
+    /// hiding a node hides its children
+    pub visible: bool,
+    /// what drawing this node alone costs, filled in by whatever renders it
+    #[serde(skip)]
+    pub stats: DrawStats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub translation: DVec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DrawStats {
+    pub draws: u32,
+    pub triangles: u64,
+}
+
+//
+
+impl Scene {
+    pub fn insert(&mut self, name: impl Into<String>, parent: Option<NodeId>) -> NodeId {
+        self.insert_node(Node {
+            name: name.into(),
+            parent,
+            transform: Transform::IDENTITY,
+            visible: true,
+            stats: DrawStats::default(),
+        })
+    }
+
+    pub fn insert_node(&mut self, node: Node) -> NodeId {
+        if let Some(parent) = node.parent {
+            assert!(self.get(parent).is_some(), "no parent {parent:?}");
+        }
+        self.nodes.push(Some(node));
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    /// an id for a node that isn't there yet, [`Self::restore`] puts it in
+    pub fn reserve(&mut self) -> NodeId {
+        self.nodes.push(None);
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    /// remove `id` and its descendants, parents before children,
+    /// [`Self::restore`] puts them back
+    pub fn remove(&mut self, id: NodeId) -> Vec<(NodeId, Node)> {
+        let ids = self.subtree(id);
+        ids.into_iter()
+            .filter_map(|id| Some((id, self.nodes[id.index()].take()?)))
+            .collect()
+    }
+
+    /// undo a [`Self::remove`]
+    pub fn restore(&mut self, removed: Vec<(NodeId, Node)>) {
+        for (id, node) in removed {
+            let slot = &mut self.nodes[id.index()];
+            assert!(slot.is_none(), "{id:?} is in use");
+            *slot = Some(node);
+        }
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.index())?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.index())?.as_mut()
+    }
+
+    /// in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| Some((NodeId(i as u32), node.as_ref()?)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.iter()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(id, _)| id)
+    }
+
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.iter()
+            .filter(move |(_, node)| node.parent == Some(id))
+            .map(|(id, _)| id)
+    }
+
+    /// `id` and everything under it, depth first, parents before children
+    pub fn subtree(&self, id: NodeId) -> Vec<NodeId> {
+        let mut ids = Vec::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if self.get(id).is_none() {
+                continue;
+            }
+            ids.push(id);
+            let first = stack.len();
+            stack.extend(self.children(id));
+            stack[first..].reverse();
+        }
+        ids
+    }
+
+    pub fn world_transform(&self, id: NodeId) -> DAffine3 {
+        let mut transform = DAffine3::IDENTITY;
+        let mut next = Some(id);
+        while let Some(node) = next.and_then(|id| self.get(id)) {
+            transform = node.transform.affine() * transform;
+            next = node.parent;
+        }
+        transform
+    }
+
+    /// visible itself and all of its parents
+    pub fn is_shown(&self, id: NodeId) -> bool {
+        let mut next = Some(id);
+        while let Some(node) = next.and_then(|id| self.get(id)) {
+            if !node.visible {
+                return false;
+            }
+            next = node.parent;
+        }
+        true
+    }
+
+    /// the stats of the shown nodes in the subtree of `id`
+    pub fn subtree_stats(&self, id: NodeId) -> DrawStats {
+        self.subtree(id)
+            .into_iter()
+            .filter(|&id| self.is_shown(id))
+            .map(|id| self.get(id).unwrap().stats)
+            .fold(DrawStats::default(), DrawStats::add)
+    }
+}
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: DVec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn affine(&self) -> DAffine3 {
+        DAffine3::from_scale_rotation_translation(
+            self.scale.as_dvec3(),
+            self.rotation.as_f64(),
+            self.translation,
+        )
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Add for DrawStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            draws: self.draws + other.draws,
+            triangles: self.triangles + other.triangles,
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hierarchy() {
+        let mut scene = Scene::default();
+        let root = scene.insert("root", None);
+        let child = scene.insert("child", Some(root));
+        let grandchild = scene.insert("grandchild", Some(child));
+        let other = scene.insert("other", None);
+
+        scene.get_mut(root).unwrap().transform.translation = DVec3::X;
+        scene.get_mut(child).unwrap().transform.scale = Vec3::splat(2.0);
+        scene.get_mut(grandchild).unwrap().transform.translation = DVec3::Y;
+        let world = scene.world_transform(grandchild);
+        assert_eq!(
+            world.transform_point3(DVec3::ZERO),
+            DVec3::new(1.0, 2.0, 0.0)
+        );
+
+        assert_eq!(scene.roots().collect::<Vec<_>>(), [root, other]);
+        assert_eq!(scene.subtree(root), [root, child, grandchild]);
+
+        let before = scene.clone();
+        let removed = scene.remove(child);
+        assert_eq!(scene.len(), 2);
+        assert!(scene.get(grandchild).is_none());
+        scene.restore(removed);
+        assert_eq!(scene, before);
+    }
+
+    #[test]
+    fn hidden_parents_hide_children() {
+        let mut scene = Scene::default();
+        let root = scene.insert("root", None);
+        let child = scene.insert("child", Some(root));
+        for id in [root, child] {
+            scene.get_mut(id).unwrap().stats = DrawStats {
+                draws: 1,
+                triangles: 100,
+            };
+        }
+        assert_eq!(scene.subtree_stats(root).triangles, 200);
+
+        scene.get_mut(root).unwrap().visible = false;
+        assert!(!scene.is_shown(child));
+        assert_eq!(scene.subtree_stats(root), DrawStats::default());
+    }
+}
@@ -0,0 +1,484 @@
+use toml_edit::{Array, ArrayOfTables, Entry, Item, TableLike, Value};
+
+//
+
+/// prefix of the keys holding values the user wrote that no longer fit the schema
+pub const OLD_PREFIX: &str = "_old_";
+
+//
+
+/// reconcile the user's settings `original` with the schema shaped `new`
+///
+/// - keys missing from `original` are added from `new`
+/// - values of the same type are the user's and stay untouched,
+///   arrays are values too, their contents aren't merged
+/// - tables and inline tables merge recursively, in any combination
+/// - arrays of tables (`[[key]]` or `key = [{ .. }]`) merge entry by entry,
+///   entries only in `new` are appended, entries only in `original` are kept
+/// - a value with the wrong type is replaced, the user's value moves to `_old_<key>`
+///   so nothing they wrote is lost
+/// - `_old_<key>` entries are dropped once `<key>` is no longer in `new`
+/// - keys only in `original` are kept, comments and formatting are preserved
+///
+/// merging the same `new` again changes nothing
+pub fn merge_document(original: &mut impl TableLike, new: &impl TableLike) {
+    merge(original, new, false);
+}
+
+/// `inline`: `original` is an inline table, which can only hold values
+fn merge(original: &mut impl TableLike, new: &impl TableLike, inline: bool) {
+    let fit = |item: &Item| -> Item {
+        let mut item = item.clone();
+        remove_old_item(&mut item);
+        if inline {
+            // tables become inline tables, arrays of tables become arrays
+            item.into_value().map(Item::Value).unwrap_or(Item::None)
+        } else {
+            item
+        }
+    };
+
+    for (key, value) in new.iter() {
+        if key.starts_with(OLD_PREFIX) {
+            continue;
+        }
+
+        match original.entry(key) {
+            Entry::Occupied(mut entry) => match (entry.get_mut(), value) {
+                (Item::Table(entry), Item::Table(value)) => merge(entry, value, false),
+                (Item::Table(entry), Item::Value(Value::InlineTable(value))) => {
+                    merge(entry, value, false)
+                }
+                (
+                    Item::Value(Value::InlineTable(entry)),
+                    Item::Value(Value::InlineTable(value)),
+                ) => merge(entry, value, true),
+                (Item::Value(Value::InlineTable(entry)), Item::Table(value)) => {
+                    merge(entry, value, true)
+                }
+                (Item::ArrayOfTables(entry), value) if table_array(value).is_some() => {
+                    merge_array_of_tables(entry, &table_array(value).unwrap())
+                }
+                (Item::Value(Value::Array(entry)), value)
+                    if is_table_array(entry) && table_array(value).is_some() =>
+                {
+                    merge_array(entry, &table_array(value).unwrap())
+                }
+                (Item::Value(a), Item::Value(b)) if a.type_name() == b.type_name() => {}
+                (entry, value) => {
+                    tracing::error!(
+                        "setting `{key}` should be {}, not {}, the old value was kept as `{OLD_PREFIX}{key}`",
+                        value.type_name(),
+                        entry.type_name()
+                    );
+                    let old = std::mem::replace(entry, fit(value));
+                    original.insert(&format!("{OLD_PREFIX}{key}"), old);
+                }
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(fit(value));
+            }
+        }
+    }
+
+    // garbage collect the old values of settings that were removed
+    let stale: Vec<String> = original
+        .iter()
+        .filter_map(|(key, _)| {
+            let setting = key.strip_prefix(OLD_PREFIX)?;
+            (!new.contains_key(setting)).then(|| key.to_string())
+        })
+        .collect();
+    for key in stale {
+        original.remove(&key);
+    }
+}
+
+fn merge_array_of_tables(original: &mut ArrayOfTables, new: &[Item]) {
+    let len = original.len();
+    for (entry, value) in original.iter_mut().zip(new) {
+        merge_item(entry, value, false);
+    }
+    for value in &new[len.min(new.len())..] {
+        let mut table = match value.clone() {
+            Item::Table(table) => table,
+            Item::Value(Value::InlineTable(table)) => table.into_table(),
+            _ => continue,
+        };
+        remove_old(&mut table);
+        original.push(table);
+    }
+}
+
+fn merge_array(original: &mut Array, new: &[Item]) {
+    let len = original.len();
+    for (entry, value) in original.iter_mut().zip(new) {
+        if let Value::InlineTable(entry) = entry {
+            merge_item(entry, value, true);
+        }
+    }
+    for value in &new[len.min(new.len())..] {
+        let mut table = match value.clone() {
+            Item::Table(table) => table.into_inline_table(),
+            Item::Value(Value::InlineTable(table)) => table,
+            _ => continue,
+        };
+        remove_old(&mut table);
+        original.push(table);
+    }
+}
+
+fn merge_item(original: &mut impl TableLike, new: &Item, inline: bool) {
+    match new {
+        Item::Table(new) => merge(original, new, inline),
+        Item::Value(Value::InlineTable(new)) => merge(original, new, inline),
+        _ => {}
+    }
+}
+
+/// the entries of an array of tables, in either syntax
+fn table_array(item: &Item) -> Option<Vec<Item>> {
+    match item {
+        Item::ArrayOfTables(array) => Some(array.iter().cloned().map(Item::Table).collect()),
+        Item::Value(Value::Array(array)) if is_table_array(array) => {
+            Some(array.iter().cloned().map(Item::Value).collect())
+        }
+        _ => None,
+    }
+}
+
+/// empty arrays could be either
+fn is_table_array(array: &Array) -> bool {
+    array.iter().all(Value::is_inline_table)
+}
+
+/// `new` is copied without its own old values
+fn remove_old(table: &mut dyn TableLike) {
+    let old: Vec<String> = table
+        .iter()
+        .filter(|(key, _)| key.starts_with(OLD_PREFIX))
+        .map(|(key, _)| key.to_string())
+        .collect();
+    for key in old {
+        table.remove(&key);
+    }
+    for (_, item) in table.iter_mut() {
+        remove_old_item(item);
+    }
+}
+
+fn remove_old_item(item: &mut Item) {
+    match item {
+        Item::ArrayOfTables(array) => {
+            for table in array.iter_mut() {
+                remove_old(table);
+            }
+        }
+        Item::Value(Value::Array(array)) => {
+            for table in array.iter_mut().filter_map(Value::as_inline_table_mut) {
+                remove_old(table);
+            }
+        }
+        item => {
+            if let Some(table) = item.as_table_like_mut() {
+                remove_old(table);
+            }
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use proptest::prelude::*;
+    use toml_edit::{Array, Document, InlineTable, Table};
+
+    use super::*;
+
+    fn merged(original: &str, new: &str) -> Document {
+        let mut original: Document = original.parse().unwrap();
+        let new: Document = new.parse().unwrap();
+        merge_document(original.as_table_mut(), new.as_table());
+        original
+    }
+
+    #[test]
+    fn keeps_user_values() {
+        let doc = merged(
+            "a = 5\n[t]\nb = \"user\"\n",
+            "a = 1\nc = 2\n[t]\nb = \"x\"\n",
+        );
+        assert_eq!(doc["a"].as_integer(), Some(5));
+        assert_eq!(doc["c"].as_integer(), Some(2));
+        assert_eq!(doc["t"]["b"].as_str(), Some("user"));
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let doc = merged("# mine\na = 5 # five\n", "a = 1\n");
+        assert_eq!(doc.to_string(), "# mine\na = 5 # five\n");
+    }
+
+    #[test]
+    fn arrays_are_values() {
+        let doc = merged("a = [1, 2, 3]\n", "a = [\"x\"]\n");
+        assert_eq!(doc.to_string(), "a = [1, 2, 3]\n");
+    }
+
+    #[test]
+    fn type_mismatch_moves_to_old() {
+        let doc = merged("a = \"five\"\n", "a = 5\n");
+        assert_eq!(doc["a"].as_integer(), Some(5));
+        assert_eq!(doc["_old_a"].as_str(), Some("five"));
+    }
+
+    #[test]
+    fn table_mismatch_moves_to_old() {
+        let doc = merged("t = 1\n", "[t]\nb = true\n");
+        assert_eq!(doc["t"]["b"].as_bool(), Some(true));
+        assert_eq!(doc["_old_t"].as_integer(), Some(1));
+        reparse(&doc);
+    }
+
+    #[test]
+    fn inline_and_regular_tables_merge() {
+        let doc = merged("t = { a = 5 }\n", "[t]\na = 1\nb = 2\n[t.u]\nc = 3\n");
+        assert_eq!(doc["t"]["a"].as_integer(), Some(5));
+        assert_eq!(doc["t"]["u"]["c"].as_integer(), Some(3));
+        assert!(doc["t"].is_inline_table());
+        reparse(&doc);
+
+        let doc = merged("[t]\na = 5\n", "t = { a = 1, b = 2 }\n");
+        assert_eq!(doc["t"]["a"].as_integer(), Some(5));
+        assert_eq!(doc["t"]["b"].as_integer(), Some(2));
+    }
+
+    #[test]
+    fn mismatch_inside_inline_table_stays_inline() {
+        let doc = merged("t = { a = 5 }\n", "[t.a]\nb = 2\n");
+        assert_eq!(doc["t"]["a"]["b"].as_integer(), Some(2));
+        assert_eq!(doc["t"]["_old_a"].as_integer(), Some(5));
+        reparse(&doc);
+    }
+
+    #[test]
+    fn old_keys_are_collected() {
+        let doc = merged(
+            "a = 1\n_old_a = \"x\"\n_old_gone = 2\n[t]\n_old_gone = 3\n",
+            "a = 1\n[t]\n",
+        );
+        assert_eq!(doc["_old_a"].as_str(), Some("x"));
+        assert!(!doc.contains_key("_old_gone"));
+        assert!(!doc["t"].as_table().unwrap().contains_key("_old_gone"));
+    }
+
+    #[test]
+    fn new_old_keys_are_ignored() {
+        let doc = merged("", "a = 1\n_old_a = 2\n");
+        assert!(!doc.contains_key("_old_a"));
+    }
+
+    #[test]
+    fn arrays_of_tables_merge_by_entry() {
+        let doc = merged(
+            "[[m]]\nname = \"a\"\n[[m]]\nname = \"b\"\nx = \"user\"\n[[m]]\nname = \"c\"\n",
+            "[[m]]\nname = \"\"\nx = 1\n[[m]]\nname = \"\"\nx = 2\n",
+        );
+        let m = doc["m"].as_array_of_tables().unwrap();
+        assert_eq!(m.len(), 3);
+        assert_eq!(m.get(0).unwrap()["name"].as_str(), Some("a"));
+        assert_eq!(m.get(0).unwrap()["x"].as_integer(), Some(1));
+        assert_eq!(m.get(1).unwrap()["x"].as_integer(), Some(2));
+        assert_eq!(m.get(1).unwrap()["_old_x"].as_str(), Some("user"));
+        assert!(!m.get(2).unwrap().contains_key("x"));
+    }
+
+    #[test]
+    fn arrays_of_tables_append_new_entries() {
+        let doc = merged(
+            "m = [{ a = 5 }]\n",
+            "[[m]]\na = 1\n[[m]]\na = 2\n_old_a = 3\n",
+        );
+        let m = doc["m"].as_array().unwrap();
+        assert_eq!(m.len(), 2);
+        let second = m.get(1).unwrap().as_inline_table().unwrap();
+        assert_eq!(second.get("a").unwrap().as_integer(), Some(2));
+        assert!(!second.contains_key("_old_a"));
+        reparse(&doc);
+    }
+
+    #[test]
+    fn arrays_of_tables_and_scalar_arrays_mismatch() {
+        let doc = merged("m = [1, 2]\n", "[[m]]\na = 1\n");
+        assert_eq!(doc["m"].as_array_of_tables().unwrap().len(), 1);
+        assert_eq!(doc["_old_m"].as_array().unwrap().len(), 2);
+        reparse(&doc);
+    }
+
+    #[test]
+    fn default_settings_merge_into_themselves() {
+        let doc = merged(
+            super::super::DEFAULT_SETTINGS,
+            super::super::DEFAULT_SETTINGS,
+        );
+        assert_eq!(doc.to_string(), super::super::DEFAULT_SETTINGS);
+    }
+
+    // property tests
+
+    #[derive(Debug, Clone)]
+    enum Node {
+        Integer(i64),
+        Bool(bool),
+        String(String),
+        Array(Vec<Node>),
+        Table(BTreeMap<String, Node>),
+        InlineTable(BTreeMap<String, Node>),
+        TableArray(Vec<BTreeMap<String, Node>>),
+    }
+
+    fn key() -> impl Strategy<Value = String> {
+        // a few names so the documents collide often
+        prop_oneof!["[abc]", "_old_[abc]"]
+    }
+
+    fn node() -> impl Strategy<Value = Node> {
+        let leaf = prop_oneof![
+            any::<i64>().prop_map(Node::Integer),
+            any::<bool>().prop_map(Node::Bool),
+            "[a-z ]{0,4}".prop_map(Node::String),
+        ];
+        leaf.prop_recursive(4, 32, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..3).prop_map(Node::Array),
+                prop::collection::btree_map(key(), inner.clone(), 0..4).prop_map(Node::Table),
+                prop::collection::btree_map(key(), inner.clone(), 0..4).prop_map(Node::InlineTable),
+                // empty arrays of tables aren't written at all
+                prop::collection::vec(prop::collection::btree_map(key(), inner, 0..3), 1..3)
+                    .prop_map(Node::TableArray),
+            ]
+        })
+    }
+
+    fn document() -> impl Strategy<Value = Document> {
+        prop::collection::btree_map(key(), node(), 0..5).prop_map(|entries| {
+            let mut doc = Document::new();
+            for (key, node) in entries {
+                doc.insert(&key, item(node, false));
+            }
+            doc
+        })
+    }
+
+    fn item(node: Node, inline: bool) -> Item {
+        match node {
+            Node::Table(entries) if !inline => {
+                let mut table = Table::new();
+                for (key, node) in entries {
+                    table.insert(&key, item(node, false));
+                }
+                Item::Table(table)
+            }
+            Node::TableArray(tables) if !inline => {
+                let mut array = ArrayOfTables::new();
+                for entries in tables {
+                    let Item::Table(table) = item(Node::Table(entries), false) else {
+                        unreachable!()
+                    };
+                    array.push(table);
+                }
+                Item::ArrayOfTables(array)
+            }
+            node => Item::Value(value(node)),
+        }
+    }
+
+    fn value(node: Node) -> Value {
+        match node {
+            Node::Integer(v) => v.into(),
+            Node::Bool(v) => v.into(),
+            Node::String(v) => v.into(),
+            Node::Array(nodes) => Value::Array(nodes.into_iter().map(value).collect::<Array>()),
+            Node::Table(entries) | Node::InlineTable(entries) => {
+                let mut table = InlineTable::new();
+                for (key, node) in entries {
+                    table.insert(&key, value(node));
+                }
+                Value::InlineTable(table)
+            }
+            Node::TableArray(tables) => Value::Array(
+                tables
+                    .into_iter()
+                    .map(|entries| value(Node::InlineTable(entries)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn reparse(doc: &Document) -> Document {
+        let text = doc.to_string();
+        text.parse()
+            .unwrap_or_else(|err| panic!("merged document is invalid: {err}\n{text}"))
+    }
+
+    /// every key of `new` is in `original`, tables are tables and values are values
+    fn assert_covers(original: &dyn TableLike, new: &dyn TableLike) {
+        for (key, value) in new.iter() {
+            if key.starts_with(OLD_PREFIX) {
+                continue;
+            }
+            let item = original
+                .get(key)
+                .unwrap_or_else(|| panic!("`{key}` is missing"));
+            if let (Some(items), Some(values)) = (table_array(item), table_array(value)) {
+                assert!(items.len() >= values.len(), "`{key}` lost entries");
+                for (item, value) in items.iter().zip(&values) {
+                    assert_covers(
+                        item.as_table_like().unwrap(),
+                        value.as_table_like().unwrap(),
+                    );
+                }
+                continue;
+            }
+            match (item.as_table_like(), value.as_table_like()) {
+                (Some(item), Some(value)) => assert_covers(item, value),
+                (None, None) => {}
+                _ => panic!("`{key}` has the wrong type"),
+            }
+        }
+        for (key, _) in original.iter() {
+            if let Some(setting) = key.strip_prefix(OLD_PREFIX) {
+                assert!(new.contains_key(setting), "stale `{key}` was kept");
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn merge_covers_new(original in document(), new in document()) {
+            let mut merged = original.clone();
+            merge_document(merged.as_table_mut(), new.as_table());
+            assert_covers(reparse(&merged).as_table(), new.as_table());
+        }
+
+        #[test]
+        fn merge_is_idempotent(original in document(), new in document()) {
+            let mut once = original;
+            merge_document(once.as_table_mut(), new.as_table());
+            let mut twice = once.clone();
+            merge_document(twice.as_table_mut(), new.as_table());
+            prop_assert_eq!(once.to_string(), twice.to_string());
+        }
+
+        #[test]
+        fn merge_into_empty_is_new(new in document()) {
+            let mut merged = Document::new();
+            merge_document(merged.as_table_mut(), new.as_table());
+            assert_covers(reparse(&merged).as_table(), new.as_table());
+            prop_assert!(merged.iter().all(|(key, _)| new.contains_key(key)));
+        }
+    }
+}
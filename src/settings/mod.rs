@@ -0,0 +1,986 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{Read, Write},
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use toml_edit::{Document, Item};
+use wgpu::{Backends, Dx12Compiler, PowerPreference, PresentMode};
+
+use crate::{
+    camera::{DepthMode, LogDepth},
+    color::{ColorSpace, ColorVision},
+    coords::CoordinateSystem,
+    dirs::APP_DIRS,
+    graphics::{blend::BlendMode, post::Dither},
+    input::{Action, Binding},
+    platform::safe_area::SafeArea,
+};
+
+pub use merge::merge_document;
+
+//
+
+pub mod merge;
+
+//
+
+/// the settings file written on the first start
+pub const DEFAULT_SETTINGS: &str = include_str!("./settings.toml");
+
+/// graphics settings that older default files listed under `[window]`
+const MOVED_TO_GRAPHICS: [&str; 4] = [
+    "gpu_preference",
+    "force_software_rendering",
+    "vsync",
+    "allowed_backends",
+];
+
+//
+
+#[derive(Debug, Default, Clone)]
+pub struct GlobalSettings {
+    inner: SettingsInner,
+
+    document: Option<Document>,
+    // modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsInner {
+    pub window: WindowSettings,
+    pub graphics: GraphicsSettings,
+    pub rng: RngSettings,
+    pub assets: AssetSettings,
+    pub simulation: SimulationSettings,
+    pub input: InputSettings,
+    pub accessibility: AccessibilitySettings,
+    pub updates: UpdateSettings,
+    pub coordinates: CoordinateSystem,
+    pub features: FeatureSettings,
+    pub kiosk: KioskSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub resolution: (u32, u32),
+    pub title: Arc<str>,
+    pub force_wayland: bool,
+    pub force_x11: bool,
+    /// a system tray icon with show/hide, vsync and quit
+    pub tray: bool,
+    /// see-through, changing it recreates the window
+    pub transparent: bool,
+    /// `[[window.monitor]]` entries
+    #[serde(rename = "monitor")]
+    pub monitors: Vec<MonitorOverride>,
+    pub mirror: MirrorSettings,
+    pub render_mode: RenderMode,
+    pub cursor: CursorSettings,
+    /// of the main window surface
+    pub color_space: ColorSpace,
+    pub hdr: HdrSettings,
+    pub idle: IdleSettings,
+}
+
+/// when the main window renders
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderMode {
+    /// every frame, as fast as vsync and the frame limiter allow
+    #[default]
+    Continuous,
+    /// only after input, window events or while something animates,
+    /// the GPU stays idle otherwise
+    Reactive,
+}
+
+/// a second window showing the main one, for projectors and previews
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MirrorSettings {
+    pub enabled: bool,
+    /// fullscreen on the monitor with this name, a window otherwise
+    pub monitor: Option<Arc<str>>,
+    /// window resolution, when not fullscreen
+    pub resolution: (u32, u32),
+    pub color_space: ColorSpace,
+}
+
+/// the mouse cursor over the main window
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorSettings {
+    /// hide the OS cursor and draw one in the final pass
+    pub software: bool,
+    /// size of the software cursor image
+    pub scale: f32,
+}
+
+/// output levels with an extended color space
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HdrSettings {
+    /// how bright a scene value of 1 is shown
+    pub paper_white_nits: f32,
+    /// the display's peak brightness, instead of what it reports
+    pub max_nits: Option<f32>,
+}
+
+/// anti burn-in after a while without input, for kiosks and installations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleSettings {
+    pub enabled: bool,
+    /// minutes without input before the effect starts
+    pub after_minutes: f32,
+    pub mode: IdleMode,
+    /// of [`IdleMode::Dim`], the output color is multiplied with it
+    pub brightness: f32,
+    pub fade_seconds: f32,
+    /// radius of the [`IdleMode::Orbit`] circle, in output pixels
+    pub orbit_pixels: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdleMode {
+    /// fade the output to `brightness`
+    #[default]
+    Dim,
+    /// move the output around a small circle, so edges don't burn in
+    Orbit,
+}
+
+/// window settings for a specific monitor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorOverride {
+    /// the name the OS gives the monitor
+    pub name: Arc<str>,
+    pub resolution: Option<(u32, u32)>,
+    pub position: Option<(i32, i32)>,
+    /// like `window.hdr.max_nits`, for this monitor
+    pub max_nits: Option<f32>,
+    /// the insets hidden behind a notch or cutout, in logical pixels
+    pub safe_area: Option<SafeArea>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub allowed_backends: GraphicsBackends,
+    pub gpu_preference: GpuPreference,
+    pub force_software_rendering: bool,
+    pub vsync: bool,
+    pub resize_debounce_ms: u32,
+    pub pixel_art: PixelArtSettings,
+    pub indexed: IndexedSettings,
+    pub dither: Dither,
+    pub blend: BlendMode,
+    pub depth: DepthMode,
+    pub log_depth: Option<LogDepth>,
+    /// scene resolution relative to the window, the final pass scales it up
+    pub render_scale: f32,
+    pub pacing: PacingSettings,
+    pub dynamic_resolution: DynamicResolutionSettings,
+    pub budget: FrameBudgetSettings,
+    pub power: PowerSettings,
+    pub debug: GpuDebugSettings,
+    pub dx12: Dx12Settings,
+    pub advanced: AdvancedGraphicsSettings,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsBackends {
+    pub vulkan: bool,
+    pub metal: bool,
+    pub dx12: bool,
+    pub webgpu: bool,
+
+    pub gl: bool,
+    pub dx11: bool,
+}
+
+/// render at a fixed low resolution and scale it up by whole numbers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PixelArtSettings {
+    pub enabled: bool,
+    pub resolution: (u32, u32),
+}
+
+/// missed vsync handling
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PacingSettings {
+    /// lower the render scale while frames miss vsync
+    pub reduce_render_scale: bool,
+    pub max_missed_per_second: u32,
+    pub min_render_scale: f32,
+}
+
+/// render scale driven by the GPU frame time
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DynamicResolutionSettings {
+    pub enabled: bool,
+    /// GPU time per frame to aim for, 0 uses the refresh period
+    pub budget_ms: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+}
+
+/// GPU time budgets per system, optional systems are degraded
+/// while the frame goes over the target
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrameBudgetSettings {
+    pub enabled: bool,
+    /// GPU time per frame, 0 uses the refresh period
+    pub target_ms: f32,
+    pub scene_ms: f32,
+    pub post_ms: f32,
+}
+
+/// the low power profile used on battery
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerSettings {
+    /// switch to the low power profile while on battery
+    pub battery_saver: bool,
+    /// 0 for no limit
+    pub max_fps: f32,
+    /// caps the render scale, dynamic resolution included
+    pub max_render_scale: f32,
+    /// only when the app starts on battery, the GPU can't change while running
+    pub gpu_preference: GpuPreference,
+}
+
+/// backend validation, for tracking down GPU bugs
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuDebugSettings {
+    /// the Vulkan validation layer (or the DX12 debug layer), `None` in debug builds only
+    pub validation: Option<bool>,
+    /// validation of shader memory accesses on the GPU, very slow, Vulkan only
+    pub gpu_based_validation: bool,
+}
+
+/// the DX12 backend's HLSL compiler
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dx12Settings {
+    pub compiler: Dx12ShaderCompiler,
+    /// `dxcompiler.dll`, found with the usual DLL search rules if unset
+    pub dxc_path: Option<PathBuf>,
+    /// `dxil.dll`, found with the usual DLL search rules if unset
+    pub dxil_path: Option<PathBuf>,
+}
+
+/// swapchain tuning for latency or stutter on specific drivers
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdvancedGraphicsSettings {
+    /// replaces the mode picked from `vsync` if the surface supports it
+    pub present_mode: Option<PresentModeOverride>,
+    /// frames the CPU can queue ahead of the GPU, 0 leaves it to the driver
+    pub max_frames_in_flight: u32,
+    /// wait for the GPU and the swapchain before the input is handled,
+    /// Vulkan and Metal only
+    pub low_latency: bool,
+}
+
+/// render palette indices and resolve them to colors in the final pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexedSettings {
+    pub enabled: bool,
+    /// name of a builtin palette
+    pub palette: Arc<str>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RngSettings {
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetSettings {
+    pub root: PathBuf,
+    pub archives: Vec<PathBuf>,
+    pub hot_reload: bool,
+    pub remote: Vec<RemoteAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteAsset {
+    pub id: Arc<str>,
+    pub url: Arc<str>,
+    pub sha256: Arc<str>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationSettings {
+    pub tick_rate: f64,
+    pub max_catch_up_steps: u32,
+    pub catch_up_policy: CatchUpPolicy,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputSettings {
+    /// overrides for the default bindings, all of an action's at once
+    #[serde(deserialize_with = "crate::input::deserialize_bindings")]
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+    /// OS level hotkeys that work while the window is unfocused, none by default
+    pub global: BTreeMap<Action, Binding>,
+    pub latency: LatencySettings,
+}
+
+/// the click-to-photon tester, toggled with [`Action::ToggleLatencyTester`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LatencySettings {
+    /// a serial device that sends a byte whenever its photodiode sees the flash
+    pub photodiode: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub reduced_motion: SystemToggle,
+    pub high_contrast: SystemToggle,
+    pub color_vision: ColorVision,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    /// look for a newer release at startup
+    pub check: bool,
+    pub channel: Arc<str>,
+}
+
+/// the preset for exhibitions and installations
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KioskSettings {
+    pub enabled: bool,
+    /// exclusive fullscreen in the largest mode, borderless otherwise
+    pub exclusive_fullscreen: bool,
+    pub hide_cursor: bool,
+    /// Escape, Alt+F4 and closing the window exit, like without the kiosk mode
+    pub allow_exit: bool,
+    /// run the app under a supervisor that relaunches it after a crash or a hang
+    pub relaunch: bool,
+    /// a main loop stuck for this long counts as hung
+    pub watchdog_seconds: f32,
+}
+
+/// developer subsystems, shipped builds can turn them off
+/// in the settings file or a project's settings without recompiling
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureSettings {
+    /// the main loop profiler and its periodic reports
+    pub profiler: bool,
+    /// the click to photon tester and its binding
+    pub latency_tester: bool,
+    /// `--capture-frame` and `--capture-tick`
+    pub captures: bool,
+}
+
+/// a flag that follows the OS preference unless forced
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SystemToggle {
+    #[default]
+    System,
+    On,
+    Off,
+}
+
+/// what to do when the simulation can't keep up with real time
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub enum CatchUpPolicy {
+    /// drop the missed time, the simulation runs slower than real time
+    #[default]
+    SlowMotion,
+    /// keep the missed time (up to a second) and catch up over the next frames
+    CatchUp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentModeOverride {
+    /// vsync, GL swap interval 1
+    Fifo,
+    /// vsync, late frames tear instead of waiting a whole refresh
+    FifoRelaxed,
+    /// no tearing, the newest frame is shown at vsync
+    Mailbox,
+    /// no vsync, GL swap interval 0
+    Immediate,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dx12ShaderCompiler {
+    /// always there, but old and slow, shader model 5.1 at most
+    #[default]
+    Fxc,
+    /// shader model 6, faster, needs `dxcompiler.dll` and `dxil.dll` shipped with the app
+    Dxc,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub enum GpuPreference {
+    #[default]
+    HighPerformance,
+    LowPower,
+}
+
+//
+
+impl GlobalSettings {
+    /// load the config from the config file (if found)
+    ///
+    /// or use the default configuration
+    pub fn load() -> Self {
+        match Self::try_load() {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Failed to load settings: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn try_load() -> Result<Self> {
+        let mut file = Self::config_file()?;
+
+        let document: Document = if file.metadata()?.len() == 0 {
+            file.write_all(DEFAULT_SETTINGS.as_bytes())?;
+
+            DEFAULT_SETTINGS
+                .parse()
+                .map_err(|err| anyhow!("default config is invalid, this is a bug:\n{err}"))?
+        } else {
+            let mut buf = String::new();
+            file.read_to_string(&mut buf)?;
+
+            buf.parse()
+                .map_err(|err| anyhow!("config is invalid:\n{err}"))?
+        };
+
+        /* file.flush()?;
+
+        let modified = file.metadata().ok().and_then(|meta| meta.modified().ok()); */
+
+        Self::from_document(document)
+    }
+
+    /// the settings in `document`, without touching the config file
+    pub fn from_document(mut document: Document) -> Result<Self> {
+        Self::migrate(&mut document);
+        let mut inner: SettingsInner = toml_edit::de::from_document(document.clone())?;
+
+        if inner.window.force_wayland && inner.window.force_x11 {
+            tracing::error!("Both wayland and x11 were forced, ignoring both");
+            inner.window.force_wayland = false;
+            inner.window.force_x11 = false;
+        }
+
+        if let Some(log) = inner.graphics.log_depth {
+            if !(log.far.is_finite() && log.far > 0.0) {
+                tracing::error!("Invalid log_depth far {}, log depth disabled", log.far);
+                inner.graphics.log_depth = None;
+            }
+        }
+
+        // let repaired_doc = toml_edit::ser::to_document(&inner)?;
+        // merge_document(document.as_table_mut(), repaired_doc.as_table());
+
+        Ok(Self {
+            document: Some(document),
+            inner,
+            // modified,
+        })
+    }
+
+    /// move the keys older versions wrote to where they are read from now,
+    /// the first save writes them there
+    ///
+    /// a key that's already at its new place wins, the old one stays where it was
+    fn migrate(document: &mut Document) {
+        let Some(window) = document.get("window").and_then(Item::as_table_like) else {
+            return;
+        };
+        let graphics = match document.get("graphics") {
+            Some(graphics) => match graphics.as_table_like() {
+                Some(graphics) => Some(graphics),
+                // the deserializer reports it
+                None => return,
+            },
+            None => None,
+        };
+        let moved: Vec<&str> = MOVED_TO_GRAPHICS
+            .into_iter()
+            .filter(|key| window.contains_key(key))
+            .filter(|key| graphics.is_none_or(|graphics| !graphics.contains_key(key)))
+            .collect();
+        if moved.is_empty() {
+            return;
+        }
+
+        let window = document["window"].as_table_like_mut().unwrap();
+        let items: Vec<(&str, Item)> = moved
+            .into_iter()
+            .map(|key| (key, window.remove(key).unwrap()))
+            .collect();
+        let graphics = document
+            .entry("graphics")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .unwrap();
+        for (key, item) in items {
+            graphics.insert(key, item);
+            tracing::info!("setting `window.{key}` moved to `graphics.{key}`");
+        }
+    }
+
+    /// these settings with the values in `overrides` taking priority,
+    /// like a project's own settings file
+    ///
+    /// the result isn't saved, the config file keeps only the user's own settings
+    pub fn with_overrides(&self, mut overrides: Document) -> Result<Self> {
+        let base = match self.document.clone() {
+            Some(document) => document,
+            None => toml_edit::ser::to_document(&self.inner)?,
+        };
+        merge_document(overrides.as_table_mut(), base.as_table());
+        let mut settings = Self::from_document(overrides)?;
+        settings.document = None;
+        Ok(settings)
+    }
+
+    pub fn autosave(&self) {
+        if let Some(document) = self.document.as_ref() {
+            self.save(document)
+        }
+    }
+
+    pub fn save(&self, document: &Document) {
+        if let Err(err) = self.try_save(document) {
+            tracing::error!("Failed to load settings: {err}");
+        }
+    }
+
+    pub fn try_save(&self, document: &Document) -> Result<()> {
+        let mut file = Self::config_file()?;
+        file.set_len(0)?;
+
+        let contents = document.to_string();
+        file.write_all(contents.as_bytes())?;
+
+        Ok(())
+    }
+
+    /* fn get_new_if_modified(&self, file: &File) -> Option<Document> {
+        let (Some(modified), Some(file_modified)) = (
+            self.modified,
+            file.metadata().ok().and_then(|meta| meta.modified().ok()),
+        ) else {
+            return None
+        };
+
+        if modified > file_modified {
+            return None;
+        }
+
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).ok()?;
+
+        buf.parse()?;
+
+        Ok(())
+    } */
+
+    pub fn config_file() -> Result<File> {
+        let dirs = APP_DIRS
+            .as_ref()
+            .ok_or_else(|| anyhow!("Could not get project dirs"))?;
+
+        fs::create_dir_all(dirs.config_dir())?;
+
+        let config = dirs.config_dir().join("settings.toml");
+        Ok(fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(config)?)
+    }
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: (1280, 720),
+            title: "WGPU Template".into(),
+            force_wayland: false,
+            force_x11: false,
+            tray: false,
+            transparent: true,
+            monitors: Vec::new(),
+            mirror: <_>::default(),
+            render_mode: RenderMode::Continuous,
+            cursor: <_>::default(),
+            color_space: ColorSpace::Srgb,
+            hdr: <_>::default(),
+            idle: <_>::default(),
+        }
+    }
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        Self {
+            // scRGB 1.0, what the extended output showed before
+            paper_white_nits: 80.0,
+            max_nits: None,
+        }
+    }
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_minutes: 10.0,
+            mode: IdleMode::Dim,
+            brightness: 0.2,
+            fade_seconds: 2.0,
+            orbit_pixels: 8.0,
+        }
+    }
+}
+
+impl Default for CursorSettings {
+    fn default() -> Self {
+        Self {
+            software: false,
+            scale: 1.0,
+        }
+    }
+}
+
+impl Default for MirrorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            monitor: None,
+            resolution: (640, 360),
+            color_space: ColorSpace::Srgb,
+        }
+    }
+}
+
+impl WindowSettings {
+    /// the first override for a monitor called `name`
+    pub fn monitor(&self, name: &str) -> Option<&MonitorOverride> {
+        self.monitors.iter().find(|o| &*o.name == name)
+    }
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_backends: <_>::default(),
+            gpu_preference: <_>::default(),
+            force_software_rendering: false,
+            vsync: true,
+            resize_debounce_ms: 0,
+            pixel_art: <_>::default(),
+            indexed: <_>::default(),
+            dither: <_>::default(),
+            blend: <_>::default(),
+            depth: <_>::default(),
+            log_depth: None,
+            render_scale: 1.0,
+            pacing: <_>::default(),
+            dynamic_resolution: <_>::default(),
+            budget: <_>::default(),
+            power: <_>::default(),
+            debug: <_>::default(),
+            dx12: <_>::default(),
+            advanced: <_>::default(),
+        }
+    }
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        Self {
+            battery_saver: true,
+            max_fps: 30.0,
+            max_render_scale: 0.75,
+            gpu_preference: GpuPreference::LowPower,
+        }
+    }
+}
+
+impl Default for DynamicResolutionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            budget_ms: 0.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+        }
+    }
+}
+
+impl Default for FrameBudgetSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_ms: 0.0,
+            scene_ms: 12.0,
+            post_ms: 2.0,
+        }
+    }
+}
+
+impl Default for PacingSettings {
+    fn default() -> Self {
+        Self {
+            reduce_render_scale: false,
+            max_missed_per_second: 2,
+            min_render_scale: 0.5,
+        }
+    }
+}
+
+impl Default for IndexedSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            palette: "GameBoy".into(),
+        }
+    }
+}
+
+impl Default for PixelArtSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            resolution: (320, 180),
+        }
+    }
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            check: false,
+            channel: "stable".into(),
+        }
+    }
+}
+
+impl Default for KioskSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            exclusive_fullscreen: true,
+            hide_cursor: true,
+            allow_exit: false,
+            relaunch: true,
+            watchdog_seconds: 10.0,
+        }
+    }
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        Self {
+            profiler: true,
+            latency_tester: true,
+            captures: true,
+        }
+    }
+}
+
+impl Default for AssetSettings {
+    fn default() -> Self {
+        Self {
+            root: "assets".into(),
+            archives: Vec::new(),
+            hot_reload: true,
+            remote: Vec::new(),
+        }
+    }
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self {
+            tick_rate: 30.0,
+            max_catch_up_steps: 8,
+            catch_up_policy: <_>::default(),
+        }
+    }
+}
+
+impl Default for GraphicsBackends {
+    fn default() -> Self {
+        Self {
+            vulkan: true,
+            metal: true,
+            dx12: true,
+            webgpu: true,
+
+            gl: false,
+            dx11: false,
+        }
+    }
+}
+
+impl GraphicsBackends {
+    pub fn to_backends(self) -> Backends {
+        let mut backends = Backends::empty();
+
+        backends.set(Backends::VULKAN, self.vulkan);
+        backends.set(Backends::GL, self.gl);
+        backends.set(Backends::METAL, self.metal);
+        backends.set(Backends::DX12, self.dx12);
+        backends.set(Backends::DX11, self.dx11);
+        backends.set(Backends::BROWSER_WEBGPU, self.webgpu);
+
+        backends
+    }
+}
+
+impl Dx12Settings {
+    pub fn to_dx12_compiler(&self) -> Dx12Compiler {
+        match self.compiler {
+            Dx12ShaderCompiler::Fxc => Dx12Compiler::Fxc,
+            Dx12ShaderCompiler::Dxc => Dx12Compiler::Dxc {
+                dxil_path: self.dxil_path.clone(),
+                dxc_path: self.dxc_path.clone(),
+            },
+        }
+    }
+
+    /// which compiler the DX12 backend ends up using,
+    /// it quietly falls back to FXC when the DXC DLLs are missing
+    pub fn log_compiler(&self) {
+        let missing: Vec<_> = [&self.dxc_path, &self.dxil_path]
+            .into_iter()
+            .flatten()
+            .filter(|path| !path.is_file())
+            .collect();
+        match self.compiler {
+            Dx12ShaderCompiler::Fxc => tracing::info!("DX12 shader compiler: FXC"),
+            Dx12ShaderCompiler::Dxc if missing.is_empty() => tracing::info!(
+                "DX12 shader compiler: DXC (FXC if the DLLs fail to load, see the wgpu log)"
+            ),
+            Dx12ShaderCompiler::Dxc => {
+                tracing::warn!("DX12 shader compiler: FXC, DXC is missing {missing:?}")
+            }
+        }
+    }
+}
+
+impl PresentModeOverride {
+    pub fn to_present_mode(self) -> PresentMode {
+        match self {
+            PresentModeOverride::Fifo => PresentMode::Fifo,
+            PresentModeOverride::FifoRelaxed => PresentMode::FifoRelaxed,
+            PresentModeOverride::Mailbox => PresentMode::Mailbox,
+            PresentModeOverride::Immediate => PresentMode::Immediate,
+        }
+    }
+}
+
+impl GpuPreference {
+    pub fn to_power_preference(self) -> PowerPreference {
+        match self {
+            GpuPreference::HighPerformance => PowerPreference::HighPerformance,
+            GpuPreference::LowPower => PowerPreference::LowPower,
+        }
+    }
+}
+
+impl Deref for GlobalSettings {
+    type Target = SettingsInner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for GlobalSettings {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_depth(far: &str) -> Option<LogDepth> {
+        let document = format!("[graphics]\nlog_depth = {{ far = {far} }}\n");
+        GlobalSettings::from_document(document.parse().unwrap())
+            .unwrap()
+            .graphics
+            .log_depth
+    }
+
+    #[test]
+    fn log_depth_needs_a_positive_far() {
+        assert_eq!(log_depth("1e7"), Some(LogDepth { far: 1e7 }));
+        for far in ["0.0", "-1.0", "-5.0", "inf", "nan"] {
+            assert_eq!(log_depth(far), None, "far = {far}");
+        }
+        assert!(LogDepth { far: 1e7 }.coef().is_finite());
+    }
+
+    #[test]
+    fn old_graphics_keys_move_out_of_window() {
+        let old = "[window]\ntitle = \"old\"\ngpu_preference = \"LowPower\"\nvsync = false\n\n\
+                   [window.allowed_backends]\nvulkan = false\ngl = true\n";
+        let settings = GlobalSettings::from_document(old.parse().unwrap()).unwrap();
+        assert!(matches!(
+            settings.graphics.gpu_preference,
+            GpuPreference::LowPower
+        ));
+        assert!(!settings.graphics.vsync);
+        assert!(!settings.graphics.allowed_backends.vulkan);
+        assert!(settings.graphics.allowed_backends.gl);
+        assert_eq!(&*settings.window.title, "old");
+
+        // saved at the new place, loading it again changes nothing
+        let saved = settings.document.as_ref().unwrap().to_string();
+        let document: Document = saved.parse().unwrap();
+        assert!(document["window"].get("vsync").is_none());
+        assert!(document["window"].get("allowed_backends").is_none());
+        let again = GlobalSettings::from_document(document).unwrap();
+        assert_eq!(again.document.unwrap().to_string(), saved);
+
+        // the new place wins
+        let both = "[window]\nvsync = false\n\n[graphics]\nvsync = true\n";
+        let settings = GlobalSettings::from_document(both.parse().unwrap()).unwrap();
+        assert!(settings.graphics.vsync);
+    }
+}
@@ -0,0 +1,292 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::settings::{CatchUpPolicy, SimulationSettings};
+
+//
+
+/// everything the fixed-timestep simulation owns
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct SimState {
+    /// triangle rotation in radians
+    pub rotation: f32,
+}
+
+/// fixed-timestep simulation clock
+///
+/// keeps the previous and the current state,
+/// so rendering can interpolate between the two ticks around the present time
+pub struct Simulation {
+    dt: Duration,
+    last: Instant,
+    accumulator: Duration,
+    max_steps: u32,
+    policy: CatchUpPolicy,
+
+    prev: SimState,
+    curr: SimState,
+    tick: u64,
+    /// [`Self::hold_at`]
+    hold: Option<u64>,
+
+    stats: SimStats,
+
+    /// accessibility, freezes decorative animation
+    pub reduced_motion: bool,
+}
+
+/// diagnostics counters
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct SimStats {
+    /// updates where more ticks were due than `max_catch_up_steps`
+    pub clamped_updates: u64,
+    /// simulation time thrown away by [`CatchUpPolicy::SlowMotion`]
+    pub dropped: Duration,
+}
+
+//
+
+impl SimState {
+    /// radians per second
+    const ROTATION_SPEED: f32 = 1.0;
+
+    pub fn step(&mut self, dt: f32) {
+        self.rotation = (self.rotation + Self::ROTATION_SPEED * dt) % std::f32::consts::TAU;
+    }
+
+    pub fn lerp(&self, next: &Self, t: f32) -> Self {
+        // shortest way around, the rotation wraps at TAU
+        let mut delta = next.rotation - self.rotation;
+        if delta > std::f32::consts::PI {
+            delta -= std::f32::consts::TAU;
+        } else if delta < -std::f32::consts::PI {
+            delta += std::f32::consts::TAU;
+        }
+
+        Self {
+            rotation: self.rotation + delta * t,
+        }
+    }
+}
+
+impl Simulation {
+    /// the shortest tick, a zero `dt` would divide by zero in the catch-up
+    const MIN_DT: Duration = Duration::from_micros(1);
+
+    pub fn new(settings: &SimulationSettings) -> Self {
+        let tick_rate = if settings.tick_rate.is_finite() && settings.tick_rate > 0.0 {
+            settings.tick_rate
+        } else {
+            let default = SimulationSettings::default().tick_rate;
+            tracing::error!(
+                "Invalid tick rate {}, using the default {default}",
+                settings.tick_rate
+            );
+            default
+        };
+
+        Self {
+            dt: Duration::from_secs_f64(1.0 / tick_rate).max(Self::MIN_DT),
+            last: Instant::now(),
+            accumulator: Duration::ZERO,
+            max_steps: settings.max_catch_up_steps.max(1),
+            policy: settings.catch_up_policy,
+
+            prev: SimState::default(),
+            curr: SimState::default(),
+            tick: 0,
+            hold: None,
+
+            stats: SimStats::default(),
+
+            reduced_motion: false,
+        }
+    }
+
+    /// run the ticks that are due, but at most `max_catch_up_steps`
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        self.accumulator += now - self.last;
+        self.last = now;
+
+        let mut steps = 0;
+        while self.accumulator >= self.dt {
+            if self.is_held() {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            if steps == self.max_steps {
+                self.clamp();
+                break;
+            }
+
+            self.accumulator -= self.dt;
+            self.step();
+            steps += 1;
+        }
+    }
+
+    /// run the ticks due after `elapsed` simulated time, without catch-up limits
+    ///
+    /// for offline rendering, independent of the wall clock
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.accumulator += elapsed;
+        while self.accumulator >= self.dt {
+            if self.is_held() {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.accumulator -= self.dt;
+            self.step();
+        }
+    }
+
+    fn clamp(&mut self) {
+        self.stats.clamped_updates += 1;
+
+        match self.policy {
+            CatchUpPolicy::SlowMotion => {
+                let behind = self.accumulator;
+                self.accumulator =
+                    Duration::from_secs_f64(self.accumulator.as_secs_f64() % self.dt.as_secs_f64());
+                self.stats.dropped += behind - self.accumulator;
+            }
+            CatchUpPolicy::CatchUp => {
+                // never fall more than a second behind
+                let limit = Duration::from_secs(1).max(self.dt * self.max_steps);
+                if self.accumulator > limit {
+                    self.stats.dropped += self.accumulator - limit;
+                    self.accumulator = limit;
+                }
+            }
+        }
+
+        tracing::debug!(
+            "simulation fell behind, clamped {} times, {:?} dropped",
+            self.stats.clamped_updates,
+            self.stats.dropped
+        );
+    }
+
+    pub fn stats(&self) -> SimStats {
+        self.stats
+    }
+
+    /// run exactly one tick
+    pub fn step(&mut self) {
+        self.prev = self.curr;
+        if !self.reduced_motion {
+            self.curr.step(self.dt.as_secs_f32());
+        }
+        self.tick += 1;
+    }
+
+    /// the state changes from tick to tick, rendering can't wait for input
+    pub fn is_animating(&self) -> bool {
+        !self.reduced_motion
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// stop [`Self::update`] and [`Self::advance`] at exactly `tick`, `None` resumes
+    ///
+    /// time doesn't pass while held, so catch-up can't overshoot the tick
+    /// and resuming doesn't burst through the ticks missed while held
+    pub fn hold_at(&mut self, tick: Option<u64>) {
+        self.hold = tick;
+    }
+
+    /// stopped at the [`Self::hold_at`] tick
+    pub fn is_held(&self) -> bool {
+        self.hold == Some(self.tick)
+    }
+
+    /// how far the present time is between the previous and the current tick
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.dt.as_secs_f64()) as f32
+    }
+
+    /// the state of the latest tick
+    pub fn current(&self) -> SimState {
+        self.curr
+    }
+
+    /// the state at the present time, interpolated between the last two ticks
+    ///
+    /// lags one tick behind [`Self::current`]
+    pub fn interpolated(&self) -> SimState {
+        self.prev.lerp(&self.curr, self.alpha())
+    }
+
+    /// [`Self::interpolated`] or [`Self::current`]
+    pub fn render_state(&self, interpolate: bool) -> SimState {
+        if interpolate {
+            self.interpolated()
+        } else {
+            self.current()
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_at_the_exact_tick() {
+        let settings = SimulationSettings {
+            tick_rate: 10.0,
+            ..SimulationSettings::default()
+        };
+
+        let mut chunked = Simulation::new(&settings);
+        chunked.hold_at(Some(7));
+        for _ in 0..5 {
+            chunked.advance(Duration::from_millis(230));
+        }
+        assert!(chunked.is_held());
+        assert_eq!(chunked.tick(), 7);
+        assert_eq!(chunked.alpha(), 0.0);
+
+        let mut stepped = Simulation::new(&settings);
+        for _ in 0..7 {
+            stepped.step();
+        }
+        assert_eq!(chunked.current(), stepped.current());
+
+        // the time spent held is gone
+        chunked.hold_at(None);
+        chunked.advance(Duration::from_millis(100));
+        assert_eq!(chunked.tick(), 8);
+    }
+
+    #[test]
+    fn unusable_tick_rates_fall_back() {
+        let default = SimulationSettings::default().tick_rate;
+        for tick_rate in [f64::INFINITY, f64::NAN, 0.0, -30.0] {
+            let sim = Simulation::new(&SimulationSettings {
+                tick_rate,
+                ..SimulationSettings::default()
+            });
+            assert_eq!(sim.dt, Duration::from_secs_f64(1.0 / default));
+        }
+
+        // finite, but a tick shorter than a nanosecond
+        let mut sim = Simulation::new(&SimulationSettings {
+            tick_rate: 1e12,
+            max_catch_up_steps: 4,
+            catch_up_policy: CatchUpPolicy::SlowMotion,
+        });
+        assert_eq!(sim.dt, Simulation::MIN_DT);
+        sim.last -= Duration::from_millis(10);
+        sim.update();
+        assert_eq!(sim.tick(), 4);
+        assert_eq!(sim.stats().clamped_updates, 1);
+        assert!(sim.alpha().is_finite());
+    }
+}
@@ -0,0 +1,237 @@
+use glam::Vec3;
+
+//
+
+/// a piecewise cubic curve, evaluated with `t` in `0..=segments`,
+/// segment `i` covering `i..=i + 1`
+///
+/// both kinds are stored as cubic Bezier segments,
+/// Catmull-Rom points are converted when the spline is built
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spline {
+    /// `p0, c0, c1, p1, c2, c3, p2, ..`, every segment shares its last point
+    /// with the next one; a closed spline's last segment ends at `p0`
+    controls: Vec<Vec3>,
+    closed: bool,
+}
+
+/// cumulative lengths along a [`Spline`], for moving along it at a constant speed
+///
+/// a spline's `t` speeds up and slows down with the spacing of its points
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcLength {
+    /// length from the start to `t = i / samples_per_segment`
+    lengths: Vec<f32>,
+    samples_per_segment: u32,
+}
+
+//
+
+impl Spline {
+    /// `points` is `p0, c0, c1, p1, ..`: 3 points per segment and the final end point,
+    /// which a closed spline leaves out
+    pub fn bezier(points: Vec<Vec3>, closed: bool) -> Self {
+        let valid = if closed {
+            points.len().is_multiple_of(3)
+        } else {
+            points.len() % 3 == 1
+        };
+        assert!(
+            valid && points.len() > 1,
+            "{} points don't make up Bezier segments",
+            points.len()
+        );
+        Self {
+            controls: points,
+            closed,
+        }
+    }
+
+    /// a uniform Catmull-Rom spline, passing through every point,
+    /// open ends continue in a straight line
+    pub fn catmull_rom(points: &[Vec3], closed: bool) -> Self {
+        assert!(points.len() >= 2, "a spline needs at least two points");
+        let n = points.len() as isize;
+        let point = |i: isize| {
+            if closed {
+                points[i.rem_euclid(n) as usize]
+            } else if i < 0 {
+                2.0 * points[0] - points[1]
+            } else if i >= n {
+                2.0 * points[n as usize - 1] - points[n as usize - 2]
+            } else {
+                points[i as usize]
+            }
+        };
+
+        let segments = if closed { n } else { n - 1 };
+        let mut controls = Vec::with_capacity(segments as usize * 3 + 1);
+        for i in 0..segments {
+            let (p0, p1, p2, p3) = (point(i - 1), point(i), point(i + 1), point(i + 2));
+            controls.extend([p1, p1 + (p2 - p0) / 6.0, p2 - (p3 - p1) / 6.0]);
+        }
+        if !closed {
+            controls.push(points[n as usize - 1]);
+        }
+
+        Self { controls, closed }
+    }
+
+    pub fn segments(&self) -> usize {
+        self.controls.len() / 3
+    }
+
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    /// the Bezier control points of segment `i`
+    pub fn segment(&self, i: usize) -> [Vec3; 4] {
+        let c = &self.controls;
+        [
+            c[i * 3],
+            c[i * 3 + 1],
+            c[i * 3 + 2],
+            c[(i * 3 + 3) % c.len()],
+        ]
+    }
+
+    pub fn point(&self, t: f32) -> Vec3 {
+        let ([p0, p1, p2, p3], t) = self.locate(t);
+        let s = 1.0 - t;
+        p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)
+    }
+
+    /// the derivative, not normalized
+    pub fn tangent(&self, t: f32) -> Vec3 {
+        let ([p0, p1, p2, p3], t) = self.locate(t);
+        let s = 1.0 - t;
+        (p1 - p0) * (3.0 * s * s) + (p2 - p1) * (6.0 * s * t) + (p3 - p2) * (3.0 * t * t)
+    }
+
+    /// `samples_per_segment` points per segment and the end point, for drawing
+    pub fn polyline(&self, samples_per_segment: u32) -> Vec<Vec3> {
+        let count = self.segments() as u32 * samples_per_segment.max(1);
+        (0..=count)
+            .map(|i| self.point(i as f32 * self.segments() as f32 / count as f32))
+            .collect()
+    }
+
+    pub fn arc_length(&self, samples_per_segment: u32) -> ArcLength {
+        let points = self.polyline(samples_per_segment);
+        let mut total = 0.0;
+        let lengths = std::iter::once(0.0)
+            .chain(points.windows(2).map(|pair| {
+                total += pair[0].distance(pair[1]);
+                total
+            }))
+            .collect();
+        ArcLength {
+            lengths,
+            samples_per_segment: samples_per_segment.max(1),
+        }
+    }
+
+    /// wraps closed splines around, clamps open ones
+    pub fn wrap(&self, t: f32) -> f32 {
+        let end = self.segments() as f32;
+        if self.closed {
+            t.rem_euclid(end)
+        } else {
+            t.clamp(0.0, end)
+        }
+    }
+
+    fn locate(&self, t: f32) -> ([Vec3; 4], f32) {
+        let t = self.wrap(t);
+        let i = (t as usize).min(self.segments() - 1);
+        (self.segment(i), t - i as f32)
+    }
+}
+
+impl ArcLength {
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap()
+    }
+
+    /// the spline parameter `distance` along it, clamped to the ends
+    pub fn parameter(&self, distance: f32) -> f32 {
+        let distance = distance.clamp(0.0, self.length());
+        let i = self
+            .lengths
+            .partition_point(|&length| length < distance)
+            .clamp(1, self.lengths.len() - 1);
+        let (a, b) = (self.lengths[i - 1], self.lengths[i]);
+        let fraction = if b > a { (distance - a) / (b - a) } else { 0.0 };
+        (i - 1) as f32 / self.samples_per_segment as f32
+            + fraction / self.samples_per_segment as f32
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Vec<Vec3> {
+        vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::Y]
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_points() {
+        for closed in [false, true] {
+            let spline = Spline::catmull_rom(&square(), closed);
+            assert_eq!(spline.segments(), if closed { 4 } else { 3 });
+            for (i, point) in square().into_iter().enumerate() {
+                assert!(spline.point(i as f32).abs_diff_eq(point, 1e-6));
+            }
+        }
+
+        // closed splines come back around smoothly
+        let closed = Spline::catmull_rom(&square(), true);
+        assert!(closed.point(4.0).abs_diff_eq(Vec3::ZERO, 1e-6));
+        assert!(closed.tangent(0.0).abs_diff_eq(closed.tangent(4.0), 1e-6));
+        assert!(closed.point(-0.5).abs_diff_eq(closed.point(3.5), 1e-6));
+    }
+
+    #[test]
+    fn bezier_tangents_follow_controls() {
+        let spline = Spline::bezier(vec![Vec3::ZERO, Vec3::X, Vec3::X, Vec3::X * 3.0], false);
+        assert!(spline.point(0.5).abs_diff_eq(Vec3::X * 1.125, 1e-6));
+        assert!(spline.tangent(0.0).abs_diff_eq(Vec3::X * 3.0, 1e-6));
+        assert!(spline.tangent(1.0).abs_diff_eq(Vec3::X * 6.0, 1e-6));
+        // clamped past the ends
+        assert_eq!(spline.point(2.0), Vec3::X * 3.0);
+    }
+
+    #[test]
+    fn arc_length_moves_at_constant_speed() {
+        // unevenly spaced controls on a straight line
+        let spline = Spline::bezier(
+            vec![Vec3::ZERO, Vec3::X * 0.1, Vec3::X * 0.2, Vec3::X],
+            false,
+        );
+        let arc = spline.arc_length(64);
+        assert!((arc.length() - 1.0).abs() < 1e-5);
+        for i in 0..=10 {
+            let distance = i as f32 / 10.0;
+            let x = spline.point(arc.parameter(distance)).x;
+            assert!((x - distance).abs() < 1e-3, "{distance} {x}");
+        }
+        assert_eq!(arc.parameter(-1.0), 0.0);
+        assert_eq!(arc.parameter(2.0), 1.0);
+
+        let circle = Spline::catmull_rom(
+            &(0..32)
+                .map(|i| {
+                    let (sin, cos) = (i as f32 / 32.0 * std::f32::consts::TAU).sin_cos();
+                    Vec3::new(cos, sin, 0.0)
+                })
+                .collect::<Vec<_>>(),
+            true,
+        );
+        let length = circle.arc_length(16).length();
+        assert!((length - std::f32::consts::TAU).abs() < 1e-2, "{length}");
+    }
+}
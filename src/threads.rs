@@ -0,0 +1,73 @@
+use std::{
+    any::Any,
+    io, panic,
+    thread::{self, JoinHandle},
+};
+
+use crate::graphics::crash;
+
+//
+
+/// report panics of every thread through tracing and the GPU crash log
+/// before the default hook prints the backtrace
+///
+/// call once at startup, worker threads should be named with [`spawn`]
+/// or their pool's builder so the reports say where they came from
+pub fn install_panic_hook() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        let name = thread.name().unwrap_or("<unnamed>");
+        let location = info
+            .location()
+            .map_or_else(|| "<unknown>".to_string(), ToString::to_string);
+        let message = panic_message(info.payload());
+
+        tracing::error!("thread `{name}` panicked at {location}: {message}");
+        crash::dump_panic(&format!(
+            "thread `{name}` panicked at {location}: {message}"
+        ));
+
+        default(info);
+    }));
+}
+
+/// a named OS thread, its panics are reported by [`install_panic_hook`]
+pub fn spawn<T: Send + 'static>(
+    name: impl Into<String>,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> io::Result<JoinHandle<T>> {
+    thread::Builder::new().name(name.into()).spawn(f)
+}
+
+/// the message of `panic!` and friends
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string payload>"
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_threads_and_messages() {
+        let handle = spawn("test worker", || thread::current().name().map(String::from)).unwrap();
+        assert_eq!(handle.join().unwrap().as_deref(), Some("test worker"));
+
+        let payload = spawn("test panic", || panic!("boom {}", 1))
+            .unwrap()
+            .join()
+            .unwrap_err();
+        assert_eq!(panic_message(&*payload), "boom 1");
+        let payload: Box<dyn Any + Send> = Box::new(1);
+        assert_eq!(panic_message(&*payload), "<non-string payload>");
+    }
+}
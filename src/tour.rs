@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use glam::{DVec2, Vec3};
+
+use crate::{
+    camera::Camera2d,
+    color::{ColorVision, Palette},
+    graphics::{blend::BlendMode, Graphics},
+    spline::{ArcLength, Spline},
+    RuntimeSettings,
+};
+
+//
+
+/// `--tour`: flies the camera along a spline over the scene and switches
+/// the effects on a schedule
+///
+/// a showcase, and a smoke test that goes through every effect once
+#[derive(Debug, Clone)]
+pub struct Tour {
+    /// `x, y` camera position and `z` zoom
+    path: Spline,
+    arc: ArcLength,
+    duration: Duration,
+    /// sorted by time
+    schedule: Vec<(Duration, TourEffect)>,
+}
+
+/// one switch on the tour's schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TourEffect {
+    Uv(bool),
+    ColorVision(ColorVision),
+    /// index into [`Palette::BUILTIN`]
+    Palette(usize),
+    Blend(BlendMode),
+    HighContrast(bool),
+}
+
+//
+
+impl Tour {
+    /// the builtin tour over the demo scene
+    pub fn demo() -> Self {
+        let path = Spline::catmull_rom(
+            &[
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(0.6, 0.3, 2.0),
+                Vec3::new(0.0, 0.8, 3.0),
+                Vec3::new(-0.6, 0.3, 1.5),
+                Vec3::new(-0.3, -0.5, 0.6),
+                Vec3::new(0.0, 0.0, 1.0),
+            ],
+            false,
+        );
+
+        let s = Duration::from_secs;
+        let mut schedule = vec![
+            (s(3), TourEffect::Uv(true)),
+            (s(6), TourEffect::Uv(false)),
+            (s(6), TourEffect::Blend(BlendMode::Additive)),
+            (s(9), TourEffect::Blend(BlendMode::Multiply)),
+            (s(12), TourEffect::Blend(BlendMode::default())),
+            (s(12), TourEffect::HighContrast(true)),
+            (s(14), TourEffect::HighContrast(false)),
+        ];
+        let mut at = s(14);
+        for color_vision in ColorVision::ALL[1..]
+            .iter()
+            .copied()
+            .chain([ColorVision::Normal])
+        {
+            schedule.push((at, TourEffect::ColorVision(color_vision)));
+            at += s(2);
+        }
+        for palette in (1..Palette::BUILTIN.len()).chain([0]) {
+            schedule.push((at, TourEffect::Palette(palette)));
+            at += s(1);
+        }
+
+        Self::new(path, at + s(2), schedule)
+    }
+
+    pub fn new(
+        path: Spline,
+        duration: Duration,
+        mut schedule: Vec<(Duration, TourEffect)>,
+    ) -> Self {
+        schedule.sort_by_key(|&(at, _)| at);
+        Self {
+            arc: path.arc_length(32),
+            path,
+            duration,
+            schedule,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// the camera `elapsed` into the tour, at a constant speed along the path
+    pub fn camera(&self, elapsed: Duration) -> Camera2d {
+        let progress = (elapsed.as_secs_f32() / self.duration.as_secs_f32()).clamp(0.0, 1.0);
+        let point = self
+            .path
+            .point(self.arc.parameter(progress * self.arc.length()));
+        Camera2d {
+            position: DVec2::new(point.x as f64, point.y as f64),
+            zoom: point.z.max(0.01),
+        }
+    }
+
+    /// the effects due after `from` up to and including `to`
+    pub fn effects(
+        &self,
+        from: Option<Duration>,
+        to: Duration,
+    ) -> impl Iterator<Item = TourEffect> + '_ {
+        self.schedule
+            .iter()
+            .filter(move |&&(at, _)| from.is_none_or(|from| at > from) && at <= to)
+            .map(|&(_, effect)| effect)
+    }
+
+    /// move the camera and apply the effects that came up since `from`
+    pub fn update(
+        &self,
+        from: Option<Duration>,
+        to: Duration,
+        graphics: &mut Graphics,
+        settings: &mut RuntimeSettings,
+    ) {
+        graphics.camera = self.camera(to);
+        for effect in self.effects(from, to) {
+            tracing::info!("tour: {effect:?}");
+            effect.apply(graphics, settings);
+        }
+    }
+}
+
+impl TourEffect {
+    pub fn apply(self, graphics: &mut Graphics, settings: &mut RuntimeSettings) {
+        match self {
+            Self::Uv(enable) => settings.enable_uv = enable,
+            Self::ColorVision(color_vision) => settings.color_vision = color_vision,
+            Self::Palette(palette) => settings.palette = palette % Palette::BUILTIN.len(),
+            Self::Blend(blend) => graphics.set_blend(blend),
+            Self::HighContrast(enable) => settings.high_contrast = enable,
+        }
+    }
+}
+
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_and_path() {
+        let tour = Tour::demo();
+        let s = Duration::from_secs;
+
+        let all: Vec<_> = tour.effects(None, tour.duration()).collect();
+        assert_eq!(all.len(), tour.schedule.len());
+        assert!(all.contains(&TourEffect::Blend(BlendMode::Additive)));
+        // every effect is switched back off before the end
+        assert_eq!(all.last(), Some(&TourEffect::Palette(0)));
+
+        // each effect comes up exactly once over consecutive frames
+        let mut count = 0;
+        let mut from = None;
+        for frame in 0..=tour.duration().as_millis() as u64 / 16 {
+            let to = Duration::from_millis(frame * 16);
+            count += tour.effects(from, to).count();
+            from = Some(to);
+        }
+        assert_eq!(count, all.len());
+        assert_eq!(tour.effects(Some(s(3)), s(3)).count(), 0);
+
+        let start = tour.camera(Duration::ZERO);
+        assert_eq!(start, Camera2d::default());
+        let end = tour.camera(tour.duration() + s(10));
+        assert!(end.position.abs_diff_eq(DVec2::ZERO, 1e-5));
+        assert!(tour.camera(tour.duration() / 2).position.length() > 0.1);
+    }
+}
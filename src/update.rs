@@ -0,0 +1,29 @@
+use anyhow::Result;
+
+use crate::{build_info::BuildInfo, settings::UpdateSettings};
+
+//
+
+/// a newer release than the running build
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub version: String,
+    /// where to get it
+    pub url: String,
+}
+
+//
+
+/// ask for a newer release, runs once at startup if `[updates] check = true`
+///
+/// this is a hook, the template doesn't have a release server to ask,
+/// fill it in with a request to wherever your releases are published
+pub async fn check(settings: &UpdateSettings, build: &BuildInfo) -> Result<Option<Update>> {
+    tracing::debug!(
+        "update check for {} {} (channel: {})",
+        build.name,
+        build.version,
+        settings.channel
+    );
+    Ok(None)
+}
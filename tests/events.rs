@@ -0,0 +1,294 @@
+//! drives the parts of the main loop that react to window events with synthetic
+//! event sequences, without a display: key mashing, focus changes, resize storms
+//! and zero-size (minimized) windows must not panic or leave stale state behind
+//!
+//! the event loop itself is the closure in `main.rs`, these go through
+//! [`events::dispatch`] like it does
+
+use std::time::{Duration, Instant};
+
+use proptest::prelude::*;
+use wgpu_template::{
+    color::ColorVision,
+    events,
+    graphics::{
+        resize::{self, ResizeDebounce},
+        Graphics,
+    },
+    idle::{self, IdleDetector, IdleEffect},
+    input::{Action, InputMap},
+    redraw::Redraw,
+    rng::RngService,
+    settings::{GlobalSettings, IdleSettings, InputSettings, RenderMode},
+    sim::SimState,
+    RuntimeSettings,
+};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{
+        DeviceId, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode,
+        WindowEvent,
+    },
+    window::WindowId,
+};
+
+//
+
+fn window_event(event: WindowEvent<'static>) -> Event<'static, ()> {
+    Event::WindowEvent {
+        // SAFETY: only compared, never passed to winit
+        window_id: unsafe { WindowId::dummy() },
+        event,
+    }
+}
+
+#[allow(deprecated)]
+fn key(key: VirtualKeyCode, state: ElementState) -> Event<'static, ()> {
+    window_event(WindowEvent::KeyboardInput {
+        // SAFETY: as above
+        device_id: unsafe { DeviceId::dummy() },
+        input: KeyboardInput {
+            scancode: 0,
+            state,
+            virtual_keycode: Some(key),
+            modifiers: ModifiersState::empty(),
+        },
+        is_synthetic: false,
+    })
+}
+
+/// the event handling state of the main loop, for the parts under test
+struct Loop {
+    input: InputMap,
+    idle: IdleDetector,
+    redraw: Redraw,
+}
+
+impl Loop {
+    fn new(idle: &IdleSettings, now: Instant) -> Self {
+        Self {
+            input: InputMap::new(&InputSettings::default()),
+            idle: IdleDetector::new(idle, now),
+            redraw: Redraw::new(RenderMode::Reactive),
+        }
+    }
+
+    /// what the main loop does with `event`, before the rest of the app sees it
+    fn handle(&mut self, event: &Event<'static, ()>, now: Instant) -> Option<Action> {
+        events::dispatch(
+            event,
+            // SAFETY: as above
+            unsafe { WindowId::dummy() },
+            now,
+            &mut self.input,
+            &mut self.idle,
+            &mut self.redraw,
+        )
+    }
+}
+
+/// any of shift, ctrl, alt and logo, one bit each
+fn modifiers(bits: u32) -> ModifiersState {
+    [
+        ModifiersState::SHIFT,
+        ModifiersState::CTRL,
+        ModifiersState::ALT,
+        ModifiersState::LOGO,
+    ]
+    .into_iter()
+    .enumerate()
+    .filter(|(bit, _)| bits & (1 << bit) != 0)
+    .fold(ModifiersState::empty(), |all, (_, m)| all | m)
+}
+
+const KEYS: [VirtualKeyCode; 10] = [
+    VirtualKeyCode::Z,
+    VirtualKeyCode::S,
+    VirtualKeyCode::O,
+    VirtualKeyCode::K,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::LControl,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Return,
+];
+
+//
+
+proptest! {
+    #[test]
+    fn key_mashing_leaves_the_bindings_working(
+        mash in prop::collection::vec((0..KEYS.len(), any::<bool>(), 0u32..16), 0..200)
+    ) {
+        let now = Instant::now();
+        let mut main = Loop::new(&IdleSettings::default(), now);
+
+        for (i, pressed, bits) in mash {
+            main.handle(&window_event(WindowEvent::ModifiersChanged(modifiers(bits))), now);
+            let state = if pressed { ElementState::Pressed } else { ElementState::Released };
+            main.handle(&key(KEYS[i], state), now);
+        }
+
+        // whatever was typed, a fresh chord is recognized
+        main.handle(&window_event(WindowEvent::ModifiersChanged(ModifiersState::CTRL)), now);
+        let action = main.handle(&key(VirtualKeyCode::Z, ElementState::Pressed), now);
+        prop_assert_eq!(action, Some(Action::Undo));
+    }
+}
+
+#[test]
+fn focus_changes_wake_up_a_reactive_loop_and_only_input_ends_idling() {
+    let start = Instant::now();
+    let settings = IdleSettings {
+        enabled: true,
+        after_minutes: 1.0,
+        ..IdleSettings::default()
+    };
+    let mut main = Loop::new(&settings, start);
+    assert!(main.redraw.take(false).is_some(), "the first frame renders");
+    assert!(main.redraw.take(false).is_none());
+
+    let later = start + Duration::from_secs(120);
+    for focused in [false, true, false, true] {
+        main.handle(&window_event(WindowEvent::Focused(focused)), later);
+        assert!(main.redraw.take(false).is_some());
+    }
+    // alt-tabbing isn't someone in front of the screen
+    assert_ne!(main.idle.effect(later), IdleEffect::NONE);
+
+    let click = window_event(WindowEvent::MouseInput {
+        // SAFETY: as above
+        device_id: unsafe { DeviceId::dummy() },
+        state: ElementState::Pressed,
+        button: MouseButton::Left,
+        #[allow(deprecated)]
+        modifiers: ModifiersState::empty(),
+    });
+    main.handle(&click, later);
+    assert_eq!(main.idle.effect(later), IdleEffect::NONE);
+    assert!(main.redraw.take(false).is_some());
+
+    let moved = window_event(WindowEvent::CursorMoved {
+        // SAFETY: as above
+        device_id: unsafe { DeviceId::dummy() },
+        position: PhysicalPosition::new(1.0, 2.0),
+        #[allow(deprecated)]
+        modifiers: ModifiersState::empty(),
+    });
+    assert!(idle::is_input(&moved));
+}
+
+#[test]
+fn other_windows_only_wake_the_loop() {
+    let now = Instant::now();
+    let mut main = Loop::new(&IdleSettings::default(), now);
+    assert!(main.redraw.take(false).is_some());
+
+    // a mirror window, with an id the dummy main window doesn't have
+    // SAFETY: as above
+    let mirror = WindowId::from(u64::from(unsafe { WindowId::dummy() }) + 1);
+    let Event::WindowEvent { event, .. } = key(VirtualKeyCode::Escape, ElementState::Pressed)
+    else {
+        unreachable!()
+    };
+    let escape = Event::WindowEvent {
+        window_id: mirror,
+        event,
+    };
+    assert_eq!(main.handle(&escape, now), None);
+    assert!(main.redraw.take(false).is_some());
+
+    let escape = key(VirtualKeyCode::Escape, ElementState::Pressed);
+    assert_eq!(main.handle(&escape, now), Some(Action::Exit));
+}
+
+#[test]
+fn resize_storms_and_zero_size_windows() {
+    let start = Instant::now();
+    let ms = |ms: u64| start + Duration::from_millis(ms);
+    let mut resize = ResizeDebounce::new(Duration::from_millis(50));
+    let current = (1280, 720);
+    let drag = |i: u32| (1 + i * 7 % 1920, 1 + i * 13 % 1080);
+
+    // a drag across the screen with a frame every 16 ms, nothing until it settles
+    for i in 0..200u32 {
+        let t = ms(i as u64 * 2);
+        resize.resized(drag(i), t);
+        if i % 8 == 0 {
+            assert_eq!(resize.take(t, false, current, false), None, "event {i}");
+        }
+    }
+    assert!(resize.is_pending());
+    assert_eq!(resize.take(ms(420), false, current, false), None);
+    // only the last size, once
+    assert_eq!(resize.take(ms(450), false, current, false), Some(drag(199)));
+    assert_eq!(resize.take(ms(1000), false, current, false), None);
+    assert!(!resize.is_pending());
+
+    // minimizing ends in 0x0 or 0xN, which is used up without a swapchain
+    for size in [(0, 0), (0, 480), (640, 0)] {
+        resize.resized(size, ms(2000));
+        assert_eq!(resize.take(ms(3000), true, current, false), None);
+        assert!(!resize.is_pending());
+        assert!(!resize::can_render(size));
+    }
+
+    // restoring to the same size only reconfigures a stale swapchain
+    resize.resized(current, ms(4000));
+    assert_eq!(resize.take(ms(4000), true, current, false), None);
+    resize.resized(current, ms(4000));
+    assert_eq!(resize.take(ms(4000), true, current, true), Some(current));
+
+    // a stale swapchain waits for the debounce too, but not while minimized,
+    // and a pending resize is not pushed back by it
+    resize.stale((0, 0), ms(5000));
+    assert!(!resize.is_pending());
+    resize.stale(current, ms(5000));
+    assert_eq!(resize.take(ms(5010), false, current, true), None);
+    resize.stale(current, ms(5040));
+    assert_eq!(resize.take(ms(5050), false, current, true), Some(current));
+}
+
+/// a headless renderer ignores resizes, offscreen renders keep their own size
+#[test]
+fn resizes_leave_headless_rendering_alone() {
+    let settings = GlobalSettings::default();
+    let rng = RngService::from_settings(&settings.rng, Some(0));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let Ok(mut graphics) = runtime.block_on(Graphics::headless(&settings, &rng)) else {
+        eprintln!("no adapter, skipping");
+        return;
+    };
+    let runtime_settings = RuntimeSettings {
+        enable_uv: false,
+        interpolate: false,
+        high_contrast: false,
+        color_vision: ColorVision::default(),
+        palette: 0,
+    };
+    let state = SimState::default();
+
+    // a drag across the screen, minimizing and restoring, all within one debounce
+    for i in 0..200u32 {
+        let size = match i % 50 {
+            0 => (0, 0),
+            1 => (0, 480),
+            _ => (1 + i * 7 % 1920, 1 + i * 13 % 1080),
+        };
+        graphics.resized(size);
+        if i % 10 == 0 {
+            graphics.frame(&runtime_settings, &state);
+        }
+    }
+    graphics.resized_redraw((0, 0), &runtime_settings, &state);
+    graphics.resized_redraw((640, 360), &runtime_settings, &state);
+
+    // rendering still works afterwards, at odd sizes too
+    for size in [(1, 1), (3, 1080), (64, 64)] {
+        let image = graphics
+            .render_offscreen(&runtime_settings, &state, size)
+            .unwrap();
+        assert_eq!((image.width, image.height), size);
+    }
+}
@@ -0,0 +1,112 @@
+//! renders the default scene without a window on the software adapters CI machines have:
+//! lavapipe (Vulkan), WARP (DX12) and llvmpipe (GL), skipping the ones that are missing
+
+use wgpu::{Backends, DeviceType, Instance, InstanceDescriptor};
+use wgpu_template::{
+    color::ColorVision,
+    graphics::Graphics,
+    rng::RngService,
+    settings::{GlobalSettings, GraphicsBackends},
+    sim::SimState,
+    RuntimeSettings,
+};
+
+//
+
+fn software_adapter(backends: Backends) -> Option<String> {
+    let instance = Instance::new(InstanceDescriptor {
+        backends,
+        ..InstanceDescriptor::default()
+    });
+    instance
+        .enumerate_adapters(backends)
+        .map(|adapter| adapter.get_info())
+        .find(|info| info.device_type == DeviceType::Cpu)
+        .map(|info| info.name)
+}
+
+fn render_default_scene(backends: GraphicsBackends) {
+    let Some(name) = software_adapter(backends.to_backends()) else {
+        eprintln!(
+            "no software adapter for {:?}, skipping",
+            backends.to_backends()
+        );
+        return;
+    };
+    eprintln!("rendering on {name}");
+
+    let mut settings = GlobalSettings::default();
+    settings.graphics.allowed_backends = backends;
+    settings.graphics.force_software_rendering = true;
+    let rng = RngService::from_settings(&settings.rng, Some(0));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut graphics = runtime
+        .block_on(Graphics::headless(&settings, &rng))
+        .unwrap();
+    // a frame without a window does nothing
+    let runtime_settings = RuntimeSettings {
+        enable_uv: false,
+        interpolate: false,
+        high_contrast: false,
+        color_vision: ColorVision::default(),
+        palette: 0,
+    };
+    graphics.frame(&runtime_settings, &SimState::default());
+
+    let image = graphics
+        .render_offscreen(&runtime_settings, &SimState::default(), (64, 64))
+        .unwrap();
+    assert_eq!((image.width, image.height), (64, 64));
+    assert_eq!(image.rgba.len(), 64 * 64 * 4);
+
+    // the triangle covers the center, around it is the transparent clear color
+    let center = &image.rgba[(32 * 64 + 32) * 4..][..4];
+    assert!(
+        center[..3].iter().any(|&c| c > 0),
+        "black center {center:?}"
+    );
+    assert_eq!(&image.rgba[..4], &[0, 0, 0, 0]);
+
+    // high contrast: a white triangle on opaque black
+    let high_contrast = RuntimeSettings {
+        high_contrast: true,
+        ..runtime_settings
+    };
+    let image = graphics
+        .render_offscreen(&high_contrast, &SimState::default(), (64, 64))
+        .unwrap();
+    let center = &image.rgba[(32 * 64 + 32) * 4..][..4];
+    assert!(center.iter().all(|&c| c >= 250), "not white {center:?}");
+    assert_eq!(&image.rgba[..4], &[0, 0, 0, 255]);
+}
+
+fn only(enable: impl FnOnce(&mut GraphicsBackends)) -> GraphicsBackends {
+    let mut backends = GraphicsBackends {
+        vulkan: false,
+        metal: false,
+        dx12: false,
+        webgpu: false,
+        gl: false,
+        dx11: false,
+    };
+    enable(&mut backends);
+    backends
+}
+
+//
+
+#[test]
+fn lavapipe() {
+    render_default_scene(only(|b| b.vulkan = true));
+}
+
+#[test]
+fn warp() {
+    render_default_scene(only(|b| b.dx12 = true));
+}
+
+#[test]
+fn gl_software() {
+    render_default_scene(only(|b| b.gl = true));
+}